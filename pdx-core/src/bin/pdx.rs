@@ -0,0 +1,788 @@
+//! `pdx` converts and validates PDX documents without opening a window, so
+//! documentation can be generated in CI.
+
+use pdx_core::bundle::{is_bundle, read_bundle, write_bundle, BUNDLE_EXTENSION};
+use pdx_core::data::{Metadata, PdxDocument};
+use pdx_core::docx::{export_as_docx, import_docx};
+use pdx_core::epub::export_as_epub;
+use pdx_core::export::{
+    export_as_html, export_as_pdf, export_as_png, HtmlOptions, PngLayout, PngOptions,
+};
+use pdx_core::latex::export_as_latex;
+use pdx_core::markdown::{export_as_markdown, import_markdown};
+use pdx_core::migrate;
+use pdx_core::odt::{export_as_odt, import_odt};
+use pdx_core::validate::{validate_document, Severity, CURRENT_VERSION};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage:
+  pdx convert <INPUT>... [--to FORMAT] [--from FORMAT] [-o OUTPUT]
+                         [--dpi DPI] [--per-page] [--inline-images]
+  pdx validate <INPUT>... [--from FORMAT] [--strict]
+  pdx help
+
+Formats: pdx, pdxz, html, pdf, png, md, docx, odt, epub, tex
+
+pdxz is a bundle: the document with its images embedded. Bundles are
+read whatever their extension.
+
+INPUT is a file, a glob such as \"docs/**/*.pdx\", or - for stdin.
+The input format follows the extension (.md and .markdown are Markdown,
+.docx is Word, .odt is OpenDocument, anything else is PDX); --from
+overrides it.
+
+Without -o each output is written next to its input. OUTPUT may be a file
+(single input), a directory, or - for stdout. A directory receives the
+inputs' own layout below their common parent, so docs/a/index.pdx and
+docs/b/index.pdx become OUTPUT/a/index.* and OUTPUT/b/index.*. The target
+format defaults to the extension of OUTPUT, or for Markdown, Word and
+OpenDocument input to pdx, or pdxz when the input has embedded images.
+pdx output fails for documents with embedded images, which it cannot hold.
+
+Symbolic links to directories are not followed by **.
+
+--dpi and --per-page apply to PNG output. Per-page images are numbered
+name-1.png, name-2.png, ...
+
+--inline-images embeds images in HTML output as data URIs, so the page
+is a single file.
+
+validate reports problems in each document and fails on errors, or on
+warnings too with --strict.
+
+Exit status: 0 on success, 1 if any input failed, 2 on usage errors.
+";
+
+// ============================================================================
+// Entry Point
+// ============================================================================
+
+enum Failure {
+    /// Bad command line; the usage text is shown
+    Usage(String),
+    /// At least one input failed; details were already reported
+    Inputs,
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let result = match args.first().map(String::as_str) {
+        Some("convert") => convert(&args[1..]),
+        Some("validate") => validate(&args[1..]),
+        Some("help" | "-h" | "--help") => {
+            print!("{}", USAGE);
+            Ok(())
+        }
+        Some(other) => Err(Failure::Usage(format!("unknown command '{}'", other))),
+        None => Err(Failure::Usage("missing command".to_string())),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(Failure::Inputs) => ExitCode::from(1),
+        Err(Failure::Usage(message)) => {
+            eprintln!("pdx: {}\n\n{}", message, USAGE);
+            ExitCode::from(2)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Pdx,
+    Bundle,
+    Html,
+    Pdf,
+    Png,
+    Markdown,
+    Docx,
+    Odt,
+    Epub,
+    Latex,
+}
+
+impl Format {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "pdx" | "json" => Some(Format::Pdx),
+            "pdxz" => Some(Format::Bundle),
+            "html" | "htm" => Some(Format::Html),
+            "pdf" => Some(Format::Pdf),
+            "png" => Some(Format::Png),
+            "md" | "markdown" => Some(Format::Markdown),
+            "docx" => Some(Format::Docx),
+            "odt" => Some(Format::Odt),
+            "epub" => Some(Format::Epub),
+            "tex" | "latex" => Some(Format::Latex),
+            _ => None,
+        }
+    }
+
+    fn from_path(path: &Path) -> Option<Self> {
+        Self::from_name(path.extension()?.to_str()?)
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Pdx => "pdx",
+            Format::Bundle => BUNDLE_EXTENSION,
+            Format::Html => "html",
+            Format::Pdf => "pdf",
+            Format::Png => "png",
+            Format::Markdown => "md",
+            Format::Docx => "docx",
+            Format::Odt => "odt",
+            Format::Epub => "epub",
+            Format::Latex => "tex",
+        }
+    }
+}
+
+/// Shared options of both commands; `to`, `output`, `png` and `html` only
+/// matter for `convert`.
+struct Options {
+    inputs: Vec<String>,
+    from: Option<Format>,
+    to: Option<Format>,
+    output: Option<String>,
+    png: PngOptions,
+    html: HtmlOptions,
+    strict: bool,
+}
+
+fn parse_options(args: &[String], convert: bool) -> Result<Options, Failure> {
+    let mut options = Options {
+        inputs: Vec::new(),
+        from: None,
+        to: None,
+        output: None,
+        png: PngOptions::default(),
+        html: HtmlOptions::default(),
+        strict: false,
+    };
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .ok_or_else(|| Failure::Usage(format!("{} needs a value", arg)))
+        };
+
+        match arg.as_str() {
+            "-f" | "--from" => options.from = Some(parse_format(&value()?)?),
+            "-t" | "--to" if convert => options.to = Some(parse_format(&value()?)?),
+            "-o" | "--output" if convert => options.output = Some(value()?),
+            "--dpi" if convert => {
+                let dpi = value()?;
+                options.png.dpi = dpi
+                    .parse()
+                    .ok()
+                    .filter(|dpi| (36.0..=1200.0).contains(dpi))
+                    .ok_or_else(|| Failure::Usage(format!("invalid DPI '{}'", dpi)))?;
+            }
+            "--per-page" if convert => options.png.layout = PngLayout::PerPage,
+            "--inline-images" if convert => options.html.inline_images = true,
+            "--strict" if !convert => options.strict = true,
+            "-" => options.inputs.push(arg.clone()),
+            option if option.starts_with('-') => {
+                return Err(Failure::Usage(format!("unknown option '{}'", option)));
+            }
+            _ => options.inputs.push(arg.clone()),
+        }
+    }
+
+    if options.inputs.is_empty() {
+        return Err(Failure::Usage("no input files".to_string()));
+    }
+
+    Ok(options)
+}
+
+fn parse_format(name: &str) -> Result<Format, Failure> {
+    Format::from_name(name).ok_or_else(|| Failure::Usage(format!("unknown format '{}'", name)))
+}
+
+/// Expands globs in the input list. A pattern that matches nothing is
+/// reported and counts as a failed input.
+fn expand_inputs(inputs: &[String], failed: &mut bool) -> Vec<String> {
+    let mut expanded = Vec::new();
+
+    for input in inputs {
+        if input == "-" || !is_glob(input) {
+            expanded.push(input.clone());
+            continue;
+        }
+
+        let matches = expand_glob(input);
+        if matches.is_empty() {
+            eprintln!("pdx: {}: no files match", input);
+            *failed = true;
+        }
+        expanded.extend(matches.iter().map(|p| p.to_string_lossy().to_string()));
+    }
+
+    expanded
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+fn convert(args: &[String]) -> Result<(), Failure> {
+    let options = parse_options(args, true)?;
+    let mut failed = false;
+    let inputs = expand_inputs(&options.inputs, &mut failed);
+
+    let to_stdout = options.output.as_deref() == Some("-");
+    let output_dir = options
+        .output
+        .as_ref()
+        .filter(|output| !to_stdout && (inputs.len() > 1 || Path::new(output).is_dir()))
+        .map(PathBuf::from);
+
+    if to_stdout && inputs.len() > 1 {
+        return Err(Failure::Usage(
+            "only one input can be written to stdout".to_string(),
+        ));
+    }
+    if let Some(dir) = &output_dir
+        && let Err(e) = fs::create_dir_all(dir)
+    {
+        eprintln!("pdx: cannot create {}: {}", dir.display(), e);
+        return Err(Failure::Inputs);
+    }
+
+    let base = common_dir(&inputs);
+    let mut written = HashSet::new();
+    for input in &inputs {
+        let output_dir = output_dir
+            .as_ref()
+            .map(|dir| dir.join(mirrored_dir(input, &base)));
+        if let Err(message) = convert_one(input, &options, output_dir.as_deref(), &mut written) {
+            eprintln!("pdx: {}: {}", input, message);
+            failed = true;
+        }
+    }
+
+    if failed {
+        Err(Failure::Inputs)
+    } else {
+        Ok(())
+    }
+}
+
+/// Converts one input. `output_dir` is where directory output for this
+/// input goes; `written` holds every file written so far, so two inputs
+/// never silently replace each other's output.
+fn convert_one(
+    input: &str,
+    options: &Options,
+    output_dir: Option<&Path>,
+    written: &mut HashSet<PathBuf>,
+) -> Result<(), String> {
+    let from = input_format(input, options.from);
+    let single_output = options
+        .output
+        .as_deref()
+        .filter(|output| *output != "-" && output_dir.is_none());
+
+    let document = load_document(input, from)?;
+    // Images extracted on import only survive in a bundle
+    let default = if document.resources.files.is_empty() {
+        Format::Pdx
+    } else {
+        Format::Bundle
+    };
+    let to = options
+        .to
+        .or_else(|| single_output.and_then(|output| Format::from_path(Path::new(output))))
+        .or(matches!(from, Format::Markdown | Format::Docx | Format::Odt).then_some(default))
+        .ok_or("no target format; pass --to")?;
+    let outputs = render(&document, to, &options.png, &options.html)?;
+
+    // Work out where the output goes
+    let target =
+        if options.output.as_deref() == Some("-") || (input == "-" && single_output.is_none()) {
+            None
+        } else if let Some(output) = single_output {
+            Some(PathBuf::from(output))
+        } else {
+            let stem = Path::new(input)
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_else(|| "document".to_string());
+            let file_name = format!("{}.{}", stem, to.extension());
+
+            match output_dir {
+                Some(dir) => Some(dir.join(file_name)),
+                None if input == "-" => None,
+                None => Some(Path::new(input).with_file_name(file_name)),
+            }
+        };
+
+    let Some(target) = target else {
+        if outputs.len() > 1 {
+            return Err("per-page PNG output needs a file, not stdout".to_string());
+        }
+        return io::stdout()
+            .write_all(&outputs[0])
+            .map_err(|e| format!("cannot write to stdout: {}", e));
+    };
+
+    if input != "-" && same_file(Path::new(input), &target) {
+        return Err(format!(
+            "output would overwrite the input: {}",
+            target.display()
+        ));
+    }
+
+    let files: Vec<PathBuf> = if outputs.len() == 1 {
+        vec![target]
+    } else {
+        let stem = target
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        (1..=outputs.len())
+            .map(|i| target.with_file_name(format!("{}-{}.{}", stem, i, to.extension())))
+            .collect()
+    };
+
+    if let Some(file) = files.iter().find(|file| written.contains(*file)) {
+        return Err(format!(
+            "output would overwrite that of another input: {}",
+            file.display()
+        ));
+    }
+    if let Some(dir) = output_dir {
+        fs::create_dir_all(dir).map_err(|e| format!("cannot create {}: {}", dir.display(), e))?;
+    }
+
+    for (file, data) in files.into_iter().zip(&outputs) {
+        write_file(&file, data)?;
+        written.insert(file);
+    }
+
+    Ok(())
+}
+
+fn validate(args: &[String]) -> Result<(), Failure> {
+    let options = parse_options(args, false)?;
+    let mut failed = false;
+    let inputs = expand_inputs(&options.inputs, &mut failed);
+
+    for input in &inputs {
+        let document = match load_document(input, input_format(input, options.from)) {
+            Ok(document) => document,
+            Err(message) => {
+                eprintln!("pdx: {}: {}", input, message);
+                failed = true;
+                continue;
+            }
+        };
+
+        let issues = validate_document(&document);
+        if issues.is_empty() {
+            println!("{}: ok", input);
+        }
+        for issue in &issues {
+            println!("{}: {}", input, issue);
+            if issue.severity == Severity::Error || options.strict {
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        Err(Failure::Inputs)
+    } else {
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Reading and Writing
+// ============================================================================
+
+fn input_format(input: &str, from: Option<Format>) -> Format {
+    from.unwrap_or(match Format::from_path(Path::new(input)) {
+        Some(format @ (Format::Markdown | Format::Docx | Format::Odt)) => format,
+        _ => Format::Pdx,
+    })
+}
+
+fn load_document(input: &str, format: Format) -> Result<PdxDocument, String> {
+    let data = if input == "-" {
+        let mut data = Vec::new();
+        io::stdin()
+            .read_to_end(&mut data)
+            .map_err(|e| format!("cannot read stdin: {}", e))?;
+        data
+    } else {
+        fs::read(input).map_err(|e| format!("cannot read: {}", e))?
+    };
+    if matches!(format, Format::Pdx | Format::Bundle) && is_bundle(&data) {
+        let mut document = read_bundle(&data).map_err(|e| e.to_string())?;
+        document.resources.base_dir = base_dir(input);
+        return Ok(document);
+    }
+    let fallback = Path::new(input)
+        .file_stem()
+        .filter(|_| input != "-")
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| Metadata::default().title);
+    if matches!(format, Format::Docx | Format::Odt) {
+        let imported = if format == Format::Docx {
+            import_docx(&data, &fallback)
+        } else {
+            import_odt(&data, &fallback)
+        };
+        let mut document = imported.map_err(|e| e.to_string())?;
+        document.resources.base_dir = base_dir(input);
+        return Ok(document);
+    }
+    let text = String::from_utf8(data).map_err(|e| format!("not UTF-8 text: {}", e))?;
+
+    match format {
+        Format::Markdown => {
+            let mut document = import_markdown(&text, &fallback).map_err(|e| e.to_string())?;
+            document.resources.base_dir = base_dir(input);
+            Ok(document)
+        }
+        Format::Pdx | Format::Bundle => {
+            let (mut document, version) = migrate::load(&text).map_err(|e| e.to_string())?;
+            if version < CURRENT_VERSION {
+                eprintln!("pdx: {}: upgraded from format version {}", input, version);
+            }
+            document.resources.base_dir = base_dir(input);
+            Ok(document)
+        }
+        other => Err(format!("cannot read {} input", other.extension())),
+    }
+}
+
+/// Deepest directory containing every input file, the root that directory
+/// output mirrors the inputs' layout from.
+fn common_dir(inputs: &[String]) -> PathBuf {
+    let mut common: Option<Vec<Component>> = None;
+
+    for input in inputs.iter().filter(|input| *input != "-") {
+        let parent: Vec<Component> = Path::new(input)
+            .parent()
+            .map(|parent| parent.components().collect())
+            .unwrap_or_default();
+        common = Some(match common {
+            None => parent,
+            Some(common) => common
+                .into_iter()
+                .zip(parent)
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a)
+                .collect(),
+        });
+    }
+
+    common.unwrap_or_default().into_iter().collect()
+}
+
+/// Directory of `input` relative to `base`. Anything that would climb out
+/// of the output directory, like `..`, flattens to the directory itself.
+fn mirrored_dir(input: &str, base: &Path) -> PathBuf {
+    let relative = Path::new(input)
+        .parent()
+        .and_then(|parent| parent.strip_prefix(base).ok())
+        .unwrap_or(Path::new(""));
+
+    if relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        relative.to_path_buf()
+    } else {
+        PathBuf::new()
+    }
+}
+
+/// Relative image paths in a file resolve against its directory.
+fn base_dir(input: &str) -> Option<PathBuf> {
+    if input == "-" {
+        return None;
+    }
+    Path::new(input).parent().map(Path::to_path_buf)
+}
+
+/// Encodes the document; PNG per-page output yields one entry per page.
+fn render(
+    document: &PdxDocument,
+    format: Format,
+    png: &PngOptions,
+    html: &HtmlOptions,
+) -> Result<Vec<Vec<u8>>, String> {
+    Ok(match format {
+        Format::Pdx if !document.resources.files.is_empty() => {
+            return Err(format!(
+                "{} embedded images would be lost in a .pdx file; pass --to {}",
+                document.resources.files.len(),
+                BUNDLE_EXTENSION
+            ));
+        }
+        Format::Pdx => vec![document.to_json().map_err(|e| e.to_string())?.into_bytes()],
+        Format::Bundle => vec![write_bundle(document).map_err(|e| e.to_string())?],
+        Format::Html => vec![export_as_html(document, html).into_bytes()],
+        Format::Pdf => vec![export_as_pdf(document).map_err(|e| e.to_string())?],
+        Format::Png => export_as_png(document, png).map_err(|e| e.to_string())?,
+        Format::Markdown => vec![export_as_markdown(document).into_bytes()],
+        Format::Docx => vec![export_as_docx(document).map_err(|e| e.to_string())?],
+        Format::Odt => vec![export_as_odt(document).map_err(|e| e.to_string())?],
+        Format::Epub => vec![export_as_epub(document).map_err(|e| e.to_string())?],
+        Format::Latex => vec![export_as_latex(document)
+            .map_err(|e| e.to_string())?
+            .into_bytes()],
+    })
+}
+
+fn write_file(path: &Path, data: &[u8]) -> Result<(), String> {
+    fs::write(path, data).map_err(|e| format!("cannot write {}: {}", path.display(), e))?;
+    eprintln!("pdx: wrote {}", path.display());
+    Ok(())
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+// ============================================================================
+// Globs
+// ============================================================================
+
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?'])
+}
+
+/// Expands `*` and `?` within path components and `**` across directories.
+/// Matches are files only, sorted. Hidden entries need an explicit leading
+/// dot in the pattern, and `**` does not descend into symlinked
+/// directories, so link cycles cannot trap it.
+fn expand_glob(pattern: &str) -> Vec<PathBuf> {
+    let mut base = PathBuf::new();
+    let mut parts = Vec::new();
+
+    for component in Path::new(pattern).components() {
+        let text = component.as_os_str().to_string_lossy().to_string();
+        if parts.is_empty() && !is_glob(&text) {
+            base.push(component);
+        } else {
+            parts.push(text);
+        }
+    }
+
+    let mut matches = Vec::new();
+    walk_glob(&base, &parts, &mut matches);
+    matches.sort();
+    matches.dedup();
+    matches
+}
+
+fn walk_glob(base: &Path, parts: &[String], matches: &mut Vec<PathBuf>) {
+    let Some((part, rest)) = parts.split_first() else {
+        if base.is_file() {
+            matches.push(base.to_path_buf());
+        }
+        return;
+    };
+
+    if !is_glob(part) {
+        walk_glob(&base.join(part), rest, matches);
+        return;
+    }
+
+    let dir = if base.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        base
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    let pattern: Vec<char> = part.chars().collect();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let path = base.join(&name);
+        if name.starts_with('.') && !part.starts_with('.') {
+            continue;
+        }
+
+        if part == "**" {
+            if entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+                walk_glob(&path, parts, matches);
+            }
+        } else if wildcard_match(&pattern, &name.chars().collect::<Vec<_>>()) {
+            walk_glob(&path, rest, matches);
+        }
+    }
+
+    // `**` also matches no directory at all
+    if part == "**" {
+        walk_glob(base, rest, matches);
+    }
+}
+
+fn wildcard_match(pattern: &[char], name: &[char]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some('*'), _) => {
+            wildcard_match(&pattern[1..], name)
+                || (!name.is_empty() && wildcard_match(pattern, &name[1..]))
+        }
+        (Some('?'), Some(_)) => wildcard_match(&pattern[1..], &name[1..]),
+        (Some(p), Some(n)) if p == n => wildcard_match(&pattern[1..], &name[1..]),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh, empty directory under the system temp dir.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("pdx-cli-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn wildcards_match_within_a_name() {
+        let matches = |pattern: &str, name: &str| {
+            wildcard_match(
+                &pattern.chars().collect::<Vec<_>>(),
+                &name.chars().collect::<Vec<_>>(),
+            )
+        };
+
+        assert!(matches("*.pdx", "notes.pdx"));
+        assert!(matches("n?tes.*", "notes.md"));
+        assert!(!matches("*.pdx", "notes.pdxz"));
+    }
+
+    #[test]
+    fn directory_output_mirrors_the_input_layout() {
+        let inputs = ["docs/a/index.pdx", "docs/b/index.pdx", "docs/b/c/x.pdx"]
+            .map(str::to_string)
+            .to_vec();
+        let base = common_dir(&inputs);
+
+        assert_eq!(base, Path::new("docs"));
+        assert_eq!(mirrored_dir(&inputs[0], &base), Path::new("a"));
+        assert_eq!(mirrored_dir(&inputs[1], &base), Path::new("b"));
+        assert_eq!(mirrored_dir(&inputs[2], &base), Path::new("b/c"));
+        assert_eq!(mirrored_dir("-", &base), Path::new(""));
+    }
+
+    #[test]
+    fn inputs_converting_to_the_same_file_are_rejected() {
+        let dir = scratch_dir("collide");
+        let document = pdx_core::data::create_sample_document();
+        for name in ["a.pdx", "a.md"] {
+            let data = match name {
+                "a.md" => export_as_markdown(&document),
+                _ => document.to_json().unwrap(),
+            };
+            fs::write(dir.join(name), data).unwrap();
+        }
+        let Ok(options) = parse_options(
+            &[
+                dir.join("a.pdx").to_string_lossy().to_string(),
+                dir.join("a.md").to_string_lossy().to_string(),
+                "--to".to_string(),
+                "html".to_string(),
+            ],
+            true,
+        ) else {
+            panic!("options should parse");
+        };
+
+        let out = dir.join("out");
+        let mut written = HashSet::new();
+        let first = convert_one(&options.inputs[0], &options, Some(&out), &mut written);
+        let second = convert_one(&options.inputs[1], &options, Some(&out), &mut written);
+
+        assert!(first.is_ok());
+        assert!(second.unwrap_err().contains("another input"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn imported_images_go_into_a_bundle() {
+        let dir = scratch_dir("images");
+        let mut png = io::Cursor::new(Vec::new());
+        image::RgbImage::new(4, 2)
+            .write_to(&mut png, image::ImageFormat::Png)
+            .unwrap();
+        let mut document = pdx_core::data::create_sample_document();
+        if let pdx_core::data::Node::Document { children } = &mut document.content {
+            children.push(pdx_core::data::Node::Image {
+                path: "cat.png".to_string(),
+                alt_text: "cat".to_string(),
+                width: None,
+                height: None,
+            });
+        }
+        document
+            .resources
+            .files
+            .insert("cat.png".to_string(), png.into_inner());
+        let input = dir.join("a.docx");
+        fs::write(&input, export_as_docx(&document).unwrap()).unwrap();
+        let input = input.to_string_lossy().to_string();
+
+        let options = |args: &[&str]| {
+            let mut args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+            args.insert(0, input.clone());
+            let Ok(options) = parse_options(&args, true) else {
+                panic!("options should parse");
+            };
+            options
+        };
+        let pdx = convert_one(
+            &input,
+            &options(&["--to", "pdx"]),
+            None,
+            &mut HashSet::new(),
+        );
+        assert!(pdx.unwrap_err().contains("--to pdxz"));
+        assert!(!dir.join("a.pdx").exists());
+
+        convert_one(&input, &options(&[]), None, &mut HashSet::new()).unwrap();
+        let bundle = read_bundle(&fs::read(dir.join("a.pdxz")).unwrap()).unwrap();
+        assert_eq!(bundle.resources.files.len(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn double_star_skips_symlinked_directories() {
+        let dir = scratch_dir("cycle");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("sub/a.pdx"), "{}").unwrap();
+        std::os::unix::fs::symlink(&dir, dir.join("sub/loop")).unwrap();
+
+        let pattern = dir.join("**/*.pdx");
+        let matches = expand_glob(&pattern.to_string_lossy());
+
+        assert_eq!(matches, [dir.join("sub/a.pdx")]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,217 @@
+use crate::data::PdxDocument;
+use crate::error::PdxError;
+use sha2::{Digest, Sha256};
+use std::io::{self, Cursor, Read, Write};
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+// ============================================================================
+// Bundles
+// ============================================================================
+
+/// Extension of the packaged format: a zip holding `document.json` and the
+/// images it uses under `resources/`, named by their SHA-256.
+pub const BUNDLE_EXTENSION: &str = "pdxz";
+
+const DOCUMENT_ENTRY: &str = "document.json";
+const RESOURCE_DIR: &str = "resources/";
+
+/// Largest zip entry read into memory. Declared sizes are not trusted, so a
+/// forged header or a zip bomb fails here instead of exhausting memory.
+const MAX_ENTRY_SIZE: u64 = 256 * 1024 * 1024;
+
+/// Whether file data is a bundle rather than plain `.pdx` JSON.
+pub fn is_bundle(data: &[u8]) -> bool {
+    data.starts_with(b"PK\x03\x04")
+}
+
+pub fn read_bundle(data: &[u8]) -> Result<PdxDocument, PdxError> {
+    let mut archive = ZipArchive::new(Cursor::new(data)).map_err(bundle_error)?;
+
+    let entry = archive
+        .by_name(DOCUMENT_ENTRY)
+        .map_err(|_| PdxError::Bundle(format!("no {}", DOCUMENT_ENTRY)))?;
+    let bytes = read_entry(entry).map_err(bundle_error)?;
+    let text = String::from_utf8(bytes).map_err(bundle_error)?;
+    let mut document = PdxDocument::from_json(&text)?;
+
+    for index in 0..archive.len() {
+        let entry = archive.by_index(index).map_err(bundle_error)?;
+        if !entry.is_file() || !entry.name().starts_with(RESOURCE_DIR) {
+            continue;
+        }
+
+        let name = entry.name().to_string();
+        let bytes = read_entry(entry).map_err(bundle_error)?;
+        document.resources.files.insert(name, bytes);
+    }
+
+    Ok(document)
+}
+
+/// Packs the document and every image it can read into a bundle.
+pub fn write_bundle(document: &PdxDocument) -> Result<Vec<u8>, PdxError> {
+    let mut document = document.clone();
+    embed_images(&mut document);
+
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    // Images are compressed already
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+
+    writer
+        .start_file(DOCUMENT_ENTRY, deflated)
+        .map_err(bundle_error)?;
+    writer
+        .write_all(document.to_json()?.as_bytes())
+        .map_err(bundle_error)?;
+
+    // Only what the content still refers to, in a stable order
+    let mut names: Vec<&str> = document.content.image_paths();
+    names.retain(|name| document.resources.files.contains_key(*name));
+    names.sort_unstable();
+    names.dedup();
+
+    for name in names {
+        writer.start_file(name, stored).map_err(bundle_error)?;
+        writer
+            .write_all(&document.resources.files[name])
+            .map_err(bundle_error)?;
+    }
+
+    Ok(writer.finish().map_err(bundle_error)?.into_inner())
+}
+
+/// Copies every image the document refers to into its resources under a
+/// content-hashed name and points the content at the copy, so the document
+/// no longer depends on files next to it. Images already embedded are left
+/// alone and unreadable ones keep their path. Returns how many were embedded.
+pub fn embed_images(document: &mut PdxDocument) -> usize {
+    let resources = &mut document.resources;
+    let mut embedded = 0;
+
+    document.content.for_each_image_path(&mut |path| {
+        if resources.files.contains_key(path.as_str()) {
+            return;
+        }
+        let Some(bytes) = resources.read(path) else {
+            return;
+        };
+
+        let name = resource_name(path, &bytes);
+        resources.files.entry(name.clone()).or_insert(bytes);
+        *path = name;
+        embedded += 1;
+    });
+
+    embedded
+}
+
+/// `resources/<sha256>.<ext>`, so identical images are stored once.
+pub(crate) fn resource_name(path: &str, bytes: &[u8]) -> String {
+    let hash: String = Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    let extension = Path::new(path)
+        .extension()
+        .map_or("bin".to_string(), |ext| {
+            ext.to_string_lossy().to_lowercase()
+        });
+
+    format!("{}{}.{}", RESOURCE_DIR, hash, extension)
+}
+
+/// Reads a zip entry up to `MAX_ENTRY_SIZE`, growing the buffer as data
+/// arrives rather than by the size the entry claims.
+pub(crate) fn read_entry(entry: impl Read) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    entry.take(MAX_ENTRY_SIZE + 1).read_to_end(&mut bytes)?;
+
+    if bytes.len() as u64 > MAX_ENTRY_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("entry larger than {} MB", MAX_ENTRY_SIZE / (1024 * 1024)),
+        ));
+    }
+    Ok(bytes)
+}
+
+fn bundle_error(e: impl std::fmt::Display) -> PdxError {
+    PdxError::Bundle(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::Node;
+    use crate::test_support::{document, paragraph, png, unzip};
+
+    fn image(path: &str) -> Node {
+        Node::Image {
+            path: path.to_string(),
+            alt_text: String::new(),
+            width: None,
+            height: None,
+        }
+    }
+
+    #[test]
+    fn bundles_read_back_to_the_same_document() {
+        let name = resource_name("cat.png", &png());
+        let mut original = document(vec![paragraph("مرحبا", "ar"), image(&name)]);
+        original.resources.files.insert(name.clone(), png());
+
+        let data = write_bundle(&original).unwrap();
+        assert!(is_bundle(&data));
+        let read = read_bundle(&data).unwrap();
+
+        assert_eq!(read.content, original.content);
+        assert_eq!(read.metadata, original.metadata);
+        assert_eq!(read.resources.files, original.resources.files);
+    }
+
+    #[test]
+    fn identical_images_are_stored_once() {
+        let dir = std::env::temp_dir().join(format!("pdx-bundle-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["a.png", "b.png"] {
+            std::fs::write(dir.join(name), png()).unwrap();
+        }
+        let mut original = document(vec![image("a.png"), image("b.png")]);
+        original.resources.base_dir = Some(dir.clone());
+
+        let data = write_bundle(&original);
+        std::fs::remove_dir_all(&dir).unwrap();
+        let names: Vec<String> = unzip(&data.unwrap())
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+
+        assert_eq!(
+            names,
+            [DOCUMENT_ENTRY.to_string(), resource_name("a.png", &png())]
+        );
+    }
+
+    #[test]
+    fn files_the_content_no_longer_uses_are_left_out() {
+        let kept = resource_name("kept.png", &png());
+        let mut original = document(vec![image(&kept)]);
+        original.resources.files.insert(kept.clone(), png());
+        original
+            .resources
+            .files
+            .insert(resource_name("gone.png", b"old"), b"old".to_vec());
+
+        let read = read_bundle(&write_bundle(&original).unwrap()).unwrap();
+        assert_eq!(read.resources.files.keys().collect::<Vec<_>>(), [&kept]);
+    }
+
+    #[test]
+    fn oversized_entries_are_refused() {
+        let error = read_entry(io::repeat(0)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
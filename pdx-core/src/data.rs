@@ -0,0 +1,715 @@
+use crate::error::PdxError;
+use crate::migrate;
+use crate::pdx_text::is_rtl_language;
+use crate::validate::CURRENT_VERSION;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+// ============================================================================
+// Core Data Structures
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PdxDocument {
+    pub version: u32,
+    pub metadata: Metadata,
+    pub styles: StyleSheet,
+    pub content: Node,
+    #[serde(skip)]
+    pub resources: Resources,
+}
+
+impl PdxDocument {
+    /// Reads a document from its `.pdx` JSON form, upgrading older format
+    /// versions. See [`migrate::load`].
+    pub fn from_json(text: &str) -> Result<Self, PdxError> {
+        migrate::load(text).map(|(document, _)| document)
+    }
+
+    /// Writes the document as pretty-printed `.pdx` JSON, always in the
+    /// current format version.
+    pub fn to_json(&self) -> Result<String, PdxError> {
+        let result = if self.version == CURRENT_VERSION {
+            serde_json::to_string_pretty(self)
+        } else {
+            serde_json::to_string_pretty(&PdxDocument {
+                version: CURRENT_VERSION,
+                ..self.clone()
+            })
+        };
+        result.map_err(|e| PdxError::Serialize(e.to_string()))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Metadata {
+    pub title: String,
+    pub author: String,
+    pub language: String,
+    pub created: String,
+    pub modified: String,
+    pub keywords: Vec<String>,
+}
+
+impl Default for Metadata {
+    fn default() -> Self {
+        Self {
+            title: "Untitled Document".to_string(),
+            author: String::new(),
+            language: "en".to_string(),
+            created: chrono::Local::now().to_string(),
+            modified: chrono::Local::now().to_string(),
+            keywords: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StyleSheet {
+    pub styles: HashMap<String, Style>,
+    pub active_theme: String,
+}
+
+impl Default for StyleSheet {
+    fn default() -> Self {
+        let mut styles = HashMap::new();
+
+        styles.insert(
+            "heading1".to_string(),
+            Style {
+                font_size: 28.0,
+                font_weight: FontWeight::Bold,
+                color: Color::rgb(0, 0, 0),
+                text_align: TextAlign::Start,
+                margin: EdgeInsets::new(12.0, 0.0, 16.0, 0.0),
+                ..Default::default()
+            },
+        );
+
+        styles.insert(
+            "heading2".to_string(),
+            Style {
+                font_size: 22.0,
+                font_weight: FontWeight::Bold,
+                color: Color::rgb(40, 40, 40),
+                text_align: TextAlign::Start,
+                margin: EdgeInsets::new(10.0, 0.0, 12.0, 0.0),
+                ..Default::default()
+            },
+        );
+
+        for (level, font_size, gray, gap) in [
+            (3, 18.0, 40, 10.0),
+            (4, 16.0, 50, 8.0),
+            (5, 14.0, 60, 8.0),
+            (6, 13.0, 70, 6.0),
+        ] {
+            styles.insert(
+                format!("heading{}", level),
+                Style {
+                    font_size,
+                    font_weight: FontWeight::Bold,
+                    color: Color::rgb(gray, gray, gray),
+                    text_align: TextAlign::Start,
+                    margin: EdgeInsets::new(gap, 0.0, gap, 0.0),
+                    ..Default::default()
+                },
+            );
+        }
+
+        styles.insert(
+            "paragraph".to_string(),
+            Style {
+                font_size: 16.0,
+                font_weight: FontWeight::Normal,
+                color: Color::rgb(0, 0, 0),
+                text_align: TextAlign::Start,
+                line_height: 1.8,
+                margin: EdgeInsets::new(0.0, 0.0, 10.0, 0.0),
+                ..Default::default()
+            },
+        );
+
+        styles.insert(
+            "arabic".to_string(),
+            Style {
+                font_size: 18.0,
+                font_weight: FontWeight::Normal,
+                color: Color::rgb(0, 0, 0),
+                text_align: TextAlign::Start,
+                line_height: 2.0,
+                direction: Direction::RTL,
+                ..Default::default()
+            },
+        );
+
+        Self {
+            styles,
+            active_theme: "default".to_string(),
+        }
+    }
+}
+
+impl StyleSheet {
+    /// Looks up a style by name, falling back to `paragraph` and filling in
+    /// unset sizes so layout code always gets usable values. A missing
+    /// `headingN` falls back to the closest heading level the sheet has,
+    /// preferring the larger one, so older sheets without `heading3` and up
+    /// still render headings as headings.
+    pub fn resolve(&self, name: &str) -> Style {
+        let mut style = self
+            .styles
+            .get(name)
+            .or_else(|| self.nearest_heading(name))
+            .or_else(|| self.styles.get("paragraph"))
+            .cloned()
+            .unwrap_or_default();
+
+        if style.font_size <= 0.0 {
+            style.font_size = 16.0;
+        }
+        if style.line_height <= 0.0 {
+            style.line_height = 1.4;
+        }
+
+        style
+    }
+
+    fn nearest_heading(&self, name: &str) -> Option<&Style> {
+        let level: u32 = name.strip_prefix("heading")?.parse().ok()?;
+
+        (1..=6u32)
+            .filter_map(|other| Some((other, self.styles.get(&format!("heading{}", other))?)))
+            .min_by_key(|(other, _)| (other.abs_diff(level), *other))
+            .map(|(_, style)| style)
+    }
+
+    /// Reads a style sheet exported on its own, or the one inside a `.pdx`
+    /// document.
+    pub fn from_json(text: &str) -> Result<Self, PdxError> {
+        serde_json::from_str::<StyleSheet>(text).or_else(|e| {
+            PdxDocument::from_json(text)
+                .map(|document| document.styles)
+                .map_err(|_| e.into())
+        })
+    }
+
+    pub fn to_json(&self) -> Result<String, PdxError> {
+        serde_json::to_string_pretty(self).map_err(|e| PdxError::Serialize(e.to_string()))
+    }
+
+    /// Adds the styles of `other`, replacing any with the same name.
+    pub fn merge(&mut self, other: StyleSheet) {
+        self.styles.extend(other.styles);
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct Style {
+    #[serde(default)]
+    pub font_size: f32,
+    #[serde(default)]
+    pub font_weight: FontWeight,
+    #[serde(default)]
+    pub color: Color,
+    #[serde(default)]
+    pub text_align: TextAlign,
+    #[serde(default)]
+    pub direction: Direction,
+    #[serde(default)]
+    pub line_height: f32,
+    #[serde(default)]
+    pub margin: EdgeInsets,
+    #[serde(default)]
+    pub padding: EdgeInsets,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+pub enum FontWeight {
+    #[default]
+    Normal,
+    Bold,
+    Light,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+pub enum TextAlign {
+    #[default]
+    Start,
+    End,
+    Center,
+    Justify,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+pub enum Direction {
+    LTR,
+    RTL,
+    #[default]
+    Auto,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+pub struct EdgeInsets {
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub left: f32,
+}
+
+impl EdgeInsets {
+    pub fn new(top: f32, right: f32, bottom: f32, left: f32) -> Self {
+        Self {
+            top,
+            right,
+            bottom,
+            left,
+        }
+    }
+
+    pub fn all(value: f32) -> Self {
+        Self::new(value, value, value, value)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Node {
+    Document {
+        children: Vec<Node>,
+    },
+    Heading {
+        level: u8,
+        runs: Vec<TextRun>,
+        style: String,
+    },
+    Paragraph {
+        runs: Vec<TextRun>,
+        style: String,
+    },
+    List {
+        ordered: bool,
+        /// Number of the first item in an ordered list
+        #[serde(default = "default_list_start")]
+        start: u32,
+        items: Vec<ListItem>,
+        style: String,
+    },
+    CodeBlock {
+        language: String,
+        code: String,
+        style: String,
+    },
+    Image {
+        path: String,
+        alt_text: String,
+        width: Option<f32>,
+        height: Option<f32>,
+    },
+    Table {
+        rows: Vec<TableRow>,
+        /// Number of leading rows rendered as headers
+        header_rows: usize,
+        /// Per-column alignment; columns beyond the list use `Start`
+        alignments: Vec<TextAlign>,
+        /// `RTL` tables lay out their first column on the right
+        direction: Direction,
+        style: String,
+    },
+    Divider,
+    PageBreak,
+}
+
+fn default_list_start() -> u32 {
+    1
+}
+
+impl Default for Node {
+    fn default() -> Self {
+        Node::Document {
+            children: Vec::new(),
+        }
+    }
+}
+
+impl Node {
+    /// Points every block and run using style `from` at style `to`.
+    pub fn rename_style(&mut self, from: &str, to: &str) {
+        let rename = |style: &mut String| {
+            if style == from {
+                *style = to.to_string();
+            }
+        };
+        let rename_runs = |runs: &mut Vec<TextRun>| {
+            for run in runs {
+                rename(&mut run.style);
+            }
+        };
+
+        match self {
+            Node::Document { children } => {
+                for child in children {
+                    child.rename_style(from, to);
+                }
+            }
+            Node::Heading { runs, style, .. } | Node::Paragraph { runs, style } => {
+                rename(style);
+                rename_runs(runs);
+            }
+            Node::List { items, style, .. } => {
+                rename(style);
+                for item in items {
+                    rename_runs(&mut item.content);
+                    for child in &mut item.children {
+                        child.rename_style(from, to);
+                    }
+                }
+            }
+            Node::CodeBlock { style, .. } => rename(style),
+            Node::Table { rows, style, .. } => {
+                rename(style);
+                for cell in rows.iter_mut().flat_map(|row| &mut row.cells) {
+                    rename_runs(&mut cell.content);
+                }
+            }
+            Node::Image { .. } | Node::Divider | Node::PageBreak => {}
+        }
+    }
+
+    /// Paths of every image in the tree, in document order.
+    pub fn image_paths(&self) -> Vec<&str> {
+        match self {
+            Node::Document { children } => children.iter().flat_map(Node::image_paths).collect(),
+            Node::List { items, .. } => items
+                .iter()
+                .flat_map(|item| &item.children)
+                .flat_map(Node::image_paths)
+                .collect(),
+            Node::Image { path, .. } => vec![path.as_str()],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Calls `f` on the path of every image in the tree.
+    pub fn for_each_image_path(&mut self, f: &mut impl FnMut(&mut String)) {
+        match self {
+            Node::Document { children } => {
+                for child in children {
+                    child.for_each_image_path(f);
+                }
+            }
+            Node::List { items, .. } => {
+                for child in items.iter_mut().flat_map(|item| &mut item.children) {
+                    child.for_each_image_path(f);
+                }
+            }
+            Node::Image { path, .. } => f(path),
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TextRun {
+    pub text: String,
+    pub language: String,
+    pub direction: Direction,
+    pub style: String,
+    #[serde(default, skip_serializing_if = "InlineMarks::is_plain")]
+    pub marks: InlineMarks,
+}
+
+/// Inline formatting applied to a whole run.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InlineMarks {
+    #[serde(default)]
+    pub strong: bool,
+    #[serde(default)]
+    pub emphasis: bool,
+    #[serde(default)]
+    pub code: bool,
+    #[serde(default)]
+    pub strikethrough: bool,
+    #[serde(default)]
+    pub underline: bool,
+    #[serde(default)]
+    pub link: Option<String>,
+}
+
+impl InlineMarks {
+    pub fn is_plain(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl TextRun {
+    pub fn new(text: &str, language: &str, style: &str) -> Self {
+        let direction = if is_rtl_language(language) {
+            Direction::RTL
+        } else {
+            Direction::LTR
+        };
+
+        Self {
+            text: text.to_string(),
+            language: language.to_string(),
+            direction,
+            style: style.to_string(),
+            marks: InlineMarks::default(),
+        }
+    }
+
+    pub fn with_marks(mut self, marks: InlineMarks) -> Self {
+        self.marks = marks;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ListItem {
+    pub content: Vec<TextRun>,
+    /// Blocks nested under the item, such as sub-lists
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<Node>,
+}
+
+impl ListItem {
+    /// Language of the item's first run, used to pick marker digits.
+    pub fn language(&self) -> &str {
+        self.content
+            .first()
+            .map_or("en", |run| run.language.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TableRow {
+    pub cells: Vec<TableCell>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TableCell {
+    pub content: Vec<TextRun>,
+}
+
+impl TableCell {
+    /// Adds the runs of one of an imported cell's paragraphs, after a line
+    /// break when the cell has text already. Runs take the `paragraph`
+    /// style, and header cells drop bold, since they are bold when
+    /// rendered anyway. The break ends the previous run and runs that
+    /// match it are merged, as the importers do for text within a paragraph.
+    pub(crate) fn push_paragraph(&mut self, runs: Vec<TextRun>, header: bool) {
+        if runs.is_empty() {
+            return;
+        }
+        if let Some(last) = self.content.last_mut() {
+            last.text.push('\n');
+        }
+        for mut run in runs {
+            run.style = "paragraph".to_string();
+            if header {
+                run.marks.strong = false;
+            }
+            match self.content.last_mut() {
+                Some(last)
+                    if last.language == run.language
+                        && last.direction == run.direction
+                        && last.marks == run.marks =>
+                {
+                    last.text.push_str(&run.text);
+                }
+                _ => self.content.push(run),
+            }
+        }
+    }
+}
+
+/// Files a document carries with it, and where to find the ones it doesn't.
+#[derive(Debug, Clone, Default)]
+pub struct Resources {
+    pub images: HashMap<String, DynamicImage>,
+    /// Embedded files by the path the content refers to them with, e.g.
+    /// `resources/<sha256>.png` in a bundle
+    pub files: HashMap<String, Vec<u8>>,
+    /// Directory of the document file; relative paths resolve against it
+    pub base_dir: Option<PathBuf>,
+}
+
+impl Resources {
+    /// Where a referenced file is on disk: absolute paths as they are,
+    /// relative ones against the document directory when known.
+    pub fn resolve(&self, path: &str) -> PathBuf {
+        match &self.base_dir {
+            Some(dir) if Path::new(path).is_relative() => dir.join(path),
+            _ => PathBuf::from(path),
+        }
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.images.contains_key(path)
+            || self.files.contains_key(path)
+            || self.resolve(path).exists()
+    }
+
+    /// Reads a referenced file, embedded or on disk.
+    pub fn read(&self, path: &str) -> Option<Vec<u8>> {
+        match self.files.get(path) {
+            Some(bytes) => Some(bytes.clone()),
+            None => std::fs::read(self.resolve(path)).ok(),
+        }
+    }
+
+    pub fn load_image(&self, path: &str) -> Option<DynamicImage> {
+        if let Some(image) = self.images.get(path) {
+            return Some(image.clone());
+        }
+        match self.files.get(path) {
+            Some(bytes) => image::load_from_memory(bytes).ok(),
+            None => image::open(self.resolve(path)).ok(),
+        }
+    }
+}
+
+// ============================================================================
+// Sample Document
+// ============================================================================
+
+pub fn create_sample_document() -> PdxDocument {
+    PdxDocument {
+        version: CURRENT_VERSION,
+        metadata: Metadata {
+            title: "PDX Demo Document".to_string(),
+            author: "PDX Editor".to_string(),
+            language: "en".to_string(),
+            created: chrono::Local::now().to_string(),
+            modified: chrono::Local::now().to_string(),
+            keywords: vec!["pdx".to_string(), "document".to_string(), "مستند".to_string()],
+        },
+        styles: StyleSheet::default(),
+        content: Node::Document {
+            children: vec![
+                Node::Heading {
+                    level: 1,
+                    runs: vec![TextRun::new("Welcome to PDX Editor", "en", "heading1")],
+                    style: "heading1".to_string(),
+                },
+                Node::Paragraph {
+                    runs: vec![TextRun::new(
+                        "PDX is a modern document format with full Arabic support, real PDF/PNG export, and a comfortable theme for long writing sessions.",
+                        "en",
+                        "paragraph",
+                    )],
+                    style: "paragraph".to_string(),
+                },
+                Node::Divider,
+                Node::Heading {
+                    level: 2,
+                    runs: vec![TextRun::new("مرحباً بك في محرر PDX", "ar", "heading2")],
+                    style: "heading2".to_string(),
+                },
+                Node::Paragraph {
+                    runs: vec![TextRun::new(
+                        "هذا المحرر يدعم اللغة العربية بشكل كامل مع الكتابة من اليمين إلى اليسار. يمكنك كتابة المستندات بالعربية بسهولة تامة.",
+                        "ar",
+                        "arabic",
+                    )],
+                    style: "arabic".to_string(),
+                },
+                Node::Divider,
+                Node::Heading {
+                    level: 2,
+                    runs: vec![TextRun::new(
+                        "New Features - المميزات الجديدة",
+                        "en",
+                        "heading2",
+                    )],
+                    style: "heading2".to_string(),
+                },
+                Node::List {
+                    ordered: false,
+                    start: 1,
+                    items: vec![
+                        ListItem {
+                            content: vec![TextRun::new(
+                                "Real PDF export with Arabic font embedding",
+                                "en",
+                                "paragraph",
+                            )],
+                            children: Vec::new(),
+                        },
+                        ListItem {
+                            content: vec![TextRun::new(
+                                "PNG image export for sharing",
+                                "en",
+                                "paragraph",
+                            )],
+                            children: Vec::new(),
+                        },
+                        ListItem {
+                            content: vec![TextRun::new(
+                                "Image embedding support in documents",
+                                "en",
+                                "paragraph",
+                            )],
+                            children: Vec::new(),
+                        },
+                        ListItem {
+                            content: vec![TextRun::new(
+                                "Comfort theme - optimized for long writing sessions",
+                                "en",
+                                "paragraph",
+                            )],
+                            children: Vec::new(),
+                        },
+                    ],
+                    style: "list".to_string(),
+                },
+            ],
+        },
+        resources: Resources::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_heading_level_has_a_bold_default() {
+        let sheet = StyleSheet::default();
+
+        for level in 1..=6 {
+            let style = &sheet.styles[&format!("heading{}", level)];
+            assert_eq!(style.font_weight, FontWeight::Bold);
+        }
+    }
+
+    #[test]
+    fn missing_heading_levels_use_the_nearest_heading() {
+        let mut sheet = StyleSheet::default();
+        for level in 3..=6 {
+            sheet.styles.remove(&format!("heading{}", level));
+        }
+
+        assert_eq!(sheet.resolve("heading4"), sheet.resolve("heading2"));
+        assert_eq!(sheet.resolve("heading9"), sheet.resolve("heading2"));
+        assert_eq!(sheet.resolve("custom"), sheet.resolve("paragraph"));
+
+        sheet.styles.remove("heading1");
+        assert_eq!(sheet.resolve("heading1"), sheet.resolve("heading2"));
+    }
+}
@@ -0,0 +1,49 @@
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+
+// ============================================================================
+// Metadata Dates
+// ============================================================================
+
+/// A metadata date in the W3C format (`2024-03-01T08:20:30Z`) that DOCX,
+/// ODT, EPUB, HTML and LaTeX metadata use, or `None` when it can't be read.
+/// Metadata dates are usually `chrono`'s default format; RFC 3339 and plain
+/// dates are accepted too.
+pub(crate) fn w3c_date(text: &str) -> Option<String> {
+    let parsed = DateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S%.f %:z")
+        .or_else(|_| DateTime::parse_from_rfc3339(text))
+        .map(|date| date.with_timezone(&Utc));
+    match parsed {
+        Ok(date) => Some(date.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        Err(_) => NaiveDate::parse_from_str(text, "%Y-%m-%d")
+            .ok()
+            .map(|date| format!("{}T00:00:00Z", date)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates_are_given_in_utc_to_the_second() {
+        assert_eq!(
+            w3c_date("2024-03-01 10:20:30.123456789 +02:00").as_deref(),
+            Some("2024-03-01T08:20:30Z")
+        );
+        assert_eq!(
+            w3c_date("2024-03-01T10:20:30-05:00").as_deref(),
+            Some("2024-03-01T15:20:30Z")
+        );
+        assert_eq!(
+            w3c_date("2024-03-01").as_deref(),
+            Some("2024-03-01T00:00:00Z")
+        );
+    }
+
+    #[test]
+    fn unreadable_dates_are_left_out() {
+        assert_eq!(w3c_date(""), None);
+        assert_eq!(w3c_date("March 2024"), None);
+        assert_eq!(w3c_date("2024-13-01"), None);
+    }
+}
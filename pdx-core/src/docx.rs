@@ -0,0 +1,2212 @@
+use crate::bundle::{read_entry, resource_name};
+use crate::data::{
+    Color, Direction, FontWeight, InlineMarks, ListItem, Metadata, Node, PdxDocument, Resources,
+    Style, StyleSheet, TableCell, TableRow, TextAlign, TextRun,
+};
+use crate::dates::w3c_date;
+use crate::error::PdxError;
+use crate::layout::PX_TO_PT;
+use crate::pdx_text::{is_rtl_block, is_rtl_language, tagged_language};
+use crate::validate::CURRENT_VERSION;
+use crate::xml::{self, escape, Element};
+use ::image::ImageFormat;
+use std::collections::{BTreeSet, HashMap};
+use std::io::{Cursor, Read, Seek, Write};
+use zip::result::ZipError;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+// ============================================================================
+// DOCX Export
+// ============================================================================
+
+const MAIN_NS: &str = "http://schemas.openxmlformats.org/wordprocessingml/2006/main";
+const REL_NS: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+const PACKAGE_REL_NS: &str = "http://schemas.openxmlformats.org/package/2006/relationships";
+
+/// A4 with the 20 mm margins of the PDF export, in twentieths of a point.
+const PAGE_WIDTH: u32 = 11906;
+const PAGE_HEIGHT: u32 = 16838;
+const PAGE_MARGIN: u32 = 1134;
+
+const EMU_PER_TWIP: u64 = 635;
+const EMU_PER_PX: f32 = 9525.0;
+
+/// Fonts every Word and LibreOffice install has, Arabic included.
+const BODY_FONT: &str = "Arial";
+const CODE_FONT: &str = "Courier New";
+
+/// Writes the document as an Office Open XML (`.docx`) file. Every style in
+/// the style sheet becomes a Word paragraph style, RTL blocks become bidi
+/// paragraphs and each run carries its language, so Arabic text keeps its
+/// direction and proofing language in Word and LibreOffice.
+pub fn export_as_docx(document: &PdxDocument) -> Result<Vec<u8>, PdxError> {
+    let mut writer = DocxWriter::new(document);
+    let body = writer.blocks(&document.content, 0);
+    let document_xml = writer.document_xml(&body);
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+
+    let parts = [
+        ("[Content_Types].xml", writer.content_types_xml()),
+        ("_rels/.rels", package_rels_xml()),
+        ("docProps/core.xml", core_xml(document)),
+        ("word/document.xml", document_xml),
+        ("word/styles.xml", writer.styles_xml()),
+        ("word/numbering.xml", writer.numbering_xml()),
+        ("word/_rels/document.xml.rels", writer.document_rels_xml()),
+    ];
+
+    for (name, xml) in parts {
+        zip.start_file(name, deflated).map_err(docx_error)?;
+        zip.write_all(xml.as_bytes()).map_err(docx_error)?;
+    }
+    // Images are compressed already
+    for media in &writer.media {
+        zip.start_file(format!("word/{}", media.target), stored)
+            .map_err(docx_error)?;
+        zip.write_all(&media.bytes).map_err(docx_error)?;
+    }
+
+    Ok(zip.finish().map_err(docx_error)?.into_inner())
+}
+
+fn docx_error(e: impl std::fmt::Display) -> PdxError {
+    PdxError::Export {
+        format: "DOCX",
+        message: e.to_string(),
+    }
+}
+
+struct Media {
+    id: String,
+    /// Path inside `word/`, e.g. `media/image1.png`
+    target: String,
+    bytes: Vec<u8>,
+}
+
+/// One `w:num` per exported list, so every list restarts its numbering.
+struct Numbering {
+    ordered: bool,
+    start: u32,
+    /// Nesting depth of the list, its `w:ilvl`
+    level: usize,
+    /// Number format for ordered lists
+    format: &'static str,
+}
+
+/// Collects the relationships, media and numbering the body refers to
+/// while writing it.
+struct DocxWriter<'a> {
+    document: &'a PdxDocument,
+    media: Vec<Media>,
+    /// Media ids by the content path they were read from
+    media_ids: HashMap<String, String>,
+    /// Hyperlink targets by relationship id
+    links: Vec<(String, String)>,
+    numbering: Vec<Numbering>,
+    /// Style names used by the content, written even when not in the sheet
+    used_styles: BTreeSet<String>,
+    next_drawing: usize,
+}
+
+impl<'a> DocxWriter<'a> {
+    fn new(document: &'a PdxDocument) -> Self {
+        Self {
+            document,
+            media: Vec::new(),
+            media_ids: HashMap::new(),
+            links: Vec::new(),
+            numbering: Vec::new(),
+            used_styles: BTreeSet::new(),
+            next_drawing: 1,
+        }
+    }
+
+    fn styles(&self) -> &StyleSheet {
+        &self.document.styles
+    }
+
+    fn blocks(&mut self, node: &Node, depth: usize) -> String {
+        match node {
+            Node::Document { children } => children
+                .iter()
+                .map(|child| self.blocks(child, depth))
+                .collect(),
+
+            Node::Heading { level, runs, style } => {
+                let rtl = is_rtl_block(runs, self.styles().resolve(style).direction);
+                self.used_styles.insert(style.clone());
+                let properties = format!(
+                    "{}{}<w:outlineLvl w:val=\"{}\"/>",
+                    paragraph_style(style),
+                    bidi(rtl),
+                    (*level).clamp(1, 9) - 1
+                );
+                paragraph(&properties, &self.runs(runs))
+            }
+
+            Node::Paragraph { runs, style } => {
+                let rtl = is_rtl_block(runs, self.styles().resolve(style).direction);
+                self.used_styles.insert(style.clone());
+                let properties = format!("{}{}", paragraph_style(style), bidi(rtl));
+                paragraph(&properties, &self.runs(runs))
+            }
+
+            Node::List {
+                ordered,
+                start,
+                items,
+                style,
+            } => self.list(*ordered, *start, items, style, depth),
+
+            Node::CodeBlock { code, style, .. } => {
+                self.used_styles.insert(style.clone());
+                let text: Vec<String> =
+                    code.lines().map(|line| text_element(line, false)).collect();
+                let run = format!(
+                    "<w:r><w:rPr><w:rFonts w:ascii=\"{0}\" w:hAnsi=\"{0}\" w:cs=\"{0}\"/>\
+                     </w:rPr>{1}</w:r>",
+                    CODE_FONT,
+                    text.join("<w:br/>")
+                );
+                // Code reads left to right whatever the style says
+                let properties = format!("{}<w:bidi w:val=\"0\"/>", paragraph_style(style));
+                paragraph(&properties, &run)
+            }
+
+            Node::Image {
+                path,
+                alt_text,
+                width,
+                height,
+            } => match self.image(path, alt_text, *width, *height) {
+                Some(drawing) => paragraph("", &format!("<w:r>{}</w:r>", drawing)),
+                // Keep what the image was about when it cannot be read
+                None => paragraph(
+                    "",
+                    &format!(
+                        "<w:r><w:rPr><w:i/><w:iCs/></w:rPr>{}</w:r>",
+                        text_element(&format!("[{}]", alt_text), false)
+                    ),
+                ),
+            },
+
+            Node::Table {
+                rows,
+                header_rows,
+                alignments,
+                direction,
+                style,
+            } => {
+                self.used_styles.insert(style.clone());
+                self.table(rows, *header_rows, alignments, *direction, style)
+            }
+
+            Node::Divider => paragraph(
+                "<w:pBdr><w:bottom w:val=\"single\" w:sz=\"6\" w:space=\"1\" \
+                 w:color=\"auto\"/></w:pBdr>",
+                "",
+            ),
+
+            Node::PageBreak => paragraph("", "<w:r><w:br w:type=\"page\"/></w:r>"),
+        }
+    }
+
+    fn list(
+        &mut self,
+        ordered: bool,
+        start: u32,
+        items: &[ListItem],
+        style: &str,
+        depth: usize,
+    ) -> String {
+        let format = match items.first().map(|item| item.language()) {
+            Some("ar" | "ckb" | "fa" | "ur" | "ps" | "sd") if ordered => "hindiNumbers",
+            _ => "decimal",
+        };
+        self.numbering.push(Numbering {
+            ordered,
+            start,
+            level: depth.min(8),
+            format,
+        });
+        let num_id = self.numbering.len();
+        let direction = self.styles().resolve(style).direction;
+        self.used_styles.insert(style.to_string());
+
+        let mut xml = String::new();
+        for item in items {
+            let rtl = is_rtl_block(&item.content, direction);
+            let properties = format!(
+                "{}<w:numPr><w:ilvl w:val=\"{}\"/><w:numId w:val=\"{}\"/></w:numPr>{}",
+                paragraph_style(style),
+                depth.min(8),
+                num_id,
+                bidi(rtl)
+            );
+            xml.push_str(&paragraph(&properties, &self.runs(&item.content)));
+
+            for child in &item.children {
+                xml.push_str(&self.blocks(child, depth + 1));
+            }
+        }
+        xml
+    }
+
+    fn table(
+        &mut self,
+        rows: &[TableRow],
+        header_rows: usize,
+        alignments: &[TextAlign],
+        direction: Direction,
+        style: &str,
+    ) -> String {
+        let columns = rows.iter().map(|row| row.cells.len()).max().unwrap_or(0);
+        if columns == 0 {
+            return String::new();
+        }
+        let column_width = (PAGE_WIDTH - 2 * PAGE_MARGIN) / columns as u32;
+        let rtl = direction == Direction::RTL;
+
+        let mut xml = String::from("<w:tbl><w:tblPr>");
+        if rtl {
+            xml.push_str("<w:bidiVisual/>");
+        }
+        xml.push_str("<w:tblW w:w=\"0\" w:type=\"auto\"/><w:tblBorders>");
+        for side in ["top", "left", "bottom", "right", "insideH", "insideV"] {
+            xml.push_str(&format!(
+                "<w:{} w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"auto\"/>",
+                side
+            ));
+        }
+        xml.push_str("</w:tblBorders></w:tblPr><w:tblGrid>");
+        for _ in 0..columns {
+            xml.push_str(&format!("<w:gridCol w:w=\"{}\"/>", column_width));
+        }
+        xml.push_str("</w:tblGrid>");
+
+        for (r, row) in rows.iter().enumerate() {
+            let header = r < header_rows;
+            xml.push_str("<w:tr>");
+            if header {
+                xml.push_str("<w:trPr><w:tblHeader/></w:trPr>");
+            }
+
+            for c in 0..columns {
+                let runs = row.cells.get(c).map_or(&[][..], |cell| &cell.content);
+                let cell_rtl = is_rtl_block(runs, direction);
+                let mut properties = format!("{}{}", paragraph_style(style), bidi(cell_rtl));
+                properties.push_str(&justification(
+                    alignments.get(c).copied().unwrap_or(TextAlign::Start),
+                ));
+
+                let content = if header {
+                    let bold: Vec<TextRun> = runs
+                        .iter()
+                        .map(|run| {
+                            let mut run = run.clone();
+                            run.marks.strong = true;
+                            run
+                        })
+                        .collect();
+                    self.runs(&bold)
+                } else {
+                    self.runs(runs)
+                };
+
+                xml.push_str(&format!(
+                    "<w:tc><w:tcPr><w:tcW w:w=\"{}\" w:type=\"dxa\"/></w:tcPr>{}</w:tc>",
+                    column_width,
+                    paragraph(&properties, &content)
+                ));
+            }
+            xml.push_str("</w:tr>");
+        }
+
+        xml.push_str("</w:tbl>");
+        // Word needs a paragraph between a table and whatever follows
+        xml.push_str(&paragraph("", ""));
+        xml
+    }
+
+    /// Runs, with neighbours sharing a link wrapped in one `w:hyperlink`.
+    fn runs(&mut self, runs: &[TextRun]) -> String {
+        let mut xml = String::new();
+        let mut i = 0;
+
+        while i < runs.len() {
+            match &runs[i].marks.link {
+                Some(url) => {
+                    let end = runs[i..]
+                        .iter()
+                        .position(|r| r.marks.link.as_ref() != Some(url))
+                        .map_or(runs.len(), |offset| i + offset);
+
+                    let id = format!("rIdLink{}", self.links.len() + 1);
+                    self.links.push((id.clone(), url.clone()));
+
+                    xml.push_str(&format!("<w:hyperlink r:id=\"{}\">", id));
+                    for run in &runs[i..end] {
+                        xml.push_str(&run_xml(run, true));
+                    }
+                    xml.push_str("</w:hyperlink>");
+                    i = end;
+                }
+                None => {
+                    xml.push_str(&run_xml(&runs[i], false));
+                    i += 1;
+                }
+            }
+        }
+
+        xml
+    }
+
+    /// An inline drawing for the image, or `None` when it cannot be read.
+    fn image(
+        &mut self,
+        path: &str,
+        alt_text: &str,
+        width: Option<f32>,
+        height: Option<f32>,
+    ) -> Option<String> {
+        let resources = &self.document.resources;
+        let bytes = resources.read(path)?;
+        let decoded = ::image::load_from_memory(&bytes).ok()?;
+
+        let id = match self.media_ids.get(path) {
+            Some(id) => id.clone(),
+            None => {
+                let index = self.media.len() + 1;
+                // Word reads PNG, JPEG and GIF; anything else is converted
+                let (extension, bytes) = match ::image::guess_format(&bytes).ok()? {
+                    ImageFormat::Png => ("png", bytes),
+                    ImageFormat::Jpeg => ("jpeg", bytes),
+                    ImageFormat::Gif => ("gif", bytes),
+                    _ => {
+                        let mut png = Cursor::new(Vec::new());
+                        decoded.write_to(&mut png, ImageFormat::Png).ok()?;
+                        ("png", png.into_inner())
+                    }
+                };
+
+                let id = format!("rIdImage{}", index);
+                self.media.push(Media {
+                    id: id.clone(),
+                    target: format!("media/image{}.{}", index, extension),
+                    bytes,
+                });
+                self.media_ids.insert(path.to_string(), id.clone());
+                id
+            }
+        };
+
+        // Same sizing as the page layout: the given size, else the natural
+        // one, shrunk to fit the page
+        let natural_w = decoded.width().max(1) as f32;
+        let natural_h = decoded.height().max(1) as f32;
+        let (w, h) = match (width, height) {
+            (Some(w), Some(h)) => (w, h),
+            (Some(w), None) => (w, w * natural_h / natural_w),
+            (None, Some(h)) => (h * natural_w / natural_h, h),
+            (None, None) => (natural_w, natural_h),
+        };
+        let max_w = ((PAGE_WIDTH - 2 * PAGE_MARGIN) as u64 * EMU_PER_TWIP) as f32;
+        let max_h = ((PAGE_HEIGHT - 2 * PAGE_MARGIN) as u64 * EMU_PER_TWIP) as f32;
+        let scale = (max_w / (w * EMU_PER_PX))
+            .min(max_h / (h * EMU_PER_PX))
+            .min(1.0);
+        let cx = (w * EMU_PER_PX * scale) as u64;
+        let cy = (h * EMU_PER_PX * scale) as u64;
+
+        let drawing = self.next_drawing;
+        self.next_drawing += 1;
+        let name = escape(path.rsplit(['/', '\\']).next().unwrap_or(path));
+        let alt = escape(alt_text);
+
+        Some(format!(
+            "<w:drawing><wp:inline distT=\"0\" distB=\"0\" distL=\"0\" distR=\"0\">\
+             <wp:extent cx=\"{cx}\" cy=\"{cy}\"/>\
+             <wp:docPr id=\"{drawing}\" name=\"{name}\" descr=\"{alt}\"/>\
+             <wp:cNvGraphicFramePr><a:graphicFrameLocks noChangeAspect=\"1\"/></wp:cNvGraphicFramePr>\
+             <a:graphic><a:graphicData uri=\"http://schemas.openxmlformats.org/drawingml/2006/picture\">\
+             <pic:pic><pic:nvPicPr><pic:cNvPr id=\"{drawing}\" name=\"{name}\" descr=\"{alt}\"/>\
+             <pic:cNvPicPr/></pic:nvPicPr>\
+             <pic:blipFill><a:blip r:embed=\"{id}\"/><a:stretch><a:fillRect/></a:stretch></pic:blipFill>\
+             <pic:spPr><a:xfrm><a:off x=\"0\" y=\"0\"/><a:ext cx=\"{cx}\" cy=\"{cy}\"/></a:xfrm>\
+             <a:prstGeom prst=\"rect\"><a:avLst/></a:prstGeom></pic:spPr></pic:pic>\
+             </a:graphicData></a:graphic></wp:inline></w:drawing>"
+        ))
+    }
+
+    // ========================================================================
+    // Package Parts
+    // ========================================================================
+
+    fn document_xml(&self, body: &str) -> String {
+        let rtl = is_rtl_language(&self.document.metadata.language);
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+             <w:document xmlns:w=\"{MAIN_NS}\" xmlns:r=\"{REL_NS}\" \
+             xmlns:wp=\"http://schemas.openxmlformats.org/drawingml/2006/wordprocessingDrawing\" \
+             xmlns:a=\"http://schemas.openxmlformats.org/drawingml/2006/main\" \
+             xmlns:pic=\"http://schemas.openxmlformats.org/drawingml/2006/picture\">\
+             <w:body>{body}<w:sectPr>\
+             <w:pgSz w:w=\"{PAGE_WIDTH}\" w:h=\"{PAGE_HEIGHT}\"/>\
+             <w:pgMar w:top=\"{PAGE_MARGIN}\" w:right=\"{PAGE_MARGIN}\" w:bottom=\"{PAGE_MARGIN}\" \
+             w:left=\"{PAGE_MARGIN}\" w:header=\"708\" w:footer=\"708\" w:gutter=\"0\"/>{}\
+             </w:sectPr></w:body></w:document>",
+            if rtl { "<w:bidi/>" } else { "" }
+        )
+    }
+
+    /// A paragraph style per style sheet entry and per style the content
+    /// uses, which `StyleSheet::resolve` fills in. `paragraph` is Word's
+    /// default style and `headingN` its built-in headings, so outlines and
+    /// the navigation pane work.
+    fn styles_xml(&self) -> String {
+        let language = &self.document.metadata.language;
+        let lang = if is_rtl_language(language) {
+            format!("<w:lang w:val=\"en\" w:bidi=\"{}\"/>", escape(language))
+        } else {
+            format!("<w:lang w:val=\"{}\" w:bidi=\"ar\"/>", escape(language))
+        };
+
+        let mut xml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+             <w:styles xmlns:w=\"{MAIN_NS}\"><w:docDefaults><w:rPrDefault><w:rPr>\
+             <w:rFonts w:ascii=\"{BODY_FONT}\" w:hAnsi=\"{BODY_FONT}\" w:eastAsia=\"{BODY_FONT}\" \
+             w:cs=\"{BODY_FONT}\"/>{lang}</w:rPr></w:rPrDefault>\
+             <w:pPrDefault><w:pPr><w:spacing w:after=\"0\"/></w:pPr></w:pPrDefault></w:docDefaults>"
+        );
+
+        let mut names: BTreeSet<&str> = self.styles().styles.keys().map(String::as_str).collect();
+        names.extend(self.used_styles.iter().map(String::as_str));
+        names.insert("paragraph");
+
+        for name in names {
+            xml.push_str(&style_xml(name, &self.styles().resolve(name)));
+        }
+
+        xml.push_str(
+            "<w:style w:type=\"character\" w:styleId=\"Hyperlink\"><w:name w:val=\"Hyperlink\"/>\
+             <w:uiPriority w:val=\"99\"/><w:unhideWhenUsed/><w:rPr><w:color w:val=\"0563C1\"/>\
+             <w:u w:val=\"single\"/></w:rPr></w:style></w:styles>",
+        );
+        xml
+    }
+
+    fn numbering_xml(&self) -> String {
+        let mut xml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+             <w:numbering xmlns:w=\"{MAIN_NS}\">"
+        );
+
+        // One definition per list keeps each list's own start number
+        for (i, list) in self.numbering.iter().enumerate() {
+            xml.push_str(&format!(
+                "<w:abstractNum w:abstractNumId=\"{}\"><w:multiLevelType w:val=\"hybridMultilevel\"/>",
+                i
+            ));
+            for level in 0..9 {
+                let indent = 720 * (level + 1);
+                let (format, text) = if list.ordered {
+                    (list.format, format!("%{}.", level + 1))
+                } else {
+                    ("bullet", ["•", "◦", "▪"][level % 3].to_string())
+                };
+                let start = if level == list.level { list.start } else { 1 };
+                xml.push_str(&format!(
+                    "<w:lvl w:ilvl=\"{level}\"><w:start w:val=\"{start}\"/>\
+                     <w:numFmt w:val=\"{format}\"/><w:lvlText w:val=\"{text}\"/>\
+                     <w:lvlJc w:val=\"left\"/><w:pPr><w:ind w:left=\"{indent}\" w:hanging=\"360\"/>\
+                     </w:pPr></w:lvl>"
+                ));
+            }
+            xml.push_str("</w:abstractNum>");
+        }
+        for i in 0..self.numbering.len() {
+            xml.push_str(&format!(
+                "<w:num w:numId=\"{}\"><w:abstractNumId w:val=\"{}\"/></w:num>",
+                i + 1,
+                i
+            ));
+        }
+
+        xml.push_str("</w:numbering>");
+        xml
+    }
+
+    fn document_rels_xml(&self) -> String {
+        let mut xml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+             <Relationships xmlns=\"{PACKAGE_REL_NS}\">\
+             <Relationship Id=\"rIdStyles\" Type=\"{REL_NS}/styles\" Target=\"styles.xml\"/>\
+             <Relationship Id=\"rIdNumbering\" Type=\"{REL_NS}/numbering\" Target=\"numbering.xml\"/>"
+        );
+        for media in &self.media {
+            xml.push_str(&format!(
+                "<Relationship Id=\"{}\" Type=\"{}/image\" Target=\"{}\"/>",
+                media.id, REL_NS, media.target
+            ));
+        }
+        for (id, url) in &self.links {
+            xml.push_str(&format!(
+                "<Relationship Id=\"{}\" Type=\"{}/hyperlink\" Target=\"{}\" TargetMode=\"External\"/>",
+                id,
+                REL_NS,
+                escape(url)
+            ));
+        }
+        xml.push_str("</Relationships>");
+        xml
+    }
+
+    fn content_types_xml(&self) -> String {
+        let main = "application/vnd.openxmlformats-officedocument.wordprocessingml";
+        let mut xml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+             <Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">\
+             <Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>\
+             <Default Extension=\"xml\" ContentType=\"application/xml\"/>\
+             <Override PartName=\"/word/document.xml\" ContentType=\"{main}.document.main+xml\"/>\
+             <Override PartName=\"/word/styles.xml\" ContentType=\"{main}.styles+xml\"/>\
+             <Override PartName=\"/word/numbering.xml\" ContentType=\"{main}.numbering+xml\"/>\
+             <Override PartName=\"/docProps/core.xml\" \
+             ContentType=\"application/vnd.openxmlformats-package.core-properties+xml\"/>"
+        );
+
+        let extensions: BTreeSet<&str> = self
+            .media
+            .iter()
+            .filter_map(|media| media.target.rsplit('.').next())
+            .collect();
+        for extension in extensions {
+            xml.push_str(&format!(
+                "<Default Extension=\"{0}\" ContentType=\"image/{0}\"/>",
+                extension
+            ));
+        }
+
+        xml.push_str("</Types>");
+        xml
+    }
+}
+
+fn package_rels_xml() -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+         <Relationships xmlns=\"{PACKAGE_REL_NS}\">\
+         <Relationship Id=\"rId1\" Type=\"{REL_NS}/officeDocument\" Target=\"word/document.xml\"/>\
+         <Relationship Id=\"rId2\" \
+         Type=\"http://schemas.openxmlformats.org/package/2006/relationships/metadata/core-properties\" \
+         Target=\"docProps/core.xml\"/></Relationships>"
+    )
+}
+
+/// Title, author, language and keywords as Word's document properties.
+fn core_xml(document: &PdxDocument) -> String {
+    let metadata = &document.metadata;
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+         <cp:coreProperties \
+         xmlns:cp=\"http://schemas.openxmlformats.org/package/2006/metadata/core-properties\" \
+         xmlns:dc=\"http://purl.org/dc/elements/1.1/\" xmlns:dcterms=\"http://purl.org/dc/terms/\" \
+         xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\">",
+    );
+
+    xml.push_str(&format!("<dc:title>{}</dc:title>", escape(&metadata.title)));
+    if !metadata.author.is_empty() {
+        xml.push_str(&format!(
+            "<dc:creator>{}</dc:creator>",
+            escape(&metadata.author)
+        ));
+    }
+    xml.push_str(&format!(
+        "<dc:language>{}</dc:language>",
+        escape(&metadata.language)
+    ));
+    if !metadata.keywords.is_empty() {
+        xml.push_str(&format!(
+            "<cp:keywords>{}</cp:keywords>",
+            escape(&metadata.keywords.join(", "))
+        ));
+    }
+    // Dates are only written when they can be given in the W3C format
+    for (element, date) in [
+        ("created", &metadata.created),
+        ("modified", &metadata.modified),
+    ] {
+        if let Some(date) = w3c_date(date) {
+            xml.push_str(&format!(
+                "<dcterms:{0} xsi:type=\"dcterms:W3CDTF\">{1}</dcterms:{0}>",
+                element, date
+            ));
+        }
+    }
+
+    xml.push_str("</cp:coreProperties>");
+    xml
+}
+
+// ============================================================================
+// WordprocessingML Helpers
+// ============================================================================
+
+/// Word style id for a style sheet name. Headings map onto the built-in
+/// heading styles and `paragraph` onto Normal.
+fn style_id(name: &str) -> String {
+    if name == "paragraph" {
+        return "Normal".to_string();
+    }
+    if let Some(level) = heading_level(name) {
+        return format!("Heading{}", level);
+    }
+
+    let id: String = name.chars().filter(|c| c.is_alphanumeric()).collect();
+    if id.is_empty() {
+        "Style".to_string()
+    } else {
+        format!("PDX{}", id)
+    }
+}
+
+/// `1` to `9` for `heading1` to `heading9`.
+fn heading_level(name: &str) -> Option<u8> {
+    name.strip_prefix("heading")
+        .and_then(|n| n.parse().ok())
+        .filter(|n| (1..=9).contains(n))
+}
+
+fn style_xml(name: &str, style: &Style) -> String {
+    let id = style_id(name);
+    let heading = heading_level(name);
+
+    let (display_name, extra) = match (name, heading) {
+        ("paragraph", _) => ("Normal".to_string(), " w:default=\"1\""),
+        (_, Some(level)) => (format!("heading {}", level), ""),
+        _ => (name.to_string(), " w:customStyle=\"1\""),
+    };
+
+    let mut xml = format!(
+        "<w:style w:type=\"paragraph\"{} w:styleId=\"{}\"><w:name w:val=\"{}\"/>",
+        extra,
+        escape(&id),
+        escape(&display_name)
+    );
+    if name != "paragraph" {
+        xml.push_str("<w:basedOn w:val=\"Normal\"/><w:next w:val=\"Normal\"/>");
+    }
+    xml.push_str("<w:qFormat/><w:pPr>");
+    if heading.is_some() {
+        xml.push_str("<w:keepNext/>");
+    }
+    xml.push_str(bidi(style.direction == Direction::RTL));
+
+    let twips = |px: f32| (px * PX_TO_PT * 20.0).round() as i32;
+    xml.push_str(&format!(
+        "<w:spacing w:before=\"{}\" w:after=\"{}\" w:line=\"{}\" w:lineRule=\"auto\"/>",
+        twips(style.margin.top + style.padding.top),
+        twips(style.margin.bottom + style.padding.bottom),
+        (style.line_height.max(1.0) * 240.0).round() as i32
+    ));
+    let start = twips(style.margin.left + style.padding.left);
+    let end = twips(style.margin.right + style.padding.right);
+    if start != 0 || end != 0 {
+        xml.push_str(&format!(
+            "<w:ind w:left=\"{}\" w:right=\"{}\"/>",
+            start, end
+        ));
+    }
+    xml.push_str(&justification(style.text_align));
+    if let Some(level) = heading {
+        xml.push_str(&format!("<w:outlineLvl w:val=\"{}\"/>", level - 1));
+    }
+    xml.push_str("</w:pPr><w:rPr>");
+
+    if style.font_weight == FontWeight::Bold {
+        xml.push_str("<w:b/><w:bCs/>");
+    }
+    let color = style.color;
+    xml.push_str(&format!(
+        "<w:color w:val=\"{:02X}{:02X}{:02X}\"/>",
+        color.r, color.g, color.b
+    ));
+    let half_points = (style.font_size * PX_TO_PT * 2.0).round() as i32;
+    xml.push_str(&format!(
+        "<w:sz w:val=\"{0}\"/><w:szCs w:val=\"{0}\"/>",
+        half_points
+    ));
+
+    xml.push_str("</w:rPr></w:style>");
+    xml
+}
+
+fn paragraph(properties: &str, content: &str) -> String {
+    if properties.is_empty() {
+        format!("<w:p>{}</w:p>", content)
+    } else {
+        format!("<w:p><w:pPr>{}</w:pPr>{}</w:p>", properties, content)
+    }
+}
+
+fn paragraph_style(name: &str) -> String {
+    format!("<w:pStyle w:val=\"{}\"/>", escape(&style_id(name)))
+}
+
+fn bidi(rtl: bool) -> &'static str {
+    if rtl {
+        "<w:bidi/>"
+    } else {
+        ""
+    }
+}
+
+/// In a bidi paragraph Word reads `left` and `right` as start and end.
+fn justification(align: TextAlign) -> String {
+    let value = match align {
+        TextAlign::Start => "left",
+        TextAlign::End => "right",
+        TextAlign::Center => "center",
+        TextAlign::Justify => "both",
+    };
+    format!("<w:jc w:val=\"{}\"/>", value)
+}
+
+fn run_xml(run: &TextRun, link: bool) -> String {
+    let marks = &run.marks;
+    let rtl = run.direction == Direction::RTL;
+    let mut properties = String::new();
+
+    if link {
+        properties.push_str("<w:rStyle w:val=\"Hyperlink\"/>");
+    }
+    if marks.code {
+        properties.push_str(&format!(
+            "<w:rFonts w:ascii=\"{0}\" w:hAnsi=\"{0}\" w:cs=\"{0}\"/>",
+            CODE_FONT
+        ));
+    }
+    if marks.strong {
+        properties.push_str("<w:b/><w:bCs/>");
+    }
+    if marks.emphasis {
+        properties.push_str("<w:i/><w:iCs/>");
+    }
+    if marks.strikethrough {
+        properties.push_str("<w:strike/>");
+    }
+    if marks.underline {
+        properties.push_str("<w:u w:val=\"single\"/>");
+    }
+    if rtl {
+        properties.push_str("<w:rtl/>");
+    }
+    // Right-to-left scripts are Word's "complex script" language slot
+    if !run.language.is_empty() {
+        let attribute = if rtl || is_rtl_language(&run.language) {
+            "w:bidi"
+        } else {
+            "w:val"
+        };
+        properties.push_str(&format!(
+            "<w:lang {}=\"{}\"/>",
+            attribute,
+            escape(&run.language)
+        ));
+    }
+
+    format!(
+        "<w:r><w:rPr>{}</w:rPr>{}</w:r>",
+        properties,
+        text_element(&run.text, true)
+    )
+}
+
+/// Text as `w:t` elements, with line breaks and tabs as their own elements.
+fn text_element(text: &str, breaks: bool) -> String {
+    let mut xml = String::new();
+
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 && breaks {
+            xml.push_str("<w:br/>");
+        }
+        for (j, part) in line.split('\t').enumerate() {
+            if j > 0 {
+                xml.push_str("<w:tab/>");
+            }
+            if !part.is_empty() {
+                xml.push_str(&format!(
+                    "<w:t xml:space=\"preserve\">{}</w:t>",
+                    escape(part)
+                ));
+            }
+        }
+    }
+
+    xml
+}
+
+// ============================================================================
+// DOCX Import
+// ============================================================================
+
+/// Fonts whose runs are read as inline code.
+const MONOSPACE_FONTS: [&str; 6] = [
+    "courier new",
+    "courier",
+    "consolas",
+    "menlo",
+    "dejavu sans mono",
+    "liberation mono",
+];
+
+/// Reads a Word document. Paragraph styles become style sheet entries,
+/// headings are recognised by outline level, numbered paragraphs become
+/// lists and pictures are extracted into the document's resources under
+/// content-hashed names, so the document saves as a bundle.
+pub fn import_docx(data: &[u8], fallback_title: &str) -> Result<PdxDocument, PdxError> {
+    let mut archive = ZipArchive::new(Cursor::new(data)).map_err(import_error)?;
+
+    let package = read_part(&mut archive, "_rels/.rels")?
+        .map(|xml| parse_part("_rels/.rels", &xml))
+        .transpose()?;
+    let main = package
+        .as_ref()
+        .and_then(|rels| relationship_target(rels, "/officeDocument", ""))
+        .unwrap_or_else(|| "word/document.xml".to_string());
+    let (dir, file) = main.rsplit_once('/').unwrap_or(("", main.as_str()));
+
+    let body =
+        read_part(&mut archive, &main)?.ok_or_else(|| import_error(format!("no {}", main)))?;
+    let body = parse_part(&main, &body)?;
+
+    let rels_path = join_part(dir, &format!("_rels/{}.rels", file));
+    let rels = match read_part(&mut archive, &rels_path)? {
+        Some(xml) => parse_part(&rels_path, &xml)?,
+        None => Element::default(),
+    };
+
+    let mut optional = |kind: &str, default: &str| -> Result<Option<Element>, PdxError> {
+        let path = relationship_target(&rels, kind, dir).unwrap_or_else(|| join_part(dir, default));
+        read_part(&mut archive, &path)?
+            .map(|xml| parse_part(&path, &xml))
+            .transpose()
+    };
+    let styles = optional("/styles", "styles.xml")?;
+    let numbering = optional("/numbering", "numbering.xml")?;
+    let core = match package
+        .as_ref()
+        .and_then(|rels| relationship_target(rels, "/core-properties", ""))
+    {
+        Some(path) => read_part(&mut archive, &path)?
+            .map(|xml| parse_part(&path, &xml))
+            .transpose()?,
+        None => None,
+    };
+
+    // Pictures are read up front, by relationship id
+    let mut media = HashMap::new();
+    for relationship in rels.children_named("Relationship") {
+        let (Some(id), Some(kind), Some(target)) = (
+            relationship.attr("Id"),
+            relationship.attr("Type"),
+            relationship.attr("Target"),
+        ) else {
+            continue;
+        };
+        if !kind.ends_with("/image") || relationship.attr("TargetMode") == Some("External") {
+            continue;
+        }
+        let path = join_part(dir, target);
+        if let Some(bytes) = read_bytes(&mut archive, &path)? {
+            media.insert(id.to_string(), (path, bytes));
+        }
+    }
+
+    let mut reader = DocxReader::new(styles.as_ref(), numbering.as_ref(), &rels, media);
+    if let Some(body) = body.child("w:body") {
+        reader.blocks(body);
+    }
+    reader.flush_lists();
+
+    let children = std::mem::take(&mut reader.nodes);
+    let metadata = read_core(core.as_ref(), &children, fallback_title);
+
+    let mut sheet = StyleSheet::default();
+    for id in &reader.used_styles {
+        sheet
+            .styles
+            .insert(reader.style_name(id), reader.resolved_style(id));
+    }
+
+    Ok(PdxDocument {
+        version: CURRENT_VERSION,
+        metadata,
+        styles: sheet,
+        content: Node::Document { children },
+        resources: Resources {
+            files: reader.files,
+            ..Resources::default()
+        },
+    })
+}
+
+fn import_error(e: impl std::fmt::Display) -> PdxError {
+    PdxError::Import {
+        format: "DOCX",
+        message: e.to_string(),
+    }
+}
+
+fn read_part<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    path: &str,
+) -> Result<Option<String>, PdxError> {
+    Ok(read_bytes(archive, path)?.map(|bytes| String::from_utf8_lossy(&bytes).into_owned()))
+}
+
+fn read_bytes<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    path: &str,
+) -> Result<Option<Vec<u8>>, PdxError> {
+    let entry = match archive.by_name(path) {
+        Ok(entry) => entry,
+        Err(ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(import_error(e)),
+    };
+    read_entry(entry).map(Some).map_err(import_error)
+}
+
+fn parse_part(path: &str, text: &str) -> Result<Element, PdxError> {
+    xml::parse(text).map_err(|e| import_error(format!("{}: {}", path, e)))
+}
+
+/// Target of the first relationship whose type ends with `kind`, as a path
+/// in the package.
+fn relationship_target(rels: &Element, kind: &str, dir: &str) -> Option<String> {
+    rels.children_named("Relationship")
+        .find(|r| r.attr("Type").is_some_and(|t| t.ends_with(kind)))
+        .and_then(|r| r.attr("Target"))
+        .map(|target| join_part(dir, target))
+}
+
+/// Resolves a relationship target against the directory of its source part.
+fn join_part(dir: &str, target: &str) -> String {
+    let mut parts: Vec<&str> = match target.strip_prefix('/') {
+        Some(_) => Vec::new(),
+        None => dir.split('/').filter(|p| !p.is_empty()).collect(),
+    };
+    for part in target.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+/// Title, author, language and keywords from the document properties. The
+/// title falls back to the first heading, the language to the first text.
+fn read_core(core: Option<&Element>, children: &[Node], fallback_title: &str) -> Metadata {
+    let field = |name: &str| {
+        core.and_then(|core| core.child(name))
+            .map(|element| element.text().trim().to_string())
+            .filter(|text| !text.is_empty())
+    };
+    let title = field("dc:title")
+        .or_else(|| {
+            children.iter().find_map(|node| match node {
+                Node::Heading { runs, .. } => Some(runs.iter().map(|r| r.text.as_str()).collect()),
+                _ => None,
+            })
+        })
+        .unwrap_or_else(|| fallback_title.to_string());
+    let language = field("dc:language")
+        .or_else(|| {
+            children.iter().find_map(|node| match node {
+                Node::Heading { runs, .. } | Node::Paragraph { runs, .. } => {
+                    runs.first().map(|run| run.language.clone())
+                }
+                _ => None,
+            })
+        })
+        .unwrap_or_else(|| "en".to_string());
+    let keywords = field("cp:keywords")
+        .map(|keywords| {
+            keywords
+                .split([',', ';'])
+                .map(str::trim)
+                .filter(|k| !k.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let defaults = Metadata::default();
+    Metadata {
+        title,
+        author: field("dc:creator").unwrap_or(defaults.author),
+        language,
+        created: field("dcterms:created").unwrap_or(defaults.created),
+        modified: field("dcterms:modified").unwrap_or(defaults.modified),
+        keywords,
+    }
+}
+
+/// `w:val` of a toggle property such as `w:b`: on unless it says otherwise.
+fn is_on(element: Option<&Element>) -> bool {
+    element.is_some_and(|e| !matches!(e.attr("w:val"), Some("0" | "false" | "off" | "none")))
+}
+
+fn val<'e>(parent: Option<&'e Element>, name: &str) -> Option<&'e str> {
+    parent?.child(name)?.attr("w:val")
+}
+
+fn parse_align(value: &str) -> Option<TextAlign> {
+    match value {
+        "left" | "start" => Some(TextAlign::Start),
+        "right" | "end" => Some(TextAlign::End),
+        "center" => Some(TextAlign::Center),
+        "both" | "distribute" | "justify" => Some(TextAlign::Justify),
+        _ => None,
+    }
+}
+
+/// Level format and start number of a numbering level.
+#[derive(Clone, Copy)]
+struct ListLevel {
+    ordered: bool,
+    start: u32,
+}
+
+/// A list being read, one per nesting level.
+struct OpenList {
+    num_id: String,
+    level: ListLevel,
+    style: String,
+    items: Vec<ListItem>,
+}
+
+/// What a paragraph holds besides its runs.
+#[derive(Default)]
+struct ParagraphContent {
+    runs: Vec<TextRun>,
+    images: Vec<Node>,
+    /// A page break before any text
+    break_before: bool,
+    /// A page break after some text
+    break_after: bool,
+}
+
+/// Languages Word records for a run: `w:val` for Latin text and `w:bidi`
+/// for right-to-left scripts.
+#[derive(Clone, Default)]
+struct WordLanguage {
+    latin: Option<String>,
+    bidi: Option<String>,
+}
+
+impl WordLanguage {
+    fn apply(&mut self, rpr: Option<&Element>) {
+        if let Some(lang) = rpr.and_then(|rpr| rpr.child("w:lang")) {
+            if let Some(latin) = lang.attr("w:val") {
+                self.latin = Some(latin.to_string());
+            }
+            if let Some(bidi) = lang.attr("w:bidi") {
+                self.bidi = Some(bidi.to_string());
+            }
+        }
+    }
+}
+
+struct DocxReader<'a> {
+    /// `w:style` elements by style id
+    styles: HashMap<&'a str, &'a Element>,
+    default_style: Option<&'a str>,
+    default_run: Option<&'a Element>,
+    default_paragraph: Option<&'a Element>,
+    /// Levels of each `w:num`, by numbering id
+    numbering: HashMap<String, Vec<ListLevel>>,
+    rels: &'a Element,
+    /// Package path and bytes of each picture, by relationship id
+    media: HashMap<String, (String, Vec<u8>)>,
+    /// Extracted pictures by resource name
+    files: HashMap<String, Vec<u8>>,
+    used_styles: BTreeSet<String>,
+    nodes: Vec<Node>,
+    lists: Vec<OpenList>,
+    /// The last block was a paragraph in the `code` style
+    in_code: bool,
+}
+
+impl<'a> DocxReader<'a> {
+    fn new(
+        styles: Option<&'a Element>,
+        numbering: Option<&'a Element>,
+        rels: &'a Element,
+        media: HashMap<String, (String, Vec<u8>)>,
+    ) -> Self {
+        let mut reader = Self {
+            styles: HashMap::new(),
+            default_style: None,
+            default_run: None,
+            default_paragraph: None,
+            numbering: HashMap::new(),
+            rels,
+            media,
+            files: HashMap::new(),
+            used_styles: BTreeSet::new(),
+            nodes: Vec::new(),
+            lists: Vec::new(),
+            in_code: false,
+        };
+
+        if let Some(styles) = styles {
+            for style in styles.children_named("w:style") {
+                let Some(id) = style.attr("w:styleId") else {
+                    continue;
+                };
+                reader.styles.insert(id, style);
+                if style.attr("w:type") == Some("paragraph")
+                    && matches!(style.attr("w:default"), Some("1" | "true" | "on"))
+                {
+                    reader.default_style = Some(id);
+                }
+            }
+            let defaults = styles.child("w:docDefaults");
+            reader.default_run = defaults
+                .and_then(|d| d.child("w:rPrDefault"))
+                .and_then(|d| d.child("w:rPr"));
+            reader.default_paragraph = defaults
+                .and_then(|d| d.child("w:pPrDefault"))
+                .and_then(|d| d.child("w:pPr"));
+        }
+
+        if let Some(numbering) = numbering {
+            let mut abstracts: HashMap<&str, Vec<ListLevel>> = HashMap::new();
+            for definition in numbering.children_named("w:abstractNum") {
+                let Some(id) = definition.attr("w:abstractNumId") else {
+                    continue;
+                };
+                let mut levels = vec![
+                    ListLevel {
+                        ordered: false,
+                        start: 1,
+                    };
+                    9
+                ];
+                for lvl in definition.children_named("w:lvl") {
+                    let Some(index) = lvl.attr("w:ilvl").and_then(|i| i.parse::<usize>().ok())
+                    else {
+                        continue;
+                    };
+                    if let Some(level) = levels.get_mut(index) {
+                        level.ordered =
+                            !matches!(val(Some(lvl), "w:numFmt"), Some("bullet" | "none") | None);
+                        level.start = val(Some(lvl), "w:start")
+                            .and_then(|s| s.parse().ok())
+                            .unwrap_or(1);
+                    }
+                }
+                abstracts.insert(id, levels);
+            }
+
+            for num in numbering.children_named("w:num") {
+                let (Some(id), Some(abstract_id)) =
+                    (num.attr("w:numId"), val(Some(num), "w:abstractNumId"))
+                else {
+                    continue;
+                };
+                let mut levels = abstracts.get(abstract_id).cloned().unwrap_or_default();
+                for overridden in num.children_named("w:lvlOverride") {
+                    let index = overridden
+                        .attr("w:ilvl")
+                        .and_then(|i| i.parse::<usize>().ok());
+                    let start =
+                        val(Some(overridden), "w:startOverride").and_then(|s| s.parse().ok());
+                    if let (Some(level), Some(start)) =
+                        (index.and_then(|i| levels.get_mut(i)), start)
+                    {
+                        level.start = start;
+                    }
+                }
+                reader.numbering.insert(id.to_string(), levels);
+            }
+        }
+
+        reader
+    }
+
+    // ========================================================================
+    // Styles
+    // ========================================================================
+
+    /// The style and the ones it is based on, base first.
+    fn style_chain(&self, id: &str) -> Vec<&'a Element> {
+        let mut chain = Vec::new();
+        let mut current = self.styles.get(id).copied();
+        // Bounded, in case of a cycle
+        while let Some(style) = current
+            && chain.len() < 16
+        {
+            chain.push(style);
+            current = val(Some(style), "w:basedOn").and_then(|base| self.styles.get(base).copied());
+        }
+        chain.reverse();
+        chain
+    }
+
+    /// Style sheet name for a Word style: Normal is `paragraph`, the built-in
+    /// headings are `headingN`, and other names are lower-cased with dashes.
+    fn style_name(&self, id: &str) -> String {
+        let name = self
+            .styles
+            .get(id)
+            .and_then(|style| val(Some(style), "w:name"))
+            .unwrap_or(id);
+        let lower = name.trim().to_lowercase();
+
+        if lower == "normal" || Some(id) == self.default_style {
+            return "paragraph".to_string();
+        }
+        if let Some(level) = lower
+            .strip_prefix("heading")
+            .map(str::trim)
+            .and_then(|n| n.parse::<u8>().ok())
+        {
+            return format!("heading{}", level);
+        }
+        lower.split_whitespace().collect::<Vec<_>>().join("-")
+    }
+
+    /// The Word style as a style sheet entry, with what it inherits.
+    fn resolved_style(&self, id: &str) -> Style {
+        let mut style = Style {
+            // Word's own default is 10 pt
+            font_size: 20.0 / 2.0 / PX_TO_PT,
+            line_height: 1.0,
+            ..Default::default()
+        };
+
+        apply_paragraph_properties(&mut style, self.default_paragraph);
+        apply_run_properties(&mut style, self.default_run);
+        for element in self.style_chain(id) {
+            apply_paragraph_properties(&mut style, element.child("w:pPr"));
+            apply_run_properties(&mut style, element.child("w:rPr"));
+        }
+        style
+    }
+
+    /// A paragraph property set directly or by the paragraph's style.
+    fn paragraph_property(
+        &self,
+        ppr: Option<&'a Element>,
+        style_id: &str,
+        name: &str,
+    ) -> Option<&'a Element> {
+        ppr.and_then(|ppr| ppr.child(name)).or_else(|| {
+            self.style_chain(style_id)
+                .iter()
+                .rev()
+                .find_map(|style| style.child("w:pPr").and_then(|ppr| ppr.child(name)))
+        })
+    }
+
+    // ========================================================================
+    // Blocks
+    // ========================================================================
+
+    fn blocks(&mut self, container: &Element) {
+        for element in container.elements() {
+            match element.name.as_str() {
+                "w:p" => self.paragraph(element),
+                "w:tbl" => {
+                    self.flush_lists();
+                    self.in_code = false;
+                    let table = self.table(element);
+                    self.nodes.push(table);
+                }
+                "w:sdt" => {
+                    if let Some(content) = element.child("w:sdtContent") {
+                        self.blocks(content);
+                    }
+                }
+                "w:customXml" | "w:ins" => self.blocks(element),
+                _ => {}
+            }
+        }
+    }
+
+    fn paragraph(&mut self, p: &Element) {
+        let ppr = p.child("w:pPr");
+        let style_id = val(ppr, "w:pStyle")
+            .or(self.default_style)
+            .unwrap_or("Normal")
+            .to_string();
+        let style_name = self.style_name(&style_id);
+
+        let mut content = ParagraphContent::default();
+        let language = self.paragraph_language(&style_id);
+        self.inline(p, None, &language, &mut content);
+        let text: String = content.runs.iter().map(|r| r.text.as_str()).collect();
+
+        let break_before = content.break_before
+            || is_on(self.paragraph_property(ppr, &style_id, "w:pageBreakBefore"));
+        if break_before {
+            self.flush_lists();
+            self.nodes.push(Node::PageBreak);
+        }
+
+        // Word keeps one code line per paragraph; consecutive ones are one block
+        if style_name == "code" {
+            self.flush_lists();
+            self.used_styles.insert(style_id);
+            match self.nodes.last_mut() {
+                Some(Node::CodeBlock { code, .. }) if self.in_code => {
+                    code.push('\n');
+                    code.push_str(&text);
+                }
+                _ => self.nodes.push(Node::CodeBlock {
+                    language: "text".to_string(),
+                    code: text,
+                    style: style_name,
+                }),
+            }
+            self.in_code = true;
+            return;
+        }
+        self.in_code = false;
+
+        let num_id = self
+            .paragraph_property(ppr, &style_id, "w:numPr")
+            .and_then(|numpr| val(Some(numpr), "w:numId"))
+            .filter(|id| *id != "0" && self.numbering.contains_key(*id));
+        let outline = self
+            .paragraph_property(ppr, &style_id, "w:outlineLvl")
+            .and_then(|lvl| lvl.attr("w:val"))
+            .and_then(|lvl| lvl.parse::<u8>().ok())
+            .filter(|lvl| *lvl < 9);
+
+        let has_text = !text.trim().is_empty();
+        if let (Some(num_id), None) = (num_id, outline) {
+            let level = ppr
+                .and_then(|ppr| ppr.child("w:numPr"))
+                .and_then(|numpr| val(Some(numpr), "w:ilvl"))
+                .and_then(|ilvl| ilvl.parse::<usize>().ok())
+                .unwrap_or(0)
+                .min(8);
+            let runs = content
+                .runs
+                .into_iter()
+                .map(|mut run| {
+                    run.style = "paragraph".to_string();
+                    run
+                })
+                .collect();
+            let list_style = if style_name == "paragraph" {
+                "list".to_string()
+            } else {
+                self.used_styles.insert(style_id);
+                style_name
+            };
+            self.list_item(num_id.to_string(), level, list_style, runs, content.images);
+        } else {
+            self.flush_lists();
+
+            if let Some(outline) = outline
+                && has_text
+            {
+                self.used_styles.insert(style_id);
+                self.nodes.push(Node::Heading {
+                    level: outline.min(5) + 1,
+                    runs: content.runs,
+                    style: style_name,
+                });
+            } else if has_text {
+                self.used_styles.insert(style_id);
+                self.nodes.push(Node::Paragraph {
+                    runs: content.runs,
+                    style: style_name,
+                });
+            } else if content.images.is_empty()
+                && ppr
+                    .and_then(|ppr| ppr.child("w:pBdr"))
+                    .is_some_and(|border| border.child("w:bottom").is_some())
+            {
+                // An empty paragraph with a bottom border is a rule
+                self.nodes.push(Node::Divider);
+            }
+            self.nodes.extend(content.images);
+        }
+
+        if content.break_after {
+            self.flush_lists();
+            self.nodes.push(Node::PageBreak);
+        }
+    }
+
+    /// Adds an item to the open lists, opening or closing nested ones to
+    /// reach its level. A different numbering at the same level starts a
+    /// new list.
+    fn list_item(
+        &mut self,
+        num_id: String,
+        level: usize,
+        style: String,
+        runs: Vec<TextRun>,
+        images: Vec<Node>,
+    ) {
+        while self.lists.len() > level + 1 {
+            self.close_list();
+        }
+        if self.lists.len() == level + 1 && self.lists[level].num_id != num_id {
+            self.close_list();
+        }
+        while self.lists.len() < level + 1 {
+            let depth = self.lists.len();
+            let list_level = self.numbering[&num_id]
+                .get(depth)
+                .copied()
+                .unwrap_or(ListLevel {
+                    ordered: false,
+                    start: 1,
+                });
+            self.lists.push(OpenList {
+                num_id: num_id.clone(),
+                level: list_level,
+                style: style.clone(),
+                items: Vec::new(),
+            });
+        }
+
+        if let Some(list) = self.lists.last_mut() {
+            list.items.push(ListItem {
+                content: runs,
+                children: images,
+            });
+        }
+    }
+
+    /// Closes the innermost open list, nesting it in its parent's last item.
+    fn close_list(&mut self) {
+        let Some(list) = self.lists.pop() else {
+            return;
+        };
+        let node = Node::List {
+            ordered: list.level.ordered,
+            start: list.level.start,
+            items: list.items,
+            style: list.style,
+        };
+
+        match self.lists.last_mut() {
+            Some(parent) => {
+                if parent.items.is_empty() {
+                    parent.items.push(ListItem {
+                        content: Vec::new(),
+                        children: Vec::new(),
+                    });
+                }
+                if let Some(item) = parent.items.last_mut() {
+                    item.children.push(node);
+                }
+            }
+            None => self.nodes.push(node),
+        }
+    }
+
+    fn flush_lists(&mut self) {
+        while !self.lists.is_empty() {
+            self.close_list();
+        }
+    }
+
+    fn table(&mut self, tbl: &Element) -> Node {
+        let mut rows = Vec::new();
+        let mut header_rows = 0;
+        let mut alignments = Vec::new();
+
+        for (r, tr) in tbl.children_named("w:tr").enumerate() {
+            let header = tr
+                .child("w:trPr")
+                .is_some_and(|trpr| is_on(trpr.child("w:tblHeader")));
+            if header && header_rows == r {
+                header_rows += 1;
+            }
+
+            let header = header && r < header_rows;
+            let mut cells = Vec::new();
+            for tc in tr.children_named("w:tc") {
+                let mut cell = TableCell {
+                    content: Vec::new(),
+                };
+                for p in tc.children_named("w:p") {
+                    let style_id = val(p.child("w:pPr"), "w:pStyle")
+                        .or(self.default_style)
+                        .unwrap_or("Normal")
+                        .to_string();
+                    let language = self.paragraph_language(&style_id);
+                    let mut content = ParagraphContent::default();
+                    self.inline(p, None, &language, &mut content);
+
+                    if r == 0 {
+                        let align = self
+                            .paragraph_property(p.child("w:pPr"), &style_id, "w:jc")
+                            .and_then(|jc| jc.attr("w:val"))
+                            .and_then(parse_align)
+                            .unwrap_or(TextAlign::Start);
+                        if alignments.len() == cells.len() {
+                            alignments.push(align);
+                        }
+                    }
+                    cell.push_paragraph(content.runs, header);
+                }
+                cells.push(cell);
+            }
+            rows.push(TableRow { cells });
+        }
+
+        let bidi_visual = tbl
+            .child("w:tblPr")
+            .and_then(|tblpr| tblpr.child("w:bidiVisual"));
+        let is_rtl = match bidi_visual {
+            Some(_) => is_on(bidi_visual),
+            None => rows
+                .iter()
+                .flat_map(|row: &TableRow| &row.cells)
+                .any(|cell| {
+                    cell.content
+                        .iter()
+                        .any(|run| run.direction == Direction::RTL)
+                }),
+        };
+
+        Node::Table {
+            rows,
+            header_rows,
+            alignments,
+            direction: if is_rtl {
+                Direction::RTL
+            } else {
+                Direction::LTR
+            },
+            style: "table".to_string(),
+        }
+    }
+
+    // ========================================================================
+    // Runs
+    // ========================================================================
+
+    /// Languages a paragraph's runs inherit from the defaults and its style.
+    fn paragraph_language(&self, style_id: &str) -> WordLanguage {
+        let mut language = WordLanguage::default();
+        language.apply(self.default_run);
+        for style in self.style_chain(style_id) {
+            language.apply(style.child("w:rPr"));
+        }
+        language
+    }
+
+    /// Collects the runs under `element`, descending into links, tracked
+    /// insertions and content controls.
+    fn inline(
+        &mut self,
+        element: &Element,
+        link: Option<&str>,
+        language: &WordLanguage,
+        content: &mut ParagraphContent,
+    ) {
+        for child in element.elements() {
+            match child.name.as_str() {
+                "w:r" => self.run(child, link, language, content),
+                "w:hyperlink" => {
+                    let target = child
+                        .attr("r:id")
+                        .and_then(|id| {
+                            self.rels
+                                .children_named("Relationship")
+                                .find(|r| r.attr("Id") == Some(id))
+                                .and_then(|r| r.attr("Target"))
+                                .map(str::to_string)
+                        })
+                        .or_else(|| child.attr("w:anchor").map(|anchor| format!("#{}", anchor)));
+                    let link = target.as_deref().or(link);
+                    self.inline(child, link, language, content);
+                }
+                "w:ins" | "w:smartTag" | "w:sdtContent" | "w:fldSimple" | "w:customXml"
+                | "w:dir" | "w:bdo" => self.inline(child, link, language, content),
+                "w:sdt" => {
+                    if let Some(inner) = child.child("w:sdtContent") {
+                        self.inline(inner, link, language, content);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn run(
+        &mut self,
+        r: &Element,
+        link: Option<&str>,
+        language: &WordLanguage,
+        content: &mut ParagraphContent,
+    ) {
+        let rpr = r.child("w:rPr");
+
+        // Character styles first, then direct formatting
+        let styled: Vec<&Element> = val(rpr, "w:rStyle")
+            .map(|id| {
+                self.style_chain(id)
+                    .into_iter()
+                    .filter_map(|style| style.child("w:rPr"))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let properties = styled
+            .iter()
+            .map(|props| (*props, false))
+            .chain(rpr.map(|props| (props, true)));
+
+        let mut marks = InlineMarks {
+            link: link.map(str::to_string),
+            ..Default::default()
+        };
+        let mut rtl = false;
+        let mut language = language.clone();
+        for (props, direct) in properties {
+            let props = Some(props);
+            let toggle = |name: &str, current: bool| match props.and_then(|p| p.child(name)) {
+                Some(element) => is_on(Some(element)),
+                None => current,
+            };
+            marks.strong = toggle("w:b", marks.strong);
+            marks.emphasis = toggle("w:i", marks.emphasis);
+            marks.strikethrough = toggle("w:dstrike", toggle("w:strike", marks.strikethrough));
+            // Links are underlined by their character style, not by the author
+            if direct || link.is_none() {
+                marks.underline = toggle("w:u", marks.underline);
+            }
+            rtl = toggle("w:rtl", rtl);
+            if let Some(font) = props
+                .and_then(|p| p.child("w:rFonts"))
+                .and_then(|fonts| fonts.attr("w:ascii").or(fonts.attr("w:hAnsi")))
+            {
+                marks.code = MONOSPACE_FONTS.contains(&font.to_lowercase().as_str());
+            }
+            language.apply(props);
+        }
+
+        let mut text = String::new();
+        for child in r.elements() {
+            match child.name.as_str() {
+                "w:t" => text.push_str(&child.text()),
+                "w:tab" => text.push('\t'),
+                "w:cr" => text.push('\n'),
+                "w:noBreakHyphen" => text.push('-'),
+                "w:br" if child.attr("w:type") == Some("page") => {
+                    self.push_run(&text, &marks, rtl, &language, content);
+                    text.clear();
+                    if content.runs.is_empty() {
+                        content.break_before = true;
+                    } else {
+                        content.break_after = true;
+                    }
+                }
+                "w:br" => text.push('\n'),
+                "w:drawing" => {
+                    if let Some(image) = self.drawing(child) {
+                        content.images.push(image);
+                    }
+                }
+                "w:pict" => {
+                    let image = child
+                        .find("v:imagedata")
+                        .and_then(|data| data.attr("r:id"))
+                        .and_then(|id| self.extract_image(id, String::new(), None, None));
+                    content.images.extend(image);
+                }
+                _ => {}
+            }
+        }
+        self.push_run(&text, &marks, rtl, &language, content);
+    }
+
+    /// Adds text as a run, merging it into the previous run when nothing
+    /// but Word's bookkeeping split them.
+    fn push_run(
+        &self,
+        text: &str,
+        marks: &InlineMarks,
+        rtl: bool,
+        word_language: &WordLanguage,
+        content: &mut ParagraphContent,
+    ) {
+        if text.is_empty() {
+            return;
+        }
+
+        let language = tagged_language(
+            text,
+            rtl,
+            word_language.latin.as_deref(),
+            word_language.bidi.as_deref(),
+            content.runs.last(),
+        );
+        if let Some(last) = content.runs.last_mut()
+            && last.marks == *marks
+            && last.language == language
+        {
+            last.text.push_str(text);
+            return;
+        }
+        content
+            .runs
+            .push(TextRun::new(text, &language, "paragraph").with_marks(marks.clone()));
+    }
+
+    fn drawing(&mut self, drawing: &Element) -> Option<Node> {
+        let id = drawing.find("a:blip")?.attr("r:embed")?;
+        let properties = drawing.find("wp:docPr");
+        let alt_text = properties
+            .and_then(|p| p.attr("descr").or(p.attr("title")))
+            .unwrap_or_default()
+            .to_string();
+
+        // Extents are in EMU; the model sizes images in pixels
+        let extent = drawing.find("wp:extent");
+        let size = |name: &str| {
+            extent
+                .and_then(|e| e.attr(name))
+                .and_then(|v| v.parse::<f32>().ok())
+                .map(|emu| (emu / EMU_PER_PX).round())
+        };
+        let (width, height) = (size("cx"), size("cy"));
+
+        self.extract_image(id, alt_text, width, height)
+    }
+
+    /// Moves a picture into the document's resources and returns its node.
+    fn extract_image(
+        &mut self,
+        id: &str,
+        alt_text: String,
+        width: Option<f32>,
+        height: Option<f32>,
+    ) -> Option<Node> {
+        let (path, bytes) = self.media.get(id)?;
+        let name = resource_name(path, bytes);
+        self.files
+            .entry(name.clone())
+            .or_insert_with(|| bytes.clone());
+
+        Some(Node::Image {
+            path: name,
+            alt_text,
+            width,
+            height,
+        })
+    }
+}
+
+fn apply_paragraph_properties(style: &mut Style, ppr: Option<&Element>) {
+    let Some(ppr) = ppr else {
+        return;
+    };
+    let twips = |element: &Element, names: &[&str]| {
+        names
+            .iter()
+            .find_map(|name| element.attr(name))
+            .and_then(|v| v.parse::<f32>().ok())
+            .map(|twips| twips / 20.0 / PX_TO_PT)
+    };
+
+    if let Some(align) = val(Some(ppr), "w:jc").and_then(parse_align) {
+        style.text_align = align;
+    }
+    if let Some(bidi) = ppr.child("w:bidi") {
+        style.direction = if is_on(Some(bidi)) {
+            Direction::RTL
+        } else {
+            Direction::LTR
+        };
+    }
+    if let Some(spacing) = ppr.child("w:spacing") {
+        if let Some(before) = twips(spacing, &["w:before"]) {
+            style.margin.top = before;
+        }
+        if let Some(after) = twips(spacing, &["w:after"]) {
+            style.margin.bottom = after;
+        }
+        let rule = spacing.attr("w:lineRule").unwrap_or("auto");
+        if let (Some(line), "auto") = (
+            spacing
+                .attr("w:line")
+                .and_then(|v| v.parse::<f32>().ok())
+                .filter(|line| *line > 0.0),
+            rule,
+        ) {
+            style.line_height = line / 240.0;
+        }
+    }
+    if let Some(indent) = ppr.child("w:ind") {
+        if let Some(start) = twips(indent, &["w:start", "w:left"]) {
+            style.margin.left = start;
+        }
+        if let Some(end) = twips(indent, &["w:end", "w:right"]) {
+            style.margin.right = end;
+        }
+    }
+}
+
+fn apply_run_properties(style: &mut Style, rpr: Option<&Element>) {
+    let Some(rpr) = rpr else {
+        return;
+    };
+
+    if let Some(bold) = rpr.child("w:b") {
+        style.font_weight = if is_on(Some(bold)) {
+            FontWeight::Bold
+        } else {
+            FontWeight::Normal
+        };
+    }
+    if let Some(size) = val(Some(rpr), "w:sz").and_then(|v| v.parse::<f32>().ok()) {
+        style.font_size = size / 2.0 / PX_TO_PT;
+    }
+    if let Some(color) = val(Some(rpr), "w:color").and_then(parse_color) {
+        style.color = color;
+    }
+}
+
+fn parse_color(hex: &str) -> Option<Color> {
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some(Color::rgb(channel(0)?, channel(2)?, channel(4)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{document, entry_xml, paragraph, png, unzip};
+
+    fn list(ordered: bool, start: u32, texts: &[&str], language: &str) -> Node {
+        Node::List {
+            ordered,
+            start,
+            items: texts
+                .iter()
+                .map(|text| ListItem {
+                    content: vec![TextRun::new(text, language, "paragraph")],
+                    children: Vec::new(),
+                })
+                .collect(),
+            style: "list".to_string(),
+        }
+    }
+
+    /// The package's parts in archive order.
+    fn export(document: &PdxDocument) -> Vec<(String, Vec<u8>)> {
+        unzip(&export_as_docx(document).unwrap())
+    }
+
+    fn paragraphs(root: &Element) -> Vec<&Element> {
+        root.child("w:body")
+            .unwrap()
+            .children_named("w:p")
+            .collect()
+    }
+
+    /// An attribute of the first element with this name below `parent`.
+    fn attr<'e>(parent: &'e Element, name: &str, attribute: &str) -> Option<&'e str> {
+        parent.find(name)?.attr(attribute)
+    }
+
+    #[test]
+    fn style_sheet_entries_become_paragraph_styles() {
+        let mut doc = document(vec![Node::Paragraph {
+            runs: vec![TextRun::new("Note", "en", "side note")],
+            style: "side note".to_string(),
+        }]);
+        doc.styles
+            .styles
+            .insert("side note".to_string(), Style::default());
+
+        let styles = entry_xml(&export(&doc), "word/styles.xml");
+        let ids: Vec<&str> = styles
+            .children_named("w:style")
+            .filter_map(|style| style.attr("w:styleId"))
+            .collect();
+
+        assert!(ids.contains(&"Normal"));
+        assert!(ids.contains(&"Heading1"));
+        assert!(ids.contains(&"PDXsidenote"));
+        let heading = styles
+            .children_named("w:style")
+            .find(|style| style.attr("w:styleId") == Some("Heading1"))
+            .unwrap();
+        assert_eq!(attr(heading, "w:outlineLvl", "w:val"), Some("0"));
+    }
+
+    #[test]
+    fn rtl_paragraphs_are_bidi_and_runs_carry_their_language() {
+        let parts = export(&document(vec![
+            paragraph("مرحبا", "ar"),
+            paragraph("Hello", "en"),
+        ]));
+        let root = entry_xml(&parts, "word/document.xml");
+        let paragraphs = paragraphs(&root);
+
+        let arabic = paragraphs[0];
+        assert!(arabic.find("w:pPr").unwrap().child("w:bidi").is_some());
+        assert!(arabic.find("w:rtl").is_some());
+        assert_eq!(attr(arabic, "w:lang", "w:bidi"), Some("ar"));
+
+        let english = paragraphs[1];
+        assert!(english.find("w:bidi").is_none());
+        assert!(english.find("w:rtl").is_none());
+        assert_eq!(attr(english, "w:lang", "w:val"), Some("en"));
+    }
+
+    #[test]
+    fn each_list_gets_its_own_numbering() {
+        let parts = export(&document(vec![
+            list(true, 3, &["three", "four"], "en"),
+            list(false, 1, &["dot"], "en"),
+            list(true, 1, &["واحد"], "ar"),
+        ]));
+
+        let root = entry_xml(&parts, "word/document.xml");
+        let ids: Vec<Option<&str>> = paragraphs(&root)
+            .iter()
+            .map(|p| attr(p, "w:numId", "w:val"))
+            .collect();
+        assert_eq!(ids, [Some("1"), Some("1"), Some("2"), Some("3")]);
+
+        let numbering = entry_xml(&parts, "word/numbering.xml");
+        let definitions: Vec<&Element> = numbering.children_named("w:abstractNum").collect();
+        assert_eq!(definitions.len(), 3);
+        assert_eq!(numbering.children_named("w:num").count(), 3);
+
+        let first_level = |i: usize| definitions[i].child("w:lvl");
+        assert_eq!(val(first_level(0), "w:start"), Some("3"));
+        assert_eq!(val(first_level(0), "w:numFmt"), Some("decimal"));
+        assert_eq!(val(first_level(1), "w:numFmt"), Some("bullet"));
+        assert_eq!(val(first_level(2), "w:numFmt"), Some("hindiNumbers"));
+    }
+
+    #[test]
+    fn images_are_embedded_once_and_unreadable_ones_keep_their_alt_text() {
+        let image = |path: &str| Node::Image {
+            path: path.to_string(),
+            alt_text: "picture".to_string(),
+            width: None,
+            height: None,
+        };
+        let mut doc = document(vec![image("a.png"), image("a.png"), image("missing.png")]);
+        doc.resources.files.insert("a.png".to_string(), png());
+
+        let parts = export(&doc);
+        let media: Vec<&String> = parts
+            .iter()
+            .map(|(name, _)| name)
+            .filter(|name| name.contains("media/"))
+            .collect();
+        assert_eq!(media, ["word/media/image1.png"]);
+
+        let rels = entry_xml(&parts, "word/_rels/document.xml.rels");
+        assert!(rels
+            .children_named("Relationship")
+            .any(|rel| rel.attr("Target") == Some("media/image1.png")));
+
+        let root = entry_xml(&parts, "word/document.xml");
+        let paragraphs = paragraphs(&root);
+        assert_eq!(attr(paragraphs[0], "a:blip", "r:embed"), Some("rIdImage1"));
+        assert_eq!(attr(paragraphs[1], "a:blip", "r:embed"), Some("rIdImage1"));
+        assert!(paragraphs[2].find("w:drawing").is_none());
+        assert_eq!(paragraphs[2].text(), "[picture]");
+    }
+
+    #[test]
+    fn page_breaks_become_word_page_breaks() {
+        let parts = export(&document(vec![
+            paragraph("one", "en"),
+            Node::PageBreak,
+            paragraph("two", "en"),
+        ]));
+        let root = entry_xml(&parts, "word/document.xml");
+        let paragraphs = paragraphs(&root);
+
+        assert_eq!(attr(paragraphs[1], "w:br", "w:type"), Some("page"));
+        assert_eq!(paragraphs[2].text(), "two");
+    }
+
+    #[test]
+    fn metadata_dates_are_written_in_w3c_format() {
+        let mut doc = document(Vec::new());
+        doc.metadata.title = "Report".to_string();
+        doc.metadata.created = "2024-03-01 10:20:30 +02:00".to_string();
+        doc.metadata.modified = "not a date".to_string();
+
+        let core = entry_xml(&export(&doc), "docProps/core.xml");
+        assert_eq!(core.child("dc:title").unwrap().text(), "Report");
+        assert_eq!(
+            core.child("dcterms:created").unwrap().text(),
+            "2024-03-01T08:20:30Z"
+        );
+        assert!(core.child("dcterms:modified").is_none());
+    }
+
+    fn import(document: &PdxDocument) -> PdxDocument {
+        import_docx(&export_as_docx(document).unwrap(), "Untitled").unwrap()
+    }
+
+    /// A minimal package as Word lays it out, with the given parts.
+    fn word_package(document_xml: &str, styles_xml: &str, numbering_xml: &str) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default();
+        let rels = format!(
+            "<Relationships xmlns=\"{PACKAGE_REL_NS}\">\
+             <Relationship Id=\"rId1\" Type=\"{REL_NS}/officeDocument\" Target=\"word/document.xml\"/>\
+             </Relationships>"
+        );
+        let namespace = |root: &str, body: &str| {
+            format!("<{root} xmlns:w=\"{MAIN_NS}\" xmlns:r=\"{REL_NS}\">{body}</{root}>")
+        };
+
+        for (name, xml) in [
+            ("_rels/.rels", rels),
+            ("word/document.xml", namespace("w:document", document_xml)),
+            ("word/styles.xml", namespace("w:styles", styles_xml)),
+            (
+                "word/numbering.xml",
+                namespace("w:numbering", numbering_xml),
+            ),
+        ] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(xml.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn exported_documents_import_back_to_the_same_content() {
+        let row = |texts: &[&str]| TableRow {
+            cells: texts
+                .iter()
+                .map(|text| TableCell {
+                    content: vec![TextRun::new(text, "en", "paragraph")],
+                })
+                .collect(),
+        };
+        let header = row(&["Name", "Value"]);
+        let body = row(&["a", "1"]);
+
+        let mut nested = list(true, 3, &["three", "four"], "en");
+        if let Node::List { items, .. } = &mut nested {
+            items[1].children.push(list(false, 1, &["inner"], "en"));
+        }
+
+        let mut doc = document(vec![
+            Node::Heading {
+                level: 2,
+                runs: vec![TextRun::new("Title", "en", "paragraph")],
+                style: "heading2".to_string(),
+            },
+            paragraph("مرحبا بالعالم", "ar"),
+            nested,
+            Node::PageBreak,
+            Node::CodeBlock {
+                language: "text".to_string(),
+                code: "let a = 1;\nlet b = 2;".to_string(),
+                style: "code".to_string(),
+            },
+            Node::Table {
+                rows: vec![header, body],
+                header_rows: 1,
+                alignments: vec![TextAlign::Start, TextAlign::End],
+                direction: Direction::LTR,
+                style: "table".to_string(),
+            },
+            Node::Image {
+                path: "a.png".to_string(),
+                alt_text: "picture".to_string(),
+                width: Some(4.0),
+                height: Some(2.0),
+            },
+        ]);
+        doc.resources.files.insert("a.png".to_string(), png());
+
+        let imported = import(&doc);
+
+        let Node::Document { children } = &imported.content else {
+            panic!("expected a document");
+        };
+        let name = resource_name("image1.png", &png());
+        let mut expected = match doc.content.clone() {
+            Node::Document { children } => children,
+            _ => unreachable!(),
+        };
+        if let Some(Node::Image { path, .. }) = expected.last_mut() {
+            path.clone_from(&name);
+        }
+        assert_eq!(children, &expected);
+        assert_eq!(imported.resources.files.get(&name), Some(&png()));
+    }
+
+    #[test]
+    fn word_styles_languages_and_numbering_are_read() {
+        let styles = "<w:style w:type=\"paragraph\" w:default=\"1\" w:styleId=\"Normal\">\
+                      <w:name w:val=\"Normal\"/></w:style>\
+                      <w:style w:type=\"paragraph\" w:styleId=\"Chapter\"><w:name w:val=\"Chapter Title\"/>\
+                      <w:pPr><w:outlineLvl w:val=\"0\"/></w:pPr><w:rPr><w:b/></w:rPr></w:style>";
+        let numbering = "<w:abstractNum w:abstractNumId=\"0\"><w:lvl w:ilvl=\"0\">\
+                         <w:start w:val=\"1\"/><w:numFmt w:val=\"decimal\"/></w:lvl></w:abstractNum>\
+                         <w:num w:numId=\"7\"><w:abstractNumId w:val=\"0\"/>\
+                         <w:lvlOverride w:ilvl=\"0\"><w:startOverride w:val=\"5\"/></w:lvlOverride></w:num>";
+        let body = "<w:body>\
+                    <w:p><w:pPr><w:pStyle w:val=\"Chapter\"/></w:pPr><w:r><w:t>One</w:t></w:r></w:p>\
+                    <w:p><w:pPr><w:bidi/></w:pPr><w:r><w:rPr><w:rtl/><w:lang w:val=\"en-US\" \
+                    w:bidi=\"fa-IR\"/></w:rPr><w:t>سلام</w:t></w:r></w:p>\
+                    <w:p><w:pPr><w:pageBreakBefore/><w:numPr><w:ilvl w:val=\"0\"/><w:numId w:val=\"7\"/>\
+                    </w:numPr></w:pPr><w:r><w:t>five</w:t></w:r></w:p>\
+                    </w:body>";
+
+        let imported = import_docx(&word_package(body, styles, numbering), "Untitled").unwrap();
+        let Node::Document { children } = &imported.content else {
+            panic!("expected a document");
+        };
+
+        assert!(matches!(
+            &children[0],
+            Node::Heading { level: 1, style, .. } if style == "chapter-title"
+        ));
+        assert_eq!(
+            imported.styles.styles["chapter-title"].font_weight,
+            FontWeight::Bold
+        );
+
+        let Node::Paragraph { runs, .. } = &children[1] else {
+            panic!("expected a paragraph, got {:?}", children[1]);
+        };
+        assert_eq!(runs[0].language, "fa-IR");
+        assert_eq!(runs[0].direction, Direction::RTL);
+
+        assert_eq!(children[2], Node::PageBreak);
+        assert!(matches!(
+            &children[3],
+            Node::List { ordered: true, start: 5, items, .. } if items.len() == 1
+        ));
+        assert_eq!(imported.metadata.title, "One");
+    }
+}
@@ -0,0 +1,819 @@
+use crate::data::{Direction, Node, PdxDocument, TableRow, TextAlign, TextRun};
+use crate::dates::w3c_date;
+use crate::error::PdxError;
+use crate::export::{runs_to_html, LanguageAttributes};
+use crate::layout::FONT_FACES;
+use crate::pdx_text::{block_language, is_rtl_block, is_rtl_language};
+use crate::xml::escape;
+use ::image::ImageFormat;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::{Cursor, Write};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+// ============================================================================
+// EPUB Export
+// ============================================================================
+
+const XHTML_NS: &str = "http://www.w3.org/1999/xhtml";
+const OPS_NS: &str = "http://www.idpf.org/2007/ops";
+
+/// The Noto faces, in the order of `FONT_FACES`, with their CSS family.
+const FONTS: [(&str, &str); 2] = [
+    ("fonts/NotoSans-Regular.ttf", "Noto Sans"),
+    ("fonts/NotoSansArabic-Regular.ttf", "Noto Sans Arabic"),
+];
+
+const STYLESHEET: &str = r#"body {
+    font-family: "Noto Sans", "Noto Sans Arabic", sans-serif;
+    line-height: 1.6;
+}
+[dir="rtl"] { font-family: "Noto Sans Arabic", "Noto Sans", sans-serif; }
+p { margin: 0 0 0.6em; }
+h1, h2, h3, h4, h5, h6 { line-height: 1.3; page-break-after: avoid; }
+code, pre { font-family: monospace; }
+pre { white-space: pre-wrap; background: #f4f4f4; padding: 0.6em; }
+hr { border: none; border-top: 1px solid #999; margin: 1em 0; }
+hr.page-break { border: none; page-break-after: always; margin: 0; }
+div.image { text-align: center; margin: 0.6em 0; }
+img { max-width: 100%; height: auto; }
+table { border-collapse: collapse; margin: 0.6em 0; }
+th, td { border: 1px solid #999; padding: 0.2em 0.5em; }
+nav ol { list-style: none; }
+"#;
+
+/// Writes the document as an EPUB 3 book. Chapters start at level-1
+/// headings and page breaks, the navigation document lists every heading,
+/// the Noto fonts and images are embedded, and blocks and runs carry
+/// `dir` and `xml:lang`, so bilingual text reads correctly on e-readers.
+pub fn export_as_epub(document: &PdxDocument) -> Result<Vec<u8>, PdxError> {
+    let mut writer = EpubWriter::new(document);
+    let children = match &document.content {
+        Node::Document { children } => children.as_slice(),
+        other => std::slice::from_ref(other),
+    };
+    let chapters: Vec<String> = split_chapters(children)
+        .iter()
+        .enumerate()
+        .map(|(index, nodes)| writer.chapter(index + 1, nodes))
+        .collect();
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+
+    // Readers identify the format by this first, uncompressed entry
+    zip.start_file("mimetype", stored).map_err(epub_error)?;
+    zip.write_all(b"application/epub+zip").map_err(epub_error)?;
+
+    let mut parts = vec![
+        ("META-INF/container.xml".to_string(), container_xml()),
+        (
+            "EPUB/package.opf".to_string(),
+            writer.package_opf(chapters.len()),
+        ),
+        ("EPUB/nav.xhtml".to_string(), writer.nav_xhtml()),
+        ("EPUB/style.css".to_string(), stylesheet()),
+    ];
+    for (index, chapter) in chapters.into_iter().enumerate() {
+        parts.push((format!("EPUB/{}", chapter_file(index + 1)), chapter));
+    }
+    for (name, text) in parts {
+        zip.start_file(name, deflated).map_err(epub_error)?;
+        zip.write_all(text.as_bytes()).map_err(epub_error)?;
+    }
+
+    for ((path, _), bytes) in FONTS.iter().zip(FONT_FACES) {
+        zip.start_file(format!("EPUB/{}", path), deflated)
+            .map_err(epub_error)?;
+        zip.write_all(bytes).map_err(epub_error)?;
+    }
+    // Images are compressed already
+    for image in &writer.images {
+        zip.start_file(format!("EPUB/{}", image.href), stored)
+            .map_err(epub_error)?;
+        zip.write_all(&image.bytes).map_err(epub_error)?;
+    }
+
+    Ok(zip.finish().map_err(epub_error)?.into_inner())
+}
+
+fn epub_error(e: impl std::fmt::Display) -> PdxError {
+    PdxError::Export {
+        format: "EPUB",
+        message: e.to_string(),
+    }
+}
+
+/// Groups top-level blocks into chapters: a level-1 heading opens a new
+/// one, and a page break ends the current one.
+fn split_chapters(children: &[Node]) -> Vec<Vec<&Node>> {
+    let mut chapters: Vec<Vec<&Node>> = vec![Vec::new()];
+
+    for node in children {
+        let current = chapters.last().map_or(0, Vec::len);
+        match node {
+            Node::PageBreak => {
+                if current > 0 {
+                    chapters.push(Vec::new());
+                }
+                continue;
+            }
+            Node::Heading { level: 1, .. } if current > 0 => chapters.push(Vec::new()),
+            _ => {}
+        }
+        if let Some(chapter) = chapters.last_mut() {
+            chapter.push(node);
+        }
+    }
+
+    // The spine needs at least one chapter, even for an empty document
+    if chapters.len() > 1 && chapters.last().is_some_and(Vec::is_empty) {
+        chapters.pop();
+    }
+    chapters
+}
+
+fn chapter_file(number: usize) -> String {
+    format!("chapter-{}.xhtml", number)
+}
+
+struct Image {
+    /// Path inside `EPUB/`, e.g. `images/image1.png`
+    href: String,
+    media_type: &'static str,
+    bytes: Vec<u8>,
+}
+
+/// A heading in the navigation document.
+struct TocEntry {
+    level: u8,
+    title: String,
+    href: String,
+}
+
+/// Collects the images and headings the chapters refer to while writing
+/// them.
+struct EpubWriter<'a> {
+    document: &'a PdxDocument,
+    images: Vec<Image>,
+    /// Image hrefs by the content path they were read from
+    image_hrefs: HashMap<String, String>,
+    toc: Vec<TocEntry>,
+    /// Title of each chapter, for the navigation of heading-less books
+    chapter_titles: Vec<String>,
+    next_heading: usize,
+}
+
+impl<'a> EpubWriter<'a> {
+    fn new(document: &'a PdxDocument) -> Self {
+        Self {
+            document,
+            images: Vec::new(),
+            image_hrefs: HashMap::new(),
+            toc: Vec::new(),
+            chapter_titles: Vec::new(),
+            next_heading: 1,
+        }
+    }
+
+    fn language(&self) -> &str {
+        match self.document.metadata.language.as_str() {
+            "" => "en",
+            language => language,
+        }
+    }
+
+    /// An XHTML content document with the usual head.
+    fn xhtml(&self, title: &str, body: &str) -> String {
+        let language = escape(self.language());
+        let dir = if is_rtl_language(self.language()) {
+            "rtl"
+        } else {
+            "ltr"
+        };
+
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html>\n\
+             <html xmlns=\"{XHTML_NS}\" xmlns:epub=\"{OPS_NS}\" xml:lang=\"{0}\" lang=\"{0}\" \
+             dir=\"{1}\">\n<head>\n<meta charset=\"UTF-8\"/>\n<title>{2}</title>\n\
+             <link rel=\"stylesheet\" type=\"text/css\" href=\"style.css\"/>\n</head>\n\
+             <body>\n{3}</body>\n</html>\n",
+            language,
+            dir,
+            escape(title),
+            body
+        )
+    }
+
+    fn chapter(&mut self, number: usize, nodes: &[&Node]) -> String {
+        let file = chapter_file(number);
+        let body: String = nodes.iter().map(|node| self.blocks(node, &file)).collect();
+
+        let title = nodes
+            .iter()
+            .find_map(|node| match node {
+                Node::Heading { runs, .. } => Some(plain_text(runs)),
+                _ => None,
+            })
+            .filter(|title| !title.trim().is_empty())
+            .unwrap_or_else(|| self.document.metadata.title.clone());
+        let html = self.xhtml(
+            &title,
+            &format!("<section epub:type=\"chapter\">\n{}</section>\n", body),
+        );
+        self.chapter_titles.push(title);
+        html
+    }
+
+    fn blocks(&mut self, node: &Node, file: &str) -> String {
+        let styles = &self.document.styles;
+
+        match node {
+            Node::Document { children } => children
+                .iter()
+                .map(|child| self.blocks(child, file))
+                .collect(),
+            Node::Heading { level, runs, style } => {
+                let level = (*level).clamp(1, 6);
+                let rtl = is_rtl_block(runs, styles.resolve(style).direction);
+                let id = format!("h{}", self.next_heading);
+                self.next_heading += 1;
+
+                let title = plain_text(runs);
+                if !title.trim().is_empty() {
+                    self.toc.push(TocEntry {
+                        level,
+                        title: title.trim().to_string(),
+                        href: format!("{}#{}", file, id),
+                    });
+                }
+                format!(
+                    "<h{0} id=\"{1}\"{2}>{3}</h{0}>\n",
+                    level,
+                    id,
+                    block_attributes(runs, rtl, self.language()),
+                    runs_to_html(runs, rtl, self.language(), LanguageAttributes::Xhtml)
+                )
+            }
+            Node::Paragraph { runs, style } => {
+                let rtl = is_rtl_block(runs, styles.resolve(style).direction);
+                format!(
+                    "<p{}>{}</p>\n",
+                    block_attributes(runs, rtl, self.language()),
+                    runs_to_html(runs, rtl, self.language(), LanguageAttributes::Xhtml)
+                )
+            }
+            Node::List {
+                ordered,
+                start,
+                items,
+                style,
+            } => {
+                let direction = styles.resolve(style).direction;
+                let tag = if *ordered { "ol" } else { "ul" };
+
+                let mut attributes = String::new();
+                if *ordered && *start != 1 {
+                    attributes.push_str(&format!(" start=\"{}\"", start));
+                }
+                let digits = match items.first().map(|item| item.language()) {
+                    Some("ar" | "ckb") => Some("arabic-indic"),
+                    Some("fa" | "ur" | "ps" | "sd") => Some("persian"),
+                    _ => None,
+                };
+                if let (true, Some(digits)) = (*ordered, digits) {
+                    attributes.push_str(&format!(" style=\"list-style-type: {}\"", digits));
+                }
+
+                let mut html = format!("<{}{}>\n", tag, attributes);
+                for item in items {
+                    let rtl = is_rtl_block(&item.content, direction);
+                    html.push_str(&format!(
+                        "<li{}>{}",
+                        block_attributes(&item.content, rtl, self.language()),
+                        runs_to_html(
+                            &item.content,
+                            rtl,
+                            self.language(),
+                            LanguageAttributes::Xhtml
+                        )
+                    ));
+                    for child in &item.children {
+                        html.push_str(&self.blocks(child, file));
+                    }
+                    html.push_str("</li>\n");
+                }
+                html.push_str(&format!("</{}>\n", tag));
+                html
+            }
+            Node::CodeBlock { language, code, .. } => {
+                let class: String = language
+                    .chars()
+                    .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '+'))
+                    .collect();
+                format!(
+                    "<pre dir=\"ltr\"><code class=\"language-{}\">{}</code></pre>\n",
+                    class,
+                    escape(code)
+                )
+            }
+            Node::Image {
+                path,
+                alt_text,
+                width,
+                height,
+            } => match self.image(path) {
+                Some(href) => {
+                    let mut size = String::new();
+                    if let Some(width) = width {
+                        size.push_str(&format!("width: {}px;", width.round()));
+                    }
+                    if let Some(height) = height {
+                        size.push_str(&format!(" height: {}px;", height.round()));
+                    }
+                    let style = match size.is_empty() {
+                        true => String::new(),
+                        false => format!(" style=\"{}\"", size.trim()),
+                    };
+                    format!(
+                        "<div class=\"image\"><img src=\"{}\" alt=\"{}\"{}/></div>\n",
+                        escape(&href),
+                        escape(alt_text),
+                        style
+                    )
+                }
+                None => format!("<p class=\"missing-image\">[{}]</p>\n", escape(alt_text)),
+            },
+            Node::Table {
+                rows,
+                header_rows,
+                alignments,
+                direction,
+                ..
+            } => {
+                let dir = if *direction == Direction::RTL {
+                    "rtl"
+                } else {
+                    "ltr"
+                };
+                let row_html = |row: &TableRow, tag: &str| -> String {
+                    let cells: String = row
+                        .cells
+                        .iter()
+                        .enumerate()
+                        .map(|(c, cell)| {
+                            let align = match alignments.get(c) {
+                                Some(TextAlign::Center) => "center",
+                                Some(TextAlign::End) => "end",
+                                Some(TextAlign::Justify) => "justify",
+                                _ => "start",
+                            };
+                            let rtl = is_rtl_block(&cell.content, *direction);
+                            format!(
+                                "<{0} style=\"text-align: {1}\"{2}>{3}</{0}>",
+                                tag,
+                                align,
+                                block_attributes(&cell.content, rtl, self.language()),
+                                runs_to_html(
+                                    &cell.content,
+                                    rtl,
+                                    self.language(),
+                                    LanguageAttributes::Xhtml
+                                )
+                            )
+                        })
+                        .collect();
+                    format!("<tr>{}</tr>\n", cells)
+                };
+
+                let split = (*header_rows).min(rows.len());
+                let mut table = format!("<table dir=\"{}\">\n", dir);
+                if split > 0 {
+                    let head: String = rows[..split].iter().map(|r| row_html(r, "th")).collect();
+                    table.push_str(&format!("<thead>\n{}</thead>\n", head));
+                }
+                if split < rows.len() {
+                    let body: String = rows[split..].iter().map(|r| row_html(r, "td")).collect();
+                    table.push_str(&format!("<tbody>\n{}</tbody>\n", body));
+                }
+                table.push_str("</table>\n");
+                table
+            }
+            Node::Divider => "<hr/>\n".to_string(),
+            // Breaks between chapters end a file; these are inside lists
+            Node::PageBreak => "<hr class=\"page-break\"/>\n".to_string(),
+        }
+    }
+
+    /// Adds an image to the book and returns its href, or `None` when it
+    /// cannot be read.
+    fn image(&mut self, path: &str) -> Option<String> {
+        if let Some(href) = self.image_hrefs.get(path) {
+            return Some(href.clone());
+        }
+
+        let bytes = self.document.resources.read(path)?;
+        // EPUB readers must support PNG, JPEG, GIF and WebP; anything else
+        // is converted
+        let (extension, media_type, bytes) = match ::image::guess_format(&bytes).ok()? {
+            ImageFormat::Png => ("png", "image/png", bytes),
+            ImageFormat::Jpeg => ("jpeg", "image/jpeg", bytes),
+            ImageFormat::Gif => ("gif", "image/gif", bytes),
+            ImageFormat::WebP => ("webp", "image/webp", bytes),
+            _ => {
+                let decoded = ::image::load_from_memory(&bytes).ok()?;
+                let mut png = Cursor::new(Vec::new());
+                decoded.write_to(&mut png, ImageFormat::Png).ok()?;
+                ("png", "image/png", png.into_inner())
+            }
+        };
+
+        let href = format!("images/image{}.{}", self.images.len() + 1, extension);
+        self.images.push(Image {
+            href: href.clone(),
+            media_type,
+            bytes,
+        });
+        self.image_hrefs.insert(path.to_string(), href.clone());
+        Some(href)
+    }
+
+    /// The navigation document: every heading, nested by level. Books
+    /// without headings list their chapters instead.
+    fn nav_xhtml(&self) -> String {
+        let chapters: Vec<TocEntry>;
+        let entries = if self.toc.is_empty() {
+            chapters = self
+                .chapter_titles
+                .iter()
+                .enumerate()
+                .map(|(index, title)| TocEntry {
+                    level: 1,
+                    title: title.clone(),
+                    href: chapter_file(index + 1),
+                })
+                .collect();
+            &chapters
+        } else {
+            &self.toc
+        };
+
+        let mut html = String::from("<ol>\n");
+        // Levels of the open items, and whether each has opened a nested list
+        let mut open: Vec<(u8, bool)> = Vec::new();
+        for entry in entries {
+            while let Some((level, nested)) = open.last_mut() {
+                if *level < entry.level {
+                    if !*nested {
+                        html.push_str("\n<ol>\n");
+                        *nested = true;
+                    }
+                    break;
+                }
+                if *nested {
+                    html.push_str("</ol>\n");
+                }
+                html.push_str("</li>\n");
+                open.pop();
+            }
+            html.push_str(&format!(
+                "<li><a href=\"{}\">{}</a>",
+                escape(&entry.href),
+                escape(&entry.title)
+            ));
+            open.push((entry.level, false));
+        }
+        while let Some((_, nested)) = open.pop() {
+            if nested {
+                html.push_str("</ol>\n");
+            }
+            html.push_str("</li>\n");
+        }
+        html.push_str("</ol>\n");
+
+        let title = &self.document.metadata.title;
+        let body = format!(
+            "<nav epub:type=\"toc\" id=\"toc\">\n<h1>{}</h1>\n{}</nav>\n",
+            escape(title),
+            html
+        );
+        self.xhtml(title, &body)
+    }
+
+    fn package_opf(&self, chapters: usize) -> String {
+        let metadata = &self.document.metadata;
+        let language = escape(self.language());
+        let modified = w3c_date(&metadata.modified).unwrap_or_else(|| {
+            chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+        });
+
+        let mut opf = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" \
+             unique-identifier=\"book-id\" xml:lang=\"{0}\">\n\
+             <metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n\
+             <dc:identifier id=\"book-id\">urn:uuid:{1}</dc:identifier>\n\
+             <dc:title>{2}</dc:title>\n<dc:language>{0}</dc:language>\n",
+            language,
+            book_uuid(self.document),
+            escape(&metadata.title)
+        );
+        if !metadata.author.trim().is_empty() {
+            opf.push_str(&format!(
+                "<dc:creator>{}</dc:creator>\n",
+                escape(metadata.author.trim())
+            ));
+        }
+        for keyword in &metadata.keywords {
+            opf.push_str(&format!("<dc:subject>{}</dc:subject>\n", escape(keyword)));
+        }
+        if let Some(created) = w3c_date(&metadata.created) {
+            opf.push_str(&format!("<dc:date>{}</dc:date>\n", created));
+        }
+        opf.push_str(&format!(
+            "<meta property=\"dcterms:modified\">{}</meta>\n</metadata>\n<manifest>\n\
+             <item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" \
+             properties=\"nav\"/>\n\
+             <item id=\"css\" href=\"style.css\" media-type=\"text/css\"/>\n",
+            modified
+        ));
+        for (index, (path, _)) in FONTS.iter().enumerate() {
+            opf.push_str(&format!(
+                "<item id=\"font-{}\" href=\"{}\" media-type=\"font/ttf\"/>\n",
+                index + 1,
+                path
+            ));
+        }
+        for number in 1..=chapters {
+            opf.push_str(&format!(
+                "<item id=\"chapter-{0}\" href=\"{1}\" media-type=\"application/xhtml+xml\"/>\n",
+                number,
+                chapter_file(number)
+            ));
+        }
+        for (index, image) in self.images.iter().enumerate() {
+            opf.push_str(&format!(
+                "<item id=\"image-{}\" href=\"{}\" media-type=\"{}\"/>\n",
+                index + 1,
+                image.href,
+                image.media_type
+            ));
+        }
+
+        let progression = if is_rtl_language(self.language()) {
+            " page-progression-direction=\"rtl\""
+        } else {
+            ""
+        };
+        opf.push_str(&format!("</manifest>\n<spine{}>\n", progression));
+        for number in 1..=chapters {
+            opf.push_str(&format!("<itemref idref=\"chapter-{}\"/>\n", number));
+        }
+        opf.push_str("</spine>\n</package>\n");
+        opf
+    }
+}
+
+fn container_xml() -> String {
+    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+     <container version=\"1.0\" xmlns=\"urn:oasis:names:tc:opendocument:xmlns:container\">\n\
+     <rootfiles><rootfile full-path=\"EPUB/package.opf\" \
+     media-type=\"application/oebps-package+xml\"/></rootfiles>\n</container>\n"
+        .to_string()
+}
+
+fn stylesheet() -> String {
+    let mut css: String = FONTS
+        .iter()
+        .map(|(path, family)| {
+            format!(
+                "@font-face {{ font-family: \"{}\"; src: url({}); }}\n",
+                family, path
+            )
+        })
+        .collect();
+    css.push_str(STYLESHEET);
+    css
+}
+
+/// A stable identifier for the book, so re-exports update the copy on a
+/// reader instead of adding another: a name-based UUID of the title,
+/// author and creation date.
+fn book_uuid(document: &PdxDocument) -> String {
+    let metadata = &document.metadata;
+    let mut hash: [u8; 32] = Sha256::digest(
+        format!(
+            "{}\n{}\n{}",
+            metadata.title, metadata.author, metadata.created
+        )
+        .as_bytes(),
+    )
+    .into();
+    hash[6] = (hash[6] & 0x0f) | 0x50;
+    hash[8] = (hash[8] & 0x3f) | 0x80;
+
+    let hex: String = hash[..16].iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+// ============================================================================
+// XHTML Helpers
+// ============================================================================
+
+fn plain_text(runs: &[TextRun]) -> String {
+    runs.iter().map(|run| run.text.as_str()).collect()
+}
+
+fn block_attributes(runs: &[TextRun], rtl: bool, fallback: &str) -> String {
+    format!(
+        " dir=\"{}\"{}",
+        if rtl { "rtl" } else { "ltr" },
+        LanguageAttributes::Xhtml.format(block_language(runs, fallback))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::Metadata;
+    use crate::test_support::{document, entry_text, entry_xml, png, unzip};
+    use crate::xml::Element;
+    use std::io::Read;
+    use zip::ZipArchive;
+
+    fn heading(level: u8, text: &str) -> Node {
+        Node::Heading {
+            level,
+            runs: vec![TextRun::new(text, "en", "paragraph")],
+            style: format!("heading{}", level),
+        }
+    }
+
+    fn paragraph(runs: Vec<TextRun>) -> Node {
+        Node::Paragraph {
+            runs,
+            style: "paragraph".to_string(),
+        }
+    }
+
+    /// The book's entries in archive order.
+    fn export(document: &PdxDocument) -> Vec<(String, Vec<u8>)> {
+        unzip(&export_as_epub(document).unwrap())
+    }
+
+    fn chapter_sizes(children: &[Node]) -> Vec<usize> {
+        split_chapters(children).iter().map(Vec::len).collect()
+    }
+
+    #[test]
+    fn chapters_start_at_level_one_headings_and_page_breaks() {
+        let p = || paragraph(vec![TextRun::new("text", "en", "paragraph")]);
+
+        assert_eq!(
+            chapter_sizes(&[
+                heading(1, "A"),
+                p(),
+                heading(2, "A.1"),
+                p(),
+                heading(1, "B"),
+                p()
+            ]),
+            [4, 2]
+        );
+        assert_eq!(chapter_sizes(&[p(), Node::PageBreak, p(), p()]), [1, 2]);
+        // Breaks with nothing before them, or at the end, add no chapters
+        assert_eq!(
+            chapter_sizes(&[Node::PageBreak, heading(1, "A"), Node::PageBreak]),
+            [1]
+        );
+        assert_eq!(chapter_sizes(&[]), [0]);
+    }
+
+    #[test]
+    fn the_mimetype_comes_first_and_uncompressed() {
+        let data = export_as_epub(&document(Vec::new())).unwrap();
+        let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+        let mut first = archive.by_index(0).unwrap();
+
+        assert_eq!(first.name(), "mimetype");
+        assert_eq!(first.compression(), CompressionMethod::Stored);
+        let mut mimetype = String::new();
+        first.read_to_string(&mut mimetype).unwrap();
+        assert_eq!(mimetype, "application/epub+zip");
+    }
+
+    #[test]
+    fn metadata_fonts_and_images_are_in_the_package() {
+        let mut doc = document(vec![Node::Image {
+            path: "cat.png".to_string(),
+            alt_text: "cat".to_string(),
+            width: None,
+            height: None,
+        }]);
+        doc.resources.files.insert("cat.png".to_string(), png());
+        doc.metadata = Metadata {
+            title: "كتاب".to_string(),
+            author: "Ann".to_string(),
+            language: "ar".to_string(),
+            keywords: vec!["one".to_string(), "two".to_string()],
+            ..Metadata::default()
+        };
+
+        let entries = export(&doc);
+        let opf = entry_xml(&entries, "EPUB/package.opf");
+        let metadata = opf.child("metadata").unwrap();
+
+        assert_eq!(metadata.child("dc:title").unwrap().text(), "كتاب");
+        assert_eq!(metadata.child("dc:creator").unwrap().text(), "Ann");
+        assert_eq!(metadata.child("dc:language").unwrap().text(), "ar");
+        let subjects: Vec<String> = metadata
+            .children_named("dc:subject")
+            .map(|s| s.text())
+            .collect();
+        assert_eq!(subjects, ["one", "two"]);
+        assert_eq!(
+            opf.child("spine")
+                .unwrap()
+                .attr("page-progression-direction"),
+            Some("rtl")
+        );
+
+        let manifest: Vec<&str> = opf
+            .child("manifest")
+            .unwrap()
+            .children_named("item")
+            .filter_map(|item| item.attr("href"))
+            .collect();
+        for (path, _) in FONTS {
+            assert!(manifest.contains(&path));
+            assert!(entries
+                .iter()
+                .any(|(name, _)| *name == format!("EPUB/{}", path)));
+        }
+        assert!(manifest.contains(&"images/image1.png"));
+        assert!(entry_text(&entries, "EPUB/chapter-1.xhtml").contains("src=\"images/image1.png\""));
+    }
+
+    #[test]
+    fn navigation_nests_headings_by_level() {
+        let entries = export(&document(vec![
+            heading(1, "One"),
+            heading(2, "One.One"),
+            heading(1, "Two"),
+        ]));
+        let nav = entry_xml(&entries, "EPUB/nav.xhtml");
+        let list = nav.find("nav").unwrap().child("ol").unwrap();
+        let items: Vec<&Element> = list.children_named("li").collect();
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(
+            items[0].child("a").unwrap().attr("href"),
+            Some("chapter-1.xhtml#h1")
+        );
+        let nested = items[0].child("ol").unwrap().child("li").unwrap();
+        assert_eq!(nested.child("a").unwrap().text(), "One.One");
+        assert_eq!(
+            items[1].child("a").unwrap().attr("href"),
+            Some("chapter-2.xhtml#h3")
+        );
+    }
+
+    #[test]
+    fn blocks_and_foreign_runs_carry_direction_and_language() {
+        let entries = export(&document(vec![paragraph(vec![
+            TextRun::new("مرحبا ", "ar", "paragraph"),
+            TextRun::new("Word", "en", "paragraph"),
+        ])]));
+        let chapter = entry_xml(&entries, "EPUB/chapter-1.xhtml");
+        let p = chapter.find("p").unwrap();
+
+        assert_eq!(p.attr("dir"), Some("rtl"));
+        assert_eq!(p.attr("xml:lang"), Some("ar"));
+        let span = p.child("span").unwrap();
+        assert_eq!(span.attr("dir"), Some("ltr"));
+        assert_eq!(span.attr("xml:lang"), Some("en"));
+        assert_eq!(span.text(), "Word");
+    }
+
+    #[test]
+    fn the_book_id_is_a_stable_name_based_uuid() {
+        let mut doc = document(Vec::new());
+        doc.metadata.title = "Book".to_string();
+        let id = book_uuid(&doc);
+
+        assert_eq!(id, book_uuid(&doc));
+        assert_eq!(id.len(), 36);
+        assert_eq!(&id[14..15], "5");
+        doc.metadata.title = "Other".to_string();
+        assert_ne!(id, book_uuid(&doc));
+    }
+}
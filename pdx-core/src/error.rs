@@ -0,0 +1,187 @@
+use crate::validate::CURRENT_VERSION;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// ============================================================================
+// Errors
+// ============================================================================
+
+/// Why reading, writing or exporting a document failed.
+#[derive(Debug)]
+pub enum PdxError {
+    /// A file could not be read or written
+    Io { path: PathBuf, source: io::Error },
+    /// Malformed `.pdx` or style sheet JSON, at the position parsing stopped
+    Parse {
+        path: Option<PathBuf>,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// Written by a newer editor, in a format version this build cannot read
+    UnsupportedVersion { path: Option<PathBuf>, found: u32 },
+    /// A `.pdxz` bundle is damaged or is missing its document
+    Bundle(String),
+    /// The document could not be written as JSON
+    Serialize(String),
+    /// A bundled font could not be loaded
+    Font(String),
+    /// Encoding the output failed
+    Export {
+        format: &'static str,
+        message: String,
+    },
+    /// A file in another format, such as DOCX, could not be read
+    Import {
+        format: &'static str,
+        message: String,
+    },
+}
+
+impl PdxError {
+    pub fn io(path: &Path, source: io::Error) -> Self {
+        PdxError::Io {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    /// Names the file a parse or version error came from.
+    pub fn with_path(self, file: &Path) -> Self {
+        match self {
+            PdxError::UnsupportedVersion { found, .. } => PdxError::UnsupportedVersion {
+                path: Some(file.to_path_buf()),
+                found,
+            },
+            PdxError::Parse {
+                line,
+                column,
+                message,
+                ..
+            } => PdxError::Parse {
+                path: Some(file.to_path_buf()),
+                line,
+                column,
+                message,
+            },
+            other => other,
+        }
+    }
+
+    /// The file involved, if any.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            PdxError::Io { path, .. } => Some(path),
+            PdxError::Parse { path, .. } | PdxError::UnsupportedVersion { path, .. } => {
+                path.as_deref()
+            }
+            _ => None,
+        }
+    }
+
+    /// Everything known about the error, one fact per line.
+    pub fn details(&self) -> String {
+        let mut lines = vec![self.to_string()];
+
+        if let Some(path) = self.path() {
+            lines.push(format!("File: {}", path.display()));
+        }
+        match self {
+            PdxError::Io { source, .. } => {
+                lines.push(format!("Kind: {:?}", source.kind()));
+            }
+            PdxError::Parse { line, column, .. } if *line > 0 => {
+                lines.push(format!("Line: {}, column: {}", line, column));
+            }
+            PdxError::UnsupportedVersion { found, .. } => {
+                lines.push(format!(
+                    "Format version: {} (supported: up to {})",
+                    found, CURRENT_VERSION
+                ));
+            }
+            PdxError::Export { format, .. } | PdxError::Import { format, .. } => {
+                lines.push(format!("Format: {}", format))
+            }
+            _ => {}
+        }
+
+        lines.join("\n")
+    }
+}
+
+impl fmt::Display for PdxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PdxError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            // Line 0 means the error came from an upgraded document, not the text
+            PdxError::Parse {
+                path,
+                line: 0,
+                message,
+                ..
+            } => match path {
+                Some(path) => write!(f, "{}: {}", path.display(), message),
+                None => write!(f, "{}", message),
+            },
+            PdxError::Parse {
+                path: Some(path),
+                line,
+                column,
+                message,
+            } => write!(f, "{}:{}:{}: {}", path.display(), line, column, message),
+            PdxError::Parse {
+                path: None,
+                line,
+                column,
+                message,
+            } => write!(f, "line {}, column {}: {}", line, column, message),
+            PdxError::UnsupportedVersion { path, found } => {
+                if let Some(path) = path {
+                    write!(f, "{}: ", path.display())?;
+                }
+                write!(
+                    f,
+                    "format version {} is newer than this build reads (up to {}); \
+                     update PDX Editor to open it",
+                    found, CURRENT_VERSION
+                )
+            }
+            PdxError::Bundle(message) => write!(f, "Not a valid PDX bundle: {}", message),
+            PdxError::Serialize(message) => write!(f, "Serialize error: {}", message),
+            PdxError::Font(message) => write!(f, "Font error: {}", message),
+            PdxError::Export { format, message } => {
+                write!(f, "{} export failed: {}", format, message)
+            }
+            PdxError::Import { format, message } => {
+                write!(f, "Not a readable {} file: {}", format, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PdxError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PdxError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for PdxError {
+    fn from(e: serde_json::Error) -> Self {
+        // serde_json appends the position to its message; it is kept apart here
+        let position = format!(" at line {} column {}", e.line(), e.column());
+        let message = e.to_string();
+        PdxError::Parse {
+            path: None,
+            line: e.line(),
+            column: e.column(),
+            message: message
+                .strip_suffix(&position)
+                .unwrap_or(&message)
+                .to_string(),
+        }
+    }
+}
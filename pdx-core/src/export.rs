@@ -0,0 +1,1029 @@
+use crate::data::{
+    Direction, EdgeInsets, FontWeight, Node, PdxDocument, Resources, Style, TableRow, TextAlign,
+    TextRun,
+};
+use crate::dates::w3c_date;
+use crate::error::PdxError;
+use crate::layout::{layout_document, FontSet, Page, PageItem, PageSetup, TextSpan, FONT_FACES};
+use crate::pdx_text::{block_language, is_rtl_block, is_rtl_language, is_rtl_run};
+use crate::xml::escape;
+use ::image::{imageops, DynamicImage, ImageFormat, RgbImage, Rgba, RgbaImage};
+use ab_glyph::Font;
+use printpdf::*;
+use std::collections::BTreeSet;
+use std::io::BufWriter;
+
+/// Horizontal shear used to synthesize italics from the upright faces.
+const ITALIC_SKEW: f32 = 0.2;
+
+// ============================================================================
+// Export Functions
+// ============================================================================
+
+/// Options for the HTML export.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HtmlOptions {
+    /// Embed images on disk as `data:` URIs too, so the page is a single
+    /// file. Images embedded in the document always are.
+    pub inline_images: bool,
+}
+
+/// Rules every page gets; the document's own styles are added after them.
+const HTML_STYLESHEET: &str = "        body {
+            font-family: 'Segoe UI', Tahoma, Geneva, Verdana, sans-serif, 'Noto Sans Arabic';
+            max-width: 800px;
+            margin: 40px auto;
+            padding: 20px;
+        }
+        code { background: #f4f4f4; padding: 2px 6px; border-radius: 3px; }
+        pre { background: #f4f4f4; padding: 15px; border-radius: 5px; overflow-x: auto; }
+        pre code { background: none; padding: 0; }
+        hr { margin: 20px 0; border: none; border-top: 1px solid #ddd; }
+        hr.page-break { border-top: 3px double #ddd; break-after: page; }
+        img { max-width: 100%; height: auto; margin: 10px 0; }
+        table { border-collapse: collapse; margin: 10px 0; }
+        th, td { border: 1px solid #ddd; padding: 6px 12px; }
+        th { background: #f4f4f4; }
+";
+
+/// Writes the document as a standalone HTML page. Every style sheet entry
+/// becomes a CSS class that the blocks using it carry, and blocks and runs
+/// are tagged with their language and direction.
+pub fn export_as_html(document: &PdxDocument, options: &HtmlOptions) -> String {
+    let mut writer = HtmlWriter {
+        document,
+        options,
+        used_styles: BTreeSet::new(),
+    };
+    let body = writer.blocks(&document.content);
+
+    let metadata = &document.metadata;
+    let rtl = is_rtl_language(&metadata.language);
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html lang=\"{}\" dir=\"{}\">\n<head>\n    <meta charset=\"UTF-8\">\n    \
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1.0\">\n    \
+         <meta name=\"generator\" content=\"PDX Editor\">\n",
+        escape(&metadata.language),
+        if rtl { "rtl" } else { "ltr" }
+    );
+    let mut meta = |name: &str, content: &str| {
+        html.push_str(&format!(
+            "    <meta name=\"{}\" content=\"{}\">\n",
+            name,
+            escape(content)
+        ));
+    };
+    if !metadata.author.is_empty() {
+        meta("author", &metadata.author);
+    }
+    if !metadata.keywords.is_empty() {
+        meta("keywords", &metadata.keywords.join(", "));
+    }
+    if let Some(created) = w3c_date(&metadata.created) {
+        meta("dcterms.created", &created);
+    }
+    if let Some(modified) = w3c_date(&metadata.modified) {
+        meta("dcterms.modified", &modified);
+    }
+
+    html.push_str(&format!(
+        "    <title>{}</title>\n    <style>\n{}",
+        escape(&metadata.title),
+        HTML_STYLESHEET
+    ));
+    // Every style in the sheet, plus any the content names that it lacks
+    let styles = &document.styles;
+    let mut names: BTreeSet<&str> = styles.styles.keys().map(String::as_str).collect();
+    names.extend(writer.used_styles.iter().map(String::as_str));
+    for name in names {
+        html.push_str(&style_css(name, &styles.resolve(name)));
+    }
+    html.push_str("    </style>\n</head>\n<body>\n");
+    html.push_str(&body);
+    html.push_str("</body>\n</html>");
+    html
+}
+
+/// A CSS rule for the class of a style sheet entry. Direction is left to
+/// the `dir` attribute each block carries.
+fn style_css(name: &str, style: &Style) -> String {
+    let weight = match style.font_weight {
+        FontWeight::Bold => "bold",
+        FontWeight::Normal => "normal",
+        FontWeight::Light => "300",
+    };
+    let align = match style.text_align {
+        TextAlign::Start => "start",
+        TextAlign::End => "end",
+        TextAlign::Center => "center",
+        TextAlign::Justify => "justify",
+    };
+    let edges = |e: &EdgeInsets| format!("{}px {}px {}px {}px", e.top, e.right, e.bottom, e.left);
+    let color = style.color;
+
+    format!(
+        "        .{} {{ font-size: {}px; font-weight: {}; color: #{:02x}{:02x}{:02x}; \
+         text-align: {}; line-height: {}; margin: {}; padding: {}; }}\n",
+        style_class(name),
+        style.font_size,
+        weight,
+        color.r,
+        color.g,
+        color.b,
+        align,
+        style.line_height,
+        edges(&style.margin),
+        edges(&style.padding)
+    )
+}
+
+/// Class name for a style: ASCII letters, digits and `-` are kept and any
+/// other character becomes its hex code between underscores, as does a
+/// leading digit or `-`. `a b` is `a_20_b` and `a_b` is `a_5f_b`, so
+/// different names never share a class.
+fn style_class(name: &str) -> String {
+    let mut class = String::new();
+    for (index, c) in name.chars().enumerate() {
+        if c.is_ascii_alphabetic() || (index > 0 && (c.is_ascii_digit() || c == '-')) {
+            class.push(c);
+        } else {
+            class.push_str(&format!("_{:x}_", c as u32));
+        }
+    }
+    if class.is_empty() {
+        class.push('_');
+    }
+    class
+}
+
+struct HtmlWriter<'a> {
+    document: &'a PdxDocument,
+    options: &'a HtmlOptions,
+    /// Style names the content uses, so each gets a CSS rule
+    used_styles: BTreeSet<String>,
+}
+
+impl HtmlWriter<'_> {
+    /// `class`, `dir` and `lang` attributes for a block.
+    fn block_attributes(&mut self, style: &str, runs: &[TextRun], rtl: bool) -> String {
+        self.used_styles.insert(style.to_string());
+        format!(
+            " class=\"{}\" dir=\"{}\" lang=\"{}\"",
+            style_class(style),
+            if rtl { "rtl" } else { "ltr" },
+            escape(block_language(runs, &self.document.metadata.language))
+        )
+    }
+
+    // The browser applies the bidi algorithm within each line; blocks only
+    // need their base direction, worked out as the page layout does
+    fn blocks(&mut self, node: &Node) -> String {
+        let styles = &self.document.styles;
+        let fallback = self.document.metadata.language.as_str();
+
+        match node {
+            Node::Document { children } => {
+                children.iter().map(|child| self.blocks(child)).collect()
+            }
+            Node::Heading { level, runs, style } => {
+                let rtl = is_rtl_block(runs, styles.resolve(style).direction);
+                format!(
+                    "<h{0}{1}>{2}</h{0}>\n",
+                    level.clamp(&1, &6),
+                    self.block_attributes(style, runs, rtl),
+                    runs_to_html(runs, rtl, fallback, LanguageAttributes::Html)
+                )
+            }
+            Node::Paragraph { runs, style } => {
+                let rtl = is_rtl_block(runs, styles.resolve(style).direction);
+                format!(
+                    "<p{}>{}</p>\n",
+                    self.block_attributes(style, runs, rtl),
+                    runs_to_html(runs, rtl, fallback, LanguageAttributes::Html)
+                )
+            }
+            Node::List {
+                ordered,
+                start,
+                items,
+                style,
+            } => {
+                let tag = if *ordered { "ol" } else { "ul" };
+                let direction = styles.resolve(style).direction;
+                self.used_styles.insert(style.to_string());
+
+                let mut attributes = format!(" class=\"{}\"", style_class(style));
+                if *ordered && *start != 1 {
+                    attributes.push_str(&format!(" start=\"{}\"", start));
+                }
+                let digits = match items.first().map(|item| item.language()) {
+                    Some("ar" | "ckb") => Some("arabic-indic"),
+                    Some("fa" | "ur" | "ps" | "sd") => Some("persian"),
+                    _ => None,
+                };
+                if let (true, Some(digits)) = (*ordered, digits) {
+                    attributes.push_str(&format!(" style=\"list-style-type: {}\"", digits));
+                }
+                // The markers sit on the side the first item reads from
+                if let Some(first) = items.first() {
+                    let rtl = is_rtl_block(&first.content, direction);
+                    attributes.push_str(if rtl { " dir=\"rtl\"" } else { " dir=\"ltr\"" });
+                }
+
+                let mut html = format!("<{}{}>", tag, attributes);
+                for item in items {
+                    let rtl = is_rtl_block(&item.content, direction);
+                    let nested: String = item
+                        .children
+                        .iter()
+                        .map(|child| self.blocks(child))
+                        .collect();
+                    html.push_str(&format!(
+                        "<li dir=\"{}\" lang=\"{}\">{}{}</li>",
+                        if rtl { "rtl" } else { "ltr" },
+                        escape(block_language(&item.content, fallback)),
+                        runs_to_html(&item.content, rtl, fallback, LanguageAttributes::Html),
+                        nested
+                    ));
+                }
+                html.push_str(&format!("</{}>\n", tag));
+                html
+            }
+            Node::CodeBlock {
+                language,
+                code,
+                style,
+            } => {
+                self.used_styles.insert(style.to_string());
+                let class = match language.as_str() {
+                    "" => String::new(),
+                    language => format!(" class=\"language-{}\"", escape(language)),
+                };
+                format!(
+                    "<pre class=\"{}\" dir=\"ltr\"><code{}>{}</code></pre>\n",
+                    style_class(style),
+                    class,
+                    escape(code)
+                )
+            }
+            Node::Image {
+                path,
+                alt_text,
+                width,
+                height,
+            } => {
+                // Embedded files have no path outside the document, so they
+                // are always inlined
+                let resources = &self.document.resources;
+                let source = (self.options.inline_images || resources.files.contains_key(path))
+                    .then(|| data_uri(resources, path))
+                    .flatten()
+                    .unwrap_or_else(|| escape(path));
+
+                let mut attributes = String::new();
+                if let Some(width) = width {
+                    attributes.push_str(&format!(" width=\"{}\"", width.round()));
+                }
+                if let Some(height) = height {
+                    attributes.push_str(&format!(" height=\"{}\"", height.round()));
+                }
+                format!(
+                    "<img src=\"{}\" alt=\"{}\"{} />\n",
+                    source,
+                    escape(alt_text),
+                    attributes
+                )
+            }
+            Node::Table {
+                rows,
+                header_rows,
+                alignments,
+                direction,
+                style,
+            } => {
+                self.used_styles.insert(style.to_string());
+                let table_rtl = *direction == Direction::RTL;
+                let row_html = |row: &TableRow, tag: &str| -> String {
+                    let cells: String = row
+                        .cells
+                        .iter()
+                        .enumerate()
+                        .map(|(c, cell)| {
+                            let align = match alignments.get(c) {
+                                Some(TextAlign::Center) => "center",
+                                Some(TextAlign::End) => "end",
+                                _ => "start",
+                            };
+                            format!(
+                                "<{0} style=\"text-align: {1}\">{2}</{0}>",
+                                tag,
+                                align,
+                                runs_to_html(
+                                    &cell.content,
+                                    table_rtl,
+                                    fallback,
+                                    LanguageAttributes::Html
+                                )
+                            )
+                        })
+                        .collect();
+                    format!("<tr>{}</tr>", cells)
+                };
+
+                let split = (*header_rows).min(rows.len());
+                let head: String = rows[..split].iter().map(|r| row_html(r, "th")).collect();
+                let body: String = rows[split..].iter().map(|r| row_html(r, "td")).collect();
+
+                let mut table = format!(
+                    "<table class=\"{}\" dir=\"{}\">",
+                    style_class(style),
+                    if table_rtl { "rtl" } else { "ltr" }
+                );
+                if !head.is_empty() {
+                    table.push_str(&format!("<thead>{}</thead>", head));
+                }
+                table.push_str(&format!("<tbody>{}</tbody></table>\n", body));
+                table
+            }
+            Node::Divider => "<hr/>\n".to_string(),
+            Node::PageBreak => "<hr class=\"page-break\"/>\n".to_string(),
+        }
+    }
+}
+
+/// How markup names a language: HTML with `lang`, XHTML with `xml:lang`
+/// as well.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum LanguageAttributes {
+    Html,
+    Xhtml,
+}
+
+impl LanguageAttributes {
+    /// The attributes for `language`, with a leading space.
+    pub(crate) fn format(self, language: &str) -> String {
+        let language = escape(language);
+        match self {
+            LanguageAttributes::Html => format!(" lang=\"{}\"", language),
+            LanguageAttributes::Xhtml => format!(" xml:lang=\"{0}\" lang=\"{0}\"", language),
+        }
+    }
+}
+
+/// Runs as HTML or XHTML. A run whose language or direction differs from
+/// its block's is wrapped in a span that says so.
+pub(crate) fn runs_to_html(
+    runs: &[TextRun],
+    block_rtl: bool,
+    fallback: &str,
+    attributes: LanguageAttributes,
+) -> String {
+    let block_language = block_language(runs, fallback);
+
+    runs.iter()
+        .map(|run| {
+            let marks = &run.marks;
+            let mut html = escape(&run.text).replace('\n', "<br/>");
+
+            if marks.code {
+                html = format!("<code>{}</code>", html);
+            }
+            if marks.underline {
+                html = format!("<u>{}</u>", html);
+            }
+            if marks.strikethrough {
+                html = format!("<del>{}</del>", html);
+            }
+            if marks.emphasis {
+                html = format!("<em>{}</em>", html);
+            }
+            if marks.strong {
+                html = format!("<strong>{}</strong>", html);
+            }
+            // Links in other schemes, `javascript:` among them, stay text
+            if let Some(url) = marks.link.as_deref().filter(|url| is_safe_link(url)) {
+                html = format!("<a href=\"{}\">{}</a>", escape(url), html);
+            }
+
+            let rtl = is_rtl_run(run);
+            let language = match run.language.as_str() {
+                "" => block_language,
+                language => language,
+            };
+            if rtl != block_rtl || language != block_language {
+                html = format!(
+                    "<span dir=\"{}\"{}>{}</span>",
+                    if rtl { "rtl" } else { "ltr" },
+                    attributes.format(language),
+                    html
+                );
+            }
+            html
+        })
+        .collect()
+}
+
+/// Whether a link may go into an `href`: web and `mailto:` links, and
+/// relative ones, `#` fragments included. Tabs and line breaks are dropped
+/// first, as browsers do, so they can't hide a scheme.
+pub(crate) fn is_safe_link(url: &str) -> bool {
+    let url: String = url
+        .chars()
+        .filter(|c| !matches!(c, '\t' | '\n' | '\r'))
+        .collect();
+    let url = url.trim_start_matches(|c: char| c <= ' ');
+
+    match url.find([':', '/', '?', '#']) {
+        Some(end) if url[end..].starts_with(':') => {
+            let scheme = url[..end].to_ascii_lowercase();
+            matches!(scheme.as_str(), "http" | "https" | "mailto")
+        }
+        _ => true,
+    }
+}
+
+/// The image as a `data:` URI, or `None` when it cannot be read or is a
+/// file on disk in a format browsers are not told the type of. Embedded
+/// files of unknown formats are still inlined, as they have no other path.
+pub(crate) fn data_uri(resources: &Resources, path: &str) -> Option<String> {
+    let bytes = resources.read(path)?;
+    let media_type = match ::image::guess_format(&bytes) {
+        Ok(format) => format.to_mime_type(),
+        // The image crate does not sniff SVG
+        Err(_) if path.to_ascii_lowercase().ends_with(".svg") => "image/svg+xml",
+        Err(_) if resources.files.contains_key(path) => "application/octet-stream",
+        Err(_) => return None,
+    };
+    Some(format!("data:{};base64,{}", media_type, base64(&bytes)))
+}
+
+/// Standard base64 with padding (RFC 4648).
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+
+    out
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PngLayout {
+    /// One image per page, matching the PDF pagination
+    PerPage,
+    /// The whole document as one continuous image
+    SingleImage,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PngOptions {
+    pub dpi: f32,
+    pub layout: PngLayout,
+}
+
+impl Default for PngOptions {
+    fn default() -> Self {
+        Self {
+            dpi: 144.0,
+            layout: PngLayout::SingleImage,
+        }
+    }
+}
+
+/// Rasterizes the document with the same layout as the PDF export and
+/// returns the encoded PNG data, one entry per output image.
+pub fn export_as_png(
+    document: &PdxDocument,
+    options: &PngOptions,
+) -> Result<Vec<Vec<u8>>, PdxError> {
+    let fonts = FontSet::bundled()?;
+    let scale = options.dpi / 72.0;
+
+    let images = match options.layout {
+        PngLayout::PerPage => {
+            let setup = PageSetup::a4();
+            layout_document(document, &setup, &fonts)
+                .iter()
+                .map(|page| render_page_to_image(page, &fonts, setup.width, setup.height, scale))
+                .collect()
+        }
+        PngLayout::SingleImage => {
+            // An unbounded page never overflows, so only explicit page breaks split it
+            let setup = PageSetup {
+                height: f32::INFINITY,
+                ..PageSetup::a4()
+            };
+            let pages: Vec<RgbaImage> = layout_document(document, &setup, &fonts)
+                .iter()
+                .map(|page| {
+                    let height = page_content_bottom(page, setup.margin) + setup.margin;
+                    render_page_to_image(page, &fonts, setup.width, height, scale)
+                })
+                .collect();
+            vec![stack_images(&pages)]
+        }
+    };
+
+    images
+        .iter()
+        .map(|img| {
+            let mut buffer = Vec::new();
+            img.write_to(&mut std::io::Cursor::new(&mut buffer), ImageFormat::Png)
+                .map_err(|e| PdxError::Export {
+                    format: "PNG",
+                    message: e.to_string(),
+                })?;
+            Ok(buffer)
+        })
+        .collect()
+}
+
+fn render_page_to_image(
+    page: &Page,
+    fonts: &FontSet,
+    width: f32,
+    height: f32,
+    scale: f32,
+) -> RgbaImage {
+    let mut canvas = RgbaImage::from_pixel(
+        ((width * scale).ceil() as u32).max(1),
+        ((height * scale).ceil() as u32).max(1),
+        Rgba([255, 255, 255, 255]),
+    );
+
+    for item in &page.items {
+        match item {
+            PageItem::Text(span) => draw_text(&mut canvas, span, fonts, scale),
+            PageItem::Rule {
+                x1,
+                x2,
+                y,
+                thickness,
+                color,
+            } => fill_rect(
+                &mut canvas,
+                x1 * scale,
+                y * scale,
+                (x2 - x1) * scale,
+                (thickness * scale).max(1.0),
+                *color,
+            ),
+            PageItem::Fill {
+                x,
+                y,
+                width,
+                height,
+                color,
+            } => fill_rect(
+                &mut canvas,
+                x * scale,
+                y * scale,
+                width * scale,
+                height * scale,
+                *color,
+            ),
+            PageItem::Image {
+                image,
+                x,
+                y,
+                width,
+                height,
+            } => {
+                let resized = image
+                    .resize_exact(
+                        ((width * scale).round() as u32).max(1),
+                        ((height * scale).round() as u32).max(1),
+                        imageops::FilterType::Triangle,
+                    )
+                    .to_rgba8();
+                imageops::overlay(
+                    &mut canvas,
+                    &resized,
+                    (x * scale).round() as i64,
+                    (y * scale).round() as i64,
+                );
+            }
+            PageItem::Link { .. } => {}
+        }
+    }
+
+    canvas
+}
+
+fn draw_text(canvas: &mut RgbaImage, span: &TextSpan, fonts: &FontSet, scale: f32) {
+    let face = fonts.face(span.face);
+    let size = span.size * scale;
+    let px_scale = fonts.px_scale(span.face, size);
+    let baseline = span.baseline * scale;
+    let mut x = span.x * scale;
+
+    for c in span.text.chars() {
+        let glyph = face
+            .glyph_id(c)
+            .with_scale_and_position(px_scale, ab_glyph::point(x, baseline));
+
+        if let Some(outlined) = face.outline_glyph(glyph) {
+            let bounds = outlined.px_bounds();
+            outlined.draw(|gx, gy, coverage| {
+                let py = bounds.min.y + gy as f32;
+                let mut px = bounds.min.x + gx as f32;
+                if span.italic {
+                    px += (baseline - py) * ITALIC_SKEW;
+                }
+
+                // Faux bold: smear each glyph a pixel to the right
+                let strokes = if span.bold { 2 } else { 1 };
+                for offset in 0..strokes {
+                    blend_pixel(
+                        canvas,
+                        px.round() as i64 + offset,
+                        py as i64,
+                        span.color,
+                        coverage,
+                    );
+                }
+            });
+        }
+
+        x += fonts.char_width(c, size);
+    }
+}
+
+fn fill_rect(
+    canvas: &mut RgbaImage,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    color: crate::data::Color,
+) {
+    let x0 = x.round() as i64;
+    let y0 = y.round() as i64;
+    let x1 = (x + width).round() as i64;
+    let y1 = (y + height).round() as i64;
+
+    for py in y0..y1.max(y0 + 1) {
+        for px in x0..x1 {
+            blend_pixel(canvas, px, py, color, 1.0);
+        }
+    }
+}
+
+fn blend_pixel(canvas: &mut RgbaImage, x: i64, y: i64, color: crate::data::Color, alpha: f32) {
+    if x < 0 || y < 0 || x >= canvas.width() as i64 || y >= canvas.height() as i64 {
+        return;
+    }
+
+    let alpha = alpha.clamp(0.0, 1.0);
+    let pixel = canvas.get_pixel_mut(x as u32, y as u32);
+    for (channel, value) in pixel.0.iter_mut().zip([color.r, color.g, color.b]) {
+        *channel = (*channel as f32 * (1.0 - alpha) + value as f32 * alpha).round() as u8;
+    }
+}
+
+/// Lowest point drawn on a page, used to crop continuous (unbounded) pages.
+fn page_content_bottom(page: &Page, top: f32) -> f32 {
+    page.items
+        .iter()
+        .map(|item| match item {
+            PageItem::Text(span) => span.baseline + span.size * 0.3,
+            PageItem::Rule { y, thickness, .. } => y + thickness,
+            PageItem::Fill { y, height, .. }
+            | PageItem::Image { y, height, .. }
+            | PageItem::Link { y, height, .. } => y + height,
+        })
+        .fold(top, f32::max)
+}
+
+fn stack_images(images: &[RgbaImage]) -> RgbaImage {
+    let width = images.iter().map(|img| img.width()).max().unwrap_or(1);
+    let height = images.iter().map(|img| img.height()).sum::<u32>().max(1);
+    let mut canvas = RgbaImage::from_pixel(width, height, Rgba([255, 255, 255, 255]));
+
+    let mut y = 0;
+    for img in images {
+        imageops::overlay(&mut canvas, img, 0, y);
+        y += img.height() as i64;
+    }
+
+    canvas
+}
+
+pub fn export_as_pdf(document: &PdxDocument) -> Result<Vec<u8>, PdxError> {
+    let setup = PageSetup::a4();
+    let fonts = FontSet::bundled()?;
+    let pages = layout_document(document, &setup, &fonts);
+
+    let page_width = Mm::from(Pt(setup.width));
+    let page_height = Mm::from(Pt(setup.height));
+    let (doc, page1, layer1) =
+        PdfDocument::new(&document.metadata.title, page_width, page_height, "Layer 1");
+
+    // Embed the bundled faces in layout order so span face indices line up.
+    // Faces no span uses are skipped, their slots borrowing the first face.
+    let used: Vec<usize> = pages
+        .iter()
+        .flat_map(|page| &page.items)
+        .filter_map(|item| match item {
+            PageItem::Text(span) => Some(span.face),
+            _ => None,
+        })
+        .collect();
+    let mut pdf_fonts: Vec<IndirectFontRef> = Vec::with_capacity(FONT_FACES.len());
+    for (index, bytes) in FONT_FACES.iter().enumerate() {
+        let font = if index == 0 || used.contains(&index) {
+            doc.add_external_font(*bytes)
+                .map_err(|e| PdxError::Font(format!("{:?}", e)))?
+        } else {
+            pdf_fonts[0].clone()
+        };
+        pdf_fonts.push(font);
+    }
+
+    for (index, page) in pages.iter().enumerate() {
+        let layer = if index == 0 {
+            doc.get_page(page1).get_layer(layer1)
+        } else {
+            let (page_index, layer_index) = doc.add_page(page_width, page_height, "Layer 1");
+            doc.get_page(page_index).get_layer(layer_index)
+        };
+
+        render_page_to_pdf(page, &layer, &pdf_fonts, setup.height);
+    }
+
+    let mut buffer = Vec::new();
+    {
+        let mut writer = BufWriter::new(&mut buffer);
+        doc.save(&mut writer).map_err(|e| PdxError::Export {
+            format: "PDF",
+            message: format!("{:?}", e),
+        })?;
+    }
+
+    Ok(buffer)
+}
+
+/// Draws one laid-out page. Layout works top-down in points; PDF is bottom-up.
+fn render_page_to_pdf(
+    page: &Page,
+    layer: &PdfLayerReference,
+    fonts: &[IndirectFontRef],
+    page_height: f32,
+) {
+    let mm = |pt: f32| Mm::from(Pt(pt));
+
+    for item in &page.items {
+        match item {
+            PageItem::Text(span) => {
+                layer.set_fill_color(pdf_color(span.color));
+                layer.begin_text_section();
+                layer.set_font(&fonts[span.face], span.size);
+
+                // The bundled fonts have no bold or italic cuts, so synthesize
+                // them with a stroked outline and a skewed text matrix
+                if span.bold {
+                    layer.set_outline_color(pdf_color(span.color));
+                    layer.set_outline_thickness(span.size * 0.04);
+                    layer.set_text_rendering_mode(TextRenderingMode::FillStroke);
+                } else {
+                    layer.set_text_rendering_mode(TextRenderingMode::Fill);
+                }
+                let skew = if span.italic { ITALIC_SKEW } else { 0.0 };
+                layer.set_text_matrix(TextMatrix::Raw([
+                    1.0,
+                    0.0,
+                    skew,
+                    1.0,
+                    span.x,
+                    page_height - span.baseline,
+                ]));
+
+                layer.write_text(span.text.as_str(), &fonts[span.face]);
+                layer.end_text_section();
+            }
+            PageItem::Rule {
+                x1,
+                x2,
+                y,
+                thickness,
+                color,
+            } => {
+                layer.set_outline_color(pdf_color(*color));
+                layer.set_outline_thickness(*thickness);
+                layer.add_line(Line {
+                    points: vec![
+                        (Point::new(mm(*x1), mm(page_height - y)), false),
+                        (Point::new(mm(*x2), mm(page_height - y)), false),
+                    ],
+                    is_closed: false,
+                });
+            }
+            PageItem::Fill {
+                x,
+                y,
+                width,
+                height,
+                color,
+            } => {
+                layer.set_fill_color(pdf_color(*color));
+                layer.add_rect(Rect::new(
+                    mm(*x),
+                    mm(page_height - y - height),
+                    mm(x + width),
+                    mm(page_height - y),
+                ));
+            }
+            PageItem::Image {
+                image,
+                x,
+                y,
+                width,
+                height,
+            } => {
+                let rgb = flatten_on_white(image);
+                let (pixel_w, pixel_h) = rgb.dimensions();
+                let xobject = ImageXObject {
+                    width: Px(pixel_w as usize),
+                    height: Px(pixel_h as usize),
+                    color_space: ColorSpace::Rgb,
+                    bits_per_component: ColorBits::Bit8,
+                    interpolate: true,
+                    image_data: rgb.into_raw(),
+                    image_filter: None,
+                    smask: None,
+                    clipping_bbox: None,
+                };
+
+                // At 72 dpi one pixel is one point, so the scale is the target size
+                Image::from(xobject).add_to_layer(
+                    layer.clone(),
+                    ImageTransform {
+                        translate_x: Some(mm(*x)),
+                        translate_y: Some(mm(page_height - y - height)),
+                        scale_x: Some(width / pixel_w.max(1) as f32),
+                        scale_y: Some(height / pixel_h.max(1) as f32),
+                        dpi: Some(72.0),
+                        ..Default::default()
+                    },
+                );
+            }
+            PageItem::Link {
+                x,
+                y,
+                width,
+                height,
+                url,
+            } => {
+                layer.add_link_annotation(LinkAnnotation::new(
+                    Rect::new(
+                        mm(*x),
+                        mm(page_height - y - height),
+                        mm(x + width),
+                        mm(page_height - y),
+                    ),
+                    Some(BorderArray::Solid([0.0, 0.0, 0.0])),
+                    None,
+                    Actions::uri(url.clone()),
+                    None,
+                ));
+            }
+        }
+    }
+}
+
+/// PDF image data here has no alpha channel, so transparent pixels are
+/// blended onto the white page before embedding.
+fn flatten_on_white(image: &DynamicImage) -> RgbImage {
+    let rgba = image.to_rgba8();
+    RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let Rgba([r, g, b, a]) = *rgba.get_pixel(x, y);
+        let blend = |value: u8| ((value as u32 * a as u32 + 255 * (255 - a as u32)) / 255) as u8;
+        ::image::Rgb([blend(r), blend(g), blend(b)])
+    })
+}
+
+fn pdf_color(color: crate::data::Color) -> Color {
+    Color::Rgb(Rgb::new(
+        color.r as f32 / 255.0,
+        color.g as f32 / 255.0,
+        color.b as f32 / 255.0,
+        None,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{document, paragraph, png};
+    use std::collections::HashSet;
+
+    fn link(text: &str, url: &str) -> TextRun {
+        let mut run = TextRun::new(text, "en", "paragraph");
+        run.marks.link = Some(url.to_string());
+        run
+    }
+
+    #[test]
+    fn text_and_links_are_escaped() {
+        let mut doc = document(vec![
+            paragraph("<script>alert('x')</script> & more", "en"),
+            Node::Paragraph {
+                runs: vec![link("site", "https://example.com/?a=1&b=\"2\"")],
+                style: "paragraph".to_string(),
+            },
+        ]);
+        doc.metadata.title = "<Title> & co".to_string();
+
+        let html = export_as_html(&doc, &HtmlOptions::default());
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("&lt;script&gt;alert(&apos;x&apos;)&lt;/script&gt; &amp; more"));
+        assert!(html.contains("<title>&lt;Title&gt; &amp; co</title>"));
+        assert!(html.contains("<a href=\"https://example.com/?a=1&amp;b=&quot;2&quot;\">site</a>"));
+    }
+
+    #[test]
+    fn embedded_images_are_inlined_even_without_the_option() {
+        let image = |path: &str| Node::Image {
+            path: path.to_string(),
+            alt_text: String::new(),
+            width: None,
+            height: None,
+        };
+        let mut doc = document(vec![image("resources/a.png"), image("photos/b.png")]);
+        doc.resources
+            .files
+            .insert("resources/a.png".to_string(), png());
+
+        let html = export_as_html(&doc, &HtmlOptions::default());
+        assert!(html.contains("<img src=\"data:image/png;base64,"));
+        assert!(!html.contains("resources/a.png"));
+        assert!(html.contains("<img src=\"photos/b.png\""));
+    }
+
+    #[test]
+    fn links_in_other_schemes_become_plain_text() {
+        let doc = document(vec![Node::Paragraph {
+            runs: vec![
+                link("one", "javascript:alert(1)"),
+                link("two", " JavaScript:alert(2)"),
+                link("three", "java\tscript:alert(3)"),
+                link("four", "data:text/html,<b>x</b>"),
+                link("five", "mailto:someone@example.com"),
+                link("six", "#notes"),
+                link("seven", "images/a:b.png"),
+            ],
+            style: "paragraph".to_string(),
+        }]);
+
+        let html = export_as_html(&doc, &HtmlOptions::default());
+        assert!(!html.to_ascii_lowercase().contains("script:"));
+        assert!(!html.contains("data:"));
+        assert!(html.contains(">onetwothreefour<a href=\"mailto:someone@example.com\">five</a>"));
+        assert!(html.contains("<a href=\"#notes\">six</a>"));
+        assert!(html.contains("<a href=\"images/a:b.png\">seven</a>"));
+    }
+
+    #[test]
+    fn transparent_pixels_are_flattened_onto_white() {
+        let mut image = RgbaImage::new(3, 1);
+        image.put_pixel(0, 0, Rgba([0, 0, 0, 0]));
+        image.put_pixel(1, 0, Rgba([0, 0, 0, 255]));
+        image.put_pixel(2, 0, Rgba([255, 0, 0, 128]));
+
+        let flat = flatten_on_white(&DynamicImage::ImageRgba8(image));
+
+        assert_eq!(flat.get_pixel(0, 0).0, [255, 255, 255]);
+        assert_eq!(flat.get_pixel(1, 0).0, [0, 0, 0]);
+        assert_eq!(flat.get_pixel(2, 0).0, [255, 127, 127]);
+    }
+
+    #[test]
+    fn style_classes_are_valid_and_distinct() {
+        assert_eq!(style_class("heading1"), "heading1");
+        assert_eq!(style_class("pull-quote"), "pull-quote");
+        assert_eq!(style_class("a b"), "a_20_b");
+        assert_eq!(style_class("a_b"), "a_5f_b");
+        assert_eq!(style_class("2col"), "_32_col");
+        assert_eq!(style_class("-x"), "_2d_x");
+        assert_eq!(style_class("عنوان"), "_639__646__648__627__646_");
+        assert_eq!(style_class(""), "_");
+
+        let names = ["a b", "a_b", "a-b", "a.b", "a_20_b", "_", ""];
+        let classes: HashSet<String> = names.iter().map(|name| style_class(name)).collect();
+        assert_eq!(classes.len(), names.len());
+    }
+}
@@ -0,0 +1,875 @@
+use crate::data::{Direction, ListItem, Node, PdxDocument, TableRow, TextAlign, TextRun};
+use crate::dates::w3c_date;
+use crate::error::PdxError;
+use crate::layout::PX_TO_PT;
+use crate::pdx_text::{block_language, dominant_script, is_rtl_language, Script};
+use ::image::{ImageFormat, ImageReader};
+use std::collections::BTreeSet;
+use std::io::Cursor;
+
+// ============================================================================
+// LaTeX Export
+// ============================================================================
+
+/// Widest a figure is set, in points: the 170 mm text width of an A4 page
+/// with the 20 mm margins the PDF export uses.
+const TEXT_WIDTH_PT: f32 = 170.0 / 25.4 * 72.0;
+
+/// Deepest LaTeX nests `itemize` and `enumerate` environments, each.
+const MAX_LIST_DEPTH: usize = 4;
+
+/// Writes the document as LaTeX source for XeLaTeX. Headings become
+/// sectioning commands and code blocks `listings`. Languages go through
+/// polyglossia: the main one comes from the metadata, and blocks and runs
+/// in other languages are put in that language's environment or `\text…`
+/// command, which also sets their direction through bidi.
+///
+/// Images embedded in the document can't be carried by the source file, so
+/// a document that shows any fails to export rather than point at files
+/// that don't exist.
+pub fn export_as_latex(document: &PdxDocument) -> Result<String, PdxError> {
+    let embedded = document
+        .content
+        .image_paths()
+        .into_iter()
+        .filter(|path| document.resources.files.contains_key(*path))
+        .collect::<BTreeSet<_>>();
+    if !embedded.is_empty() {
+        return Err(PdxError::Export {
+            format: "LaTeX",
+            message: format!(
+                "{} images are embedded in the document and a .tex file can't carry them; \
+                 export as PDF or DOCX instead",
+                embedded.len()
+            ),
+        });
+    }
+
+    let main = polyglossia_language(&document.metadata.language, "");
+    let mut writer = LatexWriter {
+        document,
+        main,
+        languages: BTreeSet::from([main]),
+        code_environments: BTreeSet::new(),
+        itemize_depth: 0,
+        enumerate_depth: 0,
+    };
+    let body = writer.blocks(&document.content, main);
+
+    let mut tex = writer.preamble();
+    tex.push_str("\\begin{document}\n\n");
+    if !document.metadata.title.is_empty() {
+        tex.push_str("\\maketitle\n\n");
+    }
+    tex.push_str(&body);
+    tex.push_str("\\end{document}\n");
+    Ok(tex)
+}
+
+struct LatexWriter<'a> {
+    document: &'a PdxDocument,
+    /// polyglossia name of the document language
+    main: &'static str,
+    /// Every language the text uses, the main one included
+    languages: BTreeSet<&'static str>,
+    /// Listings environments declared for code that closes `lstlisting`
+    code_environments: BTreeSet<String>,
+    itemize_depth: usize,
+    enumerate_depth: usize,
+}
+
+impl LatexWriter<'_> {
+    fn preamble(&self) -> String {
+        let metadata = &self.document.metadata;
+        let styles = &self.document.styles;
+
+        // The class only offers 10, 11 and 12 pt body text
+        let body_pt = styles.resolve("paragraph").font_size * PX_TO_PT;
+        let size = if body_pt < 10.5 {
+            10
+        } else if body_pt < 11.5 {
+            11
+        } else {
+            12
+        };
+
+        let mut tex = format!(
+            "% Generated by PDX Editor. Compile with XeLaTeX.\n\
+             \\documentclass[a4paper,{}pt]{{article}}\n\
+             \\usepackage[a4paper,margin=20mm]{{geometry}}\n\
+             \\usepackage{{fontspec}}\n\
+             \\usepackage{{parskip}}\n\
+             \\usepackage{{graphicx}}\n\
+             \\usepackage{{listings}}\n\
+             \\usepackage[normalem]{{ulem}}\n\
+             \\usepackage{{hyperref}}\n",
+            size
+        );
+        // polyglossia loads bidi for right-to-left languages, which has to
+        // come after hyperref
+        let others: Vec<&str> = self
+            .languages
+            .iter()
+            .copied()
+            .filter(|language| *language != self.main)
+            .collect();
+        tex.push_str("\\usepackage{polyglossia}\n");
+        tex.push_str(&format!("\\setmainlanguage{{{}}}\n", self.main));
+        if !others.is_empty() {
+            tex.push_str(&format!("\\setotherlanguages{{{}}}\n", others.join(",")));
+        }
+        for language in &self.languages {
+            if let Some(font) = language_font(language) {
+                tex.push_str(&font);
+            }
+        }
+
+        tex.push_str(
+            "\\lstset{basicstyle=\\ttfamily\\small, breaklines=true, frame=single, \
+             columns=fullflexible, keepspaces=true}\n",
+        );
+        for environment in &self.code_environments {
+            tex.push_str(&format!(
+                "\\lstnewenvironment{{{}}}[1][]{{\\lstset{{#1}}}}{{}}\n",
+                environment
+            ));
+        }
+        if let Some(dir) = &self.document.resources.base_dir {
+            // Relative image paths resolve against the document, wherever
+            // the .tex is compiled
+            let dir = dir.to_string_lossy().replace('\\', "/");
+            if !dir.is_empty() && !dir.contains(['%', '#', '{', '}', '\\', '~', '^', '$', '&']) {
+                tex.push_str(&format!(
+                    "\\graphicspath{{{{{}/}}}}\n",
+                    dir.trim_end_matches('/')
+                ));
+            }
+        }
+
+        let mut pdf_info = format!("pdftitle={{{}}}", escape(&metadata.title));
+        if !metadata.author.is_empty() {
+            pdf_info.push_str(&format!(", pdfauthor={{{}}}", escape(&metadata.author)));
+        }
+        if !metadata.keywords.is_empty() {
+            pdf_info.push_str(&format!(
+                ", pdfkeywords={{{}}}",
+                escape(&metadata.keywords.join(", "))
+            ));
+        }
+        tex.push_str(&format!("\\hypersetup{{{}}}\n\n", pdf_info));
+
+        tex.push_str(&format!("\\title{{{}}}\n", escape(&metadata.title)));
+        tex.push_str(&format!("\\author{{{}}}\n", escape(&metadata.author)));
+        let date = w3c_date(&metadata.created)
+            .map(|date| date[..10].to_string())
+            .unwrap_or_default();
+        tex.push_str(&format!("\\date{{{}}}\n\n", date));
+        tex
+    }
+
+    /// The polyglossia language of a block: its first tagged run's.
+    fn block_language(&mut self, runs: &[TextRun]) -> &'static str {
+        let language = polyglossia_language(
+            block_language(runs, &self.document.metadata.language),
+            &runs.iter().map(|run| run.text.as_str()).collect::<String>(),
+        );
+        self.languages.insert(language);
+        language
+    }
+
+    /// `content` in the environment of `language`, when that differs from
+    /// the surrounding one.
+    fn in_language(&self, content: String, language: &str, outer: &str) -> String {
+        if language == outer {
+            content
+        } else {
+            let environment = environment_name(language);
+            format!("\\begin{{{0}}}\n{1}\\end{{{0}}}\n", environment, content)
+        }
+    }
+
+    fn blocks(&mut self, node: &Node, outer: &'static str) -> String {
+        match node {
+            Node::Document { children } => children
+                .iter()
+                .map(|child| self.blocks(child, outer))
+                .collect(),
+            Node::Heading { level, runs, .. } => {
+                let language = self.block_language(runs);
+                let command = match level {
+                    1 => "section",
+                    2 => "subsection",
+                    3 => "subsubsection",
+                    4 => "paragraph",
+                    _ => "subparagraph",
+                };
+                let text = self.runs(runs, language, false);
+                // Bookmarks take plain text only
+                let plain = escape(&runs.iter().map(|r| r.text.as_str()).collect::<String>())
+                    .replace("\\newline{}", " ");
+                let title = if text == plain {
+                    text
+                } else {
+                    format!("\\texorpdfstring{{{}}}{{{}}}", text, plain)
+                };
+                let heading = format!("\\{}{{{}}}\n\n", command, title);
+                self.in_language(heading, language, outer)
+            }
+            Node::Paragraph { runs, .. } => {
+                if runs.iter().all(|run| run.text.trim().is_empty()) {
+                    return String::new();
+                }
+                let language = self.block_language(runs);
+                let text = format!("{}\n\n", self.runs(runs, language, true));
+                self.in_language(text, language, outer)
+            }
+            Node::List {
+                ordered,
+                start,
+                items,
+                ..
+            } => self.list(*ordered, *start, items, outer),
+            Node::CodeBlock { language, code, .. } => {
+                // The environment ends wherever the code closes it, so code
+                // that closes `lstlisting` gets one with a name it lacks
+                let environment = code_environment(code);
+                if environment != "lstlisting" {
+                    self.code_environments.insert(environment.clone());
+                }
+                let options = match listings_language(language) {
+                    Some(language) => format!("[language={{{}}}]", language),
+                    None => String::new(),
+                };
+                let listing = format!(
+                    "\\begin{{{0}}}{1}\n{2}\n\\end{{{0}}}\n\n",
+                    environment,
+                    options,
+                    code.trim_end_matches('\n')
+                );
+                // Code reads left to right, even in a right-to-left document
+                if is_rtl_polyglossia(outer) {
+                    format!("\\begin{{LTR}}\n{}\\end{{LTR}}\n\n", listing)
+                } else {
+                    listing
+                }
+            }
+            Node::Image {
+                path,
+                alt_text,
+                width,
+                height,
+            } => self.image(path, alt_text, *width, *height),
+            Node::Table {
+                rows,
+                header_rows,
+                alignments,
+                direction,
+                ..
+            } => self.table(rows, *header_rows, alignments, *direction, outer),
+            Node::Divider => "\\noindent\\rule{\\linewidth}{0.4pt}\n\n".to_string(),
+            Node::PageBreak => "\\newpage\n\n".to_string(),
+        }
+    }
+
+    fn list(
+        &mut self,
+        ordered: bool,
+        start: u32,
+        items: &[ListItem],
+        outer: &'static str,
+    ) -> String {
+        let depth = if ordered {
+            self.enumerate_depth
+        } else {
+            self.itemize_depth
+        };
+        // Past the deepest level LaTeX allows, items become indented paragraphs
+        if depth >= MAX_LIST_DEPTH {
+            let mut tex = String::new();
+            for item in items {
+                let language = self.block_language(&item.content);
+                let text = format!(
+                    "\\hspace*{{2em}}\\textbullet\\ {}\n\n",
+                    self.runs(&item.content, language, true)
+                );
+                tex.push_str(&self.in_language(text, language, outer));
+                for child in &item.children {
+                    tex.push_str(&self.blocks(child, outer));
+                }
+            }
+            return tex;
+        }
+        if ordered {
+            self.enumerate_depth += 1;
+        } else {
+            self.itemize_depth += 1;
+        }
+        let counter = ["enumi", "enumii", "enumiii", "enumiv"][depth];
+
+        let environment = if ordered { "enumerate" } else { "itemize" };
+        let mut tex = format!("\\begin{{{}}}\n", environment);
+        if ordered && start != 1 {
+            tex.push_str(&format!(
+                "\\setcounter{{{}}}{{{}}}\n",
+                counter,
+                start.saturating_sub(1)
+            ));
+        }
+        for item in items {
+            let language = self.block_language(&item.content);
+            let mut text = self.runs(&item.content, language, true);
+            if language != outer {
+                text = format!("\\text{}{{{}}}", language, text);
+            }
+            tex.push_str(&format!("\\item {}\n", text));
+            for child in &item.children {
+                tex.push_str(&self.blocks(child, outer));
+            }
+        }
+        tex.push_str(&format!("\\end{{{}}}\n\n", environment));
+
+        if ordered {
+            self.enumerate_depth -= 1;
+        } else {
+            self.itemize_depth -= 1;
+        }
+        tex
+    }
+
+    fn table(
+        &mut self,
+        rows: &[TableRow],
+        header_rows: usize,
+        alignments: &[TextAlign],
+        direction: Direction,
+        outer: &'static str,
+    ) -> String {
+        let columns = rows.iter().map(|row| row.cells.len()).max().unwrap_or(0);
+        if columns == 0 {
+            return String::new();
+        }
+
+        // bidi lays out tables in right-to-left text from the right; in a
+        // left-to-right document the columns are reversed instead
+        let rtl = direction == Direction::RTL;
+        let outer_rtl = is_rtl_polyglossia(outer);
+        let reverse = rtl && !outer_rtl;
+        let mut order: Vec<usize> = (0..columns).collect();
+        if reverse {
+            order.reverse();
+        }
+
+        let spec: String = order
+            .iter()
+            .map(|c| match alignments.get(*c) {
+                Some(TextAlign::Center) => "c|",
+                Some(TextAlign::End) if rtl => "l|",
+                Some(TextAlign::End) => "r|",
+                _ if rtl => "r|",
+                _ => "l|",
+            })
+            .collect();
+
+        let mut tex = format!(
+            "\\begin{{center}}\n\\begin{{tabular}}{{|{}}}\n\\hline\n",
+            spec
+        );
+        let header_rows = header_rows.min(rows.len());
+        for (r, row) in rows.iter().enumerate() {
+            let cells: Vec<String> = order
+                .iter()
+                .map(|c| {
+                    let runs = row.cells.get(*c).map_or(&[][..], |cell| &cell.content);
+                    let language = self.block_language(runs);
+                    let mut text = self.runs(runs, language, false);
+                    if language != outer && !text.is_empty() {
+                        text = format!("\\text{}{{{}}}", language, text);
+                    }
+                    if r < header_rows && !text.is_empty() {
+                        text = format!("\\textbf{{{}}}", text);
+                    }
+                    text
+                })
+                .collect();
+            tex.push_str(&cells.join(" & "));
+            tex.push_str(" \\\\\n");
+            if r + 1 == header_rows {
+                tex.push_str("\\hline\n");
+            }
+        }
+        tex.push_str("\\hline\n\\end{tabular}\n\\end{center}\n\n");
+
+        if !rtl && outer_rtl {
+            format!("\\begin{{LTR}}\n{}\\end{{LTR}}\n\n", tex)
+        } else {
+            tex
+        }
+    }
+
+    /// A figure for images XeLaTeX can include from disk; anything else,
+    /// paths TeX can't be given included, becomes a framed placeholder with
+    /// the alt text so the file still compiles.
+    fn image(&self, path: &str, alt_text: &str, width: Option<f32>, height: Option<f32>) -> String {
+        let resources = &self.document.resources;
+        // Embedded files were turned away by export_as_latex
+        let dimensions = resources.read(path).and_then(|bytes| {
+            let reader = ImageReader::new(Cursor::new(bytes))
+                .with_guessed_format()
+                .ok()?;
+            matches!(reader.format(), Some(ImageFormat::Png | ImageFormat::Jpeg)).then_some(())?;
+            reader.into_dimensions().ok()
+        });
+
+        let caption = match alt_text.trim() {
+            "" => String::new(),
+            alt => format!("\\caption{{{}}}\n", escape(alt)),
+        };
+        let (Some((natural_w, natural_h)), Some(file)) = (dimensions, graphics_path(path)) else {
+            let label = match alt_text.trim() {
+                "" => escape(path),
+                alt => escape(alt),
+            };
+            return format!(
+                "\\begin{{center}}\n\\fbox{{\\parbox{{0.8\\linewidth}}{{\\centering {}}}}}\n\\end{{center}}\n\n",
+                label
+            );
+        };
+
+        // Same sizing as the page layout: the given size, else the natural
+        // one, shrunk to the text width
+        let (natural_w, natural_h) = (natural_w.max(1) as f32, natural_h.max(1) as f32);
+        let w = match (width, height) {
+            (Some(w), _) => w,
+            (None, Some(h)) => h * natural_w / natural_h,
+            (None, None) => natural_w,
+        };
+        let width_pt = (w * PX_TO_PT).min(TEXT_WIDTH_PT);
+
+        format!(
+            "\\begin{{figure}}[htbp]\n\\centering\n\\includegraphics[width={:.1}pt]{{{}}}\n{}\\end{{figure}}\n\n",
+            width_pt, file, caption
+        )
+    }
+
+    /// Runs as LaTeX, each stretch in another language than `language`
+    /// wrapped in its `\text…` command. Line breaks become `\newline` when
+    /// `breaks` is set and spaces otherwise.
+    fn runs(&mut self, runs: &[TextRun], language: &'static str, breaks: bool) -> String {
+        let mut tex = String::new();
+        let mut group: Option<(&'static str, String)> = None;
+        let mut line_start = true;
+
+        for run in runs {
+            let run_language = match run.language.as_str() {
+                "" => language,
+                tag => polyglossia_language(tag, &run.text),
+            };
+            self.languages.insert(run_language);
+
+            let mut text = String::new();
+            for c in run.text.chars() {
+                match c {
+                    // Nothing to end at the start of a paragraph
+                    '\n' if breaks && line_start => {}
+                    '\n' if breaks => {
+                        text.push_str("\\newline{}");
+                        line_start = true;
+                        continue;
+                    }
+                    '\n' | '\t' => text.push(' '),
+                    c => text.push_str(&escape(&c.to_string())),
+                }
+                line_start = false;
+            }
+            if text.is_empty() {
+                continue;
+            }
+
+            let marks = &run.marks;
+            if marks.code {
+                text = format!("\\texttt{{{}}}", text);
+            }
+            if marks.underline {
+                text = format!("\\uline{{{}}}", text);
+            }
+            if marks.strikethrough {
+                text = format!("\\sout{{{}}}", text);
+            }
+            if marks.emphasis {
+                text = format!("\\emph{{{}}}", text);
+            }
+            if marks.strong {
+                text = format!("\\textbf{{{}}}", text);
+            }
+            if let Some(url) = &marks.link {
+                text = format!("\\href{{{}}}{{{}}}", escape_url(url), text);
+            }
+
+            // Neighbouring runs in one language share a command, so the
+            // spaces between them take that language's direction
+            match &mut group {
+                Some((current, content)) if *current == run_language => content.push_str(&text),
+                _ => {
+                    if let Some((current, content)) = group.take() {
+                        tex.push_str(&wrap_language(current, language, &content));
+                    }
+                    group = Some((run_language, text));
+                }
+            }
+        }
+        if let Some((current, content)) = group {
+            tex.push_str(&wrap_language(current, language, &content));
+        }
+
+        tex
+    }
+}
+
+fn wrap_language(language: &str, outer: &str, content: &str) -> String {
+    if language == outer {
+        content.to_string()
+    } else {
+        format!("\\text{}{{{}}}", language, content)
+    }
+}
+
+/// Escapes LaTeX's special characters.
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\textbackslash{}"),
+            '{' | '}' | '$' | '&' | '#' | '%' | '_' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '~' => out.push_str("\\textasciitilde{}"),
+            '^' => out.push_str("\\textasciicircum{}"),
+            '\n' => out.push_str("\\newline{}"),
+            c if c.is_control() => {}
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+/// A path for `\includegraphics`, or `None` when TeX can't be given it.
+/// `\detokenize` takes `~`, `_`, `$`, `&`, `^` and spaces as they are, but
+/// `%` and `#` are read before it sees them and braces must balance.
+fn graphics_path(path: &str) -> Option<String> {
+    let path = path.replace('\\', "/");
+    if path.contains(['%', '#', '{', '}']) || path.chars().any(char::is_control) {
+        return None;
+    }
+    Some(format!("\\detokenize{{{}}}", path))
+}
+
+/// A URL for `\href`, which takes most characters as they are. `&` is
+/// escaped too, since a link may sit in another command's argument or in a
+/// table cell.
+fn escape_url(url: &str) -> String {
+    let mut out = String::with_capacity(url.len());
+    for c in url.chars() {
+        if matches!(c, '\\' | '#' | '%' | '&' | '{' | '}') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// polyglossia's name for a BCP 47 tag. Right-to-left tags it does not
+/// know are set as Arabic or Hebrew by their script; other unknown tags
+/// fall back to English.
+fn polyglossia_language(tag: &str, text: &str) -> &'static str {
+    let primary = tag
+        .split(['-', '_'])
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+
+    match primary.as_str() {
+        "en" => "english",
+        "ar" => "arabic",
+        "fa" => "persian",
+        "ur" => "urdu",
+        "he" | "iw" => "hebrew",
+        "fr" => "french",
+        "de" => "german",
+        "es" => "spanish",
+        "it" => "italian",
+        "pt" => "portuguese",
+        "nl" => "dutch",
+        "sv" => "swedish",
+        "pl" => "polish",
+        "tr" => "turkish",
+        "ru" => "russian",
+        "uk" => "ukrainian",
+        "el" => "greek",
+        _ if is_rtl_language(tag) || dominant_script(text).is_some_and(Script::is_rtl) => {
+            match dominant_script(text) {
+                Some(Script::Hebrew) => "hebrew",
+                _ if primary == "yi" => "hebrew",
+                _ => "arabic",
+            }
+        }
+        _ => "english",
+    }
+}
+
+fn is_rtl_polyglossia(language: &str) -> bool {
+    matches!(language, "arabic" | "persian" | "urdu" | "hebrew")
+}
+
+/// The environment for a language; Arabic's is capitalized because
+/// `\arabic` is taken by LaTeX's counters.
+fn environment_name(language: &str) -> &str {
+    match language {
+        "arabic" => "Arabic",
+        language => language,
+    }
+}
+
+/// Font for the right-to-left languages, which Latin Modern does not cover.
+/// Amiri and DejaVu Sans come with TeX Live and are found by file name.
+fn language_font(language: &str) -> Option<String> {
+    let (script, family, faces) = match language {
+        "arabic" | "persian" | "urdu" => (
+            "Arabic",
+            "Amiri",
+            "UprightFont=*-Regular, BoldFont=*-Bold, ItalicFont=*-Slanted, \
+             BoldItalicFont=*-BoldSlanted",
+        ),
+        "hebrew" => (
+            "Hebrew",
+            "DejaVuSans",
+            "UprightFont=*, BoldFont=*-Bold, ItalicFont=*-Oblique, \
+             BoldItalicFont=*-BoldOblique",
+        ),
+        _ => return None,
+    };
+    Some(format!(
+        "\\newfontfamily\\{}font[Script={}, Extension=.ttf, {}]{{{}}}\n",
+        language, script, faces, family
+    ))
+}
+
+/// `lstlisting`, or for code that contains `\end{lstlisting}` the first of
+/// `pdxcode`, `pdxcode2`, ... that the code never closes.
+fn code_environment(code: &str) -> String {
+    let closes = |name: &str| code.contains(&format!("\\end{{{}}}", name));
+    if !closes("lstlisting") {
+        return "lstlisting".to_string();
+    }
+    std::iter::once("pdxcode".to_string())
+        .chain((2..).map(|n| format!("pdxcode{}", n)))
+        .find(|name| !closes(name))
+        .unwrap_or_default()
+}
+
+/// listings' name for a code block language, for those it highlights.
+fn listings_language(language: &str) -> Option<&'static str> {
+    let name = match language.to_ascii_lowercase().as_str() {
+        "c" => "C",
+        "cpp" | "c++" | "cc" => "C++",
+        "java" => "Java",
+        "python" | "py" => "Python",
+        "bash" | "sh" | "shell" | "zsh" => "bash",
+        "html" => "HTML",
+        "xml" => "XML",
+        "sql" => "SQL",
+        "ruby" | "rb" => "Ruby",
+        "perl" => "Perl",
+        "php" => "PHP",
+        "haskell" | "hs" => "Haskell",
+        "r" => "R",
+        "matlab" => "Matlab",
+        "fortran" => "Fortran",
+        "pascal" => "Pascal",
+        "lisp" => "Lisp",
+        "tex" | "latex" => "[LaTeX]TeX",
+        _ => return None,
+    };
+    Some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, paragraph};
+
+    fn document(language: &str, children: Vec<Node>) -> PdxDocument {
+        let mut document = test_support::document(children);
+        document.metadata.title = "Notes".to_string();
+        document.metadata.language = language.to_string();
+        document
+    }
+
+    fn code(language: &str, code: &str) -> Node {
+        Node::CodeBlock {
+            language: language.to_string(),
+            code: code.to_string(),
+            style: "code".to_string(),
+        }
+    }
+
+    /// The source from `\begin{document}` on.
+    fn body(tex: &str) -> &str {
+        let start = tex.find("\\begin{document}").unwrap();
+        &tex[start..]
+    }
+
+    #[test]
+    fn headings_become_sections_and_text_is_escaped() {
+        let tex = export_as_latex(&document(
+            "en",
+            vec![
+                Node::Heading {
+                    level: 2,
+                    runs: vec![TextRun::new("Costs", "en", "heading2")],
+                    style: "heading2".to_string(),
+                },
+                paragraph("50% & $5_x {a} \\ ~", "en"),
+            ],
+        ))
+        .unwrap();
+
+        assert!(tex.contains("\\subsection{Costs}\n"));
+        assert!(tex.contains("50\\% \\& \\$5\\_x \\{a\\} \\textbackslash{} \\textasciitilde{}\n\n"));
+        assert!(tex.contains("\\title{Notes}\n"));
+        assert!(tex.contains("\\setmainlanguage{english}\n"));
+    }
+
+    #[test]
+    fn code_blocks_are_listings_in_their_language() {
+        let tex = export_as_latex(&document("en", vec![code("py", "print(1)\n")])).unwrap();
+
+        assert!(body(&tex)
+            .contains("\\begin{lstlisting}[language={Python}]\nprint(1)\n\\end{lstlisting}\n"));
+        assert!(!tex.contains("\\lstnewenvironment"));
+    }
+
+    #[test]
+    fn code_that_closes_listings_gets_an_environment_it_never_closes() {
+        let source = "\\end{lstlisting}\n\\end{verbatim}\n\\end{pdxcode}";
+        let tex = export_as_latex(&document("en", vec![code("python", source)])).unwrap();
+
+        let declaration = "\\lstnewenvironment{pdxcode2}[1][]{\\lstset{#1}}{}\n";
+        assert!(!body(&tex).contains(declaration));
+        assert!(tex.contains(declaration));
+        assert!(body(&tex).contains(&format!(
+            "\\begin{{pdxcode2}}[language={{Python}}]\n{}\n\\end{{pdxcode2}}\n",
+            source
+        )));
+        assert_eq!(tex.matches("\\end{pdxcode2}").count(), 1);
+    }
+
+    #[test]
+    fn code_environment_names_are_never_closed_by_the_code() {
+        assert_eq!(code_environment("\\end{verbatim}"), "lstlisting");
+        assert_eq!(code_environment("x = \"\\end{lstlisting}\""), "pdxcode");
+        assert_eq!(
+            code_environment("\\end{lstlisting} \\end{pdxcode} \\end{pdxcode2}"),
+            "pdxcode3"
+        );
+    }
+
+    #[test]
+    fn other_languages_are_set_in_their_polyglossia_environment() {
+        let tex = export_as_latex(&document(
+            "en",
+            vec![paragraph("Hello", "en"), paragraph("مرحبا بالعالم", "ar")],
+        ))
+        .unwrap();
+
+        assert!(tex.contains("\\setotherlanguages{arabic}\n"));
+        assert!(tex.contains("\\newfontfamily\\arabicfont[Script=Arabic"));
+        assert!(body(&tex).contains("\\begin{Arabic}\nمرحبا بالعالم\n\n\\end{Arabic}\n"));
+    }
+
+    #[test]
+    fn code_in_a_right_to_left_document_reads_left_to_right() {
+        let tex = export_as_latex(&document(
+            "ar",
+            vec![paragraph("مرحبا", "ar"), code("text", "let x = 1;")],
+        ))
+        .unwrap();
+
+        assert!(tex.contains("\\setmainlanguage{arabic}\n"));
+        assert!(body(&tex).contains(
+            "\\begin{LTR}\n\\begin{lstlisting}\nlet x = 1;\n\\end{lstlisting}\n\n\\end{LTR}\n"
+        ));
+    }
+
+    #[test]
+    fn ordered_lists_keep_their_start() {
+        let item = |text: &str| ListItem {
+            content: vec![TextRun::new(text, "en", "paragraph")],
+            children: Vec::new(),
+        };
+        let tex = export_as_latex(&document(
+            "en",
+            vec![Node::List {
+                ordered: true,
+                start: 3,
+                items: vec![item("Third"), item("Fourth")],
+                style: "list".to_string(),
+            }],
+        ))
+        .unwrap();
+
+        assert!(body(&tex).contains(
+            "\\begin{enumerate}\n\\setcounter{enumi}{2}\n\\item Third\n\\item Fourth\n\\end{enumerate}\n"
+        ));
+    }
+
+    #[test]
+    fn embedded_images_are_reported_instead_of_referenced() {
+        let mut document = document(
+            "en",
+            vec![Node::Image {
+                path: "resources/a.png".to_string(),
+                alt_text: String::new(),
+                width: None,
+                height: None,
+            }],
+        );
+        document
+            .resources
+            .files
+            .insert("resources/a.png".to_string(), test_support::png());
+
+        assert!(matches!(
+            export_as_latex(&document),
+            Err(PdxError::Export {
+                format: "LaTeX",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn image_paths_are_detokenized_or_left_out() {
+        let dir = std::env::temp_dir().join(format!("pdx-latex-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["a~b_c.png", "50%#{.png"] {
+            std::fs::write(dir.join(name), test_support::png()).unwrap();
+        }
+        let image = |path: &str| Node::Image {
+            path: path.to_string(),
+            alt_text: String::new(),
+            width: None,
+            height: None,
+        };
+        let mut document = document("en", vec![image("a~b_c.png"), image("50%#{.png")]);
+        document.resources.base_dir = Some(dir.clone());
+
+        let tex = export_as_latex(&document).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(body(&tex).contains("pt]{\\detokenize{a~b_c.png}}\n"));
+        assert_eq!(body(&tex).matches("\\includegraphics").count(), 1);
+        assert!(body(&tex).contains("\\centering 50\\%\\#\\{.png}"));
+    }
+}
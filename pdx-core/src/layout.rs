@@ -0,0 +1,969 @@
+use crate::data::{
+    Color, Direction, FontWeight, InlineMarks, ListItem, Node, PdxDocument, Resources, Style,
+    StyleSheet, TableRow, TextAlign, TextRun,
+};
+use crate::error::PdxError;
+use crate::pdx_text::{
+    is_rtl_block, line_segments, list_marker, pdx_text, shape, split_long_word, split_words,
+    visual_line, wrap_words, Word,
+};
+use ab_glyph::{Font, FontRef, PxScale};
+use image::DynamicImage;
+
+// ============================================================================
+// Page Layout
+// ============================================================================
+
+/// Style sizes are authored in CSS pixels; pages are laid out in points.
+pub const PX_TO_PT: f32 = 0.75;
+
+/// Bundled faces in fallback order. Exporters embed them in the same order,
+/// so `TextSpan::face` indexes into both. DejaVu Sans comes last for
+/// Hebrew, N'Ko and other scripts the Noto faces lack.
+pub const FONT_FACES: [&[u8]; 3] = [
+    include_bytes!("../../assets/fonts/NotoSans-Regular.ttf"),
+    include_bytes!("../../assets/fonts/NotoSansArabic-Regular.ttf"),
+    include_bytes!("../../assets/fonts/DejaVuSans.ttf"),
+];
+
+const LIST_INDENT: f32 = 18.0;
+/// Minimum space between a wide list marker and the item text.
+const MARKER_GAP: f32 = 4.0;
+const CODE_PADDING: f32 = 8.0;
+const BLOCK_SPACING: f32 = 8.0;
+const CELL_PADDING: f32 = 5.0;
+const MIN_COLUMN_WIDTH: f32 = 24.0;
+const CODE_BACKGROUND: Color = Color {
+    r: 244,
+    g: 244,
+    b: 244,
+};
+const LINK_COLOR: Color = Color {
+    r: 0,
+    g: 102,
+    b: 204,
+};
+const RULE_COLOR: Color = Color {
+    r: 221,
+    g: 221,
+    b: 221,
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct PageSetup {
+    pub width: f32,
+    pub height: f32,
+    pub margin: f32,
+}
+
+impl PageSetup {
+    pub fn a4() -> Self {
+        Self {
+            width: 595.28,
+            height: 841.89,
+            margin: 56.69, // 20mm
+        }
+    }
+
+    pub fn content_width(&self) -> f32 {
+        self.width - 2.0 * self.margin
+    }
+
+    pub fn content_height(&self) -> f32 {
+        self.height - 2.0 * self.margin
+    }
+}
+
+impl Default for PageSetup {
+    fn default() -> Self {
+        Self::a4()
+    }
+}
+
+// ============================================================================
+// Font Metrics
+// ============================================================================
+
+pub struct FontSet {
+    faces: Vec<FontRef<'static>>,
+}
+
+impl FontSet {
+    pub fn bundled() -> Result<Self, PdxError> {
+        let faces = FONT_FACES
+            .iter()
+            .map(|bytes| FontRef::try_from_slice(bytes).map_err(|e| PdxError::Font(e.to_string())))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { faces })
+    }
+
+    pub fn face(&self, index: usize) -> &FontRef<'static> {
+        &self.faces[index]
+    }
+
+    /// Rasterizer scale for an em size of `size` pixels.
+    pub fn px_scale(&self, index: usize, size: f32) -> PxScale {
+        let face = &self.faces[index];
+        let units = face.units_per_em().unwrap_or(1000.0);
+        PxScale::from(size * face.height_unscaled() / units)
+    }
+
+    /// Picks the first face in the fallback chain that has a glyph for `c`.
+    pub fn face_for(&self, c: char) -> usize {
+        self.faces
+            .iter()
+            .position(|face| face.glyph_id(c).0 != 0)
+            .unwrap_or(0)
+    }
+
+    pub fn char_width(&self, c: char, size: f32) -> f32 {
+        if is_invisible(c) {
+            return 0.0;
+        }
+        let face = &self.faces[self.face_for(c)];
+        let units = face.units_per_em().unwrap_or(1000.0);
+        face.h_advance_unscaled(face.glyph_id(c)) / units * size
+    }
+
+    pub fn text_width(&self, text: &str, size: f32) -> f32 {
+        text.chars().map(|c| self.char_width(c, size)).sum()
+    }
+
+    /// Ascent and (negative) descent of the primary face at `size`.
+    pub fn vertical_metrics(&self, size: f32) -> (f32, f32) {
+        let face = &self.faces[0];
+        let units = face.units_per_em().unwrap_or(1000.0);
+        (
+            face.ascent_unscaled() / units * size,
+            face.descent_unscaled() / units * size,
+        )
+    }
+
+    /// Splits `text` into consecutive pieces that render with the same face.
+    pub fn segments(&self, text: &str) -> Vec<(usize, String)> {
+        let mut segments: Vec<(usize, String)> = Vec::new();
+
+        for c in text.chars().filter(|c| !is_invisible(*c)) {
+            let face = self.face_for(c);
+            match segments.last_mut() {
+                Some((last_face, segment)) if *last_face == face => segment.push(c),
+                _ => segments.push((face, c.to_string())),
+            }
+        }
+
+        segments
+    }
+}
+
+/// Bidi formatting characters survive reordering but have no glyph.
+fn is_invisible(c: char) -> bool {
+    matches!(c, '\u{200B}'..='\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}')
+}
+
+// ============================================================================
+// Page Items
+// ============================================================================
+
+/// A piece of visually ordered text drawn with a single face.
+/// Coordinates are in points from the top-left corner of the page.
+#[derive(Debug, Clone)]
+pub struct TextSpan {
+    pub text: String,
+    pub x: f32,
+    pub baseline: f32,
+    pub size: f32,
+    pub face: usize,
+    pub color: Color,
+    pub bold: bool,
+    pub italic: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum PageItem {
+    Text(TextSpan),
+    Rule {
+        x1: f32,
+        x2: f32,
+        y: f32,
+        thickness: f32,
+        color: Color,
+    },
+    Fill {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        color: Color,
+    },
+    Image {
+        image: DynamicImage,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    },
+    /// Clickable area for a hyperlink; drawn by formats that support it.
+    Link {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        url: String,
+    },
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Page {
+    pub items: Vec<PageItem>,
+}
+
+// ============================================================================
+// Layout Engine
+// ============================================================================
+
+/// Flows the document onto pages: wraps text to the content width using the
+/// bundled font metrics and starts a new page on overflow or `PageBreak`.
+/// Explicit breaks always take effect, so leading or repeated breaks leave
+/// blank pages.
+pub fn layout_document(document: &PdxDocument, setup: &PageSetup, fonts: &FontSet) -> Vec<Page> {
+    let mut engine = LayoutEngine {
+        setup: *setup,
+        fonts,
+        styles: &document.styles,
+        resources: &document.resources,
+        pages: vec![Page::default()],
+        y: setup.margin,
+        indent: 0.0,
+    };
+
+    engine.layout_node(&document.content);
+    engine.pages
+}
+
+/// Visually ordered text from a single run, or an inter-word space.
+struct VisualPiece {
+    text: String,
+    run: Option<usize>,
+    is_space: bool,
+    width: f32,
+}
+
+struct LinePlacement {
+    left: f32,
+    right: f32,
+    baseline: f32,
+    is_rtl: bool,
+    is_last: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Pen {
+    size: f32,
+    color: Color,
+    bold: bool,
+    italic: bool,
+}
+
+impl Pen {
+    fn plain(size: f32, color: Color) -> Self {
+        Self {
+            size,
+            color,
+            bold: false,
+            italic: false,
+        }
+    }
+}
+
+struct LayoutEngine<'a> {
+    setup: PageSetup,
+    fonts: &'a FontSet,
+    styles: &'a StyleSheet,
+    resources: &'a Resources,
+    pages: Vec<Page>,
+    y: f32,
+    /// Extra inset from the reading-side margin, grown by nested lists
+    indent: f32,
+}
+
+impl LayoutEngine<'_> {
+    fn layout_node(&mut self, node: &Node) {
+        match node {
+            Node::Document { children } => {
+                for child in children {
+                    self.layout_node(child);
+                }
+            }
+
+            Node::Heading { runs, style, .. } | Node::Paragraph { runs, style } => {
+                let style = self.styles.resolve(style);
+                self.space(style.margin.top * PX_TO_PT);
+                self.layout_text(runs, &style, None);
+                self.space(style.margin.bottom * PX_TO_PT);
+            }
+
+            Node::List {
+                ordered,
+                start,
+                items,
+                style,
+            } => {
+                let style = self.styles.resolve(style);
+                self.space(style.margin.top * PX_TO_PT);
+                self.layout_list(*ordered, *start, items, &style, 0);
+                self.space(style.margin.bottom * PX_TO_PT);
+            }
+
+            Node::CodeBlock { code, style, .. } => {
+                let style = self.styles.resolve(style);
+                self.layout_code(code, &style);
+            }
+
+            Node::Image {
+                path,
+                alt_text,
+                width,
+                height,
+            } => match self.resources.load_image(path) {
+                Some(image) => self.layout_image(image, *width, *height),
+                None => {
+                    let style = self.styles.resolve("paragraph");
+                    let placeholder = format!("[Image: {}]", alt_text);
+                    self.layout_text(
+                        &[TextRun::new(&placeholder, "en", "paragraph")],
+                        &style,
+                        None,
+                    );
+                }
+            },
+
+            Node::Table {
+                rows,
+                header_rows,
+                alignments,
+                direction,
+                style,
+            } => {
+                let style = self.styles.resolve(style);
+                self.layout_table(rows, *header_rows, alignments, *direction, &style);
+            }
+
+            Node::Divider => {
+                self.space(BLOCK_SPACING);
+                self.ensure(1.0);
+                let y = self.y;
+                self.push(PageItem::Rule {
+                    x1: self.setup.margin,
+                    x2: self.setup.width - self.setup.margin,
+                    y,
+                    thickness: 0.75,
+                    color: RULE_COLOR,
+                });
+                self.space(BLOCK_SPACING);
+            }
+
+            Node::PageBreak => self.new_page(),
+        }
+    }
+
+    /// Lays out list items one indent step in from the current indent, with
+    /// markers in the gutter. Nested lists go one level deeper.
+    fn layout_list(
+        &mut self,
+        ordered: bool,
+        start: u32,
+        items: &[ListItem],
+        style: &Style,
+        depth: usize,
+    ) {
+        self.indent += LIST_INDENT;
+
+        for (i, item) in items.iter().enumerate() {
+            let number = start.saturating_add(i as u32);
+            let marker = list_marker(ordered, number, depth, item.language(), false);
+            self.layout_text(&item.content, style, Some(&marker));
+
+            for child in &item.children {
+                match child {
+                    Node::List {
+                        ordered,
+                        start,
+                        items,
+                        ..
+                    } => self.layout_list(*ordered, *start, items, style, depth + 1),
+                    _ => self.layout_node(child),
+                }
+            }
+        }
+
+        self.indent -= LIST_INDENT;
+    }
+
+    /// Lays out wrapped text at the current indent. A list marker, if any,
+    /// sits in the gutter just before the text on the reading side.
+    fn layout_text(&mut self, runs: &[TextRun], style: &Style, marker: Option<&str>) {
+        let is_rtl = is_rtl_block(runs, style.direction);
+
+        let size = style.font_size * PX_TO_PT;
+        let line_height = size * style.line_height;
+        let (ascent, descent) = self.fonts.vertical_metrics(size);
+        let indent = self.indent;
+
+        let mut left = self.setup.margin + (style.margin.left + style.padding.left) * PX_TO_PT;
+        let mut right = self.setup.width
+            - self.setup.margin
+            - (style.margin.right + style.padding.right) * PX_TO_PT;
+        if is_rtl {
+            right -= indent;
+        } else {
+            left += indent;
+        }
+
+        let lines = self.wrap(runs, size, right - left);
+        let last = lines.len().saturating_sub(1);
+
+        for (index, line) in lines.iter().enumerate() {
+            self.ensure(line_height);
+            let baseline = self.y + (line_height - (ascent - descent)) / 2.0 + ascent;
+
+            if let (0, Some(marker)) = (index, marker) {
+                let (marker, x) = if is_rtl {
+                    // Ordered markers are drawn left to right, so `1.` becomes `.1`
+                    let marker = match marker.strip_suffix('.') {
+                        Some(number) => format!(".{}", number),
+                        None => marker.to_string(),
+                    };
+                    let width = self.fonts.text_width(&marker, size);
+                    let x = (right + LIST_INDENT - width).max(right + MARKER_GAP);
+                    (marker, x)
+                } else {
+                    let width = self.fonts.text_width(marker, size);
+                    let x = (left - LIST_INDENT).min(left - width - MARKER_GAP);
+                    (marker.to_string(), x)
+                };
+                self.place_text(&marker, x, baseline, Pen::plain(size, style.color));
+            }
+
+            let pieces = self.visual_line(line, size, is_rtl);
+            let placement = LinePlacement {
+                left,
+                right,
+                baseline,
+                is_rtl,
+                is_last: index == last,
+            };
+            self.place_line(pieces, runs, style, &placement);
+            self.y += line_height;
+        }
+    }
+
+    fn wrap(&self, runs: &[TextRun], size: f32, width: f32) -> Vec<Vec<Word>> {
+        let space = self.fonts.text_width(" ", size);
+        wrap_words(runs, width, space, |_, text| {
+            self.fonts.text_width(&shape(text), size)
+        })
+    }
+
+    fn word_width(&self, word: &Word, size: f32) -> f32 {
+        word.iter()
+            .map(|(_, text)| self.fonts.text_width(&shape(text), size))
+            .sum()
+    }
+
+    /// Reorders one wrapped line into measured visual pieces.
+    fn visual_line(&self, line: &[Word], size: f32, is_rtl: bool) -> Vec<VisualPiece> {
+        visual_line(&line_segments(line), is_rtl)
+            .into_iter()
+            .map(|segment| VisualPiece {
+                width: self.fonts.text_width(&segment.text, size),
+                is_space: segment.text == " ",
+                text: segment.text,
+                run: segment.run,
+            })
+            .collect()
+    }
+
+    fn place_line(
+        &mut self,
+        pieces: Vec<VisualPiece>,
+        runs: &[TextRun],
+        style: &Style,
+        placement: &LinePlacement,
+    ) {
+        let LinePlacement {
+            left,
+            right,
+            baseline,
+            is_rtl,
+            is_last,
+        } = *placement;
+
+        let size = style.font_size * PX_TO_PT;
+        let natural: f32 = pieces.iter().map(|p| p.width).sum();
+        let spaces = pieces.iter().filter(|p| p.is_space).count() as f32;
+        let available = right - left;
+
+        let mut stretch = 0.0;
+        let start = match (style.text_align, is_rtl) {
+            (TextAlign::Center, _) => left + (available - natural) / 2.0,
+            (TextAlign::Start, false) | (TextAlign::End, true) => left,
+            (TextAlign::Start, true) | (TextAlign::End, false) => right - natural,
+            (TextAlign::Justify, _) if !is_last && spaces > 0.0 => {
+                stretch = (available - natural) / spaces;
+                left
+            }
+            (TextAlign::Justify, true) => right - natural,
+            (TextAlign::Justify, false) => left,
+        };
+
+        // Position everything first so code backgrounds sit beneath the text
+        let mut x = start;
+        let positioned: Vec<(f32, f32, VisualPiece)> = pieces
+            .into_iter()
+            .map(|piece| {
+                let width = piece.width + if piece.is_space { stretch } else { 0.0 };
+                let placed = (x, width, piece);
+                x += width;
+                placed
+            })
+            .collect();
+
+        let no_marks = InlineMarks::default();
+        let marks_of = |piece: &VisualPiece| piece.run.map_or(&no_marks, |i| &runs[i].marks);
+
+        for (x, width, piece) in &positioned {
+            if marks_of(piece).code && !piece.is_space {
+                self.push(PageItem::Fill {
+                    x: x - 1.0,
+                    y: baseline - size * 0.95,
+                    width: width + 2.0,
+                    height: size * 1.25,
+                    color: CODE_BACKGROUND,
+                });
+            }
+        }
+
+        for (x, width, piece) in &positioned {
+            let marks = marks_of(piece);
+            let pen = Pen {
+                size,
+                color: if marks.link.is_some() {
+                    LINK_COLOR
+                } else {
+                    style.color
+                },
+                bold: marks.strong || matches!(style.font_weight, FontWeight::Bold),
+                italic: marks.emphasis,
+            };
+
+            if !piece.is_space {
+                self.place_text(&piece.text, *x, baseline, pen);
+            }
+
+            if marks.underline || marks.link.is_some() {
+                self.push(PageItem::Rule {
+                    x1: *x,
+                    x2: x + width,
+                    y: baseline + size * 0.12,
+                    thickness: size * 0.05,
+                    color: pen.color,
+                });
+            }
+            if marks.strikethrough {
+                self.push(PageItem::Rule {
+                    x1: *x,
+                    x2: x + width,
+                    y: baseline - size * 0.28,
+                    thickness: size * 0.05,
+                    color: pen.color,
+                });
+            }
+            if let (Some(url), false) = (&marks.link, piece.is_space) {
+                self.push(PageItem::Link {
+                    x: *x,
+                    y: baseline - size * 0.95,
+                    width: *width,
+                    height: size * 1.25,
+                    url: url.clone(),
+                });
+            }
+        }
+    }
+
+    fn place_text(&mut self, text: &str, x: f32, baseline: f32, pen: Pen) {
+        let mut x = x;
+
+        for (face, segment) in self.fonts.segments(text) {
+            let width = self.fonts.text_width(&segment, pen.size);
+            self.push(PageItem::Text(TextSpan {
+                text: segment,
+                x,
+                baseline,
+                size: pen.size,
+                face,
+                color: pen.color,
+                bold: pen.bold,
+                italic: pen.italic,
+            }));
+            x += width;
+        }
+    }
+
+    fn layout_code(&mut self, code: &str, style: &Style) {
+        let size = style.font_size * PX_TO_PT * 0.85;
+        let line_height = size * 1.4;
+        let (ascent, descent) = self.fonts.vertical_metrics(size);
+        let left = self.setup.margin + CODE_PADDING;
+        let width = self.setup.content_width() - 2.0 * CODE_PADDING;
+
+        self.space(BLOCK_SPACING);
+
+        for source_line in code.lines() {
+            let pieces: Vec<String> = if source_line.is_empty() {
+                vec![String::new()]
+            } else {
+                let measure = |_, text: &str| self.fonts.text_width(text, size);
+                split_long_word(vec![(0, source_line.to_string())], width, &measure)
+                    .into_iter()
+                    .map(|word| word.into_iter().map(|(_, text)| text).collect())
+                    .collect()
+            };
+
+            for piece in pieces {
+                self.ensure(line_height);
+                let y = self.y;
+                self.push(PageItem::Fill {
+                    x: self.setup.margin,
+                    y,
+                    width: self.setup.content_width(),
+                    height: line_height,
+                    color: CODE_BACKGROUND,
+                });
+
+                let baseline = y + (line_height - (ascent - descent)) / 2.0 + ascent;
+                self.place_text(
+                    &pdx_text(&piece),
+                    left,
+                    baseline,
+                    Pen::plain(size, style.color),
+                );
+                self.y += line_height;
+            }
+        }
+
+        self.space(BLOCK_SPACING);
+    }
+
+    fn layout_image(&mut self, image: DynamicImage, width: Option<f32>, height: Option<f32>) {
+        let natural_w = image.width().max(1) as f32;
+        let natural_h = image.height().max(1) as f32;
+
+        let (w, h) = match (width, height) {
+            (Some(w), Some(h)) => (w, h),
+            (Some(w), None) => (w, w * natural_h / natural_w),
+            (None, Some(h)) => (h * natural_w / natural_h, h),
+            (None, None) => (natural_w, natural_h),
+        };
+
+        let scale = (self.setup.content_width() / (w * PX_TO_PT))
+            .min(self.setup.content_height() / (h * PX_TO_PT))
+            .min(1.0);
+        let width = w * PX_TO_PT * scale;
+        let height = h * PX_TO_PT * scale;
+
+        self.space(BLOCK_SPACING);
+        self.ensure(height);
+
+        let x = self.setup.margin + (self.setup.content_width() - width) / 2.0;
+        let y = self.y;
+        self.push(PageItem::Image {
+            image,
+            x,
+            y,
+            width,
+            height,
+        });
+
+        self.y += height;
+        self.space(BLOCK_SPACING);
+    }
+
+    /// Draws a bordered table one row at a time. Rows are kept whole and move
+    /// to the next page when they do not fit; RTL tables put the first column
+    /// on the right.
+    fn layout_table(
+        &mut self,
+        rows: &[TableRow],
+        header_rows: usize,
+        alignments: &[TextAlign],
+        direction: Direction,
+        style: &Style,
+    ) {
+        let columns = rows.iter().map(|r| r.cells.len()).max().unwrap_or(0);
+        if columns == 0 {
+            return;
+        }
+
+        let size = style.font_size * PX_TO_PT;
+        let line_height = size * style.line_height.min(1.5);
+        let (ascent, descent) = self.fonts.vertical_metrics(size);
+        let table_rtl = direction == Direction::RTL;
+        let widths = self.column_widths(rows, columns, size);
+        let total: f32 = widths.iter().sum();
+
+        // Left edge of each logical column
+        let table_left = if table_rtl {
+            self.setup.width - self.setup.margin - total
+        } else {
+            self.setup.margin
+        };
+        let table_right = table_left + total;
+        let mut edges = Vec::with_capacity(columns);
+        let mut x = if table_rtl { table_right } else { table_left };
+        for width in &widths {
+            if table_rtl {
+                x -= width;
+                edges.push(x);
+            } else {
+                edges.push(x);
+                x += width;
+            }
+        }
+
+        self.space(BLOCK_SPACING);
+
+        for (r, row) in rows.iter().enumerate() {
+            let lines: Vec<Vec<Vec<Word>>> = (0..columns)
+                .map(|c| {
+                    row.cells.get(c).map_or_else(Vec::new, |cell| {
+                        self.wrap(&cell.content, size, widths[c] - 2.0 * CELL_PADDING)
+                    })
+                })
+                .collect();
+            let line_count = lines.iter().map(Vec::len).max().unwrap_or(0).max(1);
+            let height = line_count as f32 * line_height + 2.0 * CELL_PADDING;
+
+            let page = self.pages.len();
+            self.ensure(height);
+            let top = self.y;
+            let is_header = r < header_rows;
+
+            if is_header {
+                self.push(PageItem::Fill {
+                    x: table_left,
+                    y: top,
+                    width: total,
+                    height,
+                    color: CODE_BACKGROUND,
+                });
+            }
+
+            for (c, cell_lines) in lines.iter().enumerate() {
+                let Some(cell) = row.cells.get(c) else {
+                    continue;
+                };
+                let is_rtl = table_rtl || is_rtl_block(&cell.content, Direction::Auto);
+
+                let mut cell_style = style.clone();
+                cell_style.text_align = alignments.get(c).copied().unwrap_or_default();
+                if is_header {
+                    cell_style.font_weight = FontWeight::Bold;
+                }
+
+                for (i, line) in cell_lines.iter().enumerate() {
+                    let y = top + CELL_PADDING + i as f32 * line_height;
+                    let baseline = y + (line_height - (ascent - descent)) / 2.0 + ascent;
+                    let pieces = self.visual_line(line, size, is_rtl);
+                    let placement = LinePlacement {
+                        left: edges[c] + CELL_PADDING,
+                        right: edges[c] + widths[c] - CELL_PADDING,
+                        baseline,
+                        is_rtl,
+                        is_last: true,
+                    };
+                    self.place_line(pieces, &cell.content, &cell_style, &placement);
+                }
+            }
+
+            // Borders: a top rule for the first row on each page, then the
+            // bottom rule and the column separators
+            if r == 0 || self.pages.len() != page {
+                self.push(PageItem::Rule {
+                    x1: table_left,
+                    x2: table_right,
+                    y: top,
+                    thickness: 0.75,
+                    color: RULE_COLOR,
+                });
+            }
+            self.push(PageItem::Rule {
+                x1: table_left,
+                x2: table_right,
+                y: top + height,
+                thickness: 0.75,
+                color: RULE_COLOR,
+            });
+            for x in edges.iter().copied().chain([table_left, table_right]) {
+                self.push(PageItem::Fill {
+                    x: x - 0.375,
+                    y: top,
+                    width: 0.75,
+                    height,
+                    color: RULE_COLOR,
+                });
+            }
+
+            self.y = top + height;
+        }
+
+        self.space(BLOCK_SPACING);
+    }
+
+    /// Natural single-line column widths. When the table is wider than the
+    /// page, every column keeps room for its longest word and the remaining
+    /// space is shared out in proportion to how much more each one wanted.
+    fn column_widths(&self, rows: &[TableRow], columns: usize, size: f32) -> Vec<f32> {
+        let space = self.fonts.text_width(" ", size);
+        let (minimum, natural): (Vec<f32>, Vec<f32>) = (0..columns)
+            .map(|c| {
+                let (mut longest, mut content) = (0.0_f32, 0.0_f32);
+                for cell in rows.iter().filter_map(|row| row.cells.get(c)) {
+                    let widths: Vec<f32> = split_words(&cell.content)
+                        .iter()
+                        .map(|w| self.word_width(w, size))
+                        .collect();
+                    let gaps = widths.len().saturating_sub(1) as f32 * space;
+                    longest = widths.iter().copied().fold(longest, f32::max);
+                    content = content.max(widths.iter().sum::<f32>() + gaps);
+                }
+
+                // A point of slack covers the faux-bold stroke on headers
+                let pad = 2.0 * CELL_PADDING + 1.0;
+                (
+                    (longest + pad).max(MIN_COLUMN_WIDTH),
+                    (content + pad).max(MIN_COLUMN_WIDTH),
+                )
+            })
+            .unzip();
+
+        let available = self.setup.content_width();
+        let min_total: f32 = minimum.iter().sum();
+        let natural_total: f32 = natural.iter().sum();
+
+        if natural_total <= available {
+            natural
+        } else if min_total >= available {
+            minimum.iter().map(|w| w * available / min_total).collect()
+        } else {
+            let share = (available - min_total) / (natural_total - min_total);
+            minimum
+                .iter()
+                .zip(&natural)
+                .map(|(min, nat)| min + (nat - min) * share)
+                .collect()
+        }
+    }
+
+    // ------------------------------------------------------------------------
+    // Page bookkeeping
+    // ------------------------------------------------------------------------
+
+    fn push(&mut self, item: PageItem) {
+        if let Some(page) = self.pages.last_mut() {
+            page.items.push(item);
+        }
+    }
+
+    fn page_is_empty(&self) -> bool {
+        self.pages.last().is_none_or(|page| page.items.is_empty())
+    }
+
+    fn new_page(&mut self) {
+        self.pages.push(Page::default());
+        self.y = self.setup.margin;
+    }
+
+    /// Moves to a new page unless `height` still fits above the bottom margin.
+    fn ensure(&mut self, height: f32) {
+        if self.y + height > self.setup.height - self.setup.margin && !self.page_is_empty() {
+            self.new_page();
+        }
+    }
+
+    /// Vertical spacing is dropped at the top of a page.
+    fn space(&mut self, amount: f32) {
+        if !self.page_is_empty() {
+            self.y += amount;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{document, paragraph};
+
+    fn pages(children: Vec<Node>) -> Vec<Page> {
+        let fonts = FontSet::bundled().unwrap();
+        layout_document(&document(children), &PageSetup::a4(), &fonts)
+    }
+
+    #[test]
+    fn page_breaks_are_honoured_on_empty_pages() {
+        let result = pages(vec![
+            Node::PageBreak,
+            paragraph("one", "en"),
+            Node::PageBreak,
+            Node::PageBreak,
+            paragraph("two", "en"),
+        ]);
+
+        assert_eq!(result.len(), 4);
+        assert!(result[0].items.is_empty());
+        assert!(!result[1].items.is_empty());
+        assert!(result[2].items.is_empty());
+        assert!(!result[3].items.is_empty());
+    }
+
+    #[test]
+    fn long_text_wraps_within_the_margins_and_overflows_onto_new_pages() {
+        let setup = PageSetup::a4();
+        let text = "word ".repeat(4000);
+        let result = pages(vec![paragraph(&text, "en")]);
+
+        assert!(result.len() > 1);
+        for page in &result {
+            for item in &page.items {
+                if let PageItem::Text(span) = item {
+                    assert!(span.x >= setup.margin - 0.01);
+                    assert!(span.baseline <= setup.height - setup.margin + 0.01);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ordered_list_numbers_saturate_instead_of_overflowing() {
+        let item = ListItem {
+            content: vec![TextRun::new("item", "en", "paragraph")],
+            children: Vec::new(),
+        };
+        let result = pages(vec![Node::List {
+            ordered: true,
+            start: u32::MAX,
+            items: vec![item.clone(), item],
+            style: "paragraph".to_string(),
+        }]);
+
+        let markers: Vec<&str> = result[0]
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                PageItem::Text(span) if span.text.ends_with('.') => Some(span.text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(markers, ["4294967295.", "4294967295."]);
+    }
+}
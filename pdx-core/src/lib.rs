@@ -0,0 +1,50 @@
+//! The PDX document model and everything that works on it without a GUI:
+//! reading and writing `.pdx` files (upgrading older format versions) and
+//! `.pdxz` bundles, the editor markup parser, Markdown import and export,
+//! validation, ODT and DOCX import, and HTML, PDF, PNG, DOCX, ODT, EPUB and
+//! LaTeX export. The PDX editor and the `pdx` converter are both built on this
+//! crate.
+//!
+//! ```no_run
+//! use pdx_core::{PdxDocument, export_as_pdf, parse_content};
+//!
+//! let mut document = PdxDocument::from_json(&std::fs::read_to_string("doc.pdx")?)?;
+//! document.content = parse_content("# Title\n\nBody text");
+//! std::fs::write("doc.pdf", export_as_pdf(&document)?)?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+pub mod bundle;
+pub mod data;
+mod dates;
+pub mod docx;
+pub mod epub;
+pub mod error;
+pub mod export;
+pub mod latex;
+mod layout;
+pub mod markdown;
+pub mod migrate;
+pub mod odt;
+pub mod parser;
+pub mod pdx_text;
+#[cfg(test)]
+pub(crate) mod test_support;
+pub mod validate;
+mod xml;
+
+pub use data::{
+    Color, Direction, EdgeInsets, FontWeight, InlineMarks, ListItem, Metadata, Node, PdxDocument,
+    Resources, Style, StyleSheet, TableCell, TableRow, TextAlign, TextRun,
+};
+pub use docx::{export_as_docx, import_docx};
+pub use epub::export_as_epub;
+pub use error::PdxError;
+pub use export::{
+    HtmlOptions, PngLayout, PngOptions, export_as_html, export_as_pdf, export_as_png,
+};
+pub use latex::export_as_latex;
+pub use markdown::{export_as_markdown, import_markdown};
+pub use odt::{export_as_odt, import_odt};
+pub use parser::{parse_content, serialize_content};
+pub use validate::{CURRENT_VERSION, Issue, Severity, validate_document};
@@ -0,0 +1,1029 @@
+use crate::data::{
+    Color, Direction, EdgeInsets, InlineMarks, ListItem, Metadata, Node, PdxDocument, Resources,
+    Style, StyleSheet, TableCell, TableRow, TextAlign, TextRun,
+};
+use crate::error::PdxError;
+use crate::export::data_uri;
+use crate::parser::paragraph_style;
+use crate::pdx_text::detect_language;
+use crate::validate::CURRENT_VERSION;
+use pulldown_cmark::{Alignment, CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use yaml_rust2::{Yaml, YamlLoader};
+
+// ============================================================================
+// Markdown Import
+// ============================================================================
+
+/// Style given to paragraphs inside block quotes.
+const QUOTE_STYLE: &str = "blockquote";
+
+/// Raw HTML blocks that mark a page break, as written by the exporter.
+const PAGE_BREAK_HTML: &str = "<div style=\"page-break-after: always\"></div>";
+
+/// Reads CommonMark with the GitHub extensions (tables, strikethrough, task
+/// lists) into a document. YAML front matter fills in the metadata; without
+/// a `title` the first heading is used, then `fallback_title`. Raw HTML is
+/// dropped apart from `<u>`, `<br>` and page-break blocks.
+pub fn import_markdown(text: &str, fallback_title: &str) -> Result<PdxDocument, PdxError> {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_GFM
+        | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS;
+
+    let mut importer = Importer::default();
+    let mut front_matter = None;
+
+    for (event, range) in Parser::new_ext(text, options).into_offset_iter() {
+        if importer.in_front_matter
+            && let Event::Text(yaml) = &event
+        {
+            let first_line = text[..range.start].matches('\n').count() + 1;
+            front_matter = Some(read_front_matter(yaml, first_line)?);
+        }
+        importer.event(event);
+    }
+
+    let children = importer.blocks;
+    let (mut metadata, has_title, has_language) = front_matter.unwrap_or_default();
+
+    if !has_title {
+        metadata.title = children
+            .iter()
+            .find_map(|node| match node {
+                Node::Heading { runs, .. } => Some(runs.iter().map(|r| r.text.as_str()).collect()),
+                _ => None,
+            })
+            .unwrap_or_else(|| fallback_title.to_string());
+    }
+    if !has_language {
+        metadata.language = children
+            .iter()
+            .find_map(|node| match node {
+                Node::Heading { runs, .. } | Node::Paragraph { runs, .. } => runs.first(),
+                _ => None,
+            })
+            .map_or("en".to_string(), |run| run.language.clone());
+    }
+
+    let mut styles = StyleSheet::default();
+    if importer.used_quote_style {
+        styles
+            .styles
+            .entry(QUOTE_STYLE.to_string())
+            .or_insert_with(quote_style);
+    }
+
+    Ok(PdxDocument {
+        version: CURRENT_VERSION,
+        metadata,
+        styles,
+        content: Node::Document { children },
+        resources: Resources::default(),
+    })
+}
+
+fn quote_style() -> Style {
+    Style {
+        font_size: 16.0,
+        color: Color::rgb(90, 90, 90),
+        line_height: 1.8,
+        margin: EdgeInsets::new(0.0, 0.0, 10.0, 0.0),
+        padding: EdgeInsets::new(0.0, 0.0, 0.0, 16.0),
+        ..Default::default()
+    }
+}
+
+/// Metadata from the front matter, and whether it set the title and the
+/// language.
+fn read_front_matter(yaml: &str, first_line: usize) -> Result<(Metadata, bool, bool), PdxError> {
+    let documents = YamlLoader::load_from_str(yaml).map_err(|e| PdxError::Parse {
+        path: None,
+        line: first_line + e.marker().line() - 1,
+        column: e.marker().col() + 1,
+        message: format!("front matter: {}", e.info()),
+    })?;
+    let mut metadata = Metadata::default();
+    let Some(fields) = documents.first() else {
+        return Ok((metadata, false, false));
+    };
+
+    let first = |keys: &[&str]| {
+        keys.iter()
+            .map(|&key| &fields[key])
+            .find(|v| !v.is_badvalue())
+    };
+
+    let title = first(&["title"]).and_then(scalar);
+    let language = first(&["lang", "language"]).and_then(scalar);
+    let (has_title, has_language) = (title.is_some(), language.is_some());
+
+    if let Some(title) = title {
+        metadata.title = title;
+    }
+    if let Some(language) = language {
+        metadata.language = language;
+    }
+    if let Some(author) = first(&["author", "authors"]) {
+        metadata.author = list(author).join(", ");
+    }
+    if let Some(created) = first(&["date", "created"]).and_then(scalar) {
+        metadata.created = created;
+    }
+    if let Some(modified) = first(&["modified", "updated"]).and_then(scalar) {
+        metadata.modified = modified;
+    }
+    if let Some(keywords) = first(&["keywords", "tags"]) {
+        metadata.keywords = list(keywords);
+    }
+
+    Ok((metadata, has_title, has_language))
+}
+
+fn scalar(value: &Yaml) -> Option<String> {
+    match value {
+        Yaml::String(s) | Yaml::Real(s) => Some(s.clone()),
+        Yaml::Integer(n) => Some(n.to_string()),
+        Yaml::Boolean(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// A YAML sequence, or a comma-separated string.
+fn list(value: &Yaml) -> Vec<String> {
+    match value {
+        Yaml::Array(items) => items.iter().filter_map(scalar).collect(),
+        other => scalar(other)
+            .map(|s| {
+                s.split(',')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default(),
+    }
+}
+
+struct ListFrame {
+    ordered: bool,
+    start: u32,
+    items: Vec<ListItem>,
+}
+
+#[derive(Default)]
+struct TableFrame {
+    rows: Vec<TableRow>,
+    header_rows: usize,
+    alignments: Vec<TextAlign>,
+}
+
+/// Builds the node tree from parser events. Inline text collects in
+/// `inline` until the block holding it ends.
+#[derive(Default)]
+struct Importer {
+    blocks: Vec<Node>,
+    lists: Vec<ListFrame>,
+    table: Option<TableFrame>,
+    /// Language and text of the open code block
+    code: Option<(String, String)>,
+    /// Path and alt text of the open image
+    image: Option<(String, String)>,
+    /// HTML of the open raw HTML block
+    html: Option<String>,
+    inline: Vec<(String, InlineMarks)>,
+    marks: InlineMarks,
+    /// Images met in the current block, placed after it
+    images: Vec<Node>,
+    quote_depth: usize,
+    in_front_matter: bool,
+    used_quote_style: bool,
+}
+
+impl Importer {
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            // Read by `import_markdown` itself
+            Event::Text(_) if self.in_front_matter => {}
+            Event::Text(text) => {
+                if let Some((_, code)) = &mut self.code {
+                    code.push_str(&text);
+                } else if let Some((_, alt)) = &mut self.image {
+                    alt.push_str(&text);
+                } else {
+                    self.push_text(&text);
+                }
+            }
+            Event::Code(text) => {
+                let marks = InlineMarks {
+                    code: true,
+                    ..self.marks.clone()
+                };
+                self.inline.push((text.to_string(), marks));
+            }
+            Event::Html(html) => {
+                if let Some(block) = &mut self.html {
+                    block.push_str(&html);
+                }
+            }
+            Event::InlineHtml(html) => match html.trim().to_ascii_lowercase().as_str() {
+                "<u>" => self.marks.underline = true,
+                "</u>" => self.marks.underline = false,
+                "<br>" | "<br/>" | "<br />" => self.push_text("\n"),
+                _ => {}
+            },
+            Event::SoftBreak => self.push_text(" "),
+            Event::HardBreak => self.push_text("\n"),
+            Event::Rule => self.push_block(Node::Divider),
+            Event::TaskListMarker(checked) => self.push_text(if checked { "☑ " } else { "☐ " }),
+            _ => {}
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::MetadataBlock(_) => self.in_front_matter = true,
+            Tag::BlockQuote(_) => self.quote_depth += 1,
+            Tag::CodeBlock(kind) => {
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => {
+                        info.split_whitespace().next().unwrap_or("").to_string()
+                    }
+                    CodeBlockKind::Indented => String::new(),
+                };
+                let language = if language.is_empty() {
+                    "text".to_string()
+                } else {
+                    language
+                };
+                self.code = Some((language, String::new()));
+            }
+            Tag::HtmlBlock => self.html = Some(String::new()),
+            Tag::List(start) => {
+                // Text of a tight item comes before its sub-list
+                self.flush_item_text();
+                self.lists.push(ListFrame {
+                    ordered: start.is_some(),
+                    start: start.map_or(1, |n| u32::try_from(n).unwrap_or(u32::MAX)),
+                    items: Vec::new(),
+                });
+            }
+            Tag::Item => {
+                if let Some(list) = self.lists.last_mut() {
+                    list.items.push(ListItem {
+                        content: Vec::new(),
+                        children: Vec::new(),
+                    });
+                }
+            }
+            Tag::Table(alignments) => {
+                self.table = Some(TableFrame {
+                    alignments: alignments.into_iter().map(text_align).collect(),
+                    ..Default::default()
+                });
+            }
+            Tag::TableHead | Tag::TableRow => {
+                if let Some(table) = &mut self.table {
+                    table.rows.push(TableRow { cells: Vec::new() });
+                }
+            }
+            Tag::Emphasis => self.marks.emphasis = true,
+            Tag::Strong => self.marks.strong = true,
+            Tag::Strikethrough => self.marks.strikethrough = true,
+            Tag::Link { dest_url, .. } => self.marks.link = Some(dest_url.to_string()),
+            Tag::Image { dest_url, .. } => self.image = Some((dest_url.to_string(), String::new())),
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::MetadataBlock(_) => self.in_front_matter = false,
+            TagEnd::BlockQuote(_) => self.quote_depth = self.quote_depth.saturating_sub(1),
+            TagEnd::Paragraph => {
+                let style = if !self.lists.is_empty() {
+                    "paragraph"
+                } else if self.quote_depth > 0 {
+                    self.used_quote_style = true;
+                    QUOTE_STYLE
+                } else {
+                    paragraph_style(&self.inline_text())
+                };
+                if let Some(runs) = self.take_runs(style) {
+                    self.push_block(Node::Paragraph {
+                        runs,
+                        style: style.to_string(),
+                    });
+                }
+                self.flush_images();
+            }
+            TagEnd::Heading(level) => {
+                let level = heading_level(level);
+                let style = format!("heading{}", level);
+                let runs = self.take_runs(&style).unwrap_or_default();
+                self.push_block(Node::Heading { level, runs, style });
+                self.flush_images();
+            }
+            TagEnd::CodeBlock => {
+                if let Some((language, mut code)) = self.code.take() {
+                    if code.ends_with('\n') {
+                        code.pop();
+                    }
+                    self.push_block(Node::CodeBlock {
+                        language,
+                        code,
+                        style: "code".to_string(),
+                    });
+                }
+            }
+            TagEnd::HtmlBlock => {
+                if let Some(html) = self.html.take()
+                    && html.contains("page-break")
+                {
+                    self.push_block(Node::PageBreak);
+                }
+            }
+            TagEnd::Item => self.flush_item_text(),
+            TagEnd::List(_) => {
+                if let Some(list) = self.lists.pop() {
+                    self.push_block(Node::List {
+                        ordered: list.ordered,
+                        start: list.start,
+                        items: list.items,
+                        style: "list".to_string(),
+                    });
+                }
+            }
+            TagEnd::TableCell => {
+                let runs = self.take_runs("paragraph").unwrap_or_default();
+                if let Some(row) = self.table.as_mut().and_then(|t| t.rows.last_mut()) {
+                    row.cells.push(TableCell { content: runs });
+                }
+            }
+            TagEnd::TableHead => {
+                if let Some(table) = &mut self.table {
+                    table.header_rows = table.rows.len();
+                }
+            }
+            TagEnd::Table => {
+                if let Some(table) = self.table.take() {
+                    let is_rtl = table.rows.iter().flat_map(|r| &r.cells).any(|cell| {
+                        cell.content
+                            .iter()
+                            .any(|run| run.direction == Direction::RTL)
+                    });
+
+                    self.push_block(Node::Table {
+                        rows: table.rows,
+                        header_rows: table.header_rows,
+                        alignments: table.alignments,
+                        direction: if is_rtl {
+                            Direction::RTL
+                        } else {
+                            Direction::LTR
+                        },
+                        style: "table".to_string(),
+                    });
+                }
+                self.flush_images();
+            }
+            TagEnd::Emphasis => self.marks.emphasis = false,
+            TagEnd::Strong => self.marks.strong = false,
+            TagEnd::Strikethrough => self.marks.strikethrough = false,
+            TagEnd::Link => self.marks.link = None,
+            TagEnd::Image => {
+                if let Some((path, alt_text)) = self.image.take() {
+                    self.images.push(Node::Image {
+                        path,
+                        alt_text,
+                        width: None,
+                        height: None,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Adds text with the current marks, merging it into the previous piece
+    /// when the marks match.
+    fn push_text(&mut self, text: &str) {
+        if let Some((last, marks)) = self.inline.last_mut()
+            && *marks == self.marks
+        {
+            last.push_str(text);
+        } else {
+            self.inline.push((text.to_string(), self.marks.clone()));
+        }
+    }
+
+    fn inline_text(&self) -> String {
+        self.inline.iter().map(|(text, _)| text.as_str()).collect()
+    }
+
+    /// Turns the collected inline text into runs in the language detected
+    /// for the whole block. `None` when there is no visible text.
+    fn take_runs(&mut self, style: &str) -> Option<Vec<TextRun>> {
+        let pieces = std::mem::take(&mut self.inline);
+        let text: String = pieces.iter().map(|(text, _)| text.as_str()).collect();
+        if text.trim().is_empty() {
+            return None;
+        }
+
+        let language = detect_language(&text);
+        Some(
+            pieces
+                .into_iter()
+                .map(|(text, marks)| TextRun::new(&text, language, style).with_marks(marks))
+                .collect(),
+        )
+    }
+
+    /// Text of a tight list item, which comes without a paragraph around it.
+    fn flush_item_text(&mut self) {
+        if self.lists.is_empty() {
+            return;
+        }
+        if let Some(runs) = self.take_runs("paragraph") {
+            self.push_block(Node::Paragraph {
+                runs,
+                style: "paragraph".to_string(),
+            });
+        }
+        self.flush_images();
+    }
+
+    fn flush_images(&mut self) {
+        for image in std::mem::take(&mut self.images) {
+            self.push_block(image);
+        }
+    }
+
+    /// Adds a finished block to the open list item, or to the document. An
+    /// item's first paragraph becomes its content.
+    fn push_block(&mut self, node: Node) {
+        let Some(item) = self.lists.last_mut().and_then(|list| list.items.last_mut()) else {
+            self.blocks.push(node);
+            return;
+        };
+
+        match node {
+            Node::Paragraph { runs, .. } if item.content.is_empty() && item.children.is_empty() => {
+                item.content = runs;
+            }
+            other => item.children.push(other),
+        }
+    }
+}
+
+fn heading_level(level: HeadingLevel) -> u8 {
+    match level {
+        HeadingLevel::H1 => 1,
+        HeadingLevel::H2 => 2,
+        HeadingLevel::H3 => 3,
+        HeadingLevel::H4 => 4,
+        HeadingLevel::H5 => 5,
+        HeadingLevel::H6 => 6,
+    }
+}
+
+fn text_align(alignment: Alignment) -> TextAlign {
+    match alignment {
+        Alignment::None | Alignment::Left => TextAlign::Start,
+        Alignment::Center => TextAlign::Center,
+        Alignment::Right => TextAlign::End,
+    }
+}
+
+// ============================================================================
+// Markdown Export
+// ============================================================================
+
+/// Writes the document as GitHub-flavoured Markdown with the metadata as
+/// YAML front matter. Run languages, style names and image sizes have no
+/// Markdown form and are left out; paragraphs in the `blockquote` style
+/// become block quotes. Images embedded in the document are written as
+/// `data:` URIs, as the Markdown file can't carry them.
+pub fn export_as_markdown(document: &PdxDocument) -> String {
+    let mut out = front_matter(&document.metadata);
+
+    let mut content = document.content.clone();
+    content.for_each_image_path(&mut |path| {
+        if document.resources.files.contains_key(path.as_str())
+            && let Some(uri) = data_uri(&document.resources, path)
+        {
+            *path = uri;
+        }
+    });
+
+    let blocks = match &content {
+        Node::Document { children } => children.iter().map(block_to_markdown).collect(),
+        other => vec![block_to_markdown(other)],
+    };
+    out.push_str(&blocks.join("\n\n"));
+    out.push('\n');
+    out
+}
+
+fn front_matter(metadata: &Metadata) -> String {
+    // JSON strings are valid double-quoted YAML scalars
+    let quote = |s: &str| serde_json::Value::from(s).to_string();
+    let mut out = String::from("---\n");
+
+    out.push_str(&format!("title: {}\n", quote(&metadata.title)));
+    if !metadata.author.is_empty() {
+        out.push_str(&format!("author: {}\n", quote(&metadata.author)));
+    }
+    out.push_str(&format!("lang: {}\n", quote(&metadata.language)));
+    out.push_str(&format!("date: {}\n", quote(&metadata.created)));
+    out.push_str(&format!("modified: {}\n", quote(&metadata.modified)));
+    if !metadata.keywords.is_empty() {
+        let keywords: Vec<String> = metadata.keywords.iter().map(|k| quote(k)).collect();
+        out.push_str(&format!("keywords: [{}]\n", keywords.join(", ")));
+    }
+
+    out.push_str("---\n\n");
+    out
+}
+
+fn block_to_markdown(node: &Node) -> String {
+    match node {
+        Node::Document { children } => children
+            .iter()
+            .map(block_to_markdown)
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+
+        Node::Heading { level, runs, .. } => {
+            // A heading is one line, so breaks inside it become spaces
+            let text = runs_to_markdown(runs).replace("\\\n", " ");
+            format!("{} {}", "#".repeat((*level).clamp(1, 6) as usize), text)
+        }
+
+        Node::Paragraph { runs, style } => {
+            let text = escape_line_starts(&runs_to_markdown(runs));
+            if style == QUOTE_STYLE {
+                text.lines()
+                    .map(|line| format!("> {}", line))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            } else {
+                text
+            }
+        }
+
+        Node::List {
+            ordered,
+            start,
+            items,
+            ..
+        } => {
+            // Blocks other than sub-lists need blank lines around them
+            let loose = items
+                .iter()
+                .flat_map(|item| &item.children)
+                .any(|child| !matches!(child, Node::List { .. }));
+
+            items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| {
+                    let marker = if *ordered {
+                        format!("{}. ", *start as usize + i)
+                    } else {
+                        "- ".to_string()
+                    };
+                    let mut parts = vec![escape_line_starts(&runs_to_markdown(&item.content))];
+                    parts.extend(item.children.iter().map(block_to_markdown));
+
+                    let body = parts.join(if loose { "\n\n" } else { "\n" });
+                    format!("{}{}", marker, indent(&body, marker.len()))
+                })
+                .collect::<Vec<_>>()
+                .join(if loose { "\n\n" } else { "\n" })
+        }
+
+        Node::CodeBlock { language, code, .. } => {
+            let longest = longest_backtick_run(code);
+            let fence = "`".repeat(longest.max(2) + 1);
+            let info = if language == "text" { "" } else { language };
+            format!("{}{}\n{}\n{}", fence, info, code, fence)
+        }
+
+        Node::Image { path, alt_text, .. } => {
+            format!("![{}]({})", escape_text(alt_text), link_destination(path))
+        }
+
+        Node::Table {
+            rows,
+            header_rows,
+            alignments,
+            ..
+        } => {
+            let columns = rows.iter().map(|row| row.cells.len()).max().unwrap_or(0);
+            let row_line = |cells: Vec<String>| format!("| {} |", cells.join(" | "));
+            let cells_of = |row: &TableRow| {
+                (0..columns)
+                    .map(|i| {
+                        row.cells
+                            .get(i)
+                            .map_or(String::new(), |cell| cell_to_markdown(&cell.content))
+                    })
+                    .collect::<Vec<_>>()
+            };
+
+            // GFM tables always have one header row
+            let mut lines = Vec::new();
+            let body = if *header_rows > 0 && !rows.is_empty() {
+                lines.push(row_line(cells_of(&rows[0])));
+                &rows[1..]
+            } else {
+                lines.push(row_line(vec![String::new(); columns]));
+                &rows[..]
+            };
+
+            let delimiters = (0..columns)
+                .map(|i| match alignments.get(i) {
+                    Some(TextAlign::Center) => ":---:",
+                    Some(TextAlign::End) => "---:",
+                    _ => "---",
+                })
+                .map(str::to_string)
+                .collect();
+            lines.push(row_line(delimiters));
+            lines.extend(body.iter().map(|row| row_line(cells_of(row))));
+
+            lines.join("\n")
+        }
+
+        Node::Divider => "---".to_string(),
+        Node::PageBreak => PAGE_BREAK_HTML.to_string(),
+    }
+}
+
+/// Inline Markdown for a run sequence; neighbouring runs with the same link
+/// share one `[...](...)`.
+fn runs_to_markdown(runs: &[TextRun]) -> String {
+    let mut out = String::new();
+    let mut i = 0;
+
+    while i < runs.len() {
+        match &runs[i].marks.link {
+            Some(url) => {
+                let end = runs[i..]
+                    .iter()
+                    .position(|r| r.marks.link.as_ref() != Some(url))
+                    .map_or(runs.len(), |offset| i + offset);
+
+                out.push('[');
+                for run in &runs[i..end] {
+                    out.push_str(&run_to_markdown(run));
+                }
+                out.push_str(&format!("]({})", link_destination(url)));
+                i = end;
+            }
+            None => {
+                out.push_str(&run_to_markdown(&runs[i]));
+                i += 1;
+            }
+        }
+    }
+
+    out
+}
+
+fn run_to_markdown(run: &TextRun) -> String {
+    let marks = &run.marks;
+
+    // Delimiters must touch the text, so surrounding spaces stay outside
+    let inner = run.text.trim();
+    if inner.is_empty() {
+        return escape_text(&run.text);
+    }
+    let leading = &run.text[..run.text.len() - run.text.trim_start().len()];
+    let trailing = &run.text[run.text.trim_end().len()..];
+
+    let mut open = String::new();
+    if marks.strong {
+        open.push_str("**");
+    }
+    if marks.emphasis {
+        open.push('*');
+    }
+    if marks.strikethrough {
+        open.push_str("~~");
+    }
+    if marks.underline {
+        open.push_str("<u>");
+    }
+
+    let mut close = String::new();
+    if marks.underline {
+        close.push_str("</u>");
+    }
+    if marks.strikethrough {
+        close.push_str("~~");
+    }
+    if marks.emphasis {
+        close.push('*');
+    }
+    if marks.strong {
+        close.push_str("**");
+    }
+
+    let body = if marks.code {
+        code_span(inner)
+    } else {
+        escape_text(inner)
+    };
+
+    format!(
+        "{}{}{}{}{}",
+        escape_text(leading),
+        open,
+        body,
+        close,
+        escape_text(trailing)
+    )
+}
+
+fn code_span(text: &str) -> String {
+    let ticks = "`".repeat(longest_backtick_run(text) + 1);
+    // A space keeps a leading or trailing backtick apart from the fence
+    let pad = if text.starts_with('`') || text.ends_with('`') {
+        " "
+    } else {
+        ""
+    };
+    format!("{}{}{}{}{}", ticks, pad, text, pad, ticks)
+}
+
+fn longest_backtick_run(text: &str) -> usize {
+    text.split(|c| c != '`').map(str::len).max().unwrap_or(0)
+}
+
+/// Table cells are one line and must not contain an unescaped `|`, not
+/// even in a code span.
+fn cell_to_markdown(runs: &[TextRun]) -> String {
+    runs_to_markdown(runs)
+        .replace("\\\n", "<br>")
+        .replace("\\|", "|")
+        .replace('|', "\\|")
+}
+
+/// Backslash-escapes characters that would start inline markup; line
+/// breaks become hard breaks.
+fn escape_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let next = chars.peek().copied();
+        match c {
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '~' | '|' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '&' if next.is_some_and(|n| n.is_ascii_alphanumeric() || n == '#') => {
+                out.push_str("\\&");
+            }
+            '\n' => out.push_str("\\\n"),
+            '\r' => {}
+            _ => out.push(c),
+        }
+    }
+
+    out
+}
+
+/// Escapes what would turn the start of a line into a heading, list item,
+/// quote or rule.
+fn escape_line_starts(text: &str) -> String {
+    text.split('\n')
+        .map(|line| {
+            let digits = line.chars().take_while(char::is_ascii_digit).count();
+            if line.starts_with(['#', '-', '+', '=']) {
+                format!("\\{}", line)
+            } else if digits > 0 && line[digits..].starts_with(['.', ')']) {
+                format!("{}\\{}", &line[..digits], &line[digits..])
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn link_destination(url: &str) -> String {
+    if url.contains([' ', '(', ')', '<', '>']) {
+        format!("<{}>", url.replace('<', "%3C").replace('>', "%3E"))
+    } else {
+        url.to_string()
+    }
+}
+
+/// Indents every line after the first, for blocks nested in a list item.
+fn indent(text: &str, width: usize) -> String {
+    let pad = " ".repeat(width);
+    text.split('\n')
+        .enumerate()
+        .map(|(i, line)| {
+            if i == 0 || line.is_empty() {
+                line.to_string()
+            } else {
+                format!("{}{}", pad, line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn import(text: &str) -> PdxDocument {
+        import_markdown(text, "Untitled").expect("valid Markdown")
+    }
+
+    fn blocks(text: &str) -> Vec<Node> {
+        match import(text).content {
+            Node::Document { children } => children,
+            other => vec![other],
+        }
+    }
+
+    fn text_of(runs: &[TextRun]) -> String {
+        runs.iter().map(|run| run.text.as_str()).collect()
+    }
+
+    #[test]
+    fn blocks_map_to_nodes() {
+        let nodes = blocks(
+            "# Title\n\nSome *text*.\n\n> Quoted\n\n```rust\nfn main() {}\n```\n\n---\n\n\
+             ![A cat](cat.png)\n\n<div style=\"page-break-after: always\"></div>\n",
+        );
+
+        assert!(
+            matches!(&nodes[0], Node::Heading { level: 1, runs, .. } if text_of(runs) == "Title")
+        );
+        assert!(matches!(&nodes[1], Node::Paragraph { runs, .. } if text_of(runs) == "Some text."));
+        assert!(matches!(&nodes[2], Node::Paragraph { style, .. } if style == QUOTE_STYLE));
+        assert_eq!(
+            nodes[3],
+            Node::CodeBlock {
+                language: "rust".to_string(),
+                code: "fn main() {}".to_string(),
+                style: "code".to_string(),
+            }
+        );
+        assert_eq!(nodes[4], Node::Divider);
+        assert!(
+            matches!(&nodes[5], Node::Image { path, alt_text, .. } if path == "cat.png" && alt_text == "A cat")
+        );
+        assert_eq!(nodes[6], Node::PageBreak);
+    }
+
+    #[test]
+    fn inline_markup_becomes_marks() {
+        let nodes = blocks("**bold** *it* `code` ~~gone~~ <u>under</u> [link](https://x.org)");
+        let Node::Paragraph { runs, .. } = &nodes[0] else {
+            panic!("expected a paragraph, got {:?}", nodes[0]);
+        };
+
+        let marked = |text: &str| &runs.iter().find(|run| run.text == text).unwrap().marks;
+        assert!(marked("bold").strong);
+        assert!(marked("it").emphasis);
+        assert!(marked("code").code);
+        assert!(marked("gone").strikethrough);
+        assert!(marked("under").underline);
+        assert_eq!(marked("link").link.as_deref(), Some("https://x.org"));
+    }
+
+    #[test]
+    fn lists_nest_and_keep_their_start() {
+        let nodes = blocks("3. three\n4. four\n   - inner\n");
+        let Node::List {
+            ordered,
+            start,
+            items,
+            ..
+        } = &nodes[0]
+        else {
+            panic!("expected a list, got {:?}", nodes[0]);
+        };
+
+        assert!(*ordered);
+        assert_eq!(*start, 3);
+        assert_eq!(text_of(&items[0].content), "three");
+        assert_eq!(text_of(&items[1].content), "four");
+        assert!(
+            matches!(&items[1].children[..], [Node::List { ordered: false, items, .. }] if text_of(&items[0].content) == "inner")
+        );
+    }
+
+    #[test]
+    fn tables_keep_the_header_and_alignment() {
+        let nodes = blocks("| a | b |\n|:-:|--:|\n| 1 | 2 |\n");
+        let Node::Table {
+            rows,
+            header_rows,
+            alignments,
+            ..
+        } = &nodes[0]
+        else {
+            panic!("expected a table, got {:?}", nodes[0]);
+        };
+
+        assert_eq!(*header_rows, 1);
+        assert_eq!(alignments, &[TextAlign::Center, TextAlign::End]);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(text_of(&rows[1].cells[1].content), "2");
+    }
+
+    #[test]
+    fn front_matter_fills_the_metadata() {
+        let document = import(
+            "---\ntitle: Notes\nauthors: [Ann, Bob]\nlang: ar\ntags: a, b\ndate: 2024-01-02\n---\n\n# Heading\n",
+        );
+
+        assert_eq!(document.metadata.title, "Notes");
+        assert_eq!(document.metadata.author, "Ann, Bob");
+        assert_eq!(document.metadata.language, "ar");
+        assert_eq!(document.metadata.keywords, ["a", "b"]);
+        assert_eq!(document.metadata.created, "2024-01-02");
+    }
+
+    #[test]
+    fn title_and_language_fall_back_to_the_content() {
+        let document = import("# مرحبا بالعالم\n\nنص\n");
+
+        assert_eq!(document.metadata.title, "مرحبا بالعالم");
+        assert_eq!(document.metadata.language, "ar");
+        assert_eq!(import("plain text").metadata.title, "Untitled");
+    }
+
+    #[test]
+    fn bad_front_matter_reports_its_line() {
+        let error = import_markdown("---\ntitle: [unclosed\n---\n", "x").unwrap_err();
+
+        assert!(
+            matches!(error, PdxError::Parse { line: 2.., .. }),
+            "{error:?}"
+        );
+    }
+
+    #[test]
+    fn exported_markdown_imports_back_to_the_same_content() {
+        let source = "---\ntitle: Notes\n---\n\n# Title\n\n**Bold** and *it* with `a|b`.\n\n\
+                      > Quoted\n\n1. one\n2. two\n   - inner\n\n| a | b |\n|---|:-:|\n| 1 | 2 |\n\n\
+                      ````\n```\n````\n\n![cat](my cat.png)\n";
+        let document = import(source);
+        let again = import(&export_as_markdown(&document));
+
+        assert_eq!(again.content, document.content);
+        assert_eq!(again.metadata, document.metadata);
+    }
+
+    #[test]
+    fn text_that_looks_like_markup_is_escaped() {
+        let run = TextRun::new("# not *a* heading\n1. nor a list", "en", "paragraph");
+        let document = PdxDocument {
+            content: Node::Document {
+                children: vec![Node::Paragraph {
+                    runs: vec![run],
+                    style: "paragraph".to_string(),
+                }],
+            },
+            ..import("")
+        };
+
+        let nodes = blocks(&export_as_markdown(&document));
+        assert!(
+            matches!(&nodes[..], [Node::Paragraph { runs, .. }] if text_of(runs) == "# not *a* heading\n1. nor a list")
+        );
+    }
+
+    #[test]
+    fn embedded_images_are_written_as_data_uris() {
+        let image = |path: &str| Node::Image {
+            path: path.to_string(),
+            alt_text: "cat".to_string(),
+            width: None,
+            height: None,
+        };
+        let mut document =
+            crate::test_support::document(vec![image("resources/a.png"), image("cat.png")]);
+        document
+            .resources
+            .files
+            .insert("resources/a.png".to_string(), crate::test_support::png());
+
+        let markdown = export_as_markdown(&document);
+        assert!(markdown.contains("![cat](data:image/png;base64,"));
+        assert!(!markdown.contains("resources/a.png"));
+        assert!(markdown.contains("![cat](cat.png)"));
+    }
+}
@@ -0,0 +1,148 @@
+use crate::data::{Metadata, PdxDocument, Resources, StyleSheet};
+use crate::error::PdxError;
+use crate::parser::parse_content;
+use crate::validate::CURRENT_VERSION;
+use serde::Deserialize;
+use serde_json::Value;
+
+// ============================================================================
+// Format Migration
+// ============================================================================
+
+/// Upgrade steps, one per format version: `STEPS[n]` turns a version `n`
+/// file into version `n + 1`. Raising `CURRENT_VERSION` without adding a
+/// step does not compile.
+const STEPS: [fn(Value) -> Result<Value, PdxError>; CURRENT_VERSION as usize] = [legacy_to_v1];
+
+/// Reads `.pdx` JSON of any supported version, upgrading it to the current
+/// one. Also returns the version the file was written in.
+pub fn load(text: &str) -> Result<(PdxDocument, u32), PdxError> {
+    let mut value: Value = serde_json::from_str(text)?;
+    let found = format_version(&value);
+
+    if found > CURRENT_VERSION {
+        return Err(PdxError::UnsupportedVersion { path: None, found });
+    }
+
+    let mut document: PdxDocument = if found == CURRENT_VERSION {
+        // Straight from the text, so errors keep their line and column
+        serde_json::from_str(text)?
+    } else {
+        for step in &STEPS[found as usize..] {
+            value = step(value)?;
+        }
+        serde_json::from_value(value)?
+    };
+
+    document.version = CURRENT_VERSION;
+    Ok((document, found))
+}
+
+/// The version a file claims. The original `PdxFile` shape, with `meta` and
+/// the markup as a plain `content` string, counts as version 0 whatever its
+/// own `version` field says.
+fn format_version(value: &Value) -> u32 {
+    if value.get("meta").is_some() && value.get("content").is_some_and(Value::is_string) {
+        return 0;
+    }
+
+    // A missing or malformed version is left for deserialization to report
+    value
+        .get("version")
+        .and_then(Value::as_u64)
+        .map_or(CURRENT_VERSION, |v| u32::try_from(v).unwrap_or(u32::MAX))
+}
+
+// ============================================================================
+// Upgrade Steps
+// ============================================================================
+
+#[derive(Deserialize)]
+struct LegacyFile {
+    #[serde(default)]
+    meta: LegacyMeta,
+    #[serde(default)]
+    content: String,
+}
+
+#[derive(Deserialize, Default)]
+struct LegacyMeta {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    lang: String,
+}
+
+/// `PdxFile { version, meta: { title, lang }, content }` to the document
+/// model: the markup is parsed and the default style sheet added.
+fn legacy_to_v1(value: Value) -> Result<Value, PdxError> {
+    let legacy: LegacyFile = serde_json::from_value(value)?;
+    let defaults = Metadata::default();
+
+    let document = PdxDocument {
+        version: 1,
+        metadata: Metadata {
+            title: if legacy.meta.title.is_empty() {
+                defaults.title.clone()
+            } else {
+                legacy.meta.title
+            },
+            language: if legacy.meta.lang.is_empty() {
+                defaults.language.clone()
+            } else {
+                legacy.meta.lang
+            },
+            ..defaults
+        },
+        styles: StyleSheet::default(),
+        content: parse_content(&legacy.content),
+        resources: Resources::default(),
+    };
+
+    serde_json::to_value(&document).map_err(|e| PdxError::Serialize(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_files_are_upgraded() {
+        let text = r#"{
+            "version": 1,
+            "meta": { "title": "Notes", "lang": "ar" },
+            "content": "مرحبا بالعالم\n\nHello"
+        }"#;
+
+        let (document, found) = load(text).unwrap();
+        assert_eq!(found, 0);
+        assert_eq!(document.version, CURRENT_VERSION);
+        assert_eq!(document.metadata.title, "Notes");
+        assert_eq!(document.metadata.language, "ar");
+        assert_eq!(document.styles, StyleSheet::default());
+        assert_eq!(document.content, parse_content("مرحبا بالعالم\n\nHello"));
+    }
+
+    #[test]
+    fn newer_versions_are_refused() {
+        let text = format!(r#"{{ "version": {} }}"#, CURRENT_VERSION + 1);
+
+        assert!(matches!(
+            load(&text),
+            Err(PdxError::UnsupportedVersion { path: None, found }) if found == CURRENT_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn errors_in_current_files_keep_their_position() {
+        let text = format!(
+            "{{\n  \"version\": {},\n  \"metadata\": {{ \"title\": 5 }}\n}}",
+            CURRENT_VERSION
+        );
+
+        let Err(PdxError::Parse { line, column, .. }) = load(&text) else {
+            panic!("expected a parse error");
+        };
+        assert_eq!((line, column), (3, 26));
+    }
+}
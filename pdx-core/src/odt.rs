@@ -0,0 +1,2037 @@
+use crate::bundle::{read_entry, resource_name};
+use crate::data::{
+    Color, Direction, FontWeight, InlineMarks, ListItem, Metadata, Node, PdxDocument, Resources,
+    Style, StyleSheet, TableCell, TableRow, TextAlign, TextRun,
+};
+use crate::dates::w3c_date;
+use crate::error::PdxError;
+use crate::layout::PX_TO_PT;
+use crate::pdx_text::{is_rtl_block, is_rtl_language, tagged_language};
+use crate::validate::CURRENT_VERSION;
+use crate::xml::{self, escape, Content, Element};
+use ::image::ImageFormat;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::io::{Cursor, Write};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+// ============================================================================
+// ODT Export
+// ============================================================================
+
+const MIMETYPE: &str = "application/vnd.oasis.opendocument.text";
+const ODF_VERSION: &str = "1.3";
+
+const NAMESPACES: &str = "xmlns:office=\"urn:oasis:names:tc:opendocument:xmlns:office:1.0\" \
+     xmlns:style=\"urn:oasis:names:tc:opendocument:xmlns:style:1.0\" \
+     xmlns:text=\"urn:oasis:names:tc:opendocument:xmlns:text:1.0\" \
+     xmlns:table=\"urn:oasis:names:tc:opendocument:xmlns:table:1.0\" \
+     xmlns:draw=\"urn:oasis:names:tc:opendocument:xmlns:drawing:1.0\" \
+     xmlns:fo=\"urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0\" \
+     xmlns:svg=\"urn:oasis:names:tc:opendocument:xmlns:svg-compatible:1.0\" \
+     xmlns:xlink=\"http://www.w3.org/1999/xlink\" \
+     xmlns:dc=\"http://purl.org/dc/elements/1.1/\" \
+     xmlns:meta=\"urn:oasis:names:tc:opendocument:xmlns:meta:1.0\"";
+
+/// A4 with the 20 mm margins of the PDF export, in CSS pixels.
+const CONTENT_WIDTH: f32 = 170.0 / 25.4 * 96.0;
+const CONTENT_HEIGHT: f32 = 257.0 / 25.4 * 96.0;
+
+const BODY_FONT: &str = "Noto Sans";
+const COMPLEX_FONT: &str = "Noto Sans Arabic";
+const CODE_FONT: &str = "Liberation Mono";
+
+/// Writes the document as an OpenDocument Text (`.odt`) file. Style sheet
+/// entries become common paragraph styles; direction, alignment, page
+/// breaks and inline formatting go into automatic styles, with RTL blocks
+/// set to the `rl-tb` writing mode and each run tagged with its language.
+pub fn export_as_odt(document: &PdxDocument) -> Result<Vec<u8>, PdxError> {
+    let mut writer = OdtWriter::new(document);
+    let mut body = writer.blocks(&document.content);
+    if writer.pending_break {
+        // A trailing break still needs a paragraph to start the new page
+        let style = writer.paragraph_style("paragraph", false, None, false);
+        body.push_str(&format!("<text:p text:style-name=\"{}\"/>", style));
+    }
+    let content_xml = writer.content_xml(&body);
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+
+    // Readers identify the format by this first, uncompressed entry
+    zip.start_file("mimetype", stored).map_err(odt_error)?;
+    zip.write_all(MIMETYPE.as_bytes()).map_err(odt_error)?;
+
+    let parts = [
+        ("content.xml", content_xml),
+        ("styles.xml", writer.styles_xml()),
+        ("meta.xml", meta_xml(document)),
+        ("META-INF/manifest.xml", writer.manifest_xml()),
+    ];
+    for (name, xml) in parts {
+        zip.start_file(name, deflated).map_err(odt_error)?;
+        zip.write_all(xml.as_bytes()).map_err(odt_error)?;
+    }
+    // Images are compressed already
+    for image in &writer.images {
+        zip.start_file(image.href.as_str(), stored)
+            .map_err(odt_error)?;
+        zip.write_all(&image.bytes).map_err(odt_error)?;
+    }
+
+    Ok(zip.finish().map_err(odt_error)?.into_inner())
+}
+
+fn odt_error(e: impl std::fmt::Display) -> PdxError {
+    PdxError::Export {
+        format: "ODT",
+        message: e.to_string(),
+    }
+}
+
+struct Image {
+    /// Path in the package, e.g. `Pictures/image1.png`
+    href: String,
+    media_type: &'static str,
+    bytes: Vec<u8>,
+}
+
+/// What an automatic paragraph style adds to its common style.
+#[derive(Clone, PartialEq)]
+struct ParagraphKey {
+    parent: String,
+    rtl: bool,
+    align: Option<TextAlign>,
+    break_before: bool,
+    /// A bottom border, for dividers
+    rule: bool,
+}
+
+/// What an automatic text style sets on a span.
+#[derive(Clone, PartialEq)]
+struct TextKey {
+    strong: bool,
+    emphasis: bool,
+    code: bool,
+    strikethrough: bool,
+    underline: bool,
+    language: String,
+}
+
+/// One list style per exported list, so every list numbers on its own.
+struct ListStyle {
+    ordered: bool,
+    start: u32,
+    /// `style:num-format` of ordered lists
+    format: &'static str,
+}
+
+/// Collects the automatic styles and images the body refers to while
+/// writing it.
+struct OdtWriter<'a> {
+    document: &'a PdxDocument,
+    paragraph_styles: Vec<ParagraphKey>,
+    text_styles: Vec<TextKey>,
+    list_styles: Vec<ListStyle>,
+    /// Whether each table reads right to left and starts a page
+    table_styles: Vec<(bool, bool)>,
+    images: Vec<Image>,
+    /// Image hrefs by the content path they were read from
+    image_hrefs: HashMap<String, String>,
+    /// Style names used by the content, written even when not in the sheet
+    used_styles: BTreeSet<String>,
+    /// A page break waiting for the next paragraph or table
+    pending_break: bool,
+    next_frame: usize,
+}
+
+impl<'a> OdtWriter<'a> {
+    fn new(document: &'a PdxDocument) -> Self {
+        Self {
+            document,
+            paragraph_styles: Vec::new(),
+            text_styles: Vec::new(),
+            list_styles: Vec::new(),
+            table_styles: Vec::new(),
+            images: Vec::new(),
+            image_hrefs: HashMap::new(),
+            used_styles: BTreeSet::new(),
+            pending_break: false,
+            next_frame: 1,
+        }
+    }
+
+    fn styles(&self) -> &StyleSheet {
+        &self.document.styles
+    }
+
+    /// Name of the automatic paragraph style for a block, taking up a
+    /// pending page break.
+    fn paragraph_style(
+        &mut self,
+        parent: &str,
+        rtl: bool,
+        align: Option<TextAlign>,
+        rule: bool,
+    ) -> String {
+        self.used_styles.insert(parent.to_string());
+        let key = ParagraphKey {
+            parent: parent.to_string(),
+            rtl,
+            align,
+            break_before: std::mem::take(&mut self.pending_break),
+            rule,
+        };
+
+        let index = match self.paragraph_styles.iter().position(|k| *k == key) {
+            Some(index) => index,
+            None => {
+                self.paragraph_styles.push(key);
+                self.paragraph_styles.len() - 1
+            }
+        };
+        format!("P{}", index + 1)
+    }
+
+    fn blocks(&mut self, node: &Node) -> String {
+        match node {
+            Node::Document { children } => {
+                children.iter().map(|child| self.blocks(child)).collect()
+            }
+            Node::Heading { level, runs, style } => {
+                let rtl = is_rtl_block(runs, self.styles().resolve(style).direction);
+                let name = self.paragraph_style(style, rtl, None, false);
+                format!(
+                    "<text:h text:style-name=\"{}\" text:outline-level=\"{}\">{}</text:h>",
+                    name,
+                    (*level).clamp(1, 10),
+                    self.runs(runs)
+                )
+            }
+            Node::Paragraph { runs, style } => {
+                let rtl = is_rtl_block(runs, self.styles().resolve(style).direction);
+                let name = self.paragraph_style(style, rtl, None, false);
+                format!(
+                    "<text:p text:style-name=\"{}\">{}</text:p>",
+                    name,
+                    self.runs(runs)
+                )
+            }
+            Node::List {
+                ordered,
+                start,
+                items,
+                style,
+            } => self.list(*ordered, *start, items, style),
+            Node::CodeBlock { code, style, .. } => {
+                let name = self.paragraph_style(style, false, None, false);
+                format!(
+                    "<text:p text:style-name=\"{}\">{}</text:p>",
+                    name,
+                    text_xml(code, &mut true)
+                )
+            }
+            Node::Image {
+                path,
+                alt_text,
+                width,
+                height,
+            } => {
+                let rtl = is_rtl_language(&self.document.metadata.language);
+                let frame = self.image(path, alt_text, *width, *height);
+                let name = self.paragraph_style("paragraph", rtl, Some(TextAlign::Center), false);
+                match frame {
+                    Some(frame) => {
+                        format!("<text:p text:style-name=\"{}\">{}</text:p>", name, frame)
+                    }
+                    // Unreadable images keep their alt text in the flow
+                    None => format!(
+                        "<text:p text:style-name=\"{}\">[{}]</text:p>",
+                        name,
+                        escape(alt_text)
+                    ),
+                }
+            }
+            Node::Table {
+                rows,
+                header_rows,
+                alignments,
+                direction,
+                style,
+            } => self.table(rows, *header_rows, alignments, *direction, style),
+            Node::Divider => {
+                let name = self.paragraph_style("paragraph", false, None, true);
+                format!("<text:p text:style-name=\"{}\"/>", name)
+            }
+            Node::PageBreak => {
+                self.pending_break = true;
+                String::new()
+            }
+        }
+    }
+
+    fn list(&mut self, ordered: bool, start: u32, items: &[ListItem], style: &str) -> String {
+        let format = match items.first().map(|item| item.language()) {
+            Some("ar" | "ckb") => "١, ٢, ٣",
+            Some("fa" | "ur" | "ps" | "sd") => "۱, ۲, ۳",
+            _ => "1",
+        };
+        self.list_styles.push(ListStyle {
+            ordered,
+            start,
+            format,
+        });
+        let direction = self.styles().resolve(style).direction;
+
+        let mut xml = format!(
+            "<text:list text:style-name=\"L{}\">",
+            self.list_styles.len()
+        );
+        for item in items {
+            let rtl = is_rtl_block(&item.content, direction);
+            let name = self.paragraph_style(style, rtl, None, false);
+            xml.push_str(&format!(
+                "<text:list-item><text:p text:style-name=\"{}\">{}</text:p>",
+                name,
+                self.runs(&item.content)
+            ));
+            for child in &item.children {
+                xml.push_str(&self.blocks(child));
+            }
+            xml.push_str("</text:list-item>");
+        }
+        xml.push_str("</text:list>");
+        xml
+    }
+
+    fn table(
+        &mut self,
+        rows: &[TableRow],
+        header_rows: usize,
+        alignments: &[TextAlign],
+        direction: Direction,
+        style: &str,
+    ) -> String {
+        let columns = rows.iter().map(|row| row.cells.len()).max().unwrap_or(0);
+        if columns == 0 {
+            return String::new();
+        }
+        let rtl = direction == Direction::RTL;
+        self.table_styles
+            .push((rtl, std::mem::take(&mut self.pending_break)));
+        let number = self.table_styles.len();
+
+        let mut xml = format!(
+            "<table:table table:name=\"Table{0}\" table:style-name=\"Table{0}\">\
+             <table:table-column table:number-columns-repeated=\"{1}\"/>",
+            number, columns
+        );
+        let header_rows = header_rows.min(rows.len());
+        for (r, row) in rows.iter().enumerate() {
+            if r == 0 && header_rows > 0 {
+                xml.push_str("<table:table-header-rows>");
+            }
+            xml.push_str("<table:table-row>");
+            for c in 0..columns {
+                let runs = row.cells.get(c).map_or(&[][..], |cell| &cell.content);
+                let cell_rtl = is_rtl_block(runs, direction);
+                let align = alignments.get(c).copied().unwrap_or(TextAlign::Start);
+                let name = self.paragraph_style(style, cell_rtl, Some(align), false);
+
+                // Header rows are set bold, as the other exporters draw them
+                let content = if r < header_rows {
+                    let bold: Vec<TextRun> = runs
+                        .iter()
+                        .map(|run| {
+                            let mut run = run.clone();
+                            run.marks.strong = true;
+                            run
+                        })
+                        .collect();
+                    self.runs(&bold)
+                } else {
+                    self.runs(runs)
+                };
+                xml.push_str(&format!(
+                    "<table:table-cell office:value-type=\"string\">\
+                     <text:p text:style-name=\"{}\">{}</text:p></table:table-cell>",
+                    name, content
+                ));
+            }
+            xml.push_str("</table:table-row>");
+            if r + 1 == header_rows {
+                xml.push_str("</table:table-header-rows>");
+            }
+        }
+        xml.push_str("</table:table>");
+        xml
+    }
+
+    fn runs(&mut self, runs: &[TextRun]) -> String {
+        let mut xml = String::new();
+        let mut line_start = true;
+
+        for run in runs {
+            let marks = &run.marks;
+            let key = TextKey {
+                strong: marks.strong,
+                emphasis: marks.emphasis,
+                code: marks.code,
+                strikethrough: marks.strikethrough,
+                underline: marks.underline,
+                language: run.language.clone(),
+            };
+            let index = match self.text_styles.iter().position(|k| *k == key) {
+                Some(index) => index,
+                None => {
+                    self.text_styles.push(key);
+                    self.text_styles.len() - 1
+                }
+            };
+
+            let mut span = format!(
+                "<text:span text:style-name=\"T{}\">{}</text:span>",
+                index + 1,
+                text_xml(&run.text, &mut line_start)
+            );
+            if let Some(link) = &marks.link {
+                span = format!(
+                    "<text:a xlink:type=\"simple\" xlink:href=\"{}\">{}</text:a>",
+                    escape(link),
+                    span
+                );
+            }
+            xml.push_str(&span);
+        }
+
+        xml
+    }
+
+    /// An as-character frame holding the image, or `None` when it cannot
+    /// be read.
+    fn image(
+        &mut self,
+        path: &str,
+        alt_text: &str,
+        width: Option<f32>,
+        height: Option<f32>,
+    ) -> Option<String> {
+        let resources = &self.document.resources;
+        let bytes = resources.read(path)?;
+        let decoded = ::image::load_from_memory(&bytes).ok()?;
+
+        let href = match self.image_hrefs.get(path) {
+            Some(href) => href.clone(),
+            None => {
+                // PNG, JPEG and GIF are read everywhere; anything else is converted
+                let (extension, media_type, bytes) = match ::image::guess_format(&bytes).ok()? {
+                    ImageFormat::Png => ("png", "image/png", bytes),
+                    ImageFormat::Jpeg => ("jpeg", "image/jpeg", bytes),
+                    ImageFormat::Gif => ("gif", "image/gif", bytes),
+                    _ => {
+                        let mut png = Cursor::new(Vec::new());
+                        decoded.write_to(&mut png, ImageFormat::Png).ok()?;
+                        ("png", "image/png", png.into_inner())
+                    }
+                };
+
+                let href = format!("Pictures/image{}.{}", self.images.len() + 1, extension);
+                self.images.push(Image {
+                    href: href.clone(),
+                    media_type,
+                    bytes,
+                });
+                self.image_hrefs.insert(path.to_string(), href.clone());
+                href
+            }
+        };
+
+        // Same sizing as the page layout: the given size, else the natural
+        // one, shrunk to fit the page
+        let natural_w = decoded.width().max(1) as f32;
+        let natural_h = decoded.height().max(1) as f32;
+        let (w, h) = match (width, height) {
+            (Some(w), Some(h)) => (w, h),
+            (Some(w), None) => (w, w * natural_h / natural_w),
+            (None, Some(h)) => (h * natural_w / natural_h, h),
+            (None, None) => (natural_w, natural_h),
+        };
+        let scale = (CONTENT_WIDTH / w).min(CONTENT_HEIGHT / h).min(1.0);
+
+        let frame = self.next_frame;
+        self.next_frame += 1;
+        Some(format!(
+            "<draw:frame draw:name=\"Image{}\" text:anchor-type=\"as-char\" \
+             svg:width=\"{:.2}pt\" svg:height=\"{:.2}pt\">\
+             <draw:image xlink:href=\"{}\" xlink:type=\"simple\" xlink:show=\"embed\" \
+             xlink:actuate=\"onLoad\"/><svg:title>{}</svg:title></draw:frame>",
+            frame,
+            w * scale * PX_TO_PT,
+            h * scale * PX_TO_PT,
+            href,
+            escape(alt_text)
+        ))
+    }
+
+    fn content_xml(&self, body: &str) -> String {
+        let mut xml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+             <office:document-content {} office:version=\"{}\">{}<office:automatic-styles>",
+            NAMESPACES,
+            ODF_VERSION,
+            font_face_decls()
+        );
+
+        for (index, key) in self.paragraph_styles.iter().enumerate() {
+            let mut properties = format!(
+                " style:writing-mode=\"{}\"",
+                if key.rtl { "rl-tb" } else { "lr-tb" }
+            );
+            if let Some(align) = key.align {
+                properties.push_str(&format!(" fo:text-align=\"{}\"", text_align(align)));
+            }
+            if key.break_before {
+                properties.push_str(" fo:break-before=\"page\"");
+            }
+            if key.rule {
+                properties.push_str(
+                    " fo:border-bottom=\"0.75pt solid #999999\" fo:padding-bottom=\"0pt\"",
+                );
+            }
+            xml.push_str(&format!(
+                "<style:style style:name=\"P{}\" style:family=\"paragraph\" \
+                 style:parent-style-name=\"{}\"><style:paragraph-properties{}/></style:style>",
+                index + 1,
+                style_name(&key.parent),
+                properties
+            ));
+        }
+
+        for (index, key) in self.text_styles.iter().enumerate() {
+            let mut properties = String::new();
+            if key.strong {
+                properties.push_str(" fo:font-weight=\"bold\" style:font-weight-complex=\"bold\"");
+            }
+            if key.emphasis {
+                properties
+                    .push_str(" fo:font-style=\"italic\" style:font-style-complex=\"italic\"");
+            }
+            if key.code {
+                properties.push_str(&format!(" style:font-name=\"{}\"", CODE_FONT));
+            }
+            if key.strikethrough {
+                properties.push_str(" style:text-line-through-style=\"solid\"");
+            }
+            if key.underline {
+                properties.push_str(
+                    " style:text-underline-style=\"solid\" style:text-underline-width=\"auto\" \
+                     style:text-underline-color=\"font-color\"",
+                );
+            }
+            properties.push_str(&language_attributes(&key.language));
+            xml.push_str(&format!(
+                "<style:style style:name=\"T{}\" style:family=\"text\">\
+                 <style:text-properties{}/></style:style>",
+                index + 1,
+                properties
+            ));
+        }
+
+        for (index, list) in self.list_styles.iter().enumerate() {
+            xml.push_str(&format!("<text:list-style style:name=\"L{}\">", index + 1));
+            // Nested lists take the deeper levels of their own style
+            for level in 1..=10 {
+                let indent = format!(
+                    "<style:list-level-properties \
+                     text:list-level-position-and-space-mode=\"label-alignment\">\
+                     <style:list-level-label-alignment text:label-followed-by=\"listtab\" \
+                     fo:text-indent=\"-0.635cm\" fo:margin-left=\"{:.3}cm\"/>\
+                     </style:list-level-properties>",
+                    0.635 * level as f32
+                );
+                if list.ordered {
+                    xml.push_str(&format!(
+                        "<text:list-level-style-number text:level=\"{}\" style:num-suffix=\".\" \
+                         style:num-format=\"{}\" text:start-value=\"{}\">{}\
+                         </text:list-level-style-number>",
+                        level, list.format, list.start, indent
+                    ));
+                } else {
+                    xml.push_str(&format!(
+                        "<text:list-level-style-bullet text:level=\"{}\" text:bullet-char=\"{}\">{}\
+                         </text:list-level-style-bullet>",
+                        level,
+                        ['•', '◦', '▪'][(level - 1) % 3],
+                        indent
+                    ));
+                }
+            }
+            xml.push_str("</text:list-style>");
+        }
+
+        for (index, (rtl, break_before)) in self.table_styles.iter().enumerate() {
+            xml.push_str(&format!(
+                "<style:style style:name=\"Table{}\" style:family=\"table\">\
+                 <style:table-properties style:width=\"17cm\" table:align=\"margins\" \
+                 style:writing-mode=\"{}\"{}/></style:style>",
+                index + 1,
+                if *rtl { "rl-tb" } else { "lr-tb" },
+                if *break_before {
+                    " fo:break-before=\"page\""
+                } else {
+                    ""
+                }
+            ));
+        }
+
+        xml.push_str(&format!(
+            "</office:automatic-styles><office:body><office:text>{}</office:text>\
+             </office:body></office:document-content>",
+            body
+        ));
+        xml
+    }
+
+    /// Common styles for the style sheet entries, the page layout and the
+    /// defaults every paragraph inherits.
+    fn styles_xml(&self) -> String {
+        let metadata = &self.document.metadata;
+        let rtl = is_rtl_language(&metadata.language);
+
+        let mut xml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+             <office:document-styles {} office:version=\"{}\">{}<office:styles>\
+             <style:default-style style:family=\"paragraph\">\
+             <style:paragraph-properties style:writing-mode=\"page\"/>\
+             <style:text-properties style:font-name=\"{}\" style:font-name-complex=\"{}\" \
+             fo:font-size=\"12pt\" style:font-size-complex=\"12pt\"{}/></style:default-style>",
+            NAMESPACES,
+            ODF_VERSION,
+            font_face_decls(),
+            BODY_FONT,
+            COMPLEX_FONT,
+            language_attributes(&metadata.language)
+        );
+
+        // Every style in the sheet, plus any the content names that it lacks
+        let mut names: BTreeSet<&str> = self.styles().styles.keys().map(String::as_str).collect();
+        names.extend(self.used_styles.iter().map(String::as_str));
+        names.insert("paragraph");
+        for name in names {
+            xml.push_str(&style_xml(name, &self.styles().resolve(name)));
+        }
+
+        xml.push_str(&format!(
+            "</office:styles><office:automatic-styles>\
+             <style:page-layout style:name=\"pm1\"><style:page-layout-properties \
+             fo:page-width=\"21cm\" fo:page-height=\"29.7cm\" fo:margin-top=\"2cm\" \
+             fo:margin-bottom=\"2cm\" fo:margin-left=\"2cm\" fo:margin-right=\"2cm\" \
+             style:writing-mode=\"{}\"/></style:page-layout></office:automatic-styles>\
+             <office:master-styles><style:master-page style:name=\"Standard\" \
+             style:page-layout-name=\"pm1\"/></office:master-styles></office:document-styles>",
+            if rtl { "rl-tb" } else { "lr-tb" }
+        ));
+        xml
+    }
+
+    fn manifest_xml(&self) -> String {
+        let mut xml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+             <manifest:manifest xmlns:manifest=\"urn:oasis:names:tc:opendocument:xmlns:manifest:1.0\" \
+             manifest:version=\"{0}\"><manifest:file-entry manifest:full-path=\"/\" \
+             manifest:version=\"{0}\" manifest:media-type=\"{1}\"/>",
+            ODF_VERSION, MIMETYPE
+        );
+        for part in ["content.xml", "styles.xml", "meta.xml"] {
+            xml.push_str(&format!(
+                "<manifest:file-entry manifest:full-path=\"{}\" manifest:media-type=\"text/xml\"/>",
+                part
+            ));
+        }
+        for image in &self.images {
+            xml.push_str(&format!(
+                "<manifest:file-entry manifest:full-path=\"{}\" manifest:media-type=\"{}\"/>",
+                image.href, image.media_type
+            ));
+        }
+        xml.push_str("</manifest:manifest>");
+        xml
+    }
+}
+
+fn meta_xml(document: &PdxDocument) -> String {
+    let metadata = &document.metadata;
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+         <office:document-meta {} office:version=\"{}\"><office:meta>\
+         <meta:generator>PDX Editor</meta:generator><dc:title>{}</dc:title>\
+         <dc:language>{}</dc:language>",
+        NAMESPACES,
+        ODF_VERSION,
+        escape(&metadata.title),
+        escape(&metadata.language)
+    );
+    if !metadata.author.is_empty() {
+        xml.push_str(&format!(
+            "<meta:initial-creator>{0}</meta:initial-creator><dc:creator>{0}</dc:creator>",
+            escape(&metadata.author)
+        ));
+    }
+    for keyword in &metadata.keywords {
+        xml.push_str(&format!("<meta:keyword>{}</meta:keyword>", escape(keyword)));
+    }
+    if let Some(created) = w3c_date(&metadata.created) {
+        xml.push_str(&format!(
+            "<meta:creation-date>{}</meta:creation-date>",
+            created
+        ));
+    }
+    if let Some(modified) = w3c_date(&metadata.modified) {
+        xml.push_str(&format!("<dc:date>{}</dc:date>", modified));
+    }
+    xml.push_str("</office:meta></office:document-meta>");
+    xml
+}
+
+// ============================================================================
+// OpenDocument Helpers
+// ============================================================================
+
+fn font_face_decls() -> String {
+    let mut xml = String::from("<office:font-face-decls>");
+    for font in [BODY_FONT, COMPLEX_FONT, CODE_FONT] {
+        xml.push_str(&format!(
+            "<style:font-face style:name=\"{0}\" svg:font-family=\"&apos;{0}&apos;\"/>",
+            font
+        ));
+    }
+    xml.push_str("</office:font-face-decls>");
+    xml
+}
+
+/// ODF style name for a style sheet name: `paragraph` is the Standard
+/// style and headings are LibreOffice's built-in `Heading N`. Other names
+/// keep letters, digits, `-` and `.`, with the rest encoded as `_xx_`.
+fn style_name(name: &str) -> String {
+    if name == "paragraph" {
+        return "Standard".to_string();
+    }
+    if let Some(level) = heading_level(name) {
+        return format!("Heading_20_{}", level);
+    }
+
+    let mut encoded = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+            encoded.push(c);
+        } else {
+            encoded.push_str(&format!("_{:x}_", c as u32));
+        }
+    }
+    if !encoded.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        encoded.insert_str(0, "PDX_");
+    }
+    encoded
+}
+
+fn heading_level(name: &str) -> Option<u8> {
+    name.strip_prefix("heading")?
+        .parse::<u8>()
+        .ok()
+        .filter(|level| (1..=10).contains(level))
+}
+
+fn style_xml(name: &str, style: &Style) -> String {
+    let mut attributes = format!(
+        "style:name=\"{}\" style:family=\"paragraph\"",
+        style_name(name)
+    );
+    let level = heading_level(name);
+    match level {
+        Some(level) => attributes.push_str(&format!(
+            " style:display-name=\"Heading {0}\" style:parent-style-name=\"Standard\" \
+             style:default-outline-level=\"{0}\" style:class=\"text\"",
+            level
+        )),
+        None if name == "paragraph" => attributes.push_str(" style:class=\"text\""),
+        None => attributes.push_str(&format!(
+            " style:display-name=\"{}\" style:parent-style-name=\"Standard\" style:class=\"text\"",
+            escape(name)
+        )),
+    }
+
+    let pt = |px: f32| format!("{:.2}pt", px * PX_TO_PT);
+    let mut paragraph = format!(
+        " fo:margin-top=\"{}\" fo:margin-bottom=\"{}\" fo:margin-left=\"{}\" \
+         fo:margin-right=\"{}\" fo:text-align=\"{}\"",
+        pt(style.margin.top + style.padding.top),
+        pt(style.margin.bottom + style.padding.bottom),
+        pt(style.margin.left + style.padding.left),
+        pt(style.margin.right + style.padding.right),
+        text_align(style.text_align)
+    );
+    if style.line_height > 0.0 {
+        paragraph.push_str(&format!(
+            " fo:line-height=\"{}%\"",
+            (style.line_height * 100.0).round()
+        ));
+    }
+    match style.direction {
+        Direction::RTL => paragraph.push_str(" style:writing-mode=\"rl-tb\""),
+        Direction::LTR => paragraph.push_str(" style:writing-mode=\"lr-tb\""),
+        Direction::Auto => {}
+    }
+    if level.is_some() {
+        paragraph.push_str(" fo:keep-with-next=\"always\"");
+    }
+
+    let mut text = String::new();
+    if style.font_size > 0.0 {
+        text.push_str(&format!(
+            " fo:font-size=\"{0}\" style:font-size-complex=\"{0}\"",
+            pt(style.font_size)
+        ));
+    }
+    let weight = match style.font_weight {
+        FontWeight::Bold => "bold",
+        FontWeight::Normal => "normal",
+        FontWeight::Light => "300",
+    };
+    let color = style.color;
+    text.push_str(&format!(
+        " fo:font-weight=\"{0}\" style:font-weight-complex=\"{0}\" \
+         fo:color=\"#{1:02x}{2:02x}{3:02x}\"",
+        weight, color.r, color.g, color.b
+    ));
+    if name == "code" {
+        text.push_str(&format!(
+            " style:font-name=\"{0}\" style:font-name-complex=\"{0}\"",
+            CODE_FONT
+        ));
+    }
+
+    format!(
+        "<style:style {}><style:paragraph-properties{}/><style:text-properties{}/></style:style>",
+        attributes, paragraph, text
+    )
+}
+
+fn text_align(align: TextAlign) -> &'static str {
+    match align {
+        TextAlign::Start => "start",
+        TextAlign::End => "end",
+        TextAlign::Center => "center",
+        TextAlign::Justify => "justify",
+    }
+}
+
+/// `fo:language`/`fo:country` for a left-to-right language, or the
+/// complex-script pair for a right-to-left one.
+fn language_attributes(tag: &str) -> String {
+    let mut subtags = tag.split(['-', '_']);
+    let language = subtags.next().unwrap_or_default();
+    if language.is_empty() {
+        return String::new();
+    }
+    let country = subtags.find(|subtag| subtag.len() == 2);
+
+    let (language_attr, country_attr) = if is_rtl_language(tag) {
+        ("style:language-complex", "style:country-complex")
+    } else {
+        ("fo:language", "fo:country")
+    };
+    let mut xml = format!(" {}=\"{}\"", language_attr, escape(language));
+    if let Some(country) = country {
+        xml.push_str(&format!(" {}=\"{}\"", country_attr, escape(country)));
+    }
+    xml
+}
+
+/// Text with OpenDocument's whitespace rules: repeated and leading spaces
+/// become `text:s`, tabs and line breaks their own elements.
+fn text_xml(text: &str, line_start: &mut bool) -> String {
+    let mut xml = String::new();
+    let mut spaces = 0;
+
+    let flush = |xml: &mut String, spaces: &mut usize, line_start: bool| {
+        if *spaces == 0 {
+            return;
+        }
+        let mut collapsed = *spaces;
+        if !line_start {
+            xml.push(' ');
+            collapsed -= 1;
+        }
+        match collapsed {
+            0 => {}
+            1 => xml.push_str("<text:s/>"),
+            n => xml.push_str(&format!("<text:s text:c=\"{}\"/>", n)),
+        }
+        *spaces = 0;
+    };
+
+    for c in text.chars() {
+        if c == ' ' {
+            spaces += 1;
+            continue;
+        }
+        flush(&mut xml, &mut spaces, *line_start);
+        match c {
+            '\t' => xml.push_str("<text:tab/>"),
+            '\n' => xml.push_str("<text:line-break/>"),
+            c => xml.push_str(&escape(&c.to_string())),
+        }
+        *line_start = matches!(c, '\t' | '\n');
+    }
+    // Trailing spaces are kept too; the next run may carry on the text
+    if spaces > 0 {
+        flush(&mut xml, &mut spaces, *line_start);
+        *line_start = false;
+    }
+
+    xml
+}
+
+// ============================================================================
+// ODT Import
+// ============================================================================
+
+/// Fonts whose spans are read as inline code.
+const MONOSPACE_FONTS: [&str; 7] = [
+    "liberation mono",
+    "courier new",
+    "courier",
+    "consolas",
+    "menlo",
+    "dejavu sans mono",
+    "noto sans mono",
+];
+
+/// Reads an OpenDocument Text file. Common paragraph styles become style
+/// sheet entries, `text:h` elements headings, `text:list` elements lists
+/// and pictures are extracted into the document's resources under
+/// content-hashed names, so the document saves as a bundle.
+pub fn import_odt(data: &[u8], fallback_title: &str) -> Result<PdxDocument, PdxError> {
+    let mut archive = ZipArchive::new(Cursor::new(data)).map_err(import_error)?;
+
+    let mut parts: HashMap<String, Vec<u8>> = HashMap::new();
+    for index in 0..archive.len() {
+        let entry = archive.by_index(index).map_err(import_error)?;
+        if !entry.is_file() {
+            continue;
+        }
+        let name = entry.name().to_string();
+        let bytes = read_entry(entry).map_err(import_error)?;
+        parts.insert(name, bytes);
+    }
+
+    if let Some(mimetype) = parts.get("mimetype") {
+        let mimetype = String::from_utf8_lossy(mimetype);
+        if !mimetype.trim().starts_with(MIMETYPE) {
+            return Err(import_error(format!(
+                "not a text document ({})",
+                mimetype.trim()
+            )));
+        }
+    }
+    let parse = |name: &str| -> Result<Option<Element>, PdxError> {
+        parts
+            .get(name)
+            .map(|bytes| {
+                xml::parse(&String::from_utf8_lossy(bytes))
+                    .map_err(|e| import_error(format!("{}: {}", name, e)))
+            })
+            .transpose()
+    };
+    let content = parse("content.xml")?.ok_or_else(|| import_error("no content.xml"))?;
+    let styles = parse("styles.xml")?;
+    let meta = parse("meta.xml")?;
+
+    let mut reader = OdtReader::new(&content, styles.as_ref(), &parts);
+    if let Some(text) = content
+        .child("office:body")
+        .and_then(|body| body.child("office:text"))
+    {
+        let nodes = reader.blocks(text);
+        reader.nodes = nodes;
+    }
+
+    let children = std::mem::take(&mut reader.nodes);
+    let metadata = read_meta(meta.as_ref(), &children, fallback_title);
+
+    let mut sheet = StyleSheet::default();
+    for name in &reader.used_styles {
+        sheet
+            .styles
+            .insert(reader.sheet_name(name), reader.resolved_style(name));
+    }
+
+    Ok(PdxDocument {
+        version: CURRENT_VERSION,
+        metadata,
+        styles: sheet,
+        content: Node::Document { children },
+        resources: Resources {
+            files: reader.files,
+            ..Resources::default()
+        },
+    })
+}
+
+fn import_error(e: impl std::fmt::Display) -> PdxError {
+    PdxError::Import {
+        format: "ODT",
+        message: e.to_string(),
+    }
+}
+
+/// Title, author, language and keywords from `meta.xml`. The title falls
+/// back to the first heading, the language to the first text.
+fn read_meta(meta: Option<&Element>, children: &[Node], fallback_title: &str) -> Metadata {
+    let meta = meta.and_then(|meta| meta.child("office:meta"));
+    let field = |name: &str| {
+        meta.and_then(|meta| meta.child(name))
+            .map(|element| element.text().trim().to_string())
+            .filter(|text| !text.is_empty())
+    };
+
+    let title = field("dc:title")
+        .or_else(|| {
+            children.iter().find_map(|node| match node {
+                Node::Heading { runs, .. } => Some(runs.iter().map(|r| r.text.as_str()).collect()),
+                _ => None,
+            })
+        })
+        .unwrap_or_else(|| fallback_title.to_string());
+    let language = field("dc:language")
+        .or_else(|| {
+            children.iter().find_map(|node| match node {
+                Node::Heading { runs, .. } | Node::Paragraph { runs, .. } => {
+                    runs.first().map(|run| run.language.clone())
+                }
+                _ => None,
+            })
+        })
+        .unwrap_or_else(|| "en".to_string());
+    let keywords = meta
+        .map(|meta| {
+            meta.children_named("meta:keyword")
+                .flat_map(|keyword| {
+                    keyword
+                        .text()
+                        .split([',', ';'])
+                        .map(|k| k.trim().to_string())
+                        .collect::<Vec<_>>()
+                })
+                .filter(|k| !k.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let defaults = Metadata::default();
+    Metadata {
+        title,
+        author: field("dc:creator")
+            .or_else(|| field("meta:initial-creator"))
+            .unwrap_or(defaults.author),
+        language,
+        created: field("meta:creation-date").unwrap_or(defaults.created),
+        modified: field("dc:date").unwrap_or(defaults.modified),
+        keywords,
+    }
+}
+
+/// A length such as `0.5cm` or `12pt` in CSS pixels.
+fn length_px(value: &str) -> Option<f32> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| c.is_ascii_alphabetic() || c == '%')
+        .unwrap_or(value.len());
+    let number: f32 = value[..split].parse().ok()?;
+    let px = match &value[split..] {
+        "px" | "" => number,
+        "pt" => number / PX_TO_PT,
+        "pc" => number * 16.0,
+        "in" => number * 96.0,
+        "cm" => number * 96.0 / 2.54,
+        "mm" => number * 96.0 / 25.4,
+        _ => return None,
+    };
+    Some(px)
+}
+
+fn parse_align(value: &str) -> Option<TextAlign> {
+    match value {
+        "start" | "left" => Some(TextAlign::Start),
+        "end" | "right" => Some(TextAlign::End),
+        "center" => Some(TextAlign::Center),
+        "justify" => Some(TextAlign::Justify),
+        _ => None,
+    }
+}
+
+/// `rl-tb` and friends read right to left; `page` inherits.
+fn parse_writing_mode(value: &str) -> Option<Direction> {
+    match value {
+        "rl-tb" | "rl" | "tb-rl" => Some(Direction::RTL),
+        "lr-tb" | "lr" | "tb-lr" => Some(Direction::LTR),
+        _ => None,
+    }
+}
+
+fn parse_color(value: &str) -> Option<Color> {
+    let hex = value.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some(Color::rgb(channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// What a paragraph holds besides its runs.
+#[derive(Default)]
+struct ParagraphContent {
+    runs: Vec<TextRun>,
+    images: Vec<Node>,
+}
+
+/// Languages ODF records for text: `fo:language` for Latin and other
+/// left-to-right scripts and `style:language-complex` for right-to-left
+/// ones, each with an optional country.
+#[derive(Clone, Default)]
+struct OdfLanguage {
+    latin: Option<String>,
+    complex: Option<String>,
+}
+
+impl OdfLanguage {
+    fn apply(&mut self, properties: Option<&Element>) {
+        let Some(properties) = properties else {
+            return;
+        };
+        let tag = |language: Option<&str>, country: Option<&str>| {
+            let language = language.filter(|l| !l.is_empty() && *l != "none")?;
+            Some(match country.filter(|c| !c.is_empty() && *c != "none") {
+                Some(country) => format!("{}-{}", language, country),
+                None => language.to_string(),
+            })
+        };
+
+        if let Some(latin) = tag(
+            properties.attr("fo:language"),
+            properties.attr("fo:country"),
+        ) {
+            self.latin = Some(latin);
+        }
+        if let Some(complex) = tag(
+            properties.attr("style:language-complex"),
+            properties.attr("style:country-complex"),
+        ) {
+            self.complex = Some(complex);
+        }
+    }
+}
+
+struct OdtReader<'a> {
+    /// `style:style` elements by family and name, automatic ones included
+    styles: HashMap<(&'a str, &'a str), &'a Element>,
+    /// Names of the common paragraph styles, the ones users pick
+    common: HashSet<&'a str>,
+    default_paragraph: Option<&'a Element>,
+    list_styles: HashMap<&'a str, &'a Element>,
+    /// Package entries, for pictures
+    parts: &'a HashMap<String, Vec<u8>>,
+    /// Extracted pictures by resource name
+    files: HashMap<String, Vec<u8>>,
+    used_styles: BTreeSet<String>,
+    nodes: Vec<Node>,
+}
+
+impl<'a> OdtReader<'a> {
+    fn new(
+        content: &'a Element,
+        styles: Option<&'a Element>,
+        parts: &'a HashMap<String, Vec<u8>>,
+    ) -> Self {
+        let mut reader = Self {
+            styles: HashMap::new(),
+            common: HashSet::new(),
+            default_paragraph: None,
+            list_styles: HashMap::new(),
+            parts,
+            files: HashMap::new(),
+            used_styles: BTreeSet::new(),
+            nodes: Vec::new(),
+        };
+
+        let common = styles.and_then(|styles| styles.child("office:styles"));
+        let automatic = [
+            styles.and_then(|styles| styles.child("office:automatic-styles")),
+            content.child("office:automatic-styles"),
+        ];
+        for (container, is_common) in std::iter::once((common, true))
+            .chain(automatic.into_iter().map(|container| (container, false)))
+        {
+            let Some(container) = container else {
+                continue;
+            };
+            for element in container.elements() {
+                match element.name.as_str() {
+                    "style:style" => {
+                        let (Some(family), Some(name)) =
+                            (element.attr("style:family"), element.attr("style:name"))
+                        else {
+                            continue;
+                        };
+                        reader.styles.insert((family, name), element);
+                        if is_common && family == "paragraph" {
+                            reader.common.insert(name);
+                        }
+                    }
+                    "style:default-style" if element.attr("style:family") == Some("paragraph") => {
+                        reader.default_paragraph = Some(element);
+                    }
+                    "text:list-style" => {
+                        if let Some(name) = element.attr("style:name") {
+                            reader.list_styles.insert(name, element);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        reader
+    }
+
+    // ========================================================================
+    // Styles
+    // ========================================================================
+
+    /// The style and the ones it inherits from, base first.
+    fn style_chain(&self, family: &str, name: &str) -> Vec<&'a Element> {
+        let mut chain = Vec::new();
+        let mut current = self.styles.get(&(family, name)).copied();
+        // Bounded, in case of a cycle
+        while let Some(style) = current
+            && chain.len() < 16
+        {
+            chain.push(style);
+            current = style
+                .attr("style:parent-style-name")
+                .and_then(|parent| self.styles.get(&(family, parent)).copied());
+        }
+        chain.reverse();
+        chain
+    }
+
+    /// An attribute of a properties element, set on the style or inherited.
+    fn property(
+        &self,
+        family: &str,
+        name: &str,
+        properties: &str,
+        attribute: &str,
+    ) -> Option<&'a str> {
+        self.style_chain(family, name)
+            .iter()
+            .rev()
+            .find_map(|style| style.child(properties)?.attr(attribute))
+    }
+
+    /// The common style an automatic paragraph style refines.
+    fn common_style(&self, name: &str) -> String {
+        self.style_chain("paragraph", name)
+            .iter()
+            .rev()
+            .filter_map(|style| style.attr("style:name"))
+            .find(|name| self.common.contains(name))
+            .unwrap_or("Standard")
+            .to_string()
+    }
+
+    /// Style sheet name for an ODF style: Standard and the default body
+    /// styles are `paragraph`, `Heading N` is `headingN`, and other names
+    /// are lower-cased with dashes.
+    fn sheet_name(&self, name: &str) -> String {
+        let display = self
+            .styles
+            .get(&("paragraph", name))
+            .and_then(|style| style.attr("style:display-name"))
+            .map(str::to_string)
+            .unwrap_or_else(|| decode_style_name(name));
+        let lower = display.trim().to_lowercase();
+
+        if matches!(
+            lower.as_str(),
+            "standard" | "default paragraph style" | "default" | "text body" | "body text"
+        ) {
+            return "paragraph".to_string();
+        }
+        if let Some(level) = lower
+            .strip_prefix("heading")
+            .map(str::trim)
+            .and_then(|n| n.parse::<u8>().ok())
+        {
+            return format!("heading{}", level);
+        }
+        lower.split_whitespace().collect::<Vec<_>>().join("-")
+    }
+
+    /// The common style as a style sheet entry, with what it inherits.
+    fn resolved_style(&self, name: &str) -> Style {
+        let mut style = Style {
+            // LibreOffice's own default is 12 pt
+            font_size: 12.0 / PX_TO_PT,
+            line_height: 1.0,
+            ..Default::default()
+        };
+
+        let chain = self
+            .default_paragraph
+            .into_iter()
+            .chain(self.style_chain("paragraph", name));
+        for element in chain {
+            apply_paragraph_properties(&mut style, element.child("style:paragraph-properties"));
+            apply_text_properties(&mut style, element.child("style:text-properties"));
+        }
+        style
+    }
+
+    // ========================================================================
+    // Blocks
+    // ========================================================================
+
+    fn blocks(&mut self, container: &Element) -> Vec<Node> {
+        let mut nodes = Vec::new();
+        for element in container.elements() {
+            self.block(element, &mut nodes);
+        }
+        nodes
+    }
+
+    fn block(&mut self, element: &Element, nodes: &mut Vec<Node>) {
+        match element.name.as_str() {
+            "text:p" | "text:h" => self.paragraph(element, nodes),
+            "text:list" => {
+                let first = element
+                    .child("text:list-item")
+                    .and_then(|item| item.elements().next());
+                if let Some(list) = self.list(element, 0, None) {
+                    push_break(nodes, self.breaks(first).0);
+                    nodes.push(list);
+                }
+            }
+            "table:table" => {
+                let break_before = element.attr("table:style-name").and_then(|name| {
+                    self.property("table", name, "style:table-properties", "fo:break-before")
+                }) == Some("page");
+                push_break(nodes, break_before);
+                nodes.push(self.table(element));
+            }
+            // Sections and tracked insertions hold ordinary blocks
+            "text:section" | "text:changed-region" => {
+                for child in element.elements() {
+                    self.block(child, nodes);
+                }
+            }
+            // Generated indexes are rebuilt from the headings
+            _ => {}
+        }
+    }
+
+    /// Whether the paragraph's style starts or ends a page.
+    fn breaks(&self, paragraph: Option<&Element>) -> (bool, bool) {
+        let Some(name) = paragraph.and_then(|p| p.attr("text:style-name")) else {
+            return (false, false);
+        };
+        let property = |attribute| {
+            self.property("paragraph", name, "style:paragraph-properties", attribute)
+                == Some("page")
+        };
+        (property("fo:break-before"), property("fo:break-after"))
+    }
+
+    /// Languages a paragraph's text inherits from the defaults and its style.
+    fn paragraph_language(&self, style: &str) -> OdfLanguage {
+        let mut language = OdfLanguage::default();
+        let chain = self
+            .default_paragraph
+            .into_iter()
+            .chain(self.style_chain("paragraph", style));
+        for element in chain {
+            language.apply(element.child("style:text-properties"));
+        }
+        language
+    }
+
+    fn paragraph(&mut self, p: &Element, nodes: &mut Vec<Node>) {
+        let style = p.attr("text:style-name").unwrap_or("Standard");
+        let common = self.common_style(style);
+        let name = self.sheet_name(&common);
+        let (break_before, break_after) = self.breaks(Some(p));
+        push_break(nodes, break_before);
+
+        let mut content = ParagraphContent::default();
+        let language = self.paragraph_language(style);
+        let rtl = self
+            .property(
+                "paragraph",
+                style,
+                "style:paragraph-properties",
+                "style:writing-mode",
+            )
+            .and_then(parse_writing_mode)
+            == Some(Direction::RTL);
+        self.inline(p, &InlineMarks::default(), &language, rtl, &mut content);
+        let text: String = content.runs.iter().map(|r| r.text.as_str()).collect();
+
+        // Code blocks are one paragraph, or one per line from other editors
+        if name == "code" {
+            self.used_styles.insert(common);
+            match nodes.last_mut() {
+                Some(Node::CodeBlock { code, .. }) if !break_before => {
+                    code.push('\n');
+                    code.push_str(&text);
+                }
+                _ => nodes.push(Node::CodeBlock {
+                    language: "text".to_string(),
+                    code: text,
+                    style: name,
+                }),
+            }
+            push_break(nodes, break_after);
+            return;
+        }
+
+        let outline = (p.name == "text:h").then(|| {
+            p.attr("text:outline-level")
+                .and_then(|level| level.parse::<u8>().ok())
+                .unwrap_or(1)
+        });
+        let has_text = !text.trim().is_empty();
+
+        if let (Some(level), true) = (outline, has_text) {
+            self.used_styles.insert(common);
+            nodes.push(Node::Heading {
+                level: level.clamp(1, 6),
+                runs: content.runs,
+                style: name,
+            });
+        } else if has_text {
+            self.used_styles.insert(common);
+            nodes.push(Node::Paragraph {
+                runs: content.runs,
+                style: name,
+            });
+        } else if content.images.is_empty()
+            && self
+                .property(
+                    "paragraph",
+                    style,
+                    "style:paragraph-properties",
+                    "fo:border-bottom",
+                )
+                .is_some_and(|border| border != "none")
+        {
+            // An empty paragraph with a bottom border is a rule
+            nodes.push(Node::Divider);
+        }
+        nodes.extend(content.images);
+        push_break(nodes, break_after);
+    }
+
+    /// Reads a `text:list` into a list node. Items keep their first
+    /// paragraph as content and everything after it as children.
+    fn list(&mut self, list: &Element, depth: usize, inherited: Option<&'a str>) -> Option<Node> {
+        let style_name = list
+            .attr("text:style-name")
+            .and_then(|name| self.list_styles.get_key_value(name).map(|(k, _)| *k))
+            .or(inherited);
+        let level = style_name
+            .and_then(|name| self.list_styles.get(name))
+            .and_then(|style| {
+                style.elements().find(|level| {
+                    level
+                        .attr("text:level")
+                        .and_then(|l| l.parse::<usize>().ok())
+                        == Some(depth + 1)
+                })
+            });
+        let ordered = level.is_some_and(|level| {
+            level.name == "text:list-level-style-number"
+                && level
+                    .attr("style:num-format")
+                    .is_some_and(|format| !format.is_empty())
+        });
+        let mut start = level
+            .and_then(|level| level.attr("text:start-value"))
+            .and_then(|start| start.parse().ok())
+            .unwrap_or(1);
+
+        let mut items = Vec::new();
+        let mut list_style = None;
+        for (index, item) in list
+            .elements()
+            .filter(|e| matches!(e.name.as_str(), "text:list-item" | "text:list-header"))
+            .enumerate()
+        {
+            if index == 0
+                && let Some(value) = item.attr("text:start-value").and_then(|v| v.parse().ok())
+            {
+                start = value;
+            }
+
+            let mut content = Vec::new();
+            let mut children = Vec::new();
+            for child in item.elements() {
+                match child.name.as_str() {
+                    "text:p" | "text:h" if content.is_empty() && children.is_empty() => {
+                        let style = child.attr("text:style-name").unwrap_or("Standard");
+                        let common = self.common_style(style);
+                        let name = self.sheet_name(&common);
+                        if list_style.is_none() && name != "paragraph" {
+                            self.used_styles.insert(common);
+                            list_style = Some(name);
+                        }
+
+                        let mut paragraph = ParagraphContent::default();
+                        let language = self.paragraph_language(style);
+                        self.inline(
+                            child,
+                            &InlineMarks::default(),
+                            &language,
+                            false,
+                            &mut paragraph,
+                        );
+                        content = paragraph
+                            .runs
+                            .into_iter()
+                            .map(|mut run| {
+                                run.style = "paragraph".to_string();
+                                run
+                            })
+                            .collect();
+                        children.extend(paragraph.images);
+                    }
+                    "text:list" => children.extend(self.list(child, depth + 1, style_name)),
+                    _ => self.block(child, &mut children),
+                }
+            }
+            items.push(ListItem { content, children });
+        }
+
+        if items.is_empty() {
+            return None;
+        }
+        Some(Node::List {
+            ordered,
+            start,
+            items,
+            style: list_style.unwrap_or_else(|| "list".to_string()),
+        })
+    }
+
+    fn table(&mut self, table: &Element) -> Node {
+        let mut rows = Vec::new();
+        let mut header_rows = 0;
+        let mut alignments = Vec::new();
+
+        let mut row_elements: Vec<(&Element, bool)> = Vec::new();
+        for child in table.elements() {
+            match child.name.as_str() {
+                "table:table-header-rows" => row_elements.extend(
+                    child
+                        .children_named("table:table-row")
+                        .map(|row| (row, true)),
+                ),
+                "table:table-row" => row_elements.push((child, false)),
+                "table:table-rows" => row_elements.extend(
+                    child
+                        .children_named("table:table-row")
+                        .map(|row| (row, false)),
+                ),
+                _ => {}
+            }
+        }
+
+        for (r, (row, header)) in row_elements.into_iter().enumerate() {
+            if header && header_rows == r {
+                header_rows += 1;
+            }
+            let header = header && r < header_rows;
+            let mut cells = Vec::new();
+            for element in row.children_named("table:table-cell") {
+                let mut cell = TableCell {
+                    content: Vec::new(),
+                };
+                for p in element
+                    .elements()
+                    .filter(|e| matches!(e.name.as_str(), "text:p" | "text:h"))
+                {
+                    let style = p.attr("text:style-name").unwrap_or("Standard");
+                    if r == 0 && alignments.len() == cells.len() {
+                        let align = self
+                            .property(
+                                "paragraph",
+                                style,
+                                "style:paragraph-properties",
+                                "fo:text-align",
+                            )
+                            .and_then(parse_align)
+                            .unwrap_or(TextAlign::Start);
+                        alignments.push(align);
+                    }
+
+                    let mut content = ParagraphContent::default();
+                    let language = self.paragraph_language(style);
+                    self.inline(p, &InlineMarks::default(), &language, false, &mut content);
+                    cell.push_paragraph(content.runs, header);
+                }
+                cells.push(cell);
+            }
+            rows.push(TableRow { cells });
+        }
+
+        let writing_mode = table
+            .attr("table:style-name")
+            .and_then(|name| {
+                self.property(
+                    "table",
+                    name,
+                    "style:table-properties",
+                    "style:writing-mode",
+                )
+            })
+            .and_then(parse_writing_mode);
+        let is_rtl = match writing_mode {
+            Some(direction) => direction == Direction::RTL,
+            None => rows
+                .iter()
+                .flat_map(|row: &TableRow| &row.cells)
+                .any(|cell| {
+                    cell.content
+                        .iter()
+                        .any(|run| run.direction == Direction::RTL)
+                }),
+        };
+
+        Node::Table {
+            rows,
+            header_rows,
+            alignments,
+            direction: if is_rtl {
+                Direction::RTL
+            } else {
+                Direction::LTR
+            },
+            style: "table".to_string(),
+        }
+    }
+
+    // ========================================================================
+    // Runs
+    // ========================================================================
+
+    /// Collects the text under `element`, descending into spans and links.
+    fn inline(
+        &mut self,
+        element: &Element,
+        marks: &InlineMarks,
+        language: &OdfLanguage,
+        rtl: bool,
+        content: &mut ParagraphContent,
+    ) {
+        for child in &element.children {
+            let child = match child {
+                Content::Text(text) => {
+                    // Runs of whitespace in the markup are one space
+                    let mut collapsed = String::new();
+                    for c in text.chars() {
+                        if c.is_whitespace() {
+                            if !collapsed.ends_with(' ') {
+                                collapsed.push(' ');
+                            }
+                        } else {
+                            collapsed.push(c);
+                        }
+                    }
+                    if content.runs.is_empty() && content.images.is_empty() {
+                        collapsed = collapsed.trim_start().to_string();
+                    }
+                    push_run(&collapsed, marks, rtl, language, content);
+                    continue;
+                }
+                Content::Element(child) => child,
+            };
+
+            match child.name.as_str() {
+                "text:span" => {
+                    let (marks, language) = match child.attr("text:style-name") {
+                        Some(style) => self.span_style(style, marks, language),
+                        None => (marks.clone(), language.clone()),
+                    };
+                    self.inline(child, &marks, &language, rtl, content);
+                }
+                "text:a" => {
+                    let mut marks = marks.clone();
+                    marks.link = child.attr("xlink:href").map(str::to_string);
+                    // Links are underlined by their character style, not by the author
+                    let (mut linked, language) = match child.attr("text:style-name") {
+                        Some(style) => self.span_style(style, &marks, language),
+                        None => (marks.clone(), language.clone()),
+                    };
+                    linked.underline = marks.underline;
+                    self.inline(child, &linked, &language, rtl, content);
+                }
+                "text:s" => {
+                    let count = child
+                        .attr("text:c")
+                        .and_then(|c| c.parse::<usize>().ok())
+                        .unwrap_or(1);
+                    push_run(&" ".repeat(count), marks, rtl, language, content);
+                }
+                "text:tab" => push_run("\t", marks, rtl, language, content),
+                "text:line-break" => push_run("\n", marks, rtl, language, content),
+                "draw:frame" => {
+                    if let Some(image) = self.frame(child) {
+                        content.images.push(image);
+                    }
+                }
+                "draw:a" => {
+                    for frame in child.children_named("draw:frame") {
+                        content.images.extend(self.frame(frame));
+                    }
+                }
+                // Notes, comments and tracked deletions are not body text
+                "text:note"
+                | "office:annotation"
+                | "office:annotation-end"
+                | "text:tracked-changes"
+                | "text:bookmark"
+                | "text:bookmark-start"
+                | "text:bookmark-end"
+                | "text:soft-page-break"
+                | "text:change"
+                | "text:change-start"
+                | "text:change-end" => {}
+                _ => self.inline(child, marks, language, rtl, content),
+            }
+        }
+    }
+
+    /// The marks and languages a text style adds.
+    fn span_style(
+        &self,
+        name: &str,
+        marks: &InlineMarks,
+        language: &OdfLanguage,
+    ) -> (InlineMarks, OdfLanguage) {
+        let mut marks = marks.clone();
+        let mut language = language.clone();
+
+        for style in self.style_chain("text", name) {
+            let Some(properties) = style.child("style:text-properties") else {
+                continue;
+            };
+            if let Some(weight) = properties.attr("fo:font-weight") {
+                marks.strong = matches!(weight, "bold" | "600" | "700" | "800" | "900");
+            }
+            if let Some(font_style) = properties.attr("fo:font-style") {
+                marks.emphasis = matches!(font_style, "italic" | "oblique");
+            }
+            if let Some(line) = properties.attr("style:text-line-through-style") {
+                marks.strikethrough = line != "none";
+            }
+            if let Some(line) = properties.attr("style:text-underline-style") {
+                marks.underline = line != "none";
+            }
+            let font = properties
+                .attr("style:font-name")
+                .or(properties.attr("fo:font-family"))
+                .map(|font| font.trim_matches(['\'', '"']).to_lowercase());
+            if let Some(font) = font {
+                marks.code = MONOSPACE_FONTS.contains(&font.as_str());
+            }
+            language.apply(Some(properties));
+        }
+
+        (marks, language)
+    }
+
+    /// Moves a framed picture into the document's resources and returns
+    /// its node.
+    fn frame(&mut self, frame: &Element) -> Option<Node> {
+        let href = frame.child("draw:image")?.attr("xlink:href")?;
+        let path = href.trim_start_matches("./");
+        let bytes = self.parts.get(path)?;
+
+        let name = resource_name(path, bytes);
+        self.files
+            .entry(name.clone())
+            .or_insert_with(|| bytes.clone());
+
+        let alt_text = frame
+            .child("svg:title")
+            .or(frame.child("svg:desc"))
+            .map(|alt| alt.text().trim().to_string())
+            .unwrap_or_default();
+        let size = |name: &str| frame.attr(name).and_then(length_px).map(|px| px.round());
+
+        Some(Node::Image {
+            path: name,
+            alt_text,
+            width: size("svg:width"),
+            height: size("svg:height"),
+        })
+    }
+}
+
+/// Adds text as a run, merging it into the previous run when their marks
+/// and language match.
+fn push_run(
+    text: &str,
+    marks: &InlineMarks,
+    rtl: bool,
+    odf_language: &OdfLanguage,
+    content: &mut ParagraphContent,
+) {
+    if text.is_empty() {
+        return;
+    }
+
+    let language = tagged_language(
+        text,
+        rtl,
+        odf_language.latin.as_deref(),
+        odf_language.complex.as_deref(),
+        content.runs.last(),
+    );
+    if let Some(last) = content.runs.last_mut()
+        && last.marks == *marks
+        && last.language == language
+    {
+        last.text.push_str(text);
+        return;
+    }
+    content
+        .runs
+        .push(TextRun::new(text, &language, "paragraph").with_marks(marks.clone()));
+}
+
+fn push_break(nodes: &mut Vec<Node>, page_break: bool) {
+    if page_break && !matches!(nodes.last(), Some(Node::PageBreak) | None) {
+        nodes.push(Node::PageBreak);
+    }
+}
+
+/// `Heading_20_1` to `Heading 1`: ODF encodes characters outside names as
+/// their hex code between underscores.
+fn decode_style_name(name: &str) -> String {
+    let mut decoded = String::new();
+    let mut rest = name;
+
+    while let Some(start) = rest.find('_') {
+        decoded.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let code = after
+            .find('_')
+            .and_then(|end| Some((u32::from_str_radix(&after[..end], 16).ok()?, end)))
+            .and_then(|(code, end)| Some((char::from_u32(code)?, end)));
+        match code {
+            Some((c, end)) => {
+                decoded.push(c);
+                rest = &after[end + 1..];
+            }
+            None => {
+                decoded.push('_');
+                rest = after;
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn apply_paragraph_properties(style: &mut Style, properties: Option<&Element>) {
+    let Some(properties) = properties else {
+        return;
+    };
+    let length = |name: &str| properties.attr(name).and_then(length_px);
+
+    if let Some(align) = properties.attr("fo:text-align").and_then(parse_align) {
+        style.text_align = align;
+    }
+    if let Some(direction) = properties
+        .attr("style:writing-mode")
+        .and_then(parse_writing_mode)
+    {
+        style.direction = direction;
+    }
+    if let Some(top) = length("fo:margin-top") {
+        style.margin.top = top;
+    }
+    if let Some(bottom) = length("fo:margin-bottom") {
+        style.margin.bottom = bottom;
+    }
+    if let Some(left) = length("fo:margin-left") {
+        style.margin.left = left;
+    }
+    if let Some(right) = length("fo:margin-right") {
+        style.margin.right = right;
+    }
+    if let Some(percent) = properties
+        .attr("fo:line-height")
+        .and_then(|value| value.strip_suffix('%'))
+        .and_then(|value| value.parse::<f32>().ok())
+        .filter(|percent| *percent > 0.0)
+    {
+        style.line_height = percent / 100.0;
+    }
+}
+
+fn apply_text_properties(style: &mut Style, properties: Option<&Element>) {
+    let Some(properties) = properties else {
+        return;
+    };
+
+    if let Some(weight) = properties.attr("fo:font-weight") {
+        style.font_weight = match weight {
+            "bold" | "600" | "700" | "800" | "900" => FontWeight::Bold,
+            "100" | "200" | "300" => FontWeight::Light,
+            _ => FontWeight::Normal,
+        };
+    }
+    // Percentages are relative to the parent and left alone
+    if let Some(size) = properties.attr("fo:font-size").and_then(length_px) {
+        style.font_size = size;
+    }
+    if let Some(color) = properties.attr("fo:color").and_then(parse_color) {
+        style.color = color;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A hand-written package in LibreOffice's layout: common styles, an
+    /// RTL Arabic paragraph, a numbered list with a nested bullet list, a
+    /// framed picture and a table with a header row.
+    const BILINGUAL: &[u8] = include_bytes!("../testdata/bilingual.odt");
+
+    fn children(document: &PdxDocument) -> &[Node] {
+        match &document.content {
+            Node::Document { children } => children,
+            _ => panic!("not a document"),
+        }
+    }
+
+    fn text(runs: &[TextRun]) -> String {
+        runs.iter().map(|run| run.text.as_str()).collect()
+    }
+
+    #[test]
+    fn fixture_styles_directions_lists_and_images_are_read() {
+        let document = import_odt(BILINGUAL, "fallback").unwrap();
+        assert_eq!(document.metadata.title, "Bilingual Notes");
+        assert_eq!(document.metadata.author, "Test Author");
+        assert_eq!(document.metadata.language, "en-GB");
+        assert_eq!(document.metadata.keywords, vec!["fixture".to_string()]);
+
+        let heading = &document.styles.styles["heading1"];
+        assert_eq!(heading.font_weight, FontWeight::Bold);
+        assert!((heading.font_size - 20.0 / PX_TO_PT).abs() < 0.01);
+        assert_eq!(heading.color, Color::rgb(0x1f, 0x38, 0x64));
+        let quote = &document.styles.styles["pull-quote"];
+        assert_eq!(quote.text_align, TextAlign::Center);
+        assert_eq!(quote.margin.left, 48.0);
+        assert_eq!(quote.line_height, 1.5);
+
+        let children = children(&document);
+        let Node::Paragraph { runs, .. } = &children[1] else {
+            panic!("expected a paragraph, got {:?}", children[1]);
+        };
+        assert!(runs[1].marks.strong);
+        assert_eq!(
+            (runs[3].text.as_str(), runs[3].language.as_str()),
+            ("une phrase", "fr-FR")
+        );
+        let Node::Paragraph { runs, .. } = &children[2] else {
+            panic!("expected a paragraph, got {:?}", children[2]);
+        };
+        assert_eq!(runs[0].language, "ar-EG");
+        assert_eq!(runs[0].direction, Direction::RTL);
+        assert!(matches!(&children[3], Node::Paragraph { style, .. } if style == "pull-quote"));
+
+        let Node::List {
+            ordered: true,
+            start: 3,
+            items,
+            ..
+        } = &children[4]
+        else {
+            panic!("expected a numbered list from 3, got {:?}", children[4]);
+        };
+        assert_eq!(text(&items[1].content), "Fourth step");
+        assert!(matches!(
+            &items[1].children[..],
+            [Node::List { ordered: false, items, .. }] if text(&items[0].content) == "Detail"
+        ));
+
+        let Node::Image {
+            path,
+            alt_text,
+            width,
+            height,
+        } = &children[5]
+        else {
+            panic!("expected an image, got {:?}", children[5]);
+        };
+        assert_eq!(alt_text, "Swatch");
+        assert_eq!((*width, *height), (Some(48.0), Some(24.0)));
+        let picture = ::image::load_from_memory(&document.resources.files[path]).unwrap();
+        assert_eq!((picture.width(), picture.height()), (4, 2));
+
+        let Node::Table {
+            rows,
+            header_rows: 1,
+            ..
+        } = &children[6]
+        else {
+            panic!("expected a table with a header row, got {:?}", children[6]);
+        };
+        assert_eq!(rows[1].cells[0].content[0].direction, Direction::RTL);
+        assert_eq!(text(&rows[1].cells[1].content), "book\nvolume");
+    }
+
+    #[test]
+    fn exported_fixture_imports_back_to_the_same_document() {
+        let first = import_odt(BILINGUAL, "fallback").unwrap();
+        let second = import_odt(&export_as_odt(&first).unwrap(), "fallback").unwrap();
+
+        assert_eq!(second.content, first.content);
+        // The export writes a `list` style for list items, which resolves
+        // like the paragraph fallback the first import relied on
+        let names: HashSet<&String> = first
+            .styles
+            .styles
+            .keys()
+            .chain(second.styles.styles.keys())
+            .collect();
+        for name in names {
+            assert_eq!(
+                second.styles.resolve(name),
+                first.styles.resolve(name),
+                "{}",
+                name
+            );
+        }
+        assert_eq!(second.resources.files, first.resources.files);
+        assert_eq!(second.metadata.title, first.metadata.title);
+        assert_eq!(second.metadata.author, first.metadata.author);
+        assert_eq!(second.metadata.language, first.metadata.language);
+        assert_eq!(second.metadata.keywords, first.metadata.keywords);
+    }
+}
@@ -0,0 +1,1649 @@
+use crate::data::{
+    Direction, InlineMarks, ListItem, Node, TableCell, TableRow, TextAlign, TextRun,
+};
+use crate::pdx_text::{detect_language, dominant_script, list_marker, Script};
+use std::fmt;
+
+/// Body of a block that has no content, such as a paragraph without runs or
+/// a table without rows. A `block` attribute names the kind when it is not a
+/// paragraph.
+const EMPTY_BLOCK: &str = "\\";
+
+/// Headings deeper than this are written and read as this level.
+const MAX_HEADING_LEVEL: u8 = 6;
+
+// ============================================================================
+// Content Serialization
+// ============================================================================
+
+/// Writes a node tree as editor markup. Whatever the plain syntax cannot
+/// express (custom style names, run languages, image sizes, ...) is written
+/// as `{key=value}` attributes, so `parse_content` gives back the same tree.
+pub fn serialize_content(node: &Node) -> String {
+    match node {
+        Node::Document { children } => children
+            .iter()
+            .map(serialize_block)
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+
+        _ => serialize_block(node),
+    }
+}
+
+/// Writes one block in its plain form when that parses back to the same
+/// node, and in the explicit form (every run as an attributed span)
+/// otherwise.
+fn serialize_block(node: &Node) -> String {
+    if let Node::Heading { level, runs, style } = node
+        && !(1..=MAX_HEADING_LEVEL).contains(level)
+    {
+        return serialize_block(&Node::Heading {
+            level: (*level).clamp(1, MAX_HEADING_LEVEL),
+            runs: runs.clone(),
+            style: style.clone(),
+        });
+    }
+
+    let plain = write_block(node, false);
+    let lines: Vec<&str> = plain.lines().collect();
+
+    match parse_blocks(&lines).as_slice() {
+        [parsed] if parsed == node => plain,
+        _ => write_block(node, true),
+    }
+}
+
+fn write_block(node: &Node, explicit: bool) -> String {
+    let mut attrs = Attributes::default();
+
+    let body = match node {
+        Node::Document { .. } => return serialize_content(node),
+
+        Node::Heading { level, runs, style } => {
+            let detected = detect_language(&serialize_runs(runs));
+            let language = shared_language(runs).unwrap_or(detected);
+            let body = write_runs(runs, Some(language), style, explicit);
+
+            attrs.set_unless("style", style, &format!("heading{}", level));
+            attrs.set_unless("lang", language, detected);
+            format!("{} {}", "#".repeat(*level as usize), body)
+        }
+
+        Node::Paragraph { runs, style } if runs.is_empty() => {
+            attrs.set_unless("style", style, "paragraph");
+            EMPTY_BLOCK.to_string()
+        }
+
+        Node::Paragraph { runs, style } => {
+            let detected = detect_language(&serialize_runs(runs));
+            let language = shared_language(runs).unwrap_or(detected);
+            let body = write_runs(runs, Some(language), style, explicit);
+
+            attrs.set_unless("style", style, paragraph_style(&body));
+            attrs.set_unless("lang", language, detected);
+            body
+        }
+
+        Node::List {
+            ordered,
+            start,
+            items,
+            style,
+        } => {
+            attrs.set_unless("style", style, "list");
+            if !*ordered || items.is_empty() {
+                attrs.set_unless("start", &start.to_string(), "1");
+            }
+            if items.is_empty() {
+                let kind = if *ordered { "ordered-list" } else { "list" };
+                attrs.set("block", kind);
+                return format!("{}\n{}", attrs, EMPTY_BLOCK);
+            }
+
+            items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| {
+                    let marker = if *ordered {
+                        let number = start.saturating_add(i as u32);
+                        list_marker(true, number, 0, item.language(), false)
+                    } else {
+                        "-".to_string()
+                    };
+                    let text = write_runs(&item.content, None, "paragraph", explicit);
+                    let mut out = format!("{} {}", marker, text);
+
+                    // Nested blocks follow on indented lines
+                    for (n, child) in item.children.iter().enumerate() {
+                        if n > 0 {
+                            out.push('\n');
+                        }
+                        for line in serialize_block(child).lines() {
+                            out.push('\n');
+                            if !line.is_empty() {
+                                out.push_str("  ");
+                                out.push_str(line);
+                            }
+                        }
+                    }
+                    out
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+
+        Node::CodeBlock {
+            language,
+            code,
+            style,
+        } => {
+            attrs.set_unless("style", style, "code");
+            let info = if explicit || language.is_empty() {
+                attrs.set_unless("language", language, "text");
+                ""
+            } else {
+                language.as_str()
+            };
+
+            // The fence must be longer than any backtick line in the code
+            let longest = code
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && line.chars().all(|c| c == '`'))
+                .map(str::len)
+                .max()
+                .unwrap_or(0);
+            let fence = "`".repeat(longest.max(2) + 1);
+
+            format!("{0}{1}\n{2}\n{0}", fence, info, code)
+        }
+
+        Node::Image {
+            path,
+            alt_text,
+            width,
+            height,
+        } => {
+            if let Some(width) = width {
+                attrs.set("width", &width.to_string());
+            }
+            if let Some(height) = height {
+                attrs.set("height", &height.to_string());
+            }
+
+            if explicit {
+                attrs.set("alt", alt_text);
+                attrs.set("src", path);
+                "![]()".to_string()
+            } else {
+                format!("![{}]({})", alt_text, path)
+            }
+        }
+
+        Node::Table {
+            rows,
+            header_rows,
+            alignments,
+            direction,
+            style,
+        } => {
+            let columns = rows.iter().map(|r| r.cells.len()).max().unwrap_or(0);
+            let mut lines: Vec<String> = rows
+                .iter()
+                .map(|row| {
+                    let cells: Vec<String> = row
+                        .cells
+                        .iter()
+                        .map(|cell| {
+                            write_runs(&cell.content, None, "paragraph", explicit)
+                                .replace('|', "\\|")
+                        })
+                        .collect();
+                    format!("| {} |", cells.join(" | "))
+                })
+                .collect();
+
+            // The delimiter row marks where headers end; it is only needed
+            // when there are headers or non-default alignments to record
+            let aligned = alignments.iter().any(|a| !matches!(a, TextAlign::Start));
+            let mut parsed_alignments = Vec::new();
+            if (*header_rows > 0 || aligned) && columns > 0 {
+                parsed_alignments = (0..columns)
+                    .map(|c| match alignments.get(c) {
+                        Some(TextAlign::Center) => TextAlign::Center,
+                        Some(TextAlign::End) => TextAlign::End,
+                        _ => TextAlign::Start,
+                    })
+                    .collect();
+                let delimiters: Vec<&str> = parsed_alignments
+                    .iter()
+                    .map(|align| match align {
+                        TextAlign::Center => ":-:",
+                        TextAlign::End => "--:",
+                        _ => "---",
+                    })
+                    .collect();
+                let at = (*header_rows).min(lines.len());
+                lines.insert(at, format!("| {} |", delimiters.join(" | ")));
+            }
+
+            let is_rtl = rows.iter().flat_map(|r| &r.cells).any(|cell| {
+                cell.content
+                    .iter()
+                    .any(|run| run.direction == Direction::RTL)
+            });
+            let parsed_direction = if is_rtl {
+                Direction::RTL
+            } else {
+                Direction::LTR
+            };
+
+            attrs.set_unless("style", style, "table");
+            if *direction != parsed_direction {
+                attrs.set("dir", direction_name(*direction));
+            }
+            if parsed_alignments != *alignments {
+                let names: Vec<&str> = alignments.iter().map(|a| align_name(*a)).collect();
+                attrs.set("align", &names.join(","));
+            }
+            if *header_rows > rows.len() {
+                attrs.set("header-rows", &header_rows.to_string());
+            }
+            if rows.is_empty() {
+                attrs.set("block", "table");
+                return format!("{}\n{}", attrs, EMPTY_BLOCK);
+            }
+
+            lines.join("\n")
+        }
+
+        Node::Divider => "---".to_string(),
+        Node::PageBreak => "===".to_string(),
+    };
+
+    if attrs.is_empty() {
+        body
+    } else {
+        format!("{}\n{}", attrs, body)
+    }
+}
+
+/// Writes block text. The plain form is used when `parse_inline` rebuilds
+/// the same runs from it; otherwise every run becomes a `[text]{lang=..}`
+/// span. `language` defaults to the one detected from the text.
+fn write_runs(runs: &[TextRun], language: Option<&str>, style: &str, explicit: bool) -> String {
+    let plain = serialize_runs(runs);
+    let language = language.unwrap_or_else(|| detect_language(&plain));
+
+    if !explicit && plain == plain.trim() && parse_inline(&plain, language, style) == runs {
+        return plain;
+    }
+
+    runs.iter()
+        .map(|run| {
+            let mut attrs = Attributes::default();
+            attrs.set("lang", &run.language);
+            if run.direction != TextRun::new("", &run.language, "").direction {
+                attrs.set("dir", direction_name(run.direction));
+            }
+            attrs.set_unless("style", &run.style, style);
+
+            format!("[{}]{}", serialize_runs(std::slice::from_ref(run)), attrs)
+        })
+        .collect()
+}
+
+fn shared_language(runs: &[TextRun]) -> Option<&str> {
+    let first = runs.first()?;
+    runs.iter()
+        .all(|run| run.language == first.language)
+        .then_some(first.language.as_str())
+}
+
+pub fn parse_content(text: &str) -> Node {
+    let lines: Vec<&str> = text.lines().collect();
+
+    Node::Document {
+        children: parse_blocks(&lines),
+    }
+}
+
+fn parse_blocks(lines: &[&str]) -> Vec<Node> {
+    parse_spanned(lines)
+        .into_iter()
+        .map(|(_, node)| node)
+        .collect()
+}
+
+/// Source lines of a top-level block.
+struct BlockSpan {
+    /// The attribute line before the block, if any
+    attributes: Option<usize>,
+    start: usize,
+    /// One past the last line
+    end: usize,
+}
+
+fn parse_spanned(lines: &[&str]) -> Vec<(BlockSpan, Node)> {
+    let mut children = Vec::new();
+    let mut spans = Vec::new();
+    let mut pending = Attributes::default();
+    let mut pending_line = None;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i].trim();
+
+        if line.is_empty() {
+            i += 1;
+            continue;
+        }
+
+        // An attribute line applies to the block that follows it
+        if let Some(parsed) = Attributes::parse_line(line) {
+            pending = parsed;
+            pending_line = Some(i);
+            i += 1;
+            continue;
+        }
+        let attrs = std::mem::take(&mut pending);
+        let start = i;
+
+        // Image syntax: ![alt text](path)
+        if line.starts_with("![")
+            && line.ends_with(')')
+            && let Some(alt_end) = line.find("](")
+        {
+            let attr_f32 = |key| attrs.get(key).and_then(|v| v.parse::<f32>().ok());
+
+            children.push(Node::Image {
+                path: attrs
+                    .get("src")
+                    .unwrap_or(&line[alt_end + 2..line.len() - 1])
+                    .to_string(),
+                alt_text: attrs.get("alt").unwrap_or(&line[2..alt_end]).to_string(),
+                width: attr_f32("width"),
+                height: attr_f32("height"),
+            });
+        } else if line.starts_with('#') {
+            let hashes = line.chars().take_while(|&c| c == '#').count();
+            let text = line[hashes..].trim();
+            let level = hashes.min(MAX_HEADING_LEVEL as usize);
+            let default_style = format!("heading{}", level);
+            let style = attrs.get("style").unwrap_or(&default_style);
+
+            children.push(Node::Heading {
+                level: level as u8,
+                runs: parse_inline(
+                    text,
+                    attrs.get("lang").unwrap_or(detect_language(text)),
+                    style,
+                ),
+                style: style.to_string(),
+            });
+        } else if line.starts_with("```") {
+            let fence = line.chars().take_while(|&c| c == '`').count();
+            let info = line[fence..].trim();
+            let mut code_lines = Vec::new();
+            i += 1;
+
+            while i < lines.len() && !is_closing_fence(lines[i], fence) {
+                code_lines.push(lines[i]);
+                i += 1;
+            }
+
+            let language = match attrs.get("language") {
+                Some(language) => language,
+                None if info.is_empty() => "text",
+                None => info,
+            };
+
+            children.push(Node::CodeBlock {
+                language: language.to_string(),
+                code: code_lines.join("\n"),
+                style: attrs.get("style").unwrap_or("code").to_string(),
+            });
+        } else if line.starts_with('|') {
+            let mut rows = Vec::new();
+            let mut header_rows = 0;
+            let mut alignments = Vec::new();
+
+            while i < lines.len() && lines[i].trim().starts_with('|') {
+                let cells = split_table_row(lines[i].trim());
+
+                if let Some(parsed) = parse_delimiter_row(&cells) {
+                    header_rows = rows.len();
+                    alignments = parsed;
+                } else {
+                    rows.push(TableRow {
+                        cells: cells
+                            .iter()
+                            .map(|text| TableCell {
+                                content: parse_inline(text, detect_language(text), "paragraph"),
+                            })
+                            .collect(),
+                    });
+                }
+                i += 1;
+            }
+
+            children.push(table_node(rows, header_rows, alignments, &attrs));
+            i -= 1;
+        } else if let Some(first) = parse_list_line(lines[i]) {
+            let list = parse_list(lines, &mut i, first);
+            children.push(with_list_attributes(list, &attrs));
+            i -= 1;
+        } else if line == EMPTY_BLOCK {
+            children.push(match attrs.get("block") {
+                Some("table") => table_node(Vec::new(), 0, Vec::new(), &attrs),
+                Some(kind @ ("list" | "ordered-list")) => {
+                    let list = Node::List {
+                        ordered: kind == "ordered-list",
+                        start: 1,
+                        items: Vec::new(),
+                        style: "list".to_string(),
+                    };
+                    with_list_attributes(list, &attrs)
+                }
+                _ => Node::Paragraph {
+                    runs: Vec::new(),
+                    style: attrs.get("style").unwrap_or("paragraph").to_string(),
+                },
+            });
+        } else if line == "---" {
+            children.push(Node::Divider);
+        } else if line == "===" {
+            children.push(Node::PageBreak);
+        } else {
+            let style = attrs.get("style").unwrap_or(paragraph_style(line));
+
+            children.push(Node::Paragraph {
+                runs: parse_inline(
+                    line,
+                    attrs.get("lang").unwrap_or(detect_language(line)),
+                    style,
+                ),
+                style: style.to_string(),
+            });
+        }
+
+        i += 1;
+        spans.push(BlockSpan {
+            attributes: pending_line.take(),
+            start,
+            end: i,
+        });
+    }
+
+    spans.into_iter().zip(children).collect()
+}
+
+/// Builds a table from its parsed rows, letting attributes override what
+/// the pipe syntax implies.
+fn table_node(
+    rows: Vec<TableRow>,
+    mut header_rows: usize,
+    mut alignments: Vec<TextAlign>,
+    attrs: &Attributes,
+) -> Node {
+    let is_rtl = rows.iter().flat_map(|r| &r.cells).any(|cell| {
+        cell.content
+            .iter()
+            .any(|run| run.direction == Direction::RTL)
+    });
+
+    if let Some(names) = attrs.get("align") {
+        alignments = names
+            .split(',')
+            .filter(|name| !name.is_empty())
+            .map(parse_align)
+            .collect();
+    }
+    if let Some(count) = attrs.get("header-rows").and_then(|v| v.parse().ok()) {
+        header_rows = count;
+    }
+
+    Node::Table {
+        rows,
+        header_rows,
+        alignments,
+        direction: match attrs.get("dir") {
+            Some(name) => parse_direction(name),
+            None if is_rtl => Direction::RTL,
+            None => Direction::LTR,
+        },
+        style: attrs.get("style").unwrap_or("table").to_string(),
+    }
+}
+
+fn with_list_attributes(mut list: Node, attrs: &Attributes) -> Node {
+    if let Node::List { start, style, .. } = &mut list {
+        if let Some(value) = attrs.get("start").and_then(|v| v.parse().ok()) {
+            *start = value;
+        }
+        if let Some(value) = attrs.get("style") {
+            *style = value.to_string();
+        }
+    }
+    list
+}
+
+/// Style of the top-level block on `line` (0-based) of the editor text.
+/// `None` when the line is blank or the block has no style, like images.
+pub fn block_style_at(text: &str, line: usize) -> Option<String> {
+    let lines: Vec<&str> = text.lines().collect();
+    let (_, node) = parse_spanned(&lines)
+        .into_iter()
+        .find(|(span, _)| span.attributes.unwrap_or(span.start) <= line && line < span.end)?;
+
+    node_style(&node).map(str::to_string)
+}
+
+/// Gives the top-level block on `line` the named style by writing it into
+/// the block's attribute line, adding one when needed. Returns the new text,
+/// or `None` when there is no styleable block on that line.
+pub fn set_block_style(text: &str, line: usize, style: &str) -> Option<String> {
+    let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+    let borrowed: Vec<&str> = lines.iter().map(String::as_str).collect();
+    let (span, node) = parse_spanned(&borrowed)
+        .into_iter()
+        .find(|(span, _)| span.attributes.unwrap_or(span.start) <= line && line < span.end)?;
+    node_style(&node)?;
+
+    let indent: String = lines[span.start]
+        .chars()
+        .take_while(|c| c.is_whitespace())
+        .collect();
+
+    match span.attributes {
+        Some(at) => {
+            let mut attrs = Attributes::parse_line(lines[at].trim()).unwrap_or_default();
+            attrs.0.retain(|(key, _)| key != "style");
+            attrs.set("style", style);
+            lines[at] = format!("{}{}", indent, attrs);
+        }
+        None => {
+            let mut attrs = Attributes::default();
+            attrs.set("style", style);
+            lines.insert(span.start, format!("{}{}", indent, attrs));
+        }
+    }
+
+    let mut out = lines.join("\n");
+    if text.ends_with('\n') {
+        out.push('\n');
+    }
+    Some(out)
+}
+
+fn node_style(node: &Node) -> Option<&str> {
+    match node {
+        Node::Heading { style, .. }
+        | Node::Paragraph { style, .. }
+        | Node::List { style, .. }
+        | Node::CodeBlock { style, .. }
+        | Node::Table { style, .. } => Some(style),
+        _ => None,
+    }
+}
+
+fn is_closing_fence(line: &str, fence: usize) -> bool {
+    let line = line.trim();
+    line.len() >= fence && line.chars().all(|c| c == '`')
+}
+
+/// Style a paragraph gets when no `style` attribute is given.
+pub(crate) fn paragraph_style(text: &str) -> &'static str {
+    if dominant_script(text) == Some(Script::Arabic) {
+        "arabic"
+    } else {
+        "paragraph"
+    }
+}
+
+// ============================================================================
+// Attributes
+// ============================================================================
+
+/// A `{key=value key="quoted value"}` block. On a line of its own it applies
+/// to the next block; directly after `[text]` it applies to that span.
+#[derive(Debug, Default)]
+struct Attributes(Vec<(String, String)>);
+
+impl Attributes {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .rev()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn set(&mut self, key: &str, value: &str) {
+        self.0.push((key.to_string(), value.to_string()));
+    }
+
+    /// Records `value` only when it differs from what the parser would infer.
+    fn set_unless(&mut self, key: &str, value: &str, inferred: &str) {
+        if value != inferred {
+            self.set(key, value);
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Parses an attribute block at the start of `text`, returning it and the
+    /// number of bytes consumed.
+    fn parse_prefix(text: &str) -> Option<(Self, usize)> {
+        let mut chars = text.char_indices().peekable();
+        if chars.next()?.1 != '{' {
+            return None;
+        }
+
+        let mut attrs = Self::default();
+        loop {
+            while chars.next_if(|(_, c)| *c == ' ').is_some() {}
+
+            let (index, c) = chars.next()?;
+            if c == '}' {
+                return Some((attrs, index + 1));
+            }
+            if !is_key_char(c) {
+                return None;
+            }
+
+            let mut key = c.to_string();
+            while let Some((_, c)) = chars.next_if(|(_, c)| is_key_char(*c)) {
+                key.push(c);
+            }
+            if chars.next()?.1 != '=' {
+                return None;
+            }
+
+            let mut value = String::new();
+            if chars.next_if(|(_, c)| *c == '"').is_some() {
+                loop {
+                    match chars.next()?.1 {
+                        '"' => break,
+                        '\\' => match chars.next()?.1 {
+                            'n' => value.push('\n'),
+                            'r' => value.push('\r'),
+                            other => value.push(other),
+                        },
+                        other => value.push(other),
+                    }
+                }
+            } else {
+                while let Some((_, c)) = chars.next_if(|(_, c)| !matches!(c, ' ' | '}')) {
+                    value.push(c);
+                }
+            }
+
+            attrs.0.push((key, value));
+        }
+    }
+
+    /// Parses a line that consists of nothing but an attribute block.
+    fn parse_line(line: &str) -> Option<Self> {
+        let (attrs, consumed) = Self::parse_prefix(line)?;
+        (consumed == line.len()).then_some(attrs)
+    }
+}
+
+impl fmt::Display for Attributes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{{")?;
+
+        for (i, (key, value)) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+
+            let bare = !value.is_empty()
+                && !value
+                    .chars()
+                    .any(|c| c.is_whitespace() || matches!(c, '"' | '\\' | '{' | '}'));
+            if bare {
+                write!(f, "{}={}", key, value)?;
+            } else {
+                let quoted = value
+                    .replace('\\', "\\\\")
+                    .replace('"', "\\\"")
+                    .replace('\n', "\\n")
+                    .replace('\r', "\\r");
+                write!(f, "{}=\"{}\"", key, quoted)?;
+            }
+        }
+
+        write!(f, "}}")
+    }
+}
+
+fn is_key_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-'
+}
+
+fn direction_name(direction: Direction) -> &'static str {
+    match direction {
+        Direction::LTR => "ltr",
+        Direction::RTL => "rtl",
+        Direction::Auto => "auto",
+    }
+}
+
+fn parse_direction(name: &str) -> Direction {
+    match name {
+        "rtl" => Direction::RTL,
+        "ltr" => Direction::LTR,
+        _ => Direction::Auto,
+    }
+}
+
+fn align_name(align: TextAlign) -> &'static str {
+    match align {
+        TextAlign::Start => "start",
+        TextAlign::End => "end",
+        TextAlign::Center => "center",
+        TextAlign::Justify => "justify",
+    }
+}
+
+fn parse_align(name: &str) -> TextAlign {
+    match name {
+        "end" => TextAlign::End,
+        "center" => TextAlign::Center,
+        "justify" => TextAlign::Justify,
+        _ => TextAlign::Start,
+    }
+}
+
+// ============================================================================
+// Lists
+// ============================================================================
+
+/// A list line split into its indentation width, marker and text.
+struct ListLine<'a> {
+    indent: usize,
+    ordered: bool,
+    number: u32,
+    text: &'a str,
+}
+
+/// Recognises `- item`, `• item`, `1. item` and `1) item`. Numbers may use
+/// ASCII, Arabic-Indic or Extended Arabic-Indic digits.
+fn parse_list_line(line: &str) -> Option<ListLine<'_>> {
+    let indent = indent_width(line);
+    let trimmed = line.trim();
+
+    for bullet in ['-', '•'] {
+        if let Some(rest) = trimmed.strip_prefix(bullet)
+            && (rest.is_empty() || rest.starts_with(' '))
+        {
+            return Some(ListLine {
+                indent,
+                ordered: false,
+                number: 1,
+                text: rest.trim_start(),
+            });
+        }
+    }
+
+    let digits: String = trimmed
+        .chars()
+        .take_while(|c| digit_value(*c).is_some())
+        .collect();
+    let rest = trimmed[digits.len()..]
+        .strip_prefix('.')
+        .or_else(|| trimmed[digits.len()..].strip_prefix(')'))?;
+    if digits.is_empty() || !(rest.is_empty() || rest.starts_with(' ')) {
+        return None;
+    }
+
+    let number = digits
+        .chars()
+        .filter_map(digit_value)
+        .try_fold(0u32, |n, d| n.checked_mul(10)?.checked_add(d))?;
+
+    Some(ListLine {
+        indent,
+        ordered: true,
+        number,
+        text: rest.trim_start(),
+    })
+}
+
+fn digit_value(c: char) -> Option<u32> {
+    match c {
+        '0'..='9' => c.to_digit(10),
+        '\u{0660}'..='\u{0669}' => Some(c as u32 - 0x0660),
+        '\u{06F0}'..='\u{06F9}' => Some(c as u32 - 0x06F0),
+        _ => None,
+    }
+}
+
+/// Width of the leading whitespace, counting a tab as four columns.
+fn indent_width(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+/// Parses the list starting at `lines[*i]`, leaving `i` on the first line
+/// after it. Lines indented deeper than an item's marker (blank lines
+/// between them included) are parsed as blocks nested in that item. A blank
+/// line before a sibling, a shallower line or a change between ordered and
+/// unordered ends the list.
+fn parse_list(lines: &[&str], i: &mut usize, first: ListLine) -> Node {
+    let mut items: Vec<ListItem> = Vec::new();
+
+    while *i < lines.len() {
+        let Some(line) = parse_list_line(lines[*i]) else {
+            break;
+        };
+        if line.indent != first.indent || line.ordered != first.ordered {
+            break;
+        }
+        *i += 1;
+
+        let mut end = *i;
+        let mut body_end = *i;
+        while end < lines.len() {
+            if !lines[end].trim().is_empty() {
+                if indent_width(lines[end]) <= first.indent {
+                    break;
+                }
+                body_end = end + 1;
+            }
+            end += 1;
+        }
+
+        let body = &lines[*i..body_end];
+        let dedent = body
+            .iter()
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| l.chars().take_while(|c| c.is_whitespace()).count())
+            .min()
+            .unwrap_or(0);
+        let body: Vec<&str> = body
+            .iter()
+            .map(|l| {
+                let skip: usize = l
+                    .chars()
+                    .take(dedent)
+                    .take_while(|c| c.is_whitespace())
+                    .map(char::len_utf8)
+                    .sum();
+                &l[skip..]
+            })
+            .collect();
+        *i = body_end;
+
+        items.push(ListItem {
+            content: parse_inline(line.text, detect_language(line.text), "paragraph"),
+            children: parse_blocks(&body),
+        });
+    }
+
+    Node::List {
+        ordered: first.ordered,
+        start: first.number,
+        items,
+        style: "list".to_string(),
+    }
+}
+
+// ============================================================================
+// Tables
+// ============================================================================
+
+/// Splits `| a | b |` into trimmed cells. `\|` is a literal pipe.
+fn split_table_row(line: &str) -> Vec<String> {
+    let inner = line.strip_prefix('|').unwrap_or(line);
+    let inner = match inner.strip_suffix('|') {
+        Some(stripped) if !stripped.ends_with('\\') => stripped,
+        _ => inner,
+    };
+
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut chars = inner.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '\\' && chars.peek() == Some(&'|') {
+            cell.push('|');
+            chars.next();
+        } else if c == '|' {
+            cells.push(cell.trim().to_string());
+            cell.clear();
+        } else {
+            cell.push(c);
+        }
+    }
+    cells.push(cell.trim().to_string());
+
+    cells
+}
+
+/// Recognises `| --- | :-: | --: |` and returns the column alignments.
+fn parse_delimiter_row(cells: &[String]) -> Option<Vec<TextAlign>> {
+    cells
+        .iter()
+        .map(|cell| {
+            let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+            if dashes.is_empty() || !dashes.chars().all(|c| c == '-') {
+                return None;
+            }
+
+            Some(match (cell.starts_with(':'), cell.ends_with(':')) {
+                (true, true) => TextAlign::Center,
+                (false, true) => TextAlign::End,
+                _ => TextAlign::Start,
+            })
+        })
+        .collect()
+}
+
+// ============================================================================
+// Inline Formatting
+// ============================================================================
+
+/// Markers that toggle a mark on and off, longest first so `**` wins over `*`.
+const TOGGLE_MARKERS: [&str; 4] = ["**", "~~", "++", "*"];
+
+fn mark_flag<'a>(marks: &'a mut InlineMarks, marker: &str) -> &'a mut bool {
+    match marker {
+        "**" => &mut marks.strong,
+        "~~" => &mut marks.strikethrough,
+        "++" => &mut marks.underline,
+        _ => &mut marks.emphasis,
+    }
+}
+
+fn is_markup_char(c: char) -> bool {
+    matches!(c, '\\' | '*' | '`' | '~' | '+' | '[' | ']' | '&')
+}
+
+/// Splits `**strong**`, `*emphasis*`, `` `code` ``, `~~strike~~`,
+/// `++underline++` and `[text](url)` into runs. A marker only opens when a
+/// matching one follows, so a lone `*` stays literal. `[text]{lang=fa}`
+/// spans override the language, direction or style of their text, and
+/// `&#10;` stands for a character that cannot appear literally.
+pub fn parse_inline(text: &str, language: &str, style: &str) -> Vec<TextRun> {
+    let mut runs = Vec::new();
+    parse_inline_into(text, language, style, &InlineMarks::default(), &mut runs);
+    runs
+}
+
+fn parse_inline_into(
+    text: &str,
+    language: &str,
+    style: &str,
+    base: &InlineMarks,
+    runs: &mut Vec<TextRun>,
+) {
+    let mut marks = base.clone();
+    let mut current = String::new();
+    let mut rest = text;
+
+    let flush = |current: &mut String, marks: &InlineMarks, runs: &mut Vec<TextRun>| {
+        if !current.is_empty() {
+            runs.push(TextRun::new(current, language, style).with_marks(marks.clone()));
+            current.clear();
+        }
+    };
+
+    while let Some(c) = rest.chars().next() {
+        if c == '\\'
+            && let Some(next) = rest[1..].chars().next().filter(|n| is_markup_char(*n))
+        {
+            current.push(next);
+            rest = &rest[1 + next.len_utf8()..];
+            continue;
+        }
+
+        if c == '&'
+            && let Some((decoded, consumed)) = parse_char_reference(rest)
+        {
+            current.push(decoded);
+            rest = &rest[consumed..];
+            continue;
+        }
+
+        // Code spans are literal up to the closing backtick
+        if marks.code {
+            if c == '`' {
+                flush(&mut current, &marks, runs);
+                marks.code = false;
+            } else {
+                current.push(c);
+            }
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+
+        if c == '`' && rest[1..].contains('`') {
+            flush(&mut current, &marks, runs);
+            marks.code = true;
+            rest = &rest[1..];
+            continue;
+        }
+
+        if c == '['
+            && let Some(close) = closing_bracket(rest)
+        {
+            let label = &rest[1..close];
+            let after = &rest[close + 1..];
+
+            if let Some((url, consumed)) = parse_link_target(after) {
+                flush(&mut current, &marks, runs);
+                let mut link_marks = marks.clone();
+                link_marks.link = Some(url);
+                parse_inline_into(label, language, style, &link_marks, runs);
+                rest = &after[consumed..];
+                continue;
+            }
+
+            if let Some((attrs, consumed)) = Attributes::parse_prefix(after) {
+                flush(&mut current, &marks, runs);
+                let span_language = attrs.get("lang").unwrap_or(language);
+                let span_style = attrs.get("style").unwrap_or(style);
+
+                let first = runs.len();
+                if label.is_empty() {
+                    runs.push(
+                        TextRun::new("", span_language, span_style).with_marks(marks.clone()),
+                    );
+                } else {
+                    parse_inline_into(label, span_language, span_style, &marks, runs);
+                }
+                if let Some(direction) = attrs.get("dir").map(parse_direction) {
+                    for run in &mut runs[first..] {
+                        run.direction = direction;
+                    }
+                }
+
+                rest = &after[consumed..];
+                continue;
+            }
+        }
+
+        if let Some(marker) = TOGGLE_MARKERS.iter().find(|m| rest.starts_with(**m)) {
+            let after = &rest[marker.len()..];
+            let active = *mark_flag(&mut marks, marker);
+
+            if active || after.contains(marker) {
+                flush(&mut current, &marks, runs);
+                *mark_flag(&mut marks, marker) = !active;
+                rest = after;
+                continue;
+            }
+        }
+
+        current.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+
+    flush(&mut current, &marks, runs);
+}
+
+/// Byte index of the `]` matching the `[` that starts `text`, skipping
+/// escaped characters and nested brackets.
+fn closing_bracket(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut chars = text.char_indices();
+
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(index);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Parses `(url)` at the start of `text`, returning the unescaped URL and
+/// the number of bytes consumed.
+fn parse_link_target(text: &str) -> Option<(String, usize)> {
+    let mut chars = text.char_indices();
+    if chars.next()?.1 != '(' {
+        return None;
+    }
+
+    let mut url = String::new();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\\' => url.push(chars.next()?.1),
+            ')' => return Some((url, index + 1)),
+            _ => url.push(c),
+        }
+    }
+
+    None
+}
+
+/// Decodes a decimal character reference such as `&#10;`.
+fn parse_char_reference(text: &str) -> Option<(char, usize)> {
+    let digits = text.strip_prefix("&#")?;
+    let end = digits.find(';')?;
+    let value = digits[..end].parse::<u32>().ok()?;
+
+    Some((char::from_u32(value)?, end + 3))
+}
+
+/// Inverse of `parse_inline`. Consecutive runs sharing a link are written as
+/// one `[...](url)` group.
+pub fn serialize_runs(runs: &[TextRun]) -> String {
+    let mut out = String::new();
+    let mut i = 0;
+
+    while i < runs.len() {
+        match &runs[i].marks.link {
+            Some(url) => {
+                let end = runs[i..]
+                    .iter()
+                    .position(|r| r.marks.link.as_ref() != Some(url))
+                    .map_or(runs.len(), |offset| i + offset);
+
+                out.push('[');
+                for run in &runs[i..end] {
+                    out.push_str(&serialize_marked(run));
+                }
+                out.push_str(&format!("]({})", escape_url(url)));
+                i = end;
+            }
+            None => {
+                out.push_str(&serialize_marked(&runs[i]));
+                i += 1;
+            }
+        }
+    }
+
+    out
+}
+
+fn serialize_marked(run: &TextRun) -> String {
+    let marks = &run.marks;
+    let mut open = String::new();
+
+    if marks.strong {
+        open.push_str("**");
+    }
+    if marks.emphasis {
+        open.push('*');
+    }
+    if marks.strikethrough {
+        open.push_str("~~");
+    }
+    if marks.underline {
+        open.push_str("++");
+    }
+
+    let close: String = open.chars().rev().collect();
+    let text = if marks.code {
+        format!("`{}`", escape_inline(&run.text))
+    } else {
+        escape_inline(&run.text)
+    };
+
+    format!("{}{}{}", open, text, close)
+}
+
+fn escape_inline(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let next = chars.peek().copied();
+        let needs_escape = match c {
+            '\\' | '*' | '`' | '[' | ']' => true,
+            // Doubled, or touching a neighbouring run's marker
+            '~' | '+' => next == Some(c) || next.is_none() || out.is_empty(),
+            '&' => next == Some('#'),
+            _ => false,
+        };
+
+        match c {
+            '\n' | '\r' => out.push_str(&format!("&#{};", c as u32)),
+            _ => {
+                if needs_escape {
+                    out.push('\\');
+                }
+                out.push(c);
+            }
+        }
+    }
+
+    out
+}
+
+fn escape_url(url: &str) -> String {
+    let mut out = String::with_capacity(url.len());
+
+    for c in url.chars() {
+        if matches!(c, '\\' | '(' | ')' | '[' | ']') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn marks(f: impl FnOnce(&mut InlineMarks)) -> InlineMarks {
+        let mut marks = InlineMarks::default();
+        f(&mut marks);
+        marks
+    }
+
+    fn texts_and_marks(runs: &[TextRun]) -> Vec<(&str, InlineMarks)> {
+        runs.iter()
+            .map(|run| (run.text.as_str(), run.marks.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn inline_markers_become_marks() {
+        let runs = parse_inline("**bold** *it* `a*b` ~~gone~~ ++under++", "en", "paragraph");
+
+        assert_eq!(
+            texts_and_marks(&runs),
+            vec![
+                ("bold", marks(|m| m.strong = true)),
+                (" ", InlineMarks::default()),
+                ("it", marks(|m| m.emphasis = true)),
+                (" ", InlineMarks::default()),
+                ("a*b", marks(|m| m.code = true)),
+                (" ", InlineMarks::default()),
+                ("gone", marks(|m| m.strikethrough = true)),
+                (" ", InlineMarks::default()),
+                ("under", marks(|m| m.underline = true)),
+            ]
+        );
+    }
+
+    #[test]
+    fn links_keep_marks_inside_the_label() {
+        let runs = parse_inline(
+            r"see [the **docs**](https://x.org/a_\(b\)).",
+            "en",
+            "paragraph",
+        );
+        let link = Some("https://x.org/a_(b)".to_string());
+
+        assert_eq!(
+            texts_and_marks(&runs),
+            vec![
+                ("see ", InlineMarks::default()),
+                ("the ", marks(|m| m.link = link.clone())),
+                (
+                    "docs",
+                    marks(|m| {
+                        m.strong = true;
+                        m.link = link.clone();
+                    })
+                ),
+                (".", InlineMarks::default()),
+            ]
+        );
+    }
+
+    #[test]
+    fn unmatched_and_escaped_markers_stay_literal() {
+        for (text, literal) in [
+            ("2 * 3 = 6", "2 * 3 = 6"),
+            (r"\*not\* emphasis", "*not* emphasis"),
+            ("a ` b", "a ` b"),
+        ] {
+            let runs = parse_inline(text, "en", "paragraph");
+            assert_eq!(runs.len(), 1, "{text}");
+            assert_eq!(runs[0].text, literal);
+            assert!(runs[0].marks.is_plain());
+        }
+    }
+
+    #[test]
+    fn serialized_runs_parse_back_to_the_same_runs() {
+        for text in [
+            "**bold** and *it*",
+            "`code with * star` then ~~strike~~",
+            "[**bold link**](https://example.com/\\(x\\))",
+            r"literal \*stars\* and \[brackets\]",
+            "مرحبا **بالعالم**",
+        ] {
+            let language = detect_language(text);
+            let runs = parse_inline(text, language, "paragraph");
+            assert_eq!(
+                parse_inline(&serialize_runs(&runs), language, "paragraph"),
+                runs,
+                "{text}"
+            );
+        }
+    }
+
+    fn blocks(text: &str) -> Vec<Node> {
+        match parse_content(text) {
+            Node::Document { children } => children,
+            other => vec![other],
+        }
+    }
+
+    /// The single list `text` parses to, as `(ordered, start, items)`.
+    fn only_list(text: &str) -> (bool, u32, Vec<ListItem>) {
+        match blocks(text).pop() {
+            Some(Node::List {
+                ordered,
+                start,
+                items,
+                ..
+            }) => (ordered, start, items),
+            other => panic!("expected a list for {text:?}, got {other:?}"),
+        }
+    }
+
+    fn item_texts(items: &[ListItem]) -> Vec<String> {
+        items
+            .iter()
+            .map(|item| serialize_runs(&item.content))
+            .collect()
+    }
+
+    #[test]
+    fn ordered_lists_keep_their_start_number() {
+        let (ordered, start, items) = only_list("3. three\n4) four");
+
+        assert!(ordered);
+        assert_eq!(start, 3);
+        assert_eq!(item_texts(&items), ["three", "four"]);
+    }
+
+    #[test]
+    fn localized_digits_are_read_as_numbers() {
+        for text in ["٥. خمسة", "۵. پنج"] {
+            let (ordered, start, _) = only_list(text);
+            assert!(ordered);
+            assert_eq!(start, 5);
+        }
+    }
+
+    #[test]
+    fn indented_items_nest_under_the_previous_item() {
+        let (ordered, _, items) = only_list("- fruit\n  1. apple\n  2. pear\n- vegetables");
+
+        assert!(!ordered);
+        assert_eq!(item_texts(&items), ["fruit", "vegetables"]);
+        let [Node::List {
+            ordered: true,
+            items: nested,
+            ..
+        }] = items[0].children.as_slice()
+        else {
+            panic!("expected a nested ordered list");
+        };
+        assert_eq!(item_texts(nested), ["apple", "pear"]);
+        assert!(items[1].children.is_empty());
+    }
+
+    #[test]
+    fn switching_between_ordered_and_bulleted_starts_a_new_list() {
+        let parsed = blocks("- a\n1. b");
+
+        assert_eq!(parsed.len(), 2);
+        assert!(matches!(parsed[0], Node::List { ordered: false, .. }));
+        assert!(matches!(parsed[1], Node::List { ordered: true, .. }));
+    }
+
+    #[test]
+    fn numbers_too_large_for_u32_are_not_list_markers() {
+        assert!(matches!(
+            blocks("99999999999. big").as_slice(),
+            [Node::Paragraph { .. }]
+        ));
+    }
+
+    #[test]
+    fn ordered_markers_saturate_at_the_largest_number() {
+        let item = ListItem {
+            content: vec![TextRun::new("x", "en", "paragraph")],
+            children: Vec::new(),
+        };
+        let list = Node::List {
+            ordered: true,
+            start: u32::MAX,
+            items: vec![item.clone(), item],
+            style: "list".to_string(),
+        };
+
+        assert_eq!(serialize_content(&list), "4294967295. x\n4294967295. x");
+    }
+
+    #[test]
+    fn empty_blocks_survive_a_round_trip() {
+        let document = Node::Document {
+            children: vec![
+                Node::Paragraph {
+                    runs: Vec::new(),
+                    style: "paragraph".to_string(),
+                },
+                Node::Heading {
+                    level: 2,
+                    runs: Vec::new(),
+                    style: "heading2".to_string(),
+                },
+                Node::Table {
+                    rows: Vec::new(),
+                    header_rows: 1,
+                    alignments: vec![TextAlign::Center],
+                    direction: Direction::RTL,
+                    style: "table".to_string(),
+                },
+                Node::List {
+                    ordered: true,
+                    start: 7,
+                    items: Vec::new(),
+                    style: "list".to_string(),
+                },
+                Node::List {
+                    ordered: false,
+                    start: 1,
+                    items: Vec::new(),
+                    style: "steps".to_string(),
+                },
+            ],
+        };
+
+        assert_eq!(parse_content(&serialize_content(&document)), document);
+    }
+
+    #[test]
+    fn heading_levels_are_clamped() {
+        let heading = |level| Node::Heading {
+            level,
+            runs: vec![TextRun::new("Title", "en", "heading1")],
+            style: "heading1".to_string(),
+        };
+
+        assert_eq!(serialize_content(&heading(0)), "# Title");
+        assert_eq!(
+            blocks("######## Deep"),
+            [Node::Heading {
+                level: 6,
+                runs: vec![TextRun::new("Deep", "en", "heading6")],
+                style: "heading6".to_string(),
+            }]
+        );
+    }
+
+    mod round_trip {
+        use super::*;
+        use proptest::prelude::*;
+
+        fn text() -> impl Strategy<Value = String> {
+            prop_oneof![
+                "[a-z0-9 ]{0,10}",
+                r"[a-z *_`~+#|&{}=:.()\[\]\\-]{0,10}",
+                "[ا-ي ]{1,8}",
+                "[א-ת ]{1,6}",
+            ]
+        }
+
+        fn direction() -> impl Strategy<Value = Direction> {
+            prop_oneof![
+                Just(Direction::LTR),
+                Just(Direction::RTL),
+                Just(Direction::Auto)
+            ]
+        }
+
+        fn align() -> impl Strategy<Value = TextAlign> {
+            prop_oneof![
+                Just(TextAlign::Start),
+                Just(TextAlign::End),
+                Just(TextAlign::Center),
+                Just(TextAlign::Justify)
+            ]
+        }
+
+        fn style(default: &'static str) -> impl Strategy<Value = String> {
+            prop::sample::select(vec![default, "custom", "two words"]).prop_map(str::to_string)
+        }
+
+        fn marks() -> impl Strategy<Value = InlineMarks> {
+            (
+                any::<[bool; 5]>(),
+                prop::option::of("https://[a-z]{1,6}\\.org/[a-z()]{0,4}"),
+            )
+                .prop_map(
+                    |([strong, emphasis, code, strikethrough, underline], link)| InlineMarks {
+                        strong,
+                        emphasis,
+                        code,
+                        strikethrough,
+                        underline,
+                        link,
+                    },
+                )
+        }
+
+        fn runs() -> impl Strategy<Value = Vec<TextRun>> {
+            let run = (
+                text(),
+                prop::sample::select(vec!["en", "ar", "fa", "he"]),
+                direction(),
+                style("paragraph"),
+                marks(),
+            )
+                .prop_map(|(text, language, direction, style, marks)| TextRun {
+                    // Empty runs only come from `[]{..}` spans, which carry no marks
+                    marks: if text.is_empty() {
+                        InlineMarks::default()
+                    } else {
+                        marks
+                    },
+                    text,
+                    language: language.to_string(),
+                    direction,
+                    style,
+                });
+            prop::collection::vec(run, 0..4)
+        }
+
+        fn leaf() -> impl Strategy<Value = Node> {
+            let cell = runs().prop_map(|content| TableCell { content });
+            let row = prop::collection::vec(cell, 1..4).prop_map(|cells| TableRow { cells });
+
+            prop_oneof![
+                (1..=MAX_HEADING_LEVEL, runs(), style("heading1"))
+                    .prop_map(|(level, runs, style)| Node::Heading { level, runs, style }),
+                (runs(), style("paragraph"))
+                    .prop_map(|(runs, style)| Node::Paragraph { runs, style }),
+                ("[a-z]{0,6}", "[a-z`{}\\\\ \n]{0,20}", style("code")).prop_map(
+                    |(language, code, style)| Node::CodeBlock {
+                        language,
+                        code,
+                        style
+                    }
+                ),
+                (
+                    "[a-z/._ ()]{1,12}",
+                    text(),
+                    prop::option::of(1u16..2000),
+                    prop::option::of(1u16..2000),
+                )
+                    .prop_map(|(path, alt_text, width, height)| Node::Image {
+                        path,
+                        alt_text,
+                        width: width.map(|w| f32::from(w) / 4.0),
+                        height: height.map(|h| f32::from(h) / 4.0),
+                    }),
+                (
+                    prop::collection::vec(row, 0..4),
+                    0usize..5,
+                    prop::collection::vec(align(), 0..4),
+                    direction(),
+                    style("table"),
+                )
+                    .prop_map(
+                        |(rows, header_rows, alignments, direction, style)| {
+                            Node::Table {
+                                rows,
+                                header_rows,
+                                alignments,
+                                direction,
+                                style,
+                            }
+                        }
+                    ),
+                Just(Node::Divider),
+                Just(Node::PageBreak),
+            ]
+        }
+
+        fn block() -> impl Strategy<Value = Node> {
+            leaf().prop_recursive(3, 24, 4, |inner| {
+                let item = (runs(), prop::collection::vec(inner, 0..3))
+                    .prop_map(|(content, children)| ListItem { content, children });
+                (
+                    any::<bool>(),
+                    0u32..20,
+                    prop::collection::vec(item, 0..4),
+                    style("list"),
+                )
+                    .prop_map(|(ordered, start, items, style)| Node::List {
+                        ordered,
+                        start,
+                        items,
+                        style,
+                    })
+            })
+        }
+
+        proptest! {
+            #[test]
+            fn content_survives_serialize_then_parse(
+                children in prop::collection::vec(block(), 0..6)
+            ) {
+                let document = Node::Document { children };
+                let text = serialize_content(&document);
+                prop_assert_eq!(parse_content(&text), document, "{}", text);
+            }
+        }
+    }
+}
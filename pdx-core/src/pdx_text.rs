@@ -0,0 +1,667 @@
+use crate::data::{Direction, TextRun};
+use arabic_reshaper::ArabicReshaper;
+use std::ops::Range;
+use unicode_bidi::{bidi_class, BidiClass, BidiInfo, Level};
+use unicode_script::{Script as UnicodeScriptName, UnicodeScript};
+use unicode_segmentation::UnicodeSegmentation;
+
+// ============================================================================
+// Scripts & Languages
+// ============================================================================
+
+/// Writing systems told apart when tagging text. Follows the Unicode
+/// `Script` property for the letters of each; punctuation, digits and
+/// combining marks shared between scripts belong to none.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Script {
+    Latin,
+    Greek,
+    Cyrillic,
+    Armenian,
+    Hebrew,
+    Arabic,
+    Syriac,
+    Thaana,
+    Nko,
+    Devanagari,
+    /// Letters of any other script
+    Other,
+}
+
+impl Script {
+    /// Scripts written right to left.
+    pub fn is_rtl(self) -> bool {
+        matches!(
+            self,
+            Script::Hebrew | Script::Arabic | Script::Syriac | Script::Thaana | Script::Nko
+        )
+    }
+}
+
+/// Script of a character, or `None` for characters in the `Common` and
+/// `Inherited` scripts (spaces, digits, punctuation, combining marks).
+pub fn script_of(c: char) -> Option<Script> {
+    let script = match c.script() {
+        UnicodeScriptName::Common | UnicodeScriptName::Inherited | UnicodeScriptName::Unknown => {
+            return None;
+        }
+        UnicodeScriptName::Latin => Script::Latin,
+        UnicodeScriptName::Greek => Script::Greek,
+        UnicodeScriptName::Cyrillic => Script::Cyrillic,
+        UnicodeScriptName::Armenian => Script::Armenian,
+        UnicodeScriptName::Hebrew => Script::Hebrew,
+        UnicodeScriptName::Arabic => Script::Arabic,
+        UnicodeScriptName::Syriac => Script::Syriac,
+        UnicodeScriptName::Thaana => Script::Thaana,
+        UnicodeScriptName::Nko => Script::Nko,
+        UnicodeScriptName::Devanagari => Script::Devanagari,
+        _ => Script::Other,
+    };
+
+    Some(script)
+}
+
+/// The script most letters of the text are written in.
+pub fn dominant_script(text: &str) -> Option<Script> {
+    let mut counts: Vec<(Script, usize)> = Vec::new();
+
+    for script in text.chars().filter_map(script_of) {
+        match counts.iter_mut().find(|(s, _)| *s == script) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((script, 1)),
+        }
+    }
+
+    // Earliest script wins a tie
+    counts
+        .into_iter()
+        .rev()
+        .max_by_key(|(_, count)| *count)
+        .map(|(script, _)| script)
+}
+
+pub fn has_arabic(input: &str) -> bool {
+    input.chars().any(|c| script_of(c) == Some(Script::Arabic))
+}
+
+/// Guesses a BCP 47 language tag from the text's script. Languages that
+/// share the Arabic or Hebrew script are told apart by the letters only
+/// they use. Falls back to `en` for Latin and script-less text.
+pub fn detect_language(text: &str) -> &'static str {
+    let uses = |letters: &str| text.chars().any(|c| letters.contains(c));
+
+    match dominant_script(text) {
+        Some(Script::Arabic) => {
+            if uses("ڄڃڇڏڍڊٻڀٺٽٿڦڪڳڱڻ") {
+                "sd"
+            } else if uses("ټډړږښځڅګۍ") {
+                "ps"
+            } else if uses("ۇۈۋڭ") {
+                "ug"
+            } else if uses("ڕڵۆێ") {
+                "ckb"
+            } else if uses("ٹڈڑںہے") {
+                "ur"
+            } else if uses("پچژگکی") {
+                "fa"
+            } else {
+                "ar"
+            }
+        }
+        Some(Script::Hebrew) if uses("װױײ") => "yi",
+        Some(Script::Hebrew) => "he",
+        Some(Script::Syriac) => "syr",
+        Some(Script::Thaana) => "dv",
+        Some(Script::Nko) => "nqo",
+        Some(Script::Greek) => "el",
+        Some(Script::Cyrillic) => "ru",
+        Some(Script::Armenian) => "hy",
+        Some(Script::Devanagari) => "hi",
+        Some(Script::Latin | Script::Other) | None => "en",
+    }
+}
+
+/// Whether text tagged with `language` reads right to left. Looks at the
+/// primary subtag, or at an explicit script subtag such as `ku-Arab`.
+pub fn is_rtl_language(language: &str) -> bool {
+    let mut subtags = language.split(['-', '_']);
+    let primary = subtags.next().unwrap_or_default().to_ascii_lowercase();
+
+    if let Some(script) = subtags.find(|subtag| subtag.len() == 4) {
+        return matches!(
+            script.to_ascii_lowercase().as_str(),
+            "arab" | "hebr" | "syrc" | "thaa" | "nkoo" | "samr" | "mand" | "adlm"
+        );
+    }
+
+    matches!(
+        primary.as_str(),
+        "ar" | "arc"
+            | "ckb"
+            | "dv"
+            | "fa"
+            | "he"
+            | "iw"
+            | "ks"
+            | "nqo"
+            | "ps"
+            | "sd"
+            | "syr"
+            | "ug"
+            | "ur"
+            | "yi"
+    )
+}
+
+/// A run's language from the tags an imported file gave it: `ltr_tag` for
+/// left-to-right scripts and `rtl_tag` for right-to-left ones, which Word
+/// and OpenDocument keep apart. A tag that does not fit the text's script
+/// gives way to detection, and runs without letters follow the run before
+/// them.
+pub(crate) fn tagged_language(
+    text: &str,
+    rtl: bool,
+    ltr_tag: Option<&str>,
+    rtl_tag: Option<&str>,
+    previous: Option<&TextRun>,
+) -> String {
+    let rtl_script = match dominant_script(text) {
+        Some(script) => script.is_rtl(),
+        None => {
+            if let Some(previous) = previous {
+                return previous.language.clone();
+            }
+            rtl
+        }
+    };
+
+    let tag = if rtl_script { rtl_tag } else { ltr_tag };
+    match tag {
+        Some(tag) if is_rtl_language(tag) == rtl_script => tag.to_string(),
+        _ => detect_language(text).to_string(),
+    }
+}
+
+/// Reshapes Arabic letters into their joined presentation forms without
+/// reordering. Used where the caller performs its own bidi reordering.
+pub fn shape(input: &str) -> String {
+    if !has_arabic(input) {
+        return input.to_string();
+    }
+
+    ArabicReshaper::new().reshape(input)
+}
+
+/// Whether the text contains any right-to-left letters.
+pub fn has_rtl(input: &str) -> bool {
+    input
+        .chars()
+        .any(|c| matches!(bidi_class(c), BidiClass::R | BidiClass::AL))
+}
+
+/// Direction of the first strong character (UAX #9 rules P2–P3), if any.
+fn first_strong_rtl(text: &str) -> Option<bool> {
+    text.chars().find_map(|c| match bidi_class(c) {
+        BidiClass::L => Some(false),
+        BidiClass::R | BidiClass::AL => Some(true),
+        _ => None,
+    })
+}
+
+/// Whether a block of runs reads right to left. An explicit `direction`
+/// wins; `Auto` follows the first strong character, falling back to the
+/// runs' own tags when the text has none.
+pub fn is_rtl_block(runs: &[TextRun], direction: Direction) -> bool {
+    match direction {
+        Direction::RTL => true,
+        Direction::LTR => false,
+        Direction::Auto => runs
+            .iter()
+            .find_map(|run| first_strong_rtl(&run.text))
+            .unwrap_or_else(|| runs.iter().any(|r| r.direction == Direction::RTL)),
+    }
+}
+
+/// Whether a run reads right to left: its own direction, or its language's
+/// when that is `Auto`.
+pub(crate) fn is_rtl_run(run: &TextRun) -> bool {
+    match run.direction {
+        Direction::RTL => true,
+        Direction::LTR => false,
+        Direction::Auto => is_rtl_language(&run.language),
+    }
+}
+
+/// Language a block is tagged with: its first tagged run's, else `fallback`,
+/// usually the document's.
+pub(crate) fn block_language<'r>(runs: &'r [TextRun], fallback: &'r str) -> &'r str {
+    runs.iter()
+        .map(|run| run.language.as_str())
+        .find(|language| !language.is_empty())
+        .unwrap_or(fallback)
+}
+
+/// Shapes and reorders text for renderers that only draw left to right.
+/// Every line is its own bidi paragraph, with its direction taken from its
+/// first strong character.
+pub fn pdx_text(input: &str) -> String {
+    if !has_rtl(input) {
+        return input.to_string();
+    }
+
+    input
+        .split('\n')
+        .map(|line| {
+            let rtl = first_strong_rtl(line).unwrap_or(false);
+            visual_line(&[(line, None)], rtl)
+                .into_iter()
+                .map(|segment| segment.text)
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A piece of a visual line, ready to draw left to right.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VisualSegment {
+    /// Shaped text in visual order
+    pub text: String,
+    /// Index of the run the text came from, carried over from the input
+    pub run: Option<usize>,
+}
+
+/// Reorders one already-wrapped line for drawing left to right, following
+/// UAX #9 rules L1–L4. `segments` are the line's pieces in logical order,
+/// each tagged with the run it came from. Embedding levels are resolved
+/// over the whole line, so a right-to-left sentence with English names and
+/// numbers in other runs keeps every piece in its proper place. Arabic is
+/// shaped over the whole line too, so letters join across run boundaries,
+/// and right-to-left text is reversed by grapheme cluster so combining
+/// marks stay after their base.
+pub fn visual_line(segments: &[(&str, Option<usize>)], rtl: bool) -> Vec<VisualSegment> {
+    let mut logical = String::new();
+    let mut ranges: Vec<(Range<usize>, Option<usize>)> = Vec::new();
+
+    for (text, run) in segments {
+        let start = logical.len();
+        logical.push_str(text);
+        ranges.push((start..logical.len(), *run));
+    }
+
+    // Shaping can merge letters into ligatures, so a logical offset maps to
+    // the length of the shaped text before it
+    let shaped: Vec<char> = shape(&logical).chars().collect();
+    let shaped_at = |byte: usize| shape(&logical[..byte]).chars().count().min(shaped.len());
+
+    let base = if rtl { Level::rtl() } else { Level::ltr() };
+    let bidi = BidiInfo::new(&logical, Some(base));
+    let mut visual = Vec::new();
+
+    for para in &bidi.paragraphs {
+        let (levels, level_runs) = bidi.visual_runs(para, para.range.clone());
+
+        for level_run in level_runs {
+            let rtl = levels[level_run.start].is_rtl();
+
+            let mut pieces: Vec<VisualSegment> = ranges
+                .iter()
+                .filter_map(|(range, run)| {
+                    let start = range.start.max(level_run.start);
+                    let end = range.end.min(level_run.end);
+                    if start >= end {
+                        return None;
+                    }
+
+                    let text = if rtl {
+                        let (from, to) = (shaped_at(start), shaped_at(end));
+                        let piece: String = shaped[from..to.max(from)].iter().collect();
+                        piece
+                            .graphemes(true)
+                            .rev()
+                            .flat_map(|cluster| cluster.chars().map(mirror))
+                            .collect()
+                    } else {
+                        logical[start..end].to_string()
+                    };
+
+                    Some(VisualSegment { text, run: *run })
+                })
+                .collect();
+
+            if rtl {
+                pieces.reverse();
+            }
+            visual.extend(pieces);
+        }
+    }
+
+    visual
+}
+
+/// Mirrors paired punctuation inside right-to-left runs (UAX #9 rule L4).
+fn mirror(c: char) -> char {
+    match c {
+        '(' => ')',
+        ')' => '(',
+        '[' => ']',
+        ']' => '[',
+        '{' => '}',
+        '}' => '{',
+        '<' => '>',
+        '>' => '<',
+        '«' => '»',
+        '»' => '«',
+        _ => c,
+    }
+}
+
+/// Writes `n` with the digits customary for `language`: Arabic-Indic for
+/// Arabic and Sorani Kurdish, Extended Arabic-Indic for Persian, Urdu,
+/// Pashto and Sindhi, ASCII otherwise.
+pub fn localize_digits(n: u32, language: &str) -> String {
+    let zero = match language {
+        "ar" | "ckb" => '\u{0660}',
+        "fa" | "ur" | "ps" | "sd" => '\u{06F0}',
+        _ => return n.to_string(),
+    };
+
+    n.to_string()
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .filter_map(|d| char::from_u32(zero as u32 + d))
+        .collect()
+}
+
+/// Marker for a list item. Ordered markers are `1.` in logical order, or
+/// `.1` when `visual_rtl` is set for callers that draw left to right.
+/// Bullets change shape with nesting depth.
+pub fn list_marker(
+    ordered: bool,
+    number: u32,
+    depth: usize,
+    language: &str,
+    visual_rtl: bool,
+) -> String {
+    if !ordered {
+        return ["•", "◦", "▪"][depth % 3].to_string();
+    }
+
+    let digits = localize_digits(number, language);
+    if visual_rtl {
+        format!(".{}", digits)
+    } else {
+        format!("{}.", digits)
+    }
+}
+
+// ============================================================================
+// Text Flow
+// ============================================================================
+
+/// A word split into the runs it was typed in: `(run index, text)`.
+pub type Word = Vec<(usize, String)>;
+
+/// Splits runs into whitespace-separated words. A word can span runs, as in
+/// `**bold**ness`.
+pub fn split_words(runs: &[TextRun]) -> Vec<Word> {
+    let mut words = Vec::new();
+    let mut word: Word = Vec::new();
+
+    for (index, run) in runs.iter().enumerate() {
+        for c in run.text.chars() {
+            if c.is_whitespace() {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+                continue;
+            }
+
+            match word.last_mut() {
+                Some((run, text)) if *run == index => text.push(c),
+                _ => word.push((index, c.to_string())),
+            }
+        }
+    }
+
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    words
+}
+
+/// Greedy word wrap on logical text, shared by the page layout and the
+/// editor preview. `measure(run, text)` is the drawn width of part of a
+/// run and `space` that of the gap between words. Words wider than the
+/// line are split by character.
+pub fn wrap_words(
+    runs: &[TextRun],
+    width: f32,
+    space: f32,
+    measure: impl Fn(usize, &str) -> f32,
+) -> Vec<Vec<Word>> {
+    let word_width =
+        |word: &Word| -> f32 { word.iter().map(|(run, text)| measure(*run, text)).sum() };
+    let mut lines = Vec::new();
+    let mut line: Vec<Word> = Vec::new();
+    let mut line_width = 0.0;
+
+    for word in split_words(runs) {
+        let pieces = if word_width(&word) <= width {
+            vec![word]
+        } else {
+            split_long_word(word, width, &measure)
+        };
+
+        for piece in pieces {
+            let piece_width = word_width(&piece);
+
+            if !line.is_empty() && line_width + space + piece_width > width {
+                lines.push(std::mem::take(&mut line));
+                line_width = 0.0;
+            }
+            if !line.is_empty() {
+                line_width += space;
+            }
+
+            line.push(piece);
+            line_width += piece_width;
+        }
+    }
+
+    if !line.is_empty() {
+        lines.push(line);
+    }
+
+    lines
+}
+
+pub(crate) fn split_long_word(
+    word: Word,
+    width: f32,
+    measure: &impl Fn(usize, &str) -> f32,
+) -> Vec<Word> {
+    let mut pieces = Vec::new();
+    let mut piece: Word = Vec::new();
+    let mut piece_width = 0.0;
+
+    for (run, text) in word {
+        for c in text.chars() {
+            let char_width = measure(run, c.encode_utf8(&mut [0; 4]));
+            if !piece.is_empty() && piece_width + char_width > width {
+                pieces.push(std::mem::take(&mut piece));
+                piece_width = 0.0;
+            }
+
+            match piece.last_mut() {
+                Some((last_run, piece_text)) if *last_run == run => piece_text.push(c),
+                _ => piece.push((run, c.to_string())),
+            }
+            piece_width += char_width;
+        }
+    }
+
+    if !piece.is_empty() {
+        pieces.push(piece);
+    }
+
+    pieces
+}
+
+/// A wrapped line in logical order, as input for `visual_line`. The space
+/// between two words keeps its run's marks when both neighbours share the
+/// run, so underlines and highlights continue across it.
+pub fn line_segments(line: &[Word]) -> Vec<(&str, Option<usize>)> {
+    let mut segments = Vec::new();
+
+    for (i, word) in line.iter().enumerate() {
+        if i > 0 {
+            let before = line[i - 1].last().map(|(run, _)| *run);
+            let after = word.first().map(|(run, _)| *run);
+            segments.push((" ", if before == after { before } else { None }));
+        }
+
+        for (run, text) in word {
+            segments.push((text.as_str(), Some(*run)));
+        }
+    }
+
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(segments: &[VisualSegment]) -> Vec<(&str, Option<usize>)> {
+        segments
+            .iter()
+            .map(|segment| (segment.text.as_str(), segment.run))
+            .collect()
+    }
+
+    #[test]
+    fn scripts_follow_the_unicode_script_property() {
+        assert_eq!(script_of('a'), Some(Script::Latin));
+        assert_eq!(script_of('ß'), Some(Script::Latin));
+        assert_eq!(script_of('λ'), Some(Script::Greek));
+        assert_eq!(script_of('ж'), Some(Script::Cyrillic));
+        assert_eq!(script_of('ա'), Some(Script::Armenian));
+        assert_eq!(script_of('ש'), Some(Script::Hebrew));
+        assert_eq!(script_of('ب'), Some(Script::Arabic));
+        assert_eq!(script_of('\u{FEFB}'), Some(Script::Arabic));
+        assert_eq!(script_of('ߊ'), Some(Script::Nko));
+        assert_eq!(script_of('क'), Some(Script::Devanagari));
+        assert_eq!(script_of('中'), Some(Script::Other));
+    }
+
+    #[test]
+    fn shared_characters_belong_to_no_script() {
+        // Arabic comma, tatweel, fatha, byte order mark, digits and punctuation
+        for c in [
+            '\u{060C}', '\u{0640}', '\u{064E}', '\u{FEFF}', '7', ' ', '×', ';',
+        ] {
+            assert_eq!(script_of(c), None, "{c:?}");
+        }
+    }
+
+    #[test]
+    fn syriac_and_thaana_are_tagged_right_to_left() {
+        assert_eq!(script_of('ܐ'), Some(Script::Syriac));
+        assert_eq!(script_of('ހ'), Some(Script::Thaana));
+
+        for (text, language) in [("ܫܠܡܐ", "syr"), ("ދިވެހި", "dv")] {
+            assert_eq!(detect_language(text), language);
+            let run = TextRun::new(text, detect_language(text), "paragraph");
+            assert_eq!(run.direction, Direction::RTL, "{text}");
+            assert!(is_rtl_run(&run));
+        }
+    }
+
+    #[test]
+    fn languages_are_told_apart_by_their_letters() {
+        assert_eq!(detect_language("مرحبا"), "ar");
+        assert_eq!(detect_language("پنجره"), "fa");
+        assert_eq!(detect_language("شالوم"), "ar");
+        assert_eq!(detect_language("שלום"), "he");
+        assert_eq!(detect_language("Hello, мир мир"), "ru");
+        assert_eq!(detect_language("123"), "en");
+    }
+
+    #[test]
+    fn arabic_joins_across_runs() {
+        // بيت with the last letter in a run of its own: beh initial, yeh
+        // medial, teh final, instead of yeh final and teh isolated
+        let line = visual_line(&[("بي", Some(0)), ("ت", Some(1))], true);
+
+        assert_eq!(
+            texts(&line),
+            [("\u{FE96}", Some(1)), ("\u{FEF4}\u{FE91}", Some(0))]
+        );
+    }
+
+    #[test]
+    fn ligatures_spanning_runs_go_to_the_first_run() {
+        // Lam in one run and alef in the next still form the lam-alef ligature
+        let line = visual_line(&[("سل", Some(0)), ("ام", Some(1))], true);
+
+        assert_eq!(
+            texts(&line),
+            [("\u{FEE1}", Some(1)), ("\u{FEFC}\u{FEB3}", Some(0))]
+        );
+    }
+
+    #[test]
+    fn combining_marks_stay_after_their_base_when_reversed() {
+        // Hebrew shin with shin dot and qamats, then lamed
+        let line = visual_line(&[("\u{05E9}\u{05C1}\u{05B8}\u{05DC}", None)], true);
+
+        assert_eq!(texts(&line), [("\u{05DC}\u{05E9}\u{05C1}\u{05B8}", None)]);
+    }
+
+    #[test]
+    fn mixed_direction_runs_keep_their_order() {
+        let line = visual_line(&[("Hello ", Some(0)), ("שלום", Some(1))], false);
+
+        assert_eq!(texts(&line), [("Hello ", Some(0)), ("םולש", Some(1))]);
+    }
+
+    #[test]
+    fn words_wrap_at_the_width_and_long_words_split() {
+        let runs = [
+            TextRun::new("aa bb ", "en", "paragraph"),
+            TextRun::new("cccccc", "en", "paragraph"),
+        ];
+        let lines = wrap_words(&runs, 5.0, 1.0, |_, text| text.chars().count() as f32);
+
+        let lines: Vec<Vec<String>> = lines
+            .iter()
+            .map(|line| {
+                line.iter()
+                    .map(|word| word.iter().map(|(_, text)| text.as_str()).collect())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(lines, [vec!["aa", "bb"], vec!["ccccc"], vec!["c"]]);
+    }
+
+    #[test]
+    fn spaces_between_words_of_one_run_keep_the_run() {
+        let words = split_words(&[
+            TextRun::new("one two", "en", "paragraph"),
+            TextRun::new(" three", "en", "paragraph"),
+        ]);
+
+        assert_eq!(
+            line_segments(&words),
+            [
+                ("one", Some(0)),
+                (" ", Some(0)),
+                ("two", Some(0)),
+                (" ", None),
+                ("three", Some(1)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,67 @@
+use crate::data::{Metadata, Node, PdxDocument, Resources, StyleSheet, TextRun};
+use crate::validate::CURRENT_VERSION;
+use crate::xml::{self, Element};
+use ::image::{ImageFormat, RgbImage};
+use std::io::{Cursor, Read};
+use zip::ZipArchive;
+
+// ============================================================================
+// Test Fixtures
+// ============================================================================
+
+/// A document with the default metadata and style sheet.
+pub(crate) fn document(children: Vec<Node>) -> PdxDocument {
+    PdxDocument {
+        version: CURRENT_VERSION,
+        metadata: Metadata::default(),
+        styles: StyleSheet::default(),
+        content: Node::Document { children },
+        resources: Resources::default(),
+    }
+}
+
+/// A `paragraph` of one run.
+pub(crate) fn paragraph(text: &str, language: &str) -> Node {
+    Node::Paragraph {
+        runs: vec![TextRun::new(text, language, "paragraph")],
+        style: "paragraph".to_string(),
+    }
+}
+
+/// A black 4×2 PNG.
+pub(crate) fn png() -> Vec<u8> {
+    let mut bytes = Cursor::new(Vec::new());
+    RgbImage::new(4, 2)
+        .write_to(&mut bytes, ImageFormat::Png)
+        .unwrap();
+    bytes.into_inner()
+}
+
+// ============================================================================
+// Zip Packages
+// ============================================================================
+
+/// A package's entries in archive order.
+pub(crate) fn unzip(data: &[u8]) -> Vec<(String, Vec<u8>)> {
+    let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+    (0..archive.len())
+        .map(|i| {
+            let mut entry = archive.by_index(i).unwrap();
+            let mut bytes = Vec::new();
+            entry.read_to_end(&mut bytes).unwrap();
+            (entry.name().to_string(), bytes)
+        })
+        .collect()
+}
+
+pub(crate) fn entry_text(entries: &[(String, Vec<u8>)], name: &str) -> String {
+    let (_, bytes) = entries
+        .iter()
+        .find(|(entry, _)| entry == name)
+        .unwrap_or_else(|| panic!("no {} in the package", name));
+    String::from_utf8(bytes.clone()).unwrap()
+}
+
+pub(crate) fn entry_xml(entries: &[(String, Vec<u8>)], name: &str) -> Element {
+    xml::parse(&entry_text(entries, name)).unwrap()
+}
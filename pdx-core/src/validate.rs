@@ -0,0 +1,152 @@
+use crate::data::{Node, PdxDocument, Resources, TextRun};
+use std::fmt;
+
+// ============================================================================
+// Document Validation
+// ============================================================================
+
+/// Newest file format version this build reads.
+pub const CURRENT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    /// The document renders, but probably not as intended
+    Warning,
+    /// Content is lost or exports fail
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct Issue {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{}: {}", severity, self.message)
+    }
+}
+
+/// Checks a loaded document for problems the editor and exporters would
+/// otherwise paper over. Image paths are looked up among the embedded
+/// resources, then on disk relative to the document directory, as the
+/// exporters open them.
+pub fn validate_document(document: &PdxDocument) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    if document.version > CURRENT_VERSION {
+        issues.push(error(format!(
+            "file version {} is newer than the supported version {}",
+            document.version, CURRENT_VERSION
+        )));
+    }
+
+    match &document.content {
+        Node::Document { children } => {
+            for child in children {
+                check_node(child, &document.resources, &mut issues);
+            }
+        }
+        _ => issues.push(error("content root is not a Document node".to_string())),
+    }
+
+    issues
+}
+
+fn check_node(node: &Node, resources: &Resources, issues: &mut Vec<Issue>) {
+    match node {
+        Node::Document { children } => {
+            issues.push(warning("nested Document node".to_string()));
+            for child in children {
+                check_node(child, resources, issues);
+            }
+        }
+
+        Node::Heading { level, runs, .. } => {
+            if !(1..=6).contains(level) {
+                issues.push(warning(format!("heading level {} is outside 1-6", level)));
+            }
+            check_runs(runs, issues);
+        }
+
+        Node::Paragraph { runs, .. } => check_runs(runs, issues),
+
+        Node::List { items, .. } => {
+            if items.is_empty() {
+                issues.push(warning("empty list".to_string()));
+            }
+            for item in items {
+                check_runs(&item.content, issues);
+                for child in &item.children {
+                    check_node(child, resources, issues);
+                }
+            }
+        }
+
+        Node::CodeBlock { .. } | Node::Divider | Node::PageBreak => {}
+
+        Node::Image {
+            path,
+            width,
+            height,
+            ..
+        } => {
+            if path.is_empty() {
+                issues.push(error("image without a path".to_string()));
+            } else if !resources.contains(path) {
+                issues.push(warning(format!("image not found: {}", path)));
+            }
+
+            for size in [width, height].into_iter().flatten() {
+                if !size.is_finite() || *size <= 0.0 {
+                    issues.push(error(format!("image {} has invalid size {}", path, size)));
+                }
+            }
+        }
+
+        Node::Table {
+            rows, header_rows, ..
+        } => {
+            if rows.is_empty() {
+                issues.push(warning("empty table".to_string()));
+            }
+            if *header_rows > rows.len() {
+                issues.push(warning(format!(
+                    "table has {} header rows but only {} rows",
+                    header_rows,
+                    rows.len()
+                )));
+            }
+            for cell in rows.iter().flat_map(|row| &row.cells) {
+                check_runs(&cell.content, issues);
+            }
+        }
+    }
+}
+
+fn check_runs(runs: &[TextRun], issues: &mut Vec<Issue>) {
+    for run in runs {
+        if run.marks.link.as_deref() == Some("") {
+            issues.push(warning(format!("link without a target: {}", run.text)));
+        }
+    }
+}
+
+fn warning(message: String) -> Issue {
+    Issue {
+        severity: Severity::Warning,
+        message,
+    }
+}
+
+fn error(message: String) -> Issue {
+    Issue {
+        severity: Severity::Error,
+        message,
+    }
+}
@@ -0,0 +1,189 @@
+use quick_xml::escape::resolve_predefined_entity;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+// ============================================================================
+// XML Trees
+// ============================================================================
+
+/// An element of a parsed XML part. Names keep their prefix (`w:p`), which
+/// office formats use consistently.
+#[derive(Debug, Default)]
+pub(crate) struct Element {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Content>,
+}
+
+#[derive(Debug)]
+pub(crate) enum Content {
+    Element(Element),
+    Text(String),
+}
+
+impl Element {
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|child| match child {
+            Content::Element(element) => Some(element),
+            Content::Text(_) => None,
+        })
+    }
+
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.elements().find(|element| element.name == name)
+    }
+
+    pub fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.elements().filter(move |element| element.name == name)
+    }
+
+    /// The first element with this name anywhere below, depth first.
+    pub fn find(&self, name: &str) -> Option<&Element> {
+        self.elements().find_map(|element| {
+            (element.name == name)
+                .then_some(element)
+                .or_else(|| element.find(name))
+        })
+    }
+
+    /// All text below the element, in document order.
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        for child in &self.children {
+            match child {
+                Content::Text(t) => text.push_str(t),
+                Content::Element(element) => text.push_str(&element.text()),
+            }
+        }
+        text
+    }
+}
+
+/// Reads an XML document into its root element.
+pub(crate) fn parse(text: &str) -> Result<Element, String> {
+    let mut reader = Reader::from_str(text);
+    // Whitespace inside `w:t` and friends is content
+    reader.config_mut().trim_text(false);
+
+    let mut stack = vec![Element::default()];
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| format!("at byte {}: {}", reader.error_position(), e))?;
+
+        match event {
+            Event::Start(start) => {
+                let element = open_element(&start)?;
+                stack.push(element);
+            }
+            Event::Empty(start) => {
+                let element = open_element(&start)?;
+                push_child(&mut stack, Content::Element(element));
+            }
+            Event::End(_) => {
+                let element = stack.pop().filter(|_| !stack.is_empty());
+                match element {
+                    Some(element) => push_child(&mut stack, Content::Element(element)),
+                    None => return Err("unbalanced closing tag".to_string()),
+                }
+            }
+            Event::Text(text) => {
+                let text = text.decode().map_err(|e| e.to_string())?;
+                push_text(&mut stack, &text);
+            }
+            Event::CData(data) => {
+                let text = data.decode().map_err(|e| e.to_string())?;
+                push_text(&mut stack, &text);
+            }
+            Event::GeneralRef(reference) => {
+                let resolved = match reference.resolve_char_ref().map_err(|e| e.to_string())? {
+                    Some(c) => c.to_string(),
+                    None => {
+                        let name = reference.decode().map_err(|e| e.to_string())?;
+                        resolve_predefined_entity(&name)
+                            .ok_or_else(|| format!("unknown entity &{};", name))?
+                            .to_string()
+                    }
+                };
+                push_text(&mut stack, &resolved);
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    let document = match stack.pop() {
+        Some(document) if stack.is_empty() => document,
+        _ => return Err("unclosed element at end of file".to_string()),
+    };
+    document
+        .children
+        .into_iter()
+        .find_map(|child| match child {
+            Content::Element(root) => Some(root),
+            Content::Text(_) => None,
+        })
+        .ok_or_else(|| "no root element".to_string())
+}
+
+fn open_element(start: &quick_xml::events::BytesStart) -> Result<Element, String> {
+    let name = String::from_utf8_lossy(start.name().as_ref()).to_string();
+    let mut attributes = Vec::new();
+
+    for attribute in start.attributes() {
+        let attribute = attribute.map_err(|e| e.to_string())?;
+        let key = String::from_utf8_lossy(attribute.key.as_ref()).to_string();
+        let value = attribute.unescape_value().map_err(|e| e.to_string())?;
+        attributes.push((key, value.to_string()));
+    }
+
+    Ok(Element {
+        name,
+        attributes,
+        children: Vec::new(),
+    })
+}
+
+fn push_child(stack: &mut [Element], child: Content) {
+    if let Some(parent) = stack.last_mut() {
+        parent.children.push(child);
+    }
+}
+
+fn push_text(stack: &mut [Element], text: &str) {
+    let Some(parent) = stack.last_mut() else {
+        return;
+    };
+    match parent.children.last_mut() {
+        Some(Content::Text(last)) => last.push_str(text),
+        _ => parent.children.push(Content::Text(text.to_string())),
+    }
+}
+
+/// Escapes text for XML content and attribute values, dropping characters
+/// XML 1.0 does not allow.
+pub(crate) fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\t' | '\n' | '\r' => out.push(c),
+            c if (c as u32) < 0x20 || c == '\u{FFFE}' || c == '\u{FFFF}' => {}
+            c => out.push(c),
+        }
+    }
+
+    out
+}
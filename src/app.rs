@@ -1,9 +1,21 @@
-use crate::data::{create_sample_document, Node, PdxDocument};
-use crate::parser::{parse_content, serialize_content};
+use crate::autosave::{pending_recoveries, Autosave, Recovery, DEFAULT_INTERVAL, INTERVALS};
+use crate::history::{Command, DocumentState, History};
 use crate::renderer::render_node;
 use crate::theme::AppTheme;
-use crate::ui::{export_html, export_pdf_file, export_png_file, insert_image, open_document, save_document};
+use crate::ui::{
+    export_docx_file, export_epub_file, export_html, export_latex_file, export_markdown_file,
+    export_odt_file, export_pdf_file, export_png_file, export_styles, import_styles, insert_image,
+    is_imported_file, open_document, save_document,
+};
 use eframe::egui::{self, ColorImage, RichText, ScrollArea};
+use pdx_core::bundle::{embed_images, BUNDLE_EXTENSION};
+use pdx_core::data::{
+    create_sample_document, Color, Direction, EdgeInsets, FontWeight, Node, PdxDocument, Style,
+    TextAlign, TextRun,
+};
+use pdx_core::error::PdxError;
+use pdx_core::export::{HtmlOptions, PngLayout, PngOptions};
+use pdx_core::parser::{block_style_at, parse_content, serialize_content, set_block_style};
 use std::collections::HashMap;
 use std::path::PathBuf;
 
@@ -25,6 +37,52 @@ pub enum EditorTab {
     Styles,
 }
 
+/// Document languages offered in the Metadata tab.
+const LANGUAGES: [(&str, &str); 8] = [
+    ("ar", "🇸🇦 Arabic"),
+    ("en", "🇬🇧 English"),
+    ("fr", "🇫🇷 French"),
+    ("fa", "🇮🇷 Persian"),
+    ("ur", "🇵🇰 Urdu"),
+    ("ckb", "Kurdish (Sorani)"),
+    ("he", "🇮🇱 Hebrew"),
+    ("yi", "Yiddish"),
+];
+
+/// Changes requested from the Styles tab, applied after it is drawn.
+enum StyleAction {
+    Select(String),
+    New,
+    Duplicate,
+    Delete,
+    Rename(String),
+    Import,
+    Export,
+    /// Give the block under the editor cursor the selected style
+    Apply,
+}
+
+/// What to do once the user has answered the unsaved-changes prompt.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PendingAction {
+    New,
+    Open,
+    Close,
+}
+
+/// Storage key for the autosave interval, in seconds or `off`.
+const AUTOSAVE_KEY: &str = "autosave_interval";
+
+/// Document history shortcuts; Ctrl+Y also redoes.
+const UNDO: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z);
+const REDO: egui::KeyboardShortcut = egui::KeyboardShortcut::new(
+    egui::Modifiers::COMMAND.plus(egui::Modifiers::SHIFT),
+    egui::Key::Z,
+);
+const REDO_ALT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Y);
+
 pub struct PdxApp {
     document: PdxDocument,
     path: Option<PathBuf>,
@@ -36,12 +94,35 @@ pub struct PdxApp {
     last_save: Option<String>,
     status_message: String,
     loaded_images: HashMap<String, egui::TextureHandle>,
+    png_options: PngOptions,
+    html_options: HtmlOptions,
+    history: History,
+    show_history: bool,
+    /// Editor line the cursor was last on
+    cursor_line: usize,
+    selected_style: Option<String>,
+    style_name_edit: String,
+    autosave: Autosave,
+    /// Autosaved documents found at startup, offered for recovery
+    recoveries: Vec<Recovery>,
+    show_recovery: bool,
+    /// Changed since the last New, Open or Save
+    dirty: bool,
+    /// Waiting on Save / Discard / Cancel
+    pending: Option<PendingAction>,
+    /// Set once the user agreed to close, so the next close request goes through
+    allow_close: bool,
+    window_title: String,
+    /// The failed operation and why, shown in the error dialog
+    error: Option<(String, PdxError)>,
 }
 
 impl Default for PdxApp {
     fn default() -> Self {
         let document = create_sample_document();
         let raw_content = serialize_content(&document.content);
+        let mut autosave = Autosave::new(DEFAULT_INTERVAL);
+        autosave.mark_clean(&document, &raw_content, None);
 
         Self {
             document,
@@ -54,7 +135,38 @@ impl Default for PdxApp {
             last_save: None,
             status_message: "Ready".to_string(),
             loaded_images: HashMap::new(),
+            png_options: PngOptions::default(),
+            html_options: HtmlOptions::default(),
+            history: History::default(),
+            show_history: false,
+            cursor_line: 0,
+            selected_style: None,
+            style_name_edit: String::new(),
+            autosave,
+            recoveries: Vec::new(),
+            show_recovery: false,
+            dirty: false,
+            pending: None,
+            allow_close: false,
+            window_title: String::new(),
+            error: None,
+        }
+    }
+}
+
+impl PdxApp {
+    /// Restores settings and offers to recover documents autosaved by a
+    /// session that did not exit cleanly.
+    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        let mut app = Self::default();
+
+        if let Some(value) = cc.storage.and_then(|s| s.get_string(AUTOSAVE_KEY)) {
+            app.autosave.interval = value.parse().ok();
         }
+
+        app.recoveries = pending_recoveries();
+        app.show_recovery = !app.recoveries.is_empty();
+        app
     }
 }
 
@@ -65,6 +177,8 @@ impl Default for PdxApp {
 impl eframe::App for PdxApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         self.theme.apply(ctx);
+        self.handle_shortcuts(ctx);
+        self.handle_close_request(ctx);
 
         egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
@@ -72,6 +186,7 @@ impl eframe::App for PdxApp {
                 ui.separator();
 
                 self.render_file_menu(ui);
+                self.render_edit_menu(ui);
                 self.render_view_menu(ui);
                 self.render_theme_menu(ui);
 
@@ -83,6 +198,14 @@ impl eframe::App for PdxApp {
             });
         });
 
+        if self.show_history {
+            egui::SidePanel::right("history_panel")
+                .default_width(220.0)
+                .show(ctx, |ui| {
+                    self.render_history_panel(ui);
+                });
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| match self.active_tab {
             EditorTab::Editor => {
                 self.render_editor_tab(ui, ctx);
@@ -98,6 +221,31 @@ impl eframe::App for PdxApp {
         egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
             self.render_status_bar(ui);
         });
+
+        if self.show_recovery {
+            self.render_recovery_window(ctx);
+        }
+        if self.pending.is_some() {
+            self.render_unsaved_prompt(ctx);
+        }
+        if self.error.is_some() {
+            self.render_error_window(ctx);
+        }
+
+        self.run_autosave(ctx);
+        self.update_title(ctx);
+    }
+
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        let interval = self
+            .autosave
+            .interval
+            .map_or("off".to_string(), |secs| secs.to_string());
+        storage.set_string(AUTOSAVE_KEY, interval);
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.autosave.remove_file();
     }
 }
 
@@ -105,75 +253,171 @@ impl PdxApp {
     fn render_file_menu(&mut self, ui: &mut egui::Ui) {
         ui.menu_button("📁 File", |ui| {
             if ui.button("🆕 New").clicked() {
-                *self = Self::default();
-                self.status_message = "New document created".to_string();
+                self.request(PendingAction::New);
                 ui.close_menu();
             }
 
             if ui.button("📂 Open...").clicked() {
-                if let Some((doc, path)) = open_document() {
-                    self.document = doc;
-                    self.path = Some(path.clone());
-                    self.raw_content = serialize_content(&self.document.content);
-                    self.status_message = format!("Opened: {}", path.display());
-                }
+                self.request(PendingAction::Open);
                 ui.close_menu();
             }
 
             if ui.button("💾 Save").clicked() {
-                if let Some(path) = save_document(&self.document, self.path.as_ref()) {
-                    self.path = Some(path.clone());
-                    self.last_save = Some(chrono::Local::now().format("%H:%M:%S").to_string());
-                    self.status_message = format!("Saved: {}", path.display());
-                }
+                self.save_file(false);
                 ui.close_menu();
             }
 
             if ui.button("💾 Save As...").clicked() {
-                if let Some(path) = save_document(&self.document, None) {
-                    self.path = Some(path.clone());
-                    self.last_save = Some(chrono::Local::now().format("%H:%M:%S").to_string());
-                    self.status_message = format!("Saved as: {}", path.display());
-                }
+                self.save_file(true);
                 ui.close_menu();
             }
 
             ui.separator();
 
             ui.menu_button("📤 Export as...", |ui| {
-                if ui.button("🌐 HTML").clicked() {
-                    if export_html(&self.document).is_some() {
-                        self.status_message = "Exported as HTML".to_string();
+                ui.menu_button("🌐 HTML", |ui| {
+                    ui.checkbox(
+                        &mut self.html_options.inline_images,
+                        "Embed images (single file)",
+                    );
+
+                    if ui.button("💾 Export...").clicked() {
+                        match export_html(&self.document, &self.html_options) {
+                            Ok(Some(_)) => self.status_message = "Exported as HTML".to_string(),
+                            Ok(None) => {}
+                            Err(e) => self.report_error("HTML export", e),
+                        }
+                        ui.close_menu();
+                    }
+                });
+
+                if ui.button("📝 Markdown").clicked() {
+                    match export_markdown_file(&self.document) {
+                        Ok(Some(_)) => self.status_message = "Exported as Markdown".to_string(),
+                        Ok(None) => {}
+                        Err(e) => self.report_error("Markdown export", e),
                     }
                     ui.close_menu();
                 }
 
                 if ui.button("📄 PDF").clicked() {
-                    if export_pdf_file(&self.document).is_some() {
-                        self.status_message = "Exported as PDF with Arabic support".to_string();
-                    } else {
-                        self.status_message = "PDF export failed".to_string();
+                    match export_pdf_file(&self.document) {
+                        Ok(Some(_)) => {
+                            self.status_message = "Exported as PDF with Arabic support".to_string();
+                        }
+                        Ok(None) => {}
+                        Err(e) => self.report_error("PDF export", e),
                     }
                     ui.close_menu();
                 }
 
-                if ui.button("🖼️ PNG Image").clicked() {
-                    if export_png_file().is_some() {
-                        self.status_message = "Exported as PNG image".to_string();
-                    } else {
-                        self.status_message = "PNG export failed".to_string();
+                if ui.button("📘 Word (DOCX)").clicked() {
+                    match export_docx_file(&self.document) {
+                        Ok(Some(_)) => {
+                            self.status_message = "Exported as Word document".to_string()
+                        }
+                        Ok(None) => {}
+                        Err(e) => self.report_error("DOCX export", e),
                     }
                     ui.close_menu();
                 }
+
+                if ui.button("📄 OpenDocument (ODT)").clicked() {
+                    match export_odt_file(&self.document) {
+                        Ok(Some(_)) => {
+                            self.status_message = "Exported as OpenDocument text".to_string()
+                        }
+                        Ok(None) => {}
+                        Err(e) => self.report_error("ODT export", e),
+                    }
+                    ui.close_menu();
+                }
+
+                if ui.button("📖 EPUB Book").clicked() {
+                    match export_epub_file(&self.document) {
+                        Ok(Some(_)) => self.status_message = "Exported as EPUB book".to_string(),
+                        Ok(None) => {}
+                        Err(e) => self.report_error("EPUB export", e),
+                    }
+                    ui.close_menu();
+                }
+
+                if ui.button("📐 LaTeX (XeLaTeX)").clicked() {
+                    match export_latex_file(&self.document) {
+                        Ok(Some(_)) => self.status_message = "Exported as LaTeX source".to_string(),
+                        Ok(None) => {}
+                        Err(e) => self.report_error("LaTeX export", e),
+                    }
+                    ui.close_menu();
+                }
+
+                ui.menu_button("🖼️ PNG Image", |ui| {
+                    ui.add(
+                        egui::Slider::new(&mut self.png_options.dpi, 72.0..=300.0)
+                            .step_by(1.0)
+                            .text("DPI"),
+                    );
+                    ui.radio_value(
+                        &mut self.png_options.layout,
+                        PngLayout::SingleImage,
+                        "One tall image",
+                    );
+                    ui.radio_value(
+                        &mut self.png_options.layout,
+                        PngLayout::PerPage,
+                        "One image per page",
+                    );
+
+                    if ui.button("💾 Export...").clicked() {
+                        match export_png_file(&self.document, &self.png_options) {
+                            Ok(Some(_)) => {
+                                self.status_message = "Exported as PNG image".to_string();
+                            }
+                            Ok(None) => {}
+                            Err(e) => self.report_error("PNG export", e),
+                        }
+                        ui.close_menu();
+                    }
+                });
+            });
+
+            ui.separator();
+
+            ui.menu_button("⏱ Autosave", |ui| {
+                for (interval, label) in INTERVALS {
+                    if ui
+                        .radio_value(&mut self.autosave.interval, interval, label)
+                        .clicked()
+                    {
+                        ui.close_menu();
+                    }
+                }
             });
 
+            if ui.button("🩹 Recover Unsaved...").clicked() {
+                self.recoveries = pending_recoveries();
+                self.show_recovery = true;
+                ui.close_menu();
+            }
+
             ui.separator();
 
+            if ui.button("📦 Embed All Images").clicked() {
+                self.embed_all_images();
+                ui.close_menu();
+            }
+
             if ui.button("🖼️ Insert Image...").clicked() {
                 if let Some(image_path) = insert_image() {
+                    let before = self.raw_content.clone();
                     let image_markup = format!("\n![Image]({})\n", image_path);
                     self.raw_content.push_str(&image_markup);
                     self.document.content = parse_content(&self.raw_content);
+                    self.record(Command::InsertImage {
+                        path: image_path,
+                        before,
+                        after: self.raw_content.clone(),
+                    });
                     self.status_message = "Image inserted".to_string();
                 }
                 ui.close_menu();
@@ -181,6 +425,28 @@ impl PdxApp {
         });
     }
 
+    fn render_edit_menu(&mut self, ui: &mut egui::Ui) {
+        ui.menu_button("✂️ Edit", |ui| {
+            let undo = egui::Button::new("↩ Undo").shortcut_text(ui.ctx().format_shortcut(&UNDO));
+            if ui.add_enabled(self.history.can_undo(), undo).clicked() {
+                self.undo();
+                ui.close_menu();
+            }
+
+            let redo = egui::Button::new("↪ Redo").shortcut_text(ui.ctx().format_shortcut(&REDO));
+            if ui.add_enabled(self.history.can_redo(), redo).clicked() {
+                self.redo();
+                ui.close_menu();
+            }
+
+            ui.separator();
+
+            if ui.checkbox(&mut self.show_history, "📜 History").clicked() {
+                ui.close_menu();
+            }
+        });
+    }
+
     fn render_view_menu(&mut self, ui: &mut egui::Ui) {
         ui.menu_button("👁 View", |ui| {
             if ui.button("✏️ Edit Mode").clicked() {
@@ -271,6 +537,10 @@ impl PdxApp {
                 ui.label("📁 Unsaved");
             }
 
+            if self.dirty {
+                ui.label(RichText::new("● Modified").color(ui.visuals().warn_fg_color));
+            }
+
             ui.separator();
             ui.label(format!("🔍 {}%", (self.zoom_level * 100.0) as i32));
 
@@ -296,15 +566,7 @@ impl PdxApp {
                     .id_salt("edit_scroll")
                     .show(ui, |ui| {
                         ui.heading("Editor");
-
-                        let editor = egui::TextEdit::multiline(&mut self.raw_content)
-                            .desired_width(f32::INFINITY)
-                            .desired_rows(30)
-                            .font(egui::TextStyle::Monospace);
-
-                        if ui.add(editor).changed() {
-                            self.document.content = parse_content(&self.raw_content);
-                        }
+                        self.render_text_editor(ui);
                     });
             }
 
@@ -331,15 +593,7 @@ impl PdxApp {
                         .id_salt("split_edit_scroll")
                         .show(&mut cols[0], |ui| {
                             ui.heading("Editor");
-
-                            let editor = egui::TextEdit::multiline(&mut self.raw_content)
-                                .desired_width(f32::INFINITY)
-                                .desired_rows(30)
-                                .font(egui::TextStyle::Monospace);
-
-                            if ui.add(editor).changed() {
-                                self.document.content = parse_content(&self.raw_content);
-                            }
+                            self.render_text_editor(ui);
                         });
 
                     ScrollArea::vertical()
@@ -361,43 +615,53 @@ impl PdxApp {
         }
     }
 
-    fn load_images_from_content(&mut self, ctx: &egui::Context) {
-        fn collect_image_paths(node: &Node, paths: &mut Vec<String>) {
-            match node {
-                Node::Document { children } => {
-                    for child in children {
-                        collect_image_paths(child, paths);
-                    }
-                }
-                Node::Image { path, .. } => {
-                    paths.push(path.clone());
-                }
-                _ => {}
-            }
-        }
+    /// The markup editor. Remembers the cursor line so the Styles tab can
+    /// restyle the block under it.
+    fn render_text_editor(&mut self, ui: &mut egui::Ui) {
+        let before = self.raw_content.clone();
+        let output = egui::TextEdit::multiline(&mut self.raw_content)
+            .desired_width(f32::INFINITY)
+            .desired_rows(30)
+            .font(egui::TextStyle::Monospace)
+            .show(ui);
 
-        let mut image_paths = Vec::new();
-        collect_image_paths(&self.document.content, &mut image_paths);
+        if let Some(range) = output.cursor_range {
+            let index = range.primary.ccursor.index;
+            self.cursor_line = self
+                .raw_content
+                .chars()
+                .take(index)
+                .filter(|&c| c == '\n')
+                .count();
+        }
+        if output.response.changed() {
+            self.text_changed(before);
+        }
+    }
 
-        for path in image_paths {
-            if !self.loaded_images.contains_key(&path) {
-                if let Ok(img) = image::open(&path) {
-                    let size = [img.width() as usize, img.height() as usize];
-                    let rgba = img.to_rgba8();
-                    let pixels = rgba.as_flat_samples();
+    /// Loads images from the document's embedded resources, or from disk
+    /// relative to the document.
+    fn load_images_from_content(&mut self, ctx: &egui::Context) {
+        for path in self.document.content.image_paths() {
+            if !self.loaded_images.contains_key(path)
+                && let Some(img) = self.document.resources.load_image(path)
+            {
+                let size = [img.width() as usize, img.height() as usize];
+                let rgba = img.to_rgba8();
+                let pixels = rgba.as_flat_samples();
 
-                    let color_image = ColorImage::from_rgba_unmultiplied(size, pixels.as_slice());
+                let color_image = ColorImage::from_rgba_unmultiplied(size, pixels.as_slice());
 
-                    let texture =
-                        ctx.load_texture(&path, color_image, egui::TextureOptions::default());
+                let texture = ctx.load_texture(path, color_image, egui::TextureOptions::default());
 
-                    self.loaded_images.insert(path, texture);
-                }
+                self.loaded_images.insert(path.to_string(), texture);
             }
         }
     }
 
     fn render_metadata_tab(&mut self, ui: &mut egui::Ui) {
+        let before = self.document.metadata.clone();
+
         ScrollArea::vertical()
             .id_salt("metadata_scroll")
             .show(ui, |ui| {
@@ -419,21 +683,13 @@ impl PdxApp {
                     egui::ComboBox::from_label("")
                         .selected_text(&self.document.metadata.language)
                         .show_ui(ui, |ui| {
-                            ui.selectable_value(
-                                &mut self.document.metadata.language,
-                                "ar".to_string(),
-                                "🇸🇦 Arabic",
-                            );
-                            ui.selectable_value(
-                                &mut self.document.metadata.language,
-                                "en".to_string(),
-                                "🇬🇧 English",
-                            );
-                            ui.selectable_value(
-                                &mut self.document.metadata.language,
-                                "fr".to_string(),
-                                "🇫🇷 French",
-                            );
+                            for (code, name) in LANGUAGES {
+                                ui.selectable_value(
+                                    &mut self.document.metadata.language,
+                                    code.to_string(),
+                                    name,
+                                );
+                            }
                         });
                 });
 
@@ -449,26 +705,825 @@ impl PdxApp {
                     ui.label(format!("  • {}", keyword));
                 }
             });
+
+        if self.document.metadata != before {
+            self.record(Command::EditMetadata {
+                before,
+                after: self.document.metadata.clone(),
+            });
+        }
     }
 
     fn render_styles_tab(&mut self, ui: &mut egui::Ui) {
+        let before = self.document.styles.clone();
+        let mut names: Vec<String> = self.document.styles.styles.keys().cloned().collect();
+        names.sort();
+
+        if !self
+            .selected_style
+            .as_ref()
+            .is_some_and(|name| self.document.styles.styles.contains_key(name))
+        {
+            self.select_style(names.first().cloned());
+        }
+
+        let mut action = None;
+
+        ui.columns(2, |cols| {
+            let ui = &mut cols[0];
+            ui.heading("Document Styles");
+            ui.horizontal(|ui| {
+                if ui.button("➕ New").clicked() {
+                    action = Some(StyleAction::New);
+                }
+                if ui.button("📋 Duplicate").clicked() {
+                    action = Some(StyleAction::Duplicate);
+                }
+                if ui.button("🗑 Delete").clicked() {
+                    action = Some(StyleAction::Delete);
+                }
+            });
+            ui.horizontal(|ui| {
+                if ui.button("📥 Import...").clicked() {
+                    action = Some(StyleAction::Import);
+                }
+                if ui.button("📤 Export...").clicked() {
+                    action = Some(StyleAction::Export);
+                }
+            });
+            ui.separator();
+
+            ScrollArea::vertical()
+                .id_salt("styles_scroll")
+                .show(ui, |ui| {
+                    for name in &names {
+                        let selected = self.selected_style.as_ref() == Some(name);
+                        if ui.selectable_label(selected, name).clicked() {
+                            action = Some(StyleAction::Select(name.clone()));
+                        }
+                    }
+                });
+
+            let ui = &mut cols[1];
+            let Some(name) = self.selected_style.clone() else {
+                ui.label("No styles");
+                return;
+            };
+
+            ScrollArea::vertical()
+                .id_salt("style_editor_scroll")
+                .show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Name:");
+                        ui.text_edit_singleline(&mut self.style_name_edit);
+
+                        let new_name = self.style_name_edit.trim();
+                        let can_rename = !new_name.is_empty()
+                            && !self.document.styles.styles.contains_key(new_name);
+                        if ui
+                            .add_enabled(can_rename, egui::Button::new("Rename"))
+                            .clicked()
+                        {
+                            action = Some(StyleAction::Rename(new_name.to_string()));
+                        }
+                    });
+                    ui.separator();
+
+                    if let Some(style) = self.document.styles.styles.get_mut(&name) {
+                        render_style_fields(ui, style);
+                    }
+                    ui.separator();
+
+                    ui.label(RichText::new("Selected block").strong());
+                    let line = self.cursor_line;
+                    match block_style_at(&self.raw_content, line) {
+                        Some(current) => {
+                            ui.label(format!("Line {} uses \"{}\"", line + 1, current));
+                            let apply = egui::Button::new(format!("Apply \"{}\"", name));
+                            if ui.add_enabled(current != name, apply).clicked() {
+                                action = Some(StyleAction::Apply);
+                            }
+                        }
+                        None => {
+                            ui.label(
+                                RichText::new(format!(
+                                    "No styleable block on line {} of the editor",
+                                    line + 1
+                                ))
+                                .weak(),
+                            );
+                        }
+                    }
+                    ui.separator();
+
+                    ui.label(RichText::new("Preview").strong());
+                    for (text, language) in [
+                        ("The quick brown fox jumps over the lazy dog.", "en"),
+                        ("نص تجريبي لمعاينة النمط.", "ar"),
+                    ] {
+                        let sample = Node::Paragraph {
+                            runs: vec![TextRun::new(text, language, &name)],
+                            style: name.clone(),
+                        };
+                        render_node(
+                            ui,
+                            &sample,
+                            &self.document.styles,
+                            self.zoom_level,
+                            &self.theme,
+                            &self.loaded_images,
+                        );
+                    }
+                });
+        });
+
+        if self.document.styles != before {
+            self.record(Command::EditStyles {
+                before,
+                after: self.document.styles.clone(),
+            });
+        }
+
+        if let Some(action) = action {
+            self.apply_style_action(action);
+        }
+    }
+
+    fn select_style(&mut self, name: Option<String>) {
+        self.style_name_edit = name.clone().unwrap_or_default();
+        self.selected_style = name;
+    }
+
+    fn apply_style_action(&mut self, action: StyleAction) {
+        let before = self.document.styles.clone();
+        let selected = self.selected_style.clone();
+
+        match action {
+            StyleAction::Select(name) => self.select_style(Some(name)),
+
+            StyleAction::New | StyleAction::Duplicate => {
+                let (base, style) = match (&action, &selected) {
+                    (StyleAction::Duplicate, Some(name)) => (
+                        format!("{}-copy", name),
+                        self.document.styles.styles[name].clone(),
+                    ),
+                    _ => ("style".to_string(), Style::default()),
+                };
+                let name = self.unique_style_name(&base);
+                self.document.styles.styles.insert(name.clone(), style);
+                self.select_style(Some(name));
+            }
+
+            StyleAction::Delete => {
+                if let Some(name) = &selected {
+                    self.document.styles.styles.remove(name);
+                    self.status_message = format!("Deleted style {}", name);
+                    self.select_style(None);
+                }
+            }
+
+            StyleAction::Rename(new_name) => {
+                let Some(old_name) = selected else {
+                    return;
+                };
+                let mut document = self.document.clone();
+                if let Some(style) = document.styles.styles.remove(&old_name) {
+                    document.styles.styles.insert(new_name.clone(), style);
+                }
+                document.content.rename_style(&old_name, &new_name);
+
+                let raw_content = serialize_content(&document.content);
+                let label = format!("Rename style {} to {}", old_name, new_name);
+                self.replace_document(&label, document, raw_content, self.path.clone());
+                self.select_style(Some(new_name));
+                self.status_message = label;
+                return;
+            }
+
+            StyleAction::Import => match import_styles() {
+                Ok(Some(styles)) => {
+                    let count = styles.styles.len();
+                    self.document.styles.merge(styles);
+                    self.status_message = format!("Imported {} styles", count);
+                }
+                Ok(None) => self.status_message = "No styles imported".to_string(),
+                Err(e) => self.report_error("Style import", e),
+            },
+
+            StyleAction::Export => match export_styles(&self.document.styles) {
+                Ok(Some(path)) => {
+                    self.status_message = format!("Styles exported: {}", path.display());
+                }
+                Ok(None) => {}
+                Err(e) => self.report_error("Style export", e),
+            },
+
+            StyleAction::Apply => {
+                let Some(name) = selected else {
+                    return;
+                };
+                if let Some(text) = set_block_style(&self.raw_content, self.cursor_line, &name) {
+                    let before = std::mem::replace(&mut self.raw_content, text);
+                    self.text_changed(before);
+                    self.status_message = format!("Applied style {}", name);
+                }
+                return;
+            }
+        }
+
+        if self.document.styles != before {
+            self.record(Command::EditStyles {
+                before,
+                after: self.document.styles.clone(),
+            });
+        }
+    }
+
+    fn unique_style_name(&self, base: &str) -> String {
+        (1..)
+            .map(|n| match n {
+                1 => base.to_string(),
+                n => format!("{}-{}", base, n),
+            })
+            .find(|name| !self.document.styles.styles.contains_key(name))
+            .unwrap()
+    }
+
+    // ========================================================================
+    // History
+    // ========================================================================
+
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        // Consumed before the text editor sees them, so its own single-field
+        // undo never competes with the document history
+        let (redo, undo) = ctx.input_mut(|i| {
+            let redo = i.consume_shortcut(&REDO) || i.consume_shortcut(&REDO_ALT);
+            (redo, i.consume_shortcut(&UNDO))
+        });
+
+        if redo {
+            self.redo();
+        } else if undo {
+            self.undo();
+        }
+    }
+
+    /// Records an applied change, which leaves the document modified.
+    fn record(&mut self, command: Command) {
+        self.history.record(command);
+        self.dirty = true;
+    }
+
+    fn undo(&mut self) {
+        if let Some(label) =
+            self.history
+                .undo(&mut self.document, &mut self.raw_content, &mut self.path)
+        {
+            self.dirty = !self.history.is_saved();
+            self.status_message = format!("Undo: {}", label);
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(label) =
+            self.history
+                .redo(&mut self.document, &mut self.raw_content, &mut self.path)
+        {
+            self.dirty = !self.history.is_saved();
+            self.status_message = format!("Redo: {}", label);
+        }
+    }
+
+    /// Re-parses the editor text and records the edit.
+    fn text_changed(&mut self, before: String) {
+        self.document.content = parse_content(&self.raw_content);
+        self.record(Command::EditText {
+            before,
+            after: self.raw_content.clone(),
+        });
+    }
+
+    fn replace_document(
+        &mut self,
+        label: &str,
+        document: PdxDocument,
+        raw_content: String,
+        path: Option<PathBuf>,
+    ) {
+        let before = DocumentState {
+            document: std::mem::replace(&mut self.document, document),
+            raw_content: std::mem::replace(&mut self.raw_content, raw_content),
+            path: std::mem::replace(&mut self.path, path),
+        };
+        let after = DocumentState {
+            document: self.document.clone(),
+            raw_content: self.raw_content.clone(),
+            path: self.path.clone(),
+        };
+
+        self.record(Command::ReplaceDocument {
+            label: label.to_string(),
+            before: Box::new(before),
+            after: Box::new(after),
+        });
+    }
+
+    // ========================================================================
+    // Files & Unsaved Changes
+    // ========================================================================
+
+    fn file_name(&self) -> String {
+        match &self.path {
+            Some(path) => path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+            None => "Untitled".to_string(),
+        }
+    }
+
+    /// Runs `action` right away, or asks first when there are unsaved changes.
+    fn request(&mut self, action: PendingAction) {
+        if self.dirty {
+            self.pending = Some(action);
+        } else {
+            self.perform(action);
+        }
+    }
+
+    fn perform(&mut self, action: PendingAction) {
+        match action {
+            PendingAction::New => {
+                let document = create_sample_document();
+                let raw_content = serialize_content(&document.content);
+                self.replace_document("New document", document, raw_content, None);
+                self.mark_clean();
+                self.status_message = "New document created".to_string();
+            }
+            PendingAction::Open => match open_document() {
+                Ok(Some((doc, path))) if is_imported_file(&path) => {
+                    // Not a .pdx yet, so it starts out unsaved and without a path
+                    let raw_content = serialize_content(&doc.content);
+                    let label = format!(
+                        "Import {}",
+                        path.file_name().unwrap_or_default().to_string_lossy()
+                    );
+                    self.replace_document(&label, doc, raw_content, None);
+                    self.status_message = format!("Imported: {}", path.display());
+                }
+                Ok(Some((doc, path))) => {
+                    let raw_content = serialize_content(&doc.content);
+                    let label = format!(
+                        "Open {}",
+                        path.file_name().unwrap_or_default().to_string_lossy()
+                    );
+                    self.replace_document(&label, doc, raw_content, Some(path.clone()));
+                    self.mark_clean();
+                    self.status_message = format!("Opened: {}", path.display());
+                }
+                Ok(None) => {}
+                Err(e) => self.report_error("Open", e),
+            },
+            PendingAction::Close => {
+                self.allow_close = true;
+            }
+        }
+    }
+
+    /// Saves to the current path, or asks for one. Returns false when the
+    /// user cancelled the dialog or saving failed.
+    fn save_file(&mut self, save_as: bool) -> bool {
+        let current = if save_as { None } else { self.path.as_ref() };
+        let path = match save_document(&self.document, current) {
+            Ok(Some(path)) => path,
+            Ok(None) => return false,
+            Err(e) => {
+                self.report_error("Save", e);
+                return false;
+            }
+        };
+
+        self.path = Some(path.clone());
+        self.last_save = Some(chrono::Local::now().format("%H:%M:%S").to_string());
+        self.mark_clean();
+        self.status_message = if save_as {
+            format!("Saved as: {}", path.display())
+        } else {
+            format!("Saved: {}", path.display())
+        };
+        true
+    }
+
+    /// Holds a window close back until unsaved changes are dealt with.
+    fn handle_close_request(&mut self, ctx: &egui::Context) {
+        if !ctx.input(|i| i.viewport().close_requested()) || self.allow_close {
+            return;
+        }
+        if self.dirty {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            self.pending = Some(PendingAction::Close);
+        }
+    }
+
+    fn render_unsaved_prompt(&mut self, ctx: &egui::Context) {
+        let Some(action) = self.pending else {
+            return;
+        };
+        let question = match action {
+            PendingAction::New => "before creating a new document",
+            PendingAction::Open => "before opening another document",
+            PendingAction::Close => "before closing",
+        };
+
+        let mut choice = None;
+        egui::Window::new("Unsaved Changes")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "Save changes to \"{}\" {}?",
+                    self.file_name(),
+                    question
+                ));
+                ui.label(RichText::new("Your changes will be lost if you don't save them.").weak());
+                ui.add_space(6.0);
+
+                ui.horizontal(|ui| {
+                    if ui.button("💾 Save").clicked() {
+                        choice = Some(true);
+                    }
+                    if ui.button("Discard").clicked() {
+                        choice = Some(false);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.pending = None;
+                    }
+                });
+            });
+
+        let Some(save) = choice else {
+            return;
+        };
+        self.pending = None;
+        if save && !self.save_file(false) {
+            return;
+        }
+
+        self.perform(action);
+        if action == PendingAction::Close {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+    }
+
+    /// Copies every linked image into the document so it travels with it.
+    fn embed_all_images(&mut self) {
+        let mut document = self.document.clone();
+        let count = embed_images(&mut document);
+        if count == 0 {
+            self.status_message = "No linked images to embed".to_string();
+            return;
+        }
+
+        // The images only travel in a bundle, so saving moves to a .pdxz
+        // beside the original, or asks where rather than overwrite one
+        let path = self.path.as_ref().and_then(|path| {
+            if path.extension().is_some_and(|ext| ext == BUNDLE_EXTENSION) {
+                return Some(path.clone());
+            }
+            let bundle = path.with_extension(BUNDLE_EXTENSION);
+            (!bundle.exists()).then_some(bundle)
+        });
+
+        let raw_content = serialize_content(&document.content);
+        self.replace_document("Embed images", document, raw_content, path);
+        self.status_message = format!(
+            "Embedded {} images; the document now saves as a bundle",
+            count
+        );
+    }
+
+    /// Logs a failed file operation and opens the error dialog.
+    fn report_error(&mut self, action: &str, error: PdxError) {
+        log::error!("{} failed: {}", action, error);
+        self.status_message = format!("{} failed: {}", action, error);
+        self.error = Some((action.to_string(), error));
+    }
+
+    fn render_error_window(&mut self, ctx: &egui::Context) {
+        let Some((action, error)) = &self.error else {
+            return;
+        };
+        let mut open = true;
+        let mut close = false;
+
+        egui::Window::new(format!("⚠ {} failed", action))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                ui.label(error.to_string());
+                ui.add_space(6.0);
+
+                ui.collapsing("Details", |ui| {
+                    ui.label(RichText::new(error.details()).monospace());
+                });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("📋 Copy Details").clicked() {
+                        ctx.copy_text(error.details());
+                    }
+                    if ui.button("OK").clicked() {
+                        close = true;
+                    }
+                });
+            });
+
+        if !open || close {
+            self.error = None;
+        }
+    }
+
+    /// Shows the file name in the title bar, with a marker when modified.
+    fn update_title(&mut self, ctx: &egui::Context) {
+        let title = format!(
+            "{}{} - PDX Editor",
+            if self.dirty { "● " } else { "" },
+            self.file_name()
+        );
+        if title != self.window_title {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(title.clone()));
+            self.window_title = title;
+        }
+    }
+
+    // ========================================================================
+    // Autosave & Recovery
+    // ========================================================================
+
+    /// Marks the current document as safely stored, which clears the dirty
+    /// flag and this session's recovery file.
+    fn mark_clean(&mut self) {
+        self.dirty = false;
+        self.history.mark_saved();
+        self.autosave
+            .mark_clean(&self.document, &self.raw_content, self.path.as_deref());
+    }
+
+    fn run_autosave(&mut self, ctx: &egui::Context) {
+        match self
+            .autosave
+            .tick(&self.document, &self.raw_content, self.path.as_deref())
+        {
+            Ok(Some(time)) => self.status_message = format!("Autosaved at {}", time),
+            Ok(None) => {}
+            Err(e) => {
+                log::error!("Autosave failed: {}", e);
+                self.status_message = format!("Autosave failed: {}", e);
+            }
+        }
+
+        if let Some(due) = self.autosave.due_in() {
+            ctx.request_repaint_after(due);
+        }
+    }
+
+    fn render_recovery_window(&mut self, ctx: &egui::Context) {
+        let mut recover = None;
+        let mut discard = None;
+        let mut discard_all = false;
+
+        egui::Window::new("🩹 Recover Unsaved Documents")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                if self.recoveries.is_empty() {
+                    ui.label("No unsaved documents to recover.");
+                } else {
+                    ui.label("These documents were not saved when the editor last closed:");
+                    ui.add_space(6.0);
+
+                    egui::Grid::new("recoveries")
+                        .num_columns(4)
+                        .spacing([12.0, 6.0])
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for (i, recovery) in self.recoveries.iter().enumerate() {
+                                ui.label(RichText::new(recovery.name()).strong());
+                                ui.label(RichText::new(&recovery.saved_at).weak());
+                                if ui.button("Recover").clicked() {
+                                    recover = Some(i);
+                                }
+                                if ui.button("Discard").clicked() {
+                                    discard = Some(i);
+                                }
+                                ui.end_row();
+                            }
+                        });
+                }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if !self.recoveries.is_empty() && ui.button("Discard All").clicked() {
+                        discard_all = true;
+                    }
+                    if ui.button("Later").clicked() {
+                        self.show_recovery = false;
+                    }
+                });
+            });
+
+        if let Some(i) = recover {
+            let recovery = self.recoveries.remove(i);
+            let label = format!("Recover {}", recovery.name());
+            self.replace_document(
+                &label,
+                recovery.document.clone(),
+                recovery.raw_content.clone(),
+                recovery.path.clone(),
+            );
+            recovery.discard();
+            self.status_message =
+                format!("Recovered {} from {}", recovery.name(), recovery.saved_at);
+            self.show_recovery = false;
+        }
+        if let Some(i) = discard {
+            self.recoveries.remove(i).discard();
+        }
+        if discard_all {
+            for recovery in self.recoveries.drain(..) {
+                recovery.discard();
+            }
+            self.show_recovery = false;
+        }
+    }
+
+    /// Lists applied changes above the current state and undone ones below
+    /// it. Clicking an entry undoes or redoes up to it.
+    fn render_history_panel(&mut self, ui: &mut egui::Ui) {
+        ui.heading("📜 History");
+        ui.separator();
+
+        let done = self.history.done_labels();
+        let undone = self.history.undone_labels();
+        let mut undo_steps = 0;
+        let mut redo_steps = 0;
+
         ScrollArea::vertical()
-            .id_salt("styles_scroll")
+            .id_salt("history_scroll")
             .show(ui, |ui| {
-                ui.heading("Document Styles");
-                ui.separator();
+                if ui
+                    .selectable_label(done.is_empty(), RichText::new("Original").italics())
+                    .clicked()
+                {
+                    undo_steps = done.len();
+                }
 
-                for (name, style) in &self.document.styles.styles {
-                    ui.group(|ui| {
-                        ui.heading(name);
-                        ui.label(format!("Font Size: {}pt", style.font_size));
-                        ui.label(format!("Font Weight: {:?}", style.font_weight));
-                        ui.label(format!("Text Align: {:?}", style.text_align));
-                        ui.label(format!("Direction: {:?}", style.direction));
-                        ui.label(format!("Line Height: {}", style.line_height));
-                    });
-                    ui.add_space(8.0);
+                for (i, label) in done.iter().enumerate() {
+                    let current = i + 1 == done.len();
+                    if ui.selectable_label(current, label).clicked() {
+                        undo_steps = done.len() - (i + 1);
+                    }
+                }
+
+                for (i, label) in undone.iter().enumerate() {
+                    if ui
+                        .selectable_label(false, RichText::new(label).weak())
+                        .clicked()
+                    {
+                        redo_steps = i + 1;
+                    }
                 }
             });
+
+        for _ in 0..undo_steps {
+            self.undo();
+        }
+        for _ in 0..redo_steps {
+            self.redo();
+        }
     }
-}
\ No newline at end of file
+}
+
+// ============================================================================
+// Style Editor
+// ============================================================================
+
+fn render_style_fields(ui: &mut egui::Ui, style: &mut Style) {
+    egui::Grid::new("style_fields")
+        .num_columns(2)
+        .spacing([12.0, 6.0])
+        .show(ui, |ui| {
+            ui.label("Font size:");
+            ui.add(
+                egui::DragValue::new(&mut style.font_size)
+                    .range(0.0..=96.0)
+                    .speed(0.25)
+                    .suffix(" px"),
+            )
+            .on_hover_text("0 uses the default size");
+            ui.end_row();
+
+            ui.label("Font weight:");
+            enum_combo(
+                ui,
+                "style_weight",
+                &mut style.font_weight,
+                &[
+                    (FontWeight::Light, "Light"),
+                    (FontWeight::Normal, "Normal"),
+                    (FontWeight::Bold, "Bold"),
+                ],
+            );
+            ui.end_row();
+
+            ui.label("Color:");
+            let mut rgb = [style.color.r, style.color.g, style.color.b];
+            if ui.color_edit_button_srgb(&mut rgb).changed() {
+                style.color = Color::rgb(rgb[0], rgb[1], rgb[2]);
+            }
+            ui.end_row();
+
+            ui.label("Text align:");
+            enum_combo(
+                ui,
+                "style_align",
+                &mut style.text_align,
+                &[
+                    (TextAlign::Start, "Start"),
+                    (TextAlign::Center, "Center"),
+                    (TextAlign::End, "End"),
+                    (TextAlign::Justify, "Justify"),
+                ],
+            );
+            ui.end_row();
+
+            ui.label("Direction:");
+            enum_combo(
+                ui,
+                "style_direction",
+                &mut style.direction,
+                &[
+                    (Direction::Auto, "Auto"),
+                    (Direction::LTR, "Left to right"),
+                    (Direction::RTL, "Right to left"),
+                ],
+            );
+            ui.end_row();
+
+            ui.label("Line height:");
+            ui.add(
+                egui::DragValue::new(&mut style.line_height)
+                    .range(0.0..=4.0)
+                    .speed(0.01),
+            )
+            .on_hover_text("0 uses the default line height");
+            ui.end_row();
+
+            ui.label("Margin:");
+            edge_insets_fields(ui, &mut style.margin);
+            ui.end_row();
+
+            ui.label("Padding:");
+            edge_insets_fields(ui, &mut style.padding);
+            ui.end_row();
+        });
+}
+
+fn edge_insets_fields(ui: &mut egui::Ui, insets: &mut EdgeInsets) {
+    ui.horizontal(|ui| {
+        for (value, label) in [
+            (&mut insets.top, "Top"),
+            (&mut insets.right, "Right"),
+            (&mut insets.bottom, "Bottom"),
+            (&mut insets.left, "Left"),
+        ] {
+            ui.add(egui::DragValue::new(value).range(0.0..=200.0).speed(0.5))
+                .on_hover_text(label);
+        }
+    });
+}
+
+fn enum_combo<T: Copy + PartialEq>(
+    ui: &mut egui::Ui,
+    id: &str,
+    value: &mut T,
+    options: &[(T, &str)],
+) {
+    let selected = options
+        .iter()
+        .find(|(option, _)| option == value)
+        .map_or("", |(_, label)| *label);
+
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(selected)
+        .show_ui(ui, |ui| {
+            for (option, label) in options {
+                ui.selectable_value(value, *option, *label);
+            }
+        });
+}
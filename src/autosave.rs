@@ -0,0 +1,297 @@
+use pdx_core::data::PdxDocument;
+use pdx_core::error::PdxError;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs::{self, File, TryLockError};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+// ============================================================================
+// Autosave & Recovery
+// ============================================================================
+
+/// Intervals offered in the File menu, in seconds. `None` turns autosave off.
+pub const INTERVALS: [(Option<u64>, &str); 6] = [
+    (None, "Off"),
+    (Some(30), "Every 30 seconds"),
+    (Some(60), "Every minute"),
+    (Some(120), "Every 2 minutes"),
+    (Some(300), "Every 5 minutes"),
+    (Some(600), "Every 10 minutes"),
+];
+
+pub const DEFAULT_INTERVAL: Option<u64> = Some(60);
+
+/// Recovery files live here, one per editor session.
+fn recovery_dir() -> PathBuf {
+    eframe::storage_dir("com.terabit.pdxeditor")
+        .unwrap_or_else(std::env::temp_dir)
+        .join("recovery")
+}
+
+/// An autosaved document, with what the editor needs to pick it up again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recovery {
+    pub document: PdxDocument,
+    pub raw_content: String,
+    /// Where the document was last saved, if anywhere
+    pub path: Option<PathBuf>,
+    pub saved_at: String,
+    /// Embedded images the content refers to, which the document's own
+    /// JSON leaves out. Stored base64-encoded.
+    #[serde(default, with = "base64_files")]
+    pub files: HashMap<String, Vec<u8>>,
+    /// Directory relative image paths resolve against
+    #[serde(default)]
+    pub base_dir: Option<PathBuf>,
+    /// The recovery file this was read from
+    #[serde(skip)]
+    pub file: PathBuf,
+}
+
+/// Recovery file images as base64 strings rather than arrays of numbers,
+/// which take four times the space and are slow to parse.
+mod base64_files {
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::HashMap;
+
+    pub fn serialize<S: Serializer>(
+        files: &HashMap<String, Vec<u8>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let encoded: HashMap<&str, String> = files
+            .iter()
+            .map(|(name, bytes)| (name.as_str(), STANDARD.encode(bytes)))
+            .collect();
+        encoded.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<String, Vec<u8>>, D::Error> {
+        HashMap::<String, String>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(name, text)| {
+                let bytes = STANDARD
+                    .decode(&text)
+                    .map_err(|e| D::Error::custom(format!("{}: {}", name, e)))?;
+                Ok((name, bytes))
+            })
+            .collect()
+    }
+}
+
+impl Recovery {
+    pub fn name(&self) -> String {
+        match &self.path {
+            Some(path) => path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+            None if !self.document.metadata.title.is_empty() => {
+                self.document.metadata.title.clone()
+            }
+            None => "Untitled".to_string(),
+        }
+    }
+
+    /// Removes the recovery file once the document is recovered or dropped.
+    pub fn discard(&self) {
+        if let Err(e) = fs::remove_file(&self.file) {
+            log::warn!("Could not remove {}: {}", self.file.display(), e);
+        }
+        let _ = fs::remove_file(lock_path(&self.file));
+    }
+}
+
+/// The file a session keeps locked while it runs, beside its recovery file.
+fn lock_path(file: &Path) -> PathBuf {
+    file.with_extension("lock")
+}
+
+/// Whether the session that wrote `file` is still running, i.e. still
+/// holds the lock on its lock file. This session's own file counts as live.
+fn is_live(file: &Path) -> bool {
+    let Ok(lock) = File::open(lock_path(file)) else {
+        return false;
+    };
+    matches!(lock.try_lock_shared(), Err(TryLockError::WouldBlock))
+}
+
+/// Recovery files left behind by sessions that did not exit cleanly,
+/// newest first. Files of editor windows still running are left alone.
+pub fn pending_recoveries() -> Vec<Recovery> {
+    let Ok(entries) = fs::read_dir(recovery_dir()) else {
+        return Vec::new();
+    };
+
+    let mut recoveries: Vec<Recovery> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter(|path| !is_live(path))
+        .filter_map(|file| {
+            let text = fs::read_to_string(&file).ok()?;
+            match serde_json::from_str::<Recovery>(&text) {
+                Ok(mut recovery) => {
+                    let resources = &mut recovery.document.resources;
+                    resources.files = std::mem::take(&mut recovery.files);
+                    resources.base_dir = recovery.base_dir.clone();
+                    Some(Recovery { file, ..recovery })
+                }
+                Err(e) => {
+                    let error = PdxError::from(e).with_path(&file);
+                    log::warn!("Skipping unreadable recovery file: {}", error);
+                    None
+                }
+            }
+        })
+        .collect();
+
+    recoveries.sort_by(|a, b| b.saved_at.cmp(&a.saved_at));
+    recoveries
+}
+
+/// Periodically copies the open document to this session's recovery file.
+/// The file is removed whenever the document matches what is on disk.
+pub struct Autosave {
+    /// Seconds between saves; `None` when off
+    pub interval: Option<u64>,
+    file: PathBuf,
+    last_check: Instant,
+    /// Fingerprint of the state last saved or opened by the user
+    clean: Option<u64>,
+    /// Fingerprint of the state in the recovery file
+    written: Option<u64>,
+    /// Held from the first write until exit, telling other sessions this
+    /// one's recovery file is not left over from a crash
+    lock: Option<File>,
+}
+
+impl Autosave {
+    pub fn new(interval: Option<u64>) -> Self {
+        let session = format!(
+            "session-{}-{}.json",
+            chrono::Local::now().format("%Y%m%d-%H%M%S"),
+            std::process::id()
+        );
+
+        Self {
+            interval,
+            file: recovery_dir().join(session),
+            last_check: Instant::now(),
+            clean: None,
+            written: None,
+            lock: None,
+        }
+    }
+
+    /// Records the current state as safely stored, e.g. after Open or Save.
+    pub fn mark_clean(&mut self, document: &PdxDocument, raw_content: &str, path: Option<&Path>) {
+        self.clean = Some(fingerprint(document, raw_content, path));
+        self.remove_file();
+    }
+
+    /// Time until the next check, so the caller can schedule a repaint.
+    pub fn due_in(&self) -> Option<Duration> {
+        let interval = Duration::from_secs(self.interval?);
+        Some(interval.saturating_sub(self.last_check.elapsed()))
+    }
+
+    /// Writes the recovery file if the interval has passed and the document
+    /// changed since the last write. Returns the time of a write.
+    pub fn tick(
+        &mut self,
+        document: &PdxDocument,
+        raw_content: &str,
+        path: Option<&Path>,
+    ) -> Result<Option<String>, PdxError> {
+        if self.due_in() != Some(Duration::ZERO) {
+            return Ok(None);
+        }
+        self.last_check = Instant::now();
+
+        let current = fingerprint(document, raw_content, path);
+        if Some(current) == self.clean {
+            self.remove_file();
+            return Ok(None);
+        }
+        if Some(current) == self.written {
+            return Ok(None);
+        }
+
+        let saved_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let resources = &document.resources;
+        let files = document
+            .content
+            .image_paths()
+            .into_iter()
+            .filter_map(|name| Some((name.to_string(), resources.files.get(name)?.clone())))
+            .collect();
+        let recovery = Recovery {
+            document: document.clone(),
+            raw_content: raw_content.to_string(),
+            path: path.map(Path::to_path_buf),
+            saved_at: saved_at.clone(),
+            files,
+            base_dir: resources.base_dir.clone(),
+            file: PathBuf::new(),
+        };
+        let json =
+            serde_json::to_string(&recovery).map_err(|e| PdxError::Serialize(e.to_string()))?;
+
+        if let Some(dir) = self.file.parent() {
+            fs::create_dir_all(dir).map_err(|e| PdxError::io(dir, e))?;
+        }
+        if self.lock.is_none() {
+            self.lock = Some(self.take_lock()?);
+        }
+        // Write beside the target first so a crash mid-write keeps the old copy
+        let partial = self.file.with_extension("json.partial");
+        fs::write(&partial, json).map_err(|e| PdxError::io(&partial, e))?;
+        fs::rename(&partial, &self.file).map_err(|e| PdxError::io(&self.file, e))?;
+
+        self.written = Some(current);
+        log::debug!("Autosaved to {}", self.file.display());
+        Ok(Some(saved_at))
+    }
+
+    /// Drops this session's recovery file, as on a clean exit.
+    pub fn remove_file(&mut self) {
+        if self.written.take().is_some()
+            && self.file.exists()
+            && let Err(e) = fs::remove_file(&self.file)
+        {
+            log::warn!("Could not remove {}: {}", self.file.display(), e);
+        }
+    }
+
+    fn take_lock(&self) -> Result<File, PdxError> {
+        let path = lock_path(&self.file);
+        let lock = File::create(&path).map_err(|e| PdxError::io(&path, e))?;
+        lock.lock().map_err(|e| PdxError::io(&path, e))?;
+        Ok(lock)
+    }
+}
+
+impl Drop for Autosave {
+    fn drop(&mut self) {
+        if self.lock.take().is_some() {
+            let _ = fs::remove_file(lock_path(&self.file));
+        }
+    }
+}
+
+fn fingerprint(document: &PdxDocument, raw_content: &str, path: Option<&Path>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    document.to_json().unwrap_or_default().hash(&mut hasher);
+    raw_content.hash(&mut hasher);
+    path.hash(&mut hasher);
+    hasher.finish()
+}
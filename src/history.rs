@@ -0,0 +1,242 @@
+use pdx_core::data::{Metadata, PdxDocument, StyleSheet};
+use pdx_core::parser::parse_content;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+// ============================================================================
+// Undo / Redo
+// ============================================================================
+
+/// Edits of the same kind closer together than this merge into one entry,
+/// so undo removes a burst of typing rather than a single keystroke.
+const COALESCE_WINDOW: Duration = Duration::from_millis(1000);
+
+/// Oldest entries are dropped beyond this many.
+const HISTORY_LIMIT: usize = 200;
+
+/// One undoable change, holding the state before and after it.
+#[derive(Debug, Clone)]
+pub enum Command {
+    /// Text typed or deleted in the editor
+    EditText {
+        before: String,
+        after: String,
+    },
+    /// Image markup added by Insert Image
+    InsertImage {
+        path: String,
+        before: String,
+        after: String,
+    },
+    EditMetadata {
+        before: Metadata,
+        after: Metadata,
+    },
+    EditStyles {
+        before: StyleSheet,
+        after: StyleSheet,
+    },
+    /// New, Open or a style rename swapping out the whole document
+    ReplaceDocument {
+        label: String,
+        before: Box<DocumentState>,
+        after: Box<DocumentState>,
+    },
+}
+
+/// Everything a document replacement swaps out.
+#[derive(Debug, Clone)]
+pub struct DocumentState {
+    pub document: PdxDocument,
+    pub raw_content: String,
+    pub path: Option<PathBuf>,
+}
+
+impl Command {
+    pub fn label(&self) -> String {
+        match self {
+            Command::EditText { .. } => "Typing".to_string(),
+            Command::InsertImage { path, .. } => {
+                let name = PathBuf::from(path)
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_else(|| path.clone());
+                format!("Insert image {}", name)
+            }
+            Command::EditMetadata { .. } => "Edit metadata".to_string(),
+            Command::EditStyles { .. } => "Edit styles".to_string(),
+            Command::ReplaceDocument { label, .. } => label.clone(),
+        }
+    }
+
+    /// Puts the editor into the state after (`forward`) or before the command.
+    fn apply(
+        &self,
+        forward: bool,
+        document: &mut PdxDocument,
+        raw_content: &mut String,
+        path: &mut Option<PathBuf>,
+    ) {
+        match self {
+            Command::EditText { before, after } | Command::InsertImage { before, after, .. } => {
+                let text = if forward { after } else { before };
+                raw_content.clone_from(text);
+                document.content = parse_content(text);
+            }
+            Command::EditMetadata { before, after } => {
+                document
+                    .metadata
+                    .clone_from(if forward { after } else { before });
+            }
+            Command::EditStyles { before, after } => {
+                document
+                    .styles
+                    .clone_from(if forward { after } else { before });
+            }
+            Command::ReplaceDocument { before, after, .. } => {
+                let state = if forward { after } else { before };
+                document.clone_from(&state.document);
+                raw_content.clone_from(&state.raw_content);
+                path.clone_from(&state.path);
+            }
+        }
+    }
+
+    /// Folds a following edit of the same kind into this one.
+    fn absorb(&mut self, next: &Command) -> bool {
+        match (self, next) {
+            (Command::EditText { after, .. }, Command::EditText { after: next, .. }) => {
+                after.clone_from(next);
+                true
+            }
+            (Command::EditMetadata { after, .. }, Command::EditMetadata { after: next, .. }) => {
+                after.clone_from(next);
+                true
+            }
+            (Command::EditStyles { after, .. }, Command::EditStyles { after: next, .. }) => {
+                after.clone_from(next);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+struct Entry {
+    command: Command,
+    at: Instant,
+}
+
+pub struct History {
+    undo_stack: Vec<Entry>,
+    redo_stack: Vec<Entry>,
+    /// Set by undo, redo and saving so the next edit starts a fresh entry
+    sealed: bool,
+    /// Undo stack depth when the document was last saved; `None` once no
+    /// undo or redo can lead back there
+    saved: Option<usize>,
+}
+
+impl Default for History {
+    /// A blank document counts as saved.
+    fn default() -> Self {
+        Self {
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            sealed: false,
+            saved: Some(0),
+        }
+    }
+}
+
+impl History {
+    /// Records a change that has already been applied. Clears the redo stack.
+    pub fn record(&mut self, command: Command) {
+        let now = Instant::now();
+        self.redo_stack.clear();
+        if self.saved.is_some_and(|depth| depth > self.undo_stack.len()) {
+            self.saved = None;
+        }
+
+        if !self.sealed
+            && let Some(last) = self.undo_stack.last_mut()
+            && now.duration_since(last.at) < COALESCE_WINDOW
+            && last.command.absorb(&command)
+        {
+            last.at = now;
+            return;
+        }
+
+        self.sealed = false;
+        self.undo_stack.push(Entry { command, at: now });
+        if self.undo_stack.len() > HISTORY_LIMIT {
+            self.undo_stack.remove(0);
+            self.saved = self.saved.and_then(|depth| depth.checked_sub(1));
+        }
+    }
+
+    /// Remembers the current state as the one on disk.
+    pub fn mark_saved(&mut self) {
+        self.saved = Some(self.undo_stack.len());
+        self.sealed = true;
+    }
+
+    /// Whether undo and redo have led back to the state last saved.
+    pub fn is_saved(&self) -> bool {
+        self.saved == Some(self.undo_stack.len())
+    }
+
+    /// Reverts the latest change, returning its label.
+    pub fn undo(
+        &mut self,
+        document: &mut PdxDocument,
+        raw_content: &mut String,
+        path: &mut Option<PathBuf>,
+    ) -> Option<String> {
+        let entry = self.undo_stack.pop()?;
+        entry.command.apply(false, document, raw_content, path);
+
+        let label = entry.command.label();
+        self.redo_stack.push(entry);
+        self.sealed = true;
+        Some(label)
+    }
+
+    /// Re-applies the latest undone change, returning its label.
+    pub fn redo(
+        &mut self,
+        document: &mut PdxDocument,
+        raw_content: &mut String,
+        path: &mut Option<PathBuf>,
+    ) -> Option<String> {
+        let entry = self.redo_stack.pop()?;
+        entry.command.apply(true, document, raw_content, path);
+
+        let label = entry.command.label();
+        self.undo_stack.push(entry);
+        self.sealed = true;
+        Some(label)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Labels of applied changes, oldest first.
+    pub fn done_labels(&self) -> Vec<String> {
+        self.undo_stack.iter().map(|e| e.command.label()).collect()
+    }
+
+    /// Labels of undone changes, next redo first.
+    pub fn undone_labels(&self) -> Vec<String> {
+        self.redo_stack
+            .iter()
+            .rev()
+            .map(|e| e.command.label())
+            .collect()
+    }
+}
@@ -3,10 +3,8 @@
 use eframe::egui;
 
 mod app;
-mod data;
-mod export;
-mod parser;
-mod pdx_text;
+mod autosave;
+mod history;
 mod renderer;
 mod theme;
 mod ui;
@@ -14,6 +12,9 @@ mod ui;
 use app::PdxApp;
 
 fn main() -> eframe::Result<()> {
+    // Set RUST_LOG=debug for more detail
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([1400.0, 800.0])
@@ -35,7 +36,7 @@ fn main() -> eframe::Result<()> {
             // Setup custom fonts (Arabic/Unicode support)
             ui::setup_fonts(&cc.egui_ctx);
 
-            Ok(Box::new(PdxApp::default()))
+            Ok(Box::new(PdxApp::new(cc)))
         }),
     )
 }
@@ -1,8 +1,18 @@
-use crate::data::{Direction, Node, StyleSheet};
-use crate::pdx_text::pdx_text;
 use crate::theme::AppTheme;
 use eframe::egui::{self, RichText};
+use pdx_core::data::{
+    Direction, EdgeInsets, FontWeight, ListItem, Node, Style, StyleSheet, TextAlign, TextRun,
+};
+use pdx_core::pdx_text::{
+    is_rtl_block, line_segments, list_marker, shape, visual_line, wrap_words, VisualSegment,
+};
 use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Indent per list nesting level, before zoom.
+const LIST_INDENT: f32 = 24.0;
+/// Space between a list marker and the item text, before zoom.
+const MARKER_GAP: f32 = 6.0;
 
 // ============================================================================
 // Document Rendering
@@ -16,8 +26,6 @@ pub fn render_node(
     theme: &AppTheme,
     images: &HashMap<String, egui::TextureHandle>,
 ) {
-    let text_color = theme.text_color();
-
     match node {
         Node::Document { children } => {
             for child in children {
@@ -25,139 +33,45 @@ pub fn render_node(
             }
         }
 
-        Node::Heading { level, runs, style } => {
-            let style_def = styles.styles.get(style).cloned().unwrap_or_default();
-            let size = style_def.font_size * zoom;
-
-            ui.add_space(style_def.margin.top * zoom);
-
-            let is_rtl = runs.iter().any(|r| r.direction == Direction::RTL);
-
-            if is_rtl {
-                ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
-                    ui.horizontal_wrapped(|ui| {
-                        for run in runs.iter().rev() {
-                            ui.label(
-                                RichText::new(&pdx_text(&run.text))
-                                    .size(size)
-                                    .color(text_color)
-                                    .strong(),
-                            );
-                        }
-                    });
-                });
-            } else {
-                ui.horizontal_wrapped(|ui| {
-                    for run in runs {
-                        ui.label(
-                            RichText::new(&pdx_text(&run.text))
-                                .size(size)
-                                .color(text_color)
-                                .strong(),
-                        );
-                    }
-                });
-            }
-
-            ui.add_space(style_def.margin.bottom * zoom);
+        Node::Heading { runs, style, .. } | Node::Paragraph { runs, style } => {
+            let style_def = styles.resolve(style);
+            block_frame(&style_def, zoom).show(ui, |ui| {
+                render_text(ui, runs, &style_def, zoom, theme, None, 0.0);
+            });
         }
 
-        Node::Paragraph { runs, style } => {
-            let style_def = styles.styles.get(style).cloned().unwrap_or_default();
-            let size = style_def.font_size * zoom;
-
-            ui.add_space(style_def.margin.top * zoom);
-
-            let is_rtl = runs.iter().any(|r| r.direction == Direction::RTL);
-
-            if is_rtl {
-                ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
-                    ui.horizontal_wrapped(|ui| {
-                        for run in runs.iter().rev() {
-                            ui.label(
-                                RichText::new(&pdx_text(&run.text))
-                                    .size(size)
-                                    .color(text_color),
-                            );
-                        }
-                    });
-                });
-            } else {
-                ui.horizontal_wrapped(|ui| {
-                    for run in runs {
-                        ui.label(
-                            RichText::new(&pdx_text(&run.text))
-                                .size(size)
-                                .color(text_color),
-                        );
-                    }
-                });
-            }
-
-            ui.add_space(style_def.margin.bottom * zoom);
+        Node::List {
+            ordered,
+            start,
+            items,
+            style,
+        } => {
+            let style_def = styles.resolve(style);
+            block_frame(&style_def, zoom).show(ui, |ui| {
+                render_list(
+                    ui, *ordered, *start, items, 0, &style_def, styles, zoom, theme, images,
+                );
+            });
         }
 
-        Node::List { ordered, items, .. } => {
-            for (i, item) in items.iter().enumerate() {
-                let is_rtl = item.content.iter().any(|r| r.direction == Direction::RTL);
-
-                if is_rtl {
-                    ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
-                        ui.horizontal_wrapped(|ui| {
-                            for run in item.content.iter().rev() {
-                                ui.label(
-                                    RichText::new(&pdx_text(&run.text))
-                                        .size(16.0 * zoom)
-                                        .color(text_color),
-                                );
-                            }
-
-                            let marker = if *ordered {
-                                format!(".{}", i + 1)
-                            } else {
-                                "•".to_string()
-                            };
-                            ui.label(RichText::new(marker).size(16.0 * zoom).color(text_color));
-                        });
-                    });
-                } else {
-                    ui.horizontal_wrapped(|ui| {
-                        let marker = if *ordered {
-                            format!("{}.", i + 1)
-                        } else {
-                            "•".to_string()
-                        };
-
-                        ui.label(RichText::new(marker).size(16.0 * zoom).color(text_color));
-
-                        for run in &item.content {
-                            ui.label(
-                                RichText::new(&pdx_text(&run.text))
-                                    .size(16.0 * zoom)
-                                    .color(text_color),
-                            );
-                        }
-                    });
-                }
-            }
-            ui.add_space(10.0 * zoom);
-        }
+        Node::CodeBlock {
+            language,
+            code,
+            style,
+        } => {
+            let style_def = styles.resolve(style);
+            let size = style_def.font_size * 0.85 * zoom;
+            let color = style_color(&style_def, theme);
 
-        Node::CodeBlock { language, code, .. } => {
             ui.add_space(10.0);
             ui.group(|ui| {
                 ui.label(
                     RichText::new(language)
-                        .size(11.0 * zoom)
+                        .size(size * 0.85)
                         .italics()
-                        .color(text_color),
-                );
-                ui.label(
-                    RichText::new(code)
-                        .size(13.0 * zoom)
-                        .code()
-                        .color(text_color),
+                        .color(color),
                 );
+                ui.label(RichText::new(code).size(size).code().color(color));
             });
             ui.add_space(10.0);
         }
@@ -183,6 +97,71 @@ pub fn render_node(
             ui.add_space(10.0);
         }
 
+        Node::Table {
+            rows,
+            header_rows,
+            alignments,
+            direction,
+            style,
+        } => {
+            let style_def = styles.resolve(style);
+            let columns = rows.iter().map(|r| r.cells.len()).max().unwrap_or(0).max(1);
+            let is_rtl = *direction == Direction::RTL;
+            let spacing = egui::vec2(12.0 * zoom, 6.0 * zoom);
+
+            ui.add_space(10.0 * zoom);
+            ui.group(|ui| {
+                let gaps = spacing.x * (columns - 1) as f32;
+                let cell_width = ((ui.available_width() - gaps) / columns as f32).max(40.0 * zoom);
+
+                egui::Grid::new(rows.as_ptr() as usize)
+                    .striped(true)
+                    .spacing(spacing)
+                    .show(ui, |ui| {
+                        for (r, row) in rows.iter().enumerate() {
+                            for c in 0..columns {
+                                let column = if is_rtl { columns - 1 - c } else { c };
+                                let Some(cell) = row.cells.get(column) else {
+                                    ui.allocate_exact_size(
+                                        egui::vec2(cell_width, 0.0),
+                                        egui::Sense::hover(),
+                                    );
+                                    continue;
+                                };
+
+                                let mut cell_style = style_def.clone();
+                                cell_style.text_align =
+                                    alignments.get(column).copied().unwrap_or_default();
+                                cell_style.direction = if is_rtl {
+                                    Direction::RTL
+                                } else {
+                                    Direction::Auto
+                                };
+                                cell_style.line_height = cell_style.line_height.min(1.5);
+                                if r < *header_rows {
+                                    cell_style.font_weight = FontWeight::Bold;
+                                }
+
+                                ui.allocate_ui(egui::vec2(cell_width, 0.0), |ui| {
+                                    ui.set_width(cell_width);
+                                    render_text(
+                                        ui,
+                                        &cell.content,
+                                        &cell_style,
+                                        zoom,
+                                        theme,
+                                        None,
+                                        0.0,
+                                    );
+                                });
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+            ui.add_space(10.0 * zoom);
+        }
+
         Node::Divider => {
             ui.add_space(10.0);
             ui.separator();
@@ -197,4 +176,260 @@ pub fn render_node(
             ui.add_space(20.0);
         }
     }
-}
\ No newline at end of file
+}
+
+/// Renders list items at the given nesting depth. Nested lists inside an
+/// item go one level deeper and keep the outer list's style; other nested
+/// blocks render as usual.
+#[allow(clippy::too_many_arguments)]
+fn render_list(
+    ui: &mut egui::Ui,
+    ordered: bool,
+    start: u32,
+    items: &[ListItem],
+    depth: usize,
+    style: &Style,
+    styles: &StyleSheet,
+    zoom: f32,
+    theme: &AppTheme,
+    images: &HashMap<String, egui::TextureHandle>,
+) {
+    let indent = LIST_INDENT * (depth + 1) as f32 * zoom;
+
+    for (i, item) in items.iter().enumerate() {
+        let is_rtl = is_rtl_block(&item.content, style.direction);
+        let number = start.saturating_add(i as u32);
+        let marker = list_marker(ordered, number, depth, item.language(), is_rtl);
+        render_text(ui, &item.content, style, zoom, theme, Some(&marker), indent);
+
+        for child in &item.children {
+            match child {
+                Node::List {
+                    ordered,
+                    start,
+                    items,
+                    ..
+                } => render_list(
+                    ui,
+                    *ordered,
+                    *start,
+                    items,
+                    depth + 1,
+                    style,
+                    styles,
+                    zoom,
+                    theme,
+                    images,
+                ),
+                _ => {
+                    ui.horizontal(|ui| {
+                        ui.add_space(indent);
+                        ui.vertical(|ui| render_node(ui, child, styles, zoom, theme, images));
+                    });
+                }
+            }
+        }
+    }
+}
+
+// ============================================================================
+// Text Flow
+// ============================================================================
+
+/// The box a block's margin and padding describe.
+fn block_frame(style: &Style, zoom: f32) -> egui::Frame {
+    let margin = |insets: &EdgeInsets| egui::Margin {
+        left: insets.left * zoom,
+        right: insets.right * zoom,
+        top: insets.top * zoom,
+        bottom: insets.bottom * zoom,
+    };
+
+    egui::Frame::none()
+        .outer_margin(margin(&style.margin))
+        .inner_margin(margin(&style.padding))
+}
+
+/// Colour for text in `style`. Style colours are chosen for a light page,
+/// so dark themes flip the lightness of dark colours while keeping their
+/// hue. Light weight is drawn as a lighter tint.
+fn style_color(style: &Style, theme: &AppTheme) -> egui::Color32 {
+    let (r, g, b) = (style.color.r, style.color.g, style.color.b);
+    let mut color = egui::Color32::from_rgb(r, g, b);
+
+    let lightness = r.max(g).max(b) as i32 + r.min(g).min(b) as i32;
+    if theme.is_dark() && lightness < 255 {
+        let shift = |c: u8| (c as i32 + 255 - lightness) as u8;
+        color = egui::Color32::from_rgb(shift(r), shift(g), shift(b));
+    }
+
+    if style.font_weight == FontWeight::Light {
+        color = color.lerp_to_gamma(theme.background_color(), 0.35);
+    }
+
+    color
+}
+
+/// Wraps runs into lines and paints them with the style's size, colour,
+/// weight, alignment and line height, the way the page exporters lay text
+/// out. Each wrapped line is reordered on its own (UAX #9), so runs of
+/// either direction can mix. `indent` leaves room on the reading side,
+/// where `marker` goes.
+fn render_text(
+    ui: &mut egui::Ui,
+    runs: &[TextRun],
+    style: &Style,
+    zoom: f32,
+    theme: &AppTheme,
+    marker: Option<&str>,
+    indent: f32,
+) {
+    let is_rtl = is_rtl_block(runs, style.direction);
+    let size = style.font_size * zoom;
+    let line_height = size * style.line_height;
+    let color = style_color(style, theme);
+    let bold = style.font_weight == FontWeight::Bold;
+
+    let ctx = ui.ctx().clone();
+    let visuals = ui.visuals().clone();
+    let plain = egui::TextFormat::simple(egui::FontId::proportional(size), color);
+    let format_of = |run: Option<usize>| match run {
+        Some(run) => run_format(&visuals, &runs[run], size, color),
+        None => plain.clone(),
+    };
+    let layout = |text: &str, format: egui::TextFormat| {
+        let mut job = egui::text::LayoutJob::default();
+        job.append(text, 0.0, format);
+        ctx.fonts(|f| f.layout_job(job))
+    };
+
+    let space = layout(" ", plain.clone()).size().x;
+    let width = (ui.available_width() - indent).max(size);
+    let mut lines = wrap_words(runs, width, space, |run, text| {
+        layout(&shape(text), format_of(Some(run))).size().x
+    });
+    if lines.is_empty() {
+        lines.push(Vec::new());
+    }
+    let last = lines.len() - 1;
+
+    for (index, line) in lines.iter().enumerate() {
+        let (rect, response) = ui.allocate_exact_size(
+            egui::vec2(ui.available_width(), line_height),
+            egui::Sense::hover(),
+        );
+        let (left, right) = if is_rtl {
+            (rect.left(), rect.right() - indent)
+        } else {
+            (rect.left() + indent, rect.right())
+        };
+
+        if let (0, Some(marker)) = (index, marker) {
+            let galley = layout(marker, plain.clone());
+            let gap = MARKER_GAP * zoom;
+            let x = if is_rtl {
+                right + gap
+            } else {
+                left - gap - galley.size().x
+            };
+            let pos = egui::pos2(x, rect.center().y - galley.size().y / 2.0);
+            ui.painter().galley(pos, galley, color);
+        }
+
+        let pieces: Vec<(VisualSegment, Arc<egui::Galley>)> =
+            visual_line(&line_segments(line), is_rtl)
+                .into_iter()
+                .map(|segment| {
+                    let galley = layout(&segment.text, format_of(segment.run));
+                    (segment, galley)
+                })
+                .collect();
+
+        let natural: f32 = pieces.iter().map(|(_, galley)| galley.size().x).sum();
+        let spaces = pieces.iter().filter(|(s, _)| s.text == " ").count() as f32;
+        let available = right - left;
+
+        let mut stretch = 0.0;
+        let mut x = match (style.text_align, is_rtl) {
+            (TextAlign::Center, _) => left + (available - natural) / 2.0,
+            (TextAlign::Start, false) | (TextAlign::End, true) => left,
+            (TextAlign::Start, true) | (TextAlign::End, false) => right - natural,
+            (TextAlign::Justify, _) if index != last && spaces > 0.0 => {
+                stretch = (available - natural) / spaces;
+                left
+            }
+            (TextAlign::Justify, true) => right - natural,
+            (TextAlign::Justify, false) => left,
+        };
+
+        for (i, (segment, galley)) in pieces.into_iter().enumerate() {
+            let piece_size = galley.size();
+            let pos = egui::pos2(x, rect.center().y - piece_size.y / 2.0);
+            x += piece_size.x;
+
+            if segment.text == " " {
+                x += stretch;
+            }
+            let Some(run) = segment.run.map(|run| &runs[run]) else {
+                ui.painter().galley(pos, galley, color);
+                continue;
+            };
+
+            if bold || run.marks.strong {
+                // Faux bold like the exporters: the bundled fonts have no bold cut
+                let offset = egui::vec2((size / 24.0).max(0.5), 0.0);
+                ui.painter().galley(pos + offset, galley.clone(), color);
+            }
+            ui.painter().galley(pos, galley, color);
+
+            if let Some(url) = &run.marks.link {
+                let rect = egui::Rect::from_min_size(pos, piece_size);
+                let link = ui
+                    .interact(rect, response.id.with(i), egui::Sense::click())
+                    .on_hover_cursor(egui::CursorIcon::PointingHand)
+                    .on_hover_text(url);
+                if link.clicked() {
+                    ui.ctx().open_url(egui::OpenUrl::new_tab(url));
+                }
+            }
+        }
+    }
+}
+
+/// Text format for a run's inline marks.
+fn run_format(
+    visuals: &egui::Visuals,
+    run: &TextRun,
+    size: f32,
+    color: egui::Color32,
+) -> egui::TextFormat {
+    let marks = &run.marks;
+    let color = if marks.link.is_some() {
+        visuals.hyperlink_color
+    } else {
+        color
+    };
+    let stroke = egui::Stroke::new((size / 16.0).max(1.0), color);
+
+    let mut format = egui::TextFormat {
+        font_id: if marks.code {
+            egui::FontId::monospace(size * 0.9)
+        } else {
+            egui::FontId::proportional(size)
+        },
+        color,
+        italics: marks.emphasis,
+        ..Default::default()
+    };
+    if marks.code {
+        format.background = visuals.code_bg_color;
+    }
+    if marks.underline || marks.link.is_some() {
+        format.underline = stroke;
+    }
+    if marks.strikethrough {
+        format.strikethrough = stroke;
+    }
+
+    format
+}
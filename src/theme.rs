@@ -4,12 +4,13 @@ use eframe::egui;
 // Theme System
 // ============================================================================
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub enum AppTheme {
     Dark,
     Light,
     Sepia,
     Midnight,
+    #[default]
     Comfort, // Eye-friendly theme for long writing sessions
 }
 
@@ -44,10 +45,15 @@ impl AppTheme {
         }
     }
 
+    pub fn is_dark(&self) -> bool {
+        matches!(self, AppTheme::Dark | AppTheme::Midnight)
+    }
+
     pub fn apply(&self, ctx: &egui::Context) {
-        let mut visuals = match self {
-            AppTheme::Light | AppTheme::Sepia | AppTheme::Comfort => egui::Visuals::light(),
-            AppTheme::Dark | AppTheme::Midnight => egui::Visuals::dark(),
+        let mut visuals = if self.is_dark() {
+            egui::Visuals::dark()
+        } else {
+            egui::Visuals::light()
         };
 
         visuals.override_text_color = Some(self.text_color());
@@ -82,20 +88,4 @@ impl AppTheme {
             AppTheme::Comfort => "Comfort",
         }
     }
-
-    pub fn all_themes() -> Vec<AppTheme> {
-        vec![
-            AppTheme::Light,
-            AppTheme::Dark,
-            AppTheme::Midnight,
-            AppTheme::Sepia,
-            AppTheme::Comfort,
-        ]
-    }
-}
-
-impl Default for AppTheme {
-    fn default() -> Self {
-        AppTheme::Comfort // Default to comfort theme
-    }
 }
\ No newline at end of file
@@ -1,8 +1,18 @@
-use crate::data::PdxDocument;
-use crate::export::{export_as_html, export_as_pdf, export_as_png};
 use eframe::egui::{self, FontDefinitions, FontFamily};
+use pdx_core::bundle::{is_bundle, read_bundle, write_bundle, BUNDLE_EXTENSION};
+use pdx_core::data::{PdxDocument, StyleSheet};
+use pdx_core::docx::{export_as_docx, import_docx};
+use pdx_core::epub::export_as_epub;
+use pdx_core::error::PdxError;
+use pdx_core::export::{export_as_html, export_as_pdf, export_as_png, HtmlOptions, PngOptions};
+use pdx_core::latex::export_as_latex;
+use pdx_core::markdown::{export_as_markdown, import_markdown};
+use pdx_core::migrate;
+use pdx_core::odt::{export_as_odt, import_odt};
+use pdx_core::validate::CURRENT_VERSION;
 use std::fs;
-use std::path::PathBuf;
+use std::io;
+use std::path::{Path, PathBuf};
 
 // ============================================================================
 // UI Setup
@@ -30,6 +40,21 @@ pub fn setup_fonts(ctx: &egui::Context) {
         .or_default()
         .insert(0, "arabic".to_owned());
 
+    // Fallback for Hebrew, N'Ko and other scripts the default fonts lack
+    fonts.font_data.insert(
+        "dejavu".to_owned(),
+        std::sync::Arc::new(egui::FontData::from_static(include_bytes!(
+            "../assets/fonts/DejaVuSans.ttf"
+        ))),
+    );
+    for family in [FontFamily::Proportional, FontFamily::Monospace] {
+        fonts
+            .families
+            .entry(family)
+            .or_default()
+            .push("dejavu".to_owned());
+    }
+
     ctx.set_fonts(fonts);
 
     let mut style = (*ctx.style()).clone();
@@ -63,72 +88,273 @@ pub fn setup_fonts(ctx: &egui::Context) {
 // File Operations
 // ============================================================================
 
-pub fn open_document() -> Option<(PdxDocument, PathBuf)> {
-    let path = rfd::FileDialog::new()
-        .add_filter("PDX Document", &["pdx", "json"])
-        .pick_file()?;
+/// Markdown, Word and OpenDocument files are imported rather than opened:
+/// saving them writes a `.pdx`.
+pub fn is_imported_file(path: &Path) -> bool {
+    is_markdown_file(path) || has_extension(path, &["docx", "odt"])
+}
 
-    let data = fs::read_to_string(&path).ok()?;
-    let document: PdxDocument = serde_json::from_str(&data).ok()?;
+fn is_markdown_file(path: &Path) -> bool {
+    has_extension(path, &MARKDOWN_EXTENSIONS)
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .is_some_and(|ext| extensions.iter().any(|e| ext.eq_ignore_ascii_case(e)))
+}
+
+const MARKDOWN_EXTENSIONS: [&str; 2] = ["md", "markdown"];
+
+/// Opens a plain `.pdx` file or a bundle, whatever its extension, or imports
+/// a Markdown, Word or OpenDocument file. Returns `Ok(None)` when the user cancels the dialog, as
+/// do the other file operations below.
+pub fn open_document() -> Result<Option<(PdxDocument, PathBuf)>, PdxError> {
+    let Some(path) = rfd::FileDialog::new()
+        .add_filter("PDX Document", &["pdx", BUNDLE_EXTENSION, "json"])
+        .add_filter("Markdown", &MARKDOWN_EXTENSIONS)
+        .add_filter("Word Document", &["docx"])
+        .add_filter("OpenDocument Text", &["odt"])
+        .pick_file()
+    else {
+        return Ok(None);
+    };
+
+    let data = fs::read(&path).map_err(|e| PdxError::io(&path, e))?;
+    let title = path.file_stem().unwrap_or_default().to_string_lossy();
+    // A .docx or .odt is a zip too, so it is told apart from a bundle by its name
+    let mut document = if has_extension(&path, &["docx"]) {
+        import_docx(&data, &title)?
+    } else if has_extension(&path, &["odt"]) {
+        import_odt(&data, &title)?
+    } else if is_bundle(&data) {
+        read_bundle(&data)?
+    } else if is_markdown_file(&path) {
+        let text = String::from_utf8(data)
+            .map_err(|e| PdxError::io(&path, io::Error::new(io::ErrorKind::InvalidData, e)))?;
+        import_markdown(&text, &title).map_err(|e| e.with_path(&path))?
+    } else {
+        let text = String::from_utf8(data)
+            .map_err(|e| PdxError::io(&path, io::Error::new(io::ErrorKind::InvalidData, e)))?;
+        let (document, version) = migrate::load(&text).map_err(|e| e.with_path(&path))?;
+        if version < CURRENT_VERSION {
+            log::info!(
+                "Upgraded {} from format version {}",
+                path.display(),
+                version
+            );
+        }
+        document
+    };
+    document.resources.base_dir = path.parent().map(Path::to_path_buf);
 
-    Some((document, path))
+    log::info!("Opened {}", path.display());
+    Ok(Some((document, path)))
 }
 
-pub fn save_document(document: &PdxDocument, path: Option<&PathBuf>) -> Option<PathBuf> {
-    let path = match path {
+pub fn save_document(
+    document: &PdxDocument,
+    path: Option<&PathBuf>,
+) -> Result<Option<PathBuf>, PdxError> {
+    // Embedded images only survive in a bundle
+    let embedded = !document.resources.files.is_empty();
+    let mut path = match path {
         Some(p) => p.clone(),
-        None => rfd::FileDialog::new()
+        None => match rfd::FileDialog::new()
             .add_filter("PDX Document", &["pdx"])
-            .set_file_name("document.pdx")
-            .save_file()?,
+            .add_filter("PDX Bundle (with images)", &[BUNDLE_EXTENSION])
+            .set_file_name(if embedded {
+                "document.pdxz"
+            } else {
+                "document.pdx"
+            })
+            .save_file()
+        {
+            Some(p) => p,
+            None => return Ok(None),
+        },
     };
 
-    let json = serde_json::to_string_pretty(document).unwrap();
-    fs::write(&path, json).ok()?;
+    // A bundle written under a .pdx name would not open as one elsewhere
+    if embedded {
+        path.set_extension(BUNDLE_EXTENSION);
+    }
+
+    if path.extension().is_some_and(|ext| ext == BUNDLE_EXTENSION) {
+        write_file(&path, &write_bundle(document)?)?;
+    } else {
+        write_file(&path, document.to_json()?.as_bytes())?;
+    }
 
-    Some(path)
+    Ok(Some(path))
 }
 
-pub fn export_html(document: &PdxDocument) -> Option<()> {
-    let path = rfd::FileDialog::new()
+pub fn export_html(
+    document: &PdxDocument,
+    options: &HtmlOptions,
+) -> Result<Option<PathBuf>, PdxError> {
+    let Some(path) = rfd::FileDialog::new()
         .add_filter("HTML", &["html"])
-        .set_file_name(&format!("{}.html", document.metadata.title))
-        .save_file()?;
+        .set_file_name(format!("{}.html", document.metadata.title))
+        .save_file()
+    else {
+        return Ok(None);
+    };
 
-    let html = export_as_html(document);
-    fs::write(path, html).ok()?;
+    write_file(&path, export_as_html(document, options).as_bytes())?;
 
-    Some(())
+    Ok(Some(path))
 }
 
-pub fn export_pdf_file(document: &PdxDocument) -> Option<()> {
-    let path = rfd::FileDialog::new()
+pub fn export_markdown_file(document: &PdxDocument) -> Result<Option<PathBuf>, PdxError> {
+    let Some(path) = rfd::FileDialog::new()
+        .add_filter("Markdown", &MARKDOWN_EXTENSIONS)
+        .set_file_name(format!("{}.md", document.metadata.title))
+        .save_file()
+    else {
+        return Ok(None);
+    };
+
+    write_file(&path, export_as_markdown(document).as_bytes())?;
+
+    Ok(Some(path))
+}
+
+pub fn export_docx_file(document: &PdxDocument) -> Result<Option<PathBuf>, PdxError> {
+    let Some(path) = rfd::FileDialog::new()
+        .add_filter("Word Document", &["docx"])
+        .set_file_name(format!("{}.docx", document.metadata.title))
+        .save_file()
+    else {
+        return Ok(None);
+    };
+
+    write_file(&path, &export_as_docx(document)?)?;
+
+    Ok(Some(path))
+}
+
+pub fn export_odt_file(document: &PdxDocument) -> Result<Option<PathBuf>, PdxError> {
+    let Some(path) = rfd::FileDialog::new()
+        .add_filter("OpenDocument Text", &["odt"])
+        .set_file_name(format!("{}.odt", document.metadata.title))
+        .save_file()
+    else {
+        return Ok(None);
+    };
+
+    write_file(&path, &export_as_odt(document)?)?;
+
+    Ok(Some(path))
+}
+
+pub fn export_epub_file(document: &PdxDocument) -> Result<Option<PathBuf>, PdxError> {
+    let Some(path) = rfd::FileDialog::new()
+        .add_filter("EPUB Book", &["epub"])
+        .set_file_name(format!("{}.epub", document.metadata.title))
+        .save_file()
+    else {
+        return Ok(None);
+    };
+
+    write_file(&path, &export_as_epub(document)?)?;
+
+    Ok(Some(path))
+}
+
+pub fn export_latex_file(document: &PdxDocument) -> Result<Option<PathBuf>, PdxError> {
+    let Some(path) = rfd::FileDialog::new()
+        .add_filter("LaTeX", &["tex"])
+        .set_file_name(format!("{}.tex", document.metadata.title))
+        .save_file()
+    else {
+        return Ok(None);
+    };
+
+    write_file(&path, export_as_latex(document)?.as_bytes())?;
+
+    Ok(Some(path))
+}
+
+pub fn export_pdf_file(document: &PdxDocument) -> Result<Option<PathBuf>, PdxError> {
+    let Some(path) = rfd::FileDialog::new()
         .add_filter("PDF", &["pdf"])
-        .set_file_name(&format!("{}.pdf", document.metadata.title))
-        .save_file()?;
+        .set_file_name(format!("{}.pdf", document.metadata.title))
+        .save_file()
+    else {
+        return Ok(None);
+    };
 
-    match export_as_pdf(document) {
-        Ok(pdf_data) => {
-            fs::write(path, pdf_data).ok()?;
-            Some(())
-        }
-        Err(_) => None,
-    }
+    write_file(&path, &export_as_pdf(document)?)?;
+
+    Ok(Some(path))
 }
 
-pub fn export_png_file() -> Option<()> {
-    let path = rfd::FileDialog::new()
+pub fn export_png_file(
+    document: &PdxDocument,
+    options: &PngOptions,
+) -> Result<Option<PathBuf>, PdxError> {
+    let Some(path) = rfd::FileDialog::new()
         .add_filter("PNG Image", &["png"])
-        .set_file_name("document.png")
-        .save_file()?;
+        .set_file_name(format!("{}.png", document.metadata.title))
+        .save_file()
+    else {
+        return Ok(None);
+    };
 
-    match export_as_png(1200, 1600) {
-        Ok(png_data) => {
-            fs::write(path, png_data).ok()?;
-            Some(())
+    let images = export_as_png(document, options)?;
+
+    if images.len() == 1 {
+        write_file(&path, &images[0])?;
+    } else {
+        // One file per page: "name-1.png", "name-2.png", ...
+        let stem = path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        for (i, png_data) in images.iter().enumerate() {
+            let page_path = path.with_file_name(format!("{}-{}.png", stem, i + 1));
+            write_file(&page_path, png_data)?;
         }
-        Err(_) => None,
     }
+
+    Ok(Some(path))
+}
+
+/// Loads a style sheet from an exported `.pdxstyle` file or another document.
+pub fn import_styles() -> Result<Option<StyleSheet>, PdxError> {
+    let Some(path) = rfd::FileDialog::new()
+        .add_filter("PDX Style Sheet", &["pdxstyle", "json"])
+        .add_filter("PDX Document", &["pdx"])
+        .pick_file()
+    else {
+        return Ok(None);
+    };
+
+    let data = fs::read_to_string(&path).map_err(|e| PdxError::io(&path, e))?;
+    let styles = StyleSheet::from_json(&data).map_err(|e| e.with_path(&path))?;
+
+    Ok(Some(styles))
+}
+
+pub fn export_styles(styles: &StyleSheet) -> Result<Option<PathBuf>, PdxError> {
+    let Some(path) = rfd::FileDialog::new()
+        .add_filter("PDX Style Sheet", &["pdxstyle"])
+        .set_file_name("styles.pdxstyle")
+        .save_file()
+    else {
+        return Ok(None);
+    };
+
+    write_file(&path, styles.to_json()?.as_bytes())?;
+
+    Ok(Some(path))
+}
+
+fn write_file(path: &Path, data: &[u8]) -> Result<(), PdxError> {
+    fs::write(path, data).map_err(|e| PdxError::io(path, e))?;
+    log::info!("Wrote {}", path.display());
+    Ok(())
 }
 
 pub fn insert_image() -> Option<String> {